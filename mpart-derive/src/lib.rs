@@ -0,0 +1,215 @@
+//! The derive macro for `mpart::server::extract::MultipartForm`.
+//!
+//! Use it through the `derive` feature of `mpart`, which re-exports it alongside the trait; see
+//! the docs of the `mpart::server::extract` module.
+#![deny(rust_2018_idioms)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, Data, DataStruct, DeriveInput, Expr, Fields, GenericArgument, LitStr,
+    PathArguments, Type,
+};
+
+/// Derive `MultipartForm` for a struct with named fields.
+///
+/// Each field is filled from the field of the request with the same name, converted with
+/// `FromField`. Options go in `#[mpart(...)]` attributes on the fields:
+///
+/// * `rename = "name"`: the name of the field in the request.
+/// * `limit = <expr>`: the maximum size of the field's data in bytes, as a `u64`.
+/// * `default`: use `Default::default()` if the request doesn't have the field.
+///
+/// Fields of type `Option<T>` are `None` if the request doesn't have them.
+#[proc_macro_derive(MultipartForm, attributes(mpart))]
+pub fn derive_multipart_form(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// A field of the struct, with its options.
+struct FormField<'a> {
+    ident: &'a syn::Ident,
+    /// The type converted with `FromField`, without any `Option`.
+    ty: &'a Type,
+    name: String,
+    limit: Option<Expr>,
+    optional: bool,
+    default: bool,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match input.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(ref fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "`MultipartForm` can only be derived for structs with named fields",
+            ))
+        }
+    };
+
+    let fields = fields
+        .iter()
+        .map(|field| {
+            let ident = field.ident.as_ref().expect("named fields have idents");
+            parse_field(ident, &field.ty, &field.attrs)
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let vars: Vec<_> = (0..fields.len())
+        .map(|i| format_ident!("__mpart_field{}", i))
+        .collect();
+
+    let extract = quote!(::mpart::server::extract);
+
+    let decls = fields.iter().zip(&vars).map(|(field, var)| {
+        let ty = field.ty;
+        quote! { let mut #var: ::core::option::Option<#ty> = ::core::option::Option::None; }
+    });
+
+    let arms = fields.iter().zip(&vars).map(|(field, var)| {
+        let FormField { ty, ref name, .. } = *field;
+
+        let limit = match field.limit {
+            Some(ref limit) => quote!(::core::option::Option::Some(#limit)),
+            None => quote!(::core::option::Option::None),
+        };
+
+        quote! {
+            #name => {
+                if #var.is_some() {
+                    return ::core::result::Result::Err(
+                        #extract::ExtractError::DuplicateField { field: #name }.into(),
+                    );
+                }
+
+                #var = ::core::option::Option::Some(
+                    <#ty as #extract::FromField>::from_field(field, #limit)?,
+                );
+            }
+        }
+    });
+
+    let inits = fields.iter().zip(&vars).map(|(field, var)| {
+        let FormField {
+            ident, ref name, ..
+        } = *field;
+
+        if field.optional {
+            quote!(#ident: #var)
+        } else if field.default {
+            quote!(#ident: #var.unwrap_or_default())
+        } else {
+            quote! {
+                #ident: #var.ok_or(#extract::ExtractError::MissingField { field: #name })?
+            }
+        }
+    });
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #extract::MultipartForm for #ident #ty_generics #where_clause {
+            fn from_multipart<R: ::std::io::Read>(
+                multipart: &mut ::mpart::server::Multipart<R>,
+            ) -> ::std::io::Result<Self> {
+                #(#decls)*
+
+                while let ::core::option::Option::Some(field) = multipart.read_entry()? {
+                    let name = field.headers.name.clone();
+
+                    match &*name {
+                        #(#arms)*
+                        // unknown fields are skipped by the next `read_entry()`
+                        _ => (),
+                    }
+                }
+
+                ::core::result::Result::Ok(#ident {
+                    #(#inits,)*
+                })
+            }
+        }
+    })
+}
+
+fn parse_field<'a>(
+    ident: &'a syn::Ident,
+    ty: &'a Type,
+    attrs: &[syn::Attribute],
+) -> syn::Result<FormField<'a>> {
+    let mut name = None;
+    let mut limit = None;
+    let mut default = false;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("mpart")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                name = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("limit") {
+                limit = Some(meta.value()?.parse::<Expr>()?);
+            } else if meta.path.is_ident("default") {
+                default = true;
+            } else {
+                return Err(meta.error("expected `rename`, `limit` or `default`"));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    let option_inner = option_inner(ty);
+
+    if default && option_inner.is_some() {
+        return Err(syn::Error::new_spanned(
+            ty,
+            "`Option` fields are `None` by default already",
+        ));
+    }
+
+    let name = name.unwrap_or_else(|| {
+        let ident = ident.to_string();
+        ident.strip_prefix("r#").unwrap_or(&ident).to_owned()
+    });
+
+    Ok(FormField {
+        ident,
+        ty: option_inner.unwrap_or(ty),
+        name,
+        limit,
+        optional: option_inner.is_some(),
+        default,
+    })
+}
+
+/// `T` if `ty` is `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(ty) = ty else { return None };
+
+    if ty.qself.is_some() {
+        return None;
+    }
+
+    let segment = ty.path.segments.last()?;
+
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match segment.arguments {
+        PathArguments::AngleBracketed(ref args) if args.args.len() == 1 => match args.args[0] {
+            GenericArgument::Type(ref inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,216 @@
+//! Writing a multipart body to a Tokio `AsyncWrite`. Enabled with the `client-async` feature.
+//!
+//! This mirrors the blocking `client::Multipart`, for async applications which stream large
+//! uploads without spawning a blocking thread per request.
+//!
+//! ```rust
+//! use mpart::client::async_::Multipart;
+//!
+//! async fn write_body() -> std::io::Result<Vec<u8>> {
+//!     let mut multipart = Multipart::new(Vec::new());
+//!     println!("Content-Type: {}", multipart.content_type());
+//!
+//!     multipart.write_text("text", "some text").await?;
+//!     multipart
+//!         .write_stream("file", &mut &b"file data"[..], Some("file.bin"), None)
+//!         .await?;
+//!
+//!     multipart.finish().await
+//! }
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap()
+//! #     .block_on(write_body()).unwrap();
+//! ```
+use mime::Mime;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+
+use std::io;
+use std::path::Path;
+
+use super::MultipartWriter;
+
+/// The async counterpart of `client::Multipart`, writing fields to any `AsyncWrite`.
+///
+/// Unlike the blocking `Multipart`, each method returns its error immediately. The body is
+/// left incomplete after an error, so the request should be abandoned.
+pub struct Multipart<W> {
+    inner: W,
+    /// Writes the boundaries and field headers into a buffer, which is then written to `inner`,
+    /// so the body is the same as written by the blocking `Multipart`.
+    writer: MultipartWriter<'static, Vec<u8>>,
+}
+
+impl<W: AsyncWrite + Unpin> Multipart<W> {
+    /// Write a multipart body with a random boundary to `inner`.
+    ///
+    /// Nothing is written until the first field, so the `Content-Type` header of the request
+    /// can be set from `content_type()` first.
+    pub fn new(inner: W) -> Self {
+        Multipart {
+            inner,
+            writer: MultipartWriter::new(Vec::new(), super::gen_boundary()),
+        }
+    }
+
+    /// Get the boundary string used to separate the fields of this request.
+    #[must_use]
+    pub fn boundary(&self) -> &str {
+        &self.writer.boundary
+    }
+
+    /// Get the value of the `Content-Type` header to send with this body,
+    /// i.e. `multipart/form-data; boundary={boundary}`.
+    #[must_use]
+    pub fn content_type(&self) -> String {
+        super::form_data_content_type(self.boundary())
+    }
+
+    /// Write a text field to this multipart request.
+    /// `name` and `val` can be either owned `String` or `&str`.
+    ///
+    /// ## Errors
+    /// If writing to the inner writer failed.
+    pub async fn write_text<N: AsRef<str>, V: AsRef<str>>(
+        &mut self,
+        name: N,
+        val: V,
+    ) -> io::Result<&mut Self> {
+        self.writer.write_text(name.as_ref(), val.as_ref())?;
+        self.write_buffered().await?;
+        Ok(self)
+    }
+
+    /// Open a file pointed to by `path` with `tokio::fs` and write its contents to the
+    /// multipart request, supplying its filename and guessing its `Content-Type` from its
+    /// extension.
+    ///
+    /// This must be called from within a Tokio runtime.
+    ///
+    /// ## Errors
+    /// If there was a problem opening or reading the file, or writing to the inner writer.
+    pub async fn write_file<N: AsRef<str>, P: AsRef<Path>>(
+        &mut self,
+        name: N,
+        path: P,
+    ) -> io::Result<&mut Self> {
+        let path = path.as_ref();
+        let (content_type, filename) = super::mime_filename(path);
+        let mut file = tokio::fs::File::open(path).await?;
+
+        self.write_stream(name, &mut file, filename, Some(content_type))
+            .await
+    }
+
+    /// Write a byte stream to the multipart request as a file field, supplying `filename` if
+    /// given, and `content_type` if given or `"application/octet-stream"` if not.
+    ///
+    /// ## Warning
+    /// As for the blocking `Multipart::write_stream()`, the stream **must** reach its end, or
+    /// the request will never be completed. Use `AsyncReadExt::take()` to limit it otherwise.
+    ///
+    /// ## Errors
+    /// If the stream returned an error, or if writing to the inner writer failed.
+    pub async fn write_stream<N: AsRef<str>, St: AsyncRead + Unpin>(
+        &mut self,
+        name: N,
+        stream: &mut St,
+        filename: Option<&str>,
+        content_type: Option<Mime>,
+    ) -> io::Result<&mut Self> {
+        // This is necessary to make sure it is interpreted as a file on the server end.
+        let content_type = Some(content_type.unwrap_or(mime::APPLICATION_OCTET_STREAM));
+
+        self.writer
+            .write_field_headers(name.as_ref(), filename, content_type)?;
+        self.write_buffered().await?;
+        tokio::io::copy(stream, &mut self.inner).await?;
+        Ok(self)
+    }
+
+    /// Write the closing boundary, flush the inner writer and return it.
+    ///
+    /// ## Errors
+    /// If writing to or flushing the inner writer failed.
+    pub async fn finish(self) -> io::Result<W> {
+        let Multipart { mut inner, writer } = self;
+
+        inner.write_all(&writer.finish()?).await?;
+        inner.flush().await?;
+        Ok(inner)
+    }
+
+    /// Write out what `writer` buffered.
+    async fn write_buffered(&mut self) -> io::Result<()> {
+        self.inner.write_all(&self.writer.inner).await?;
+        self.writer.inner.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::MultipartWriter;
+    use super::Multipart;
+
+    use std::future::Future;
+    use std::io::Write;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_async_writer() {
+        let mut file = tempfile::Builder::new().suffix(".txt").tempfile().unwrap();
+        file.write_all(b"file value").unwrap();
+        let path = file.path();
+
+        let (boundary, body) = block_on(async {
+            let mut multipart = Multipart::new(Vec::new());
+            multipart
+                .write_text("text", "text value")
+                .await
+                .unwrap()
+                .write_file("file", path)
+                .await
+                .unwrap()
+                .write_stream("stream", &mut &b"stream value"[..], Some("a.bin"), None)
+                .await
+                .unwrap();
+
+            let boundary = multipart.boundary().to_owned();
+            assert_eq!(
+                multipart.content_type(),
+                format!("multipart/form-data; boundary={boundary}")
+            );
+
+            (boundary, multipart.finish().await.unwrap())
+        });
+
+        // the same as written by the blocking client
+        let mut writer = MultipartWriter::new(Vec::new(), boundary.as_str());
+        writer.write_text("text", "text value").unwrap();
+        writer.write_file("file", path).unwrap();
+        writer
+            .write_stream(&mut &b"stream value"[..], "stream", Some("a.bin"), None)
+            .unwrap();
+        assert_eq!(body, writer.finish().unwrap());
+
+        let body = String::from_utf8(body).unwrap();
+        let filename = path.file_name().unwrap().to_str().unwrap();
+        assert!(body.contains(&format!(
+            "Content-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+             Content-Type: text/plain\r\n\r\nfile value\r\n"
+        )));
+
+        // an empty body still has the closing boundary
+        let body = block_on(async {
+            let multipart = Multipart::new(Vec::new());
+            let boundary = multipart.boundary().to_owned();
+            (boundary, multipart.finish().await.unwrap())
+        });
+        assert_eq!(body.1, format!("--{}--\r\n", body.0).into_bytes());
+    }
+}
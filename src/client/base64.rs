@@ -0,0 +1,191 @@
+//! Streaming base64 encoding for `Content-Transfer-Encoding: base64` fields.
+use std::io::{self, Read};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The maximum length of an encoded line as per RFC 2045, section 6.8.
+const LINE_LEN: usize = 76;
+
+/// The number of input bytes to encode at a time; must be a multiple of 3.
+const CHUNK_LEN: usize = 3 * 256;
+
+/// The length of `len` bytes of data after encoding, including line breaks.
+pub fn encoded_len(len: u64) -> u64 {
+    let chars = len.div_ceil(3) * 4;
+
+    if chars == 0 {
+        0
+    } else {
+        chars + 2 * ((chars - 1) / LINE_LEN as u64)
+    }
+}
+
+/// A `Read` adapter which yields the base64 encoding of the inner reader, broken into lines
+/// of 76 characters separated by CRLF.
+pub struct Base64Encoder<R> {
+    inner: R,
+    /// Input bytes which didn't make a full 3-byte group yet.
+    carry: Vec<u8>,
+    /// Encoded bytes which haven't been returned yet.
+    out: Vec<u8>,
+    out_pos: usize,
+    /// The number of characters written to the current line.
+    col: usize,
+    done: bool,
+}
+
+impl<R: Read> Base64Encoder<R> {
+    pub fn new(inner: R) -> Self {
+        Base64Encoder {
+            inner,
+            carry: Vec::with_capacity(3),
+            out: Vec::new(),
+            out_pos: 0,
+            col: 0,
+            done: false,
+        }
+    }
+
+    fn fill_out(&mut self) -> io::Result<()> {
+        let mut raw = [0u8; CHUNK_LEN];
+        let carry_len = self.carry.len();
+        raw[..carry_len].copy_from_slice(&self.carry);
+
+        let read = loop {
+            match self.inner.read(&mut raw[carry_len..]) {
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                res => break res?,
+            }
+        };
+
+        self.out.clear();
+        self.out_pos = 0;
+
+        if read == 0 {
+            self.done = true;
+            let carry = std::mem::take(&mut self.carry);
+            self.encode(&carry);
+            return Ok(());
+        }
+
+        let total = carry_len + read;
+        let full = total - total % 3;
+
+        self.encode(&raw[..full]);
+        self.carry.clear();
+        self.carry.extend_from_slice(&raw[full..total]);
+
+        Ok(())
+    }
+
+    /// Encode `data`, padding the last group if it is short.
+    fn encode(&mut self, data: &[u8]) {
+        for group in data.chunks(3) {
+            let b = [
+                group[0],
+                group.get(1).copied().unwrap_or(0),
+                group.get(2).copied().unwrap_or(0),
+            ];
+
+            let chars = [
+                ALPHABET[(b[0] >> 2) as usize],
+                ALPHABET[(((b[0] & 0b11) << 4) | (b[1] >> 4)) as usize],
+                ALPHABET[(((b[1] & 0b1111) << 2) | (b[2] >> 6)) as usize],
+                ALPHABET[(b[2] & 0b11_1111) as usize],
+            ];
+
+            // pad with `=` in place of the characters that only encode zero bits
+            let len = group.len() + 1;
+
+            for (i, &c) in chars.iter().enumerate() {
+                self.push_char(if i < len { c } else { b'=' });
+            }
+        }
+    }
+
+    fn push_char(&mut self, c: u8) {
+        if self.col == LINE_LEN {
+            self.out.extend_from_slice(b"\r\n");
+            self.col = 0;
+        }
+
+        self.out.push(c);
+        self.col += 1;
+    }
+}
+
+impl<R: Read> Read for Base64Encoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.out_pos == self.out.len() {
+            if self.done {
+                return Ok(0);
+            }
+
+            self.fill_out()?;
+        }
+
+        let out = &self.out[self.out_pos..];
+        let len = std::cmp::min(out.len(), buf.len());
+        buf[..len].copy_from_slice(&out[..len]);
+        self.out_pos += len;
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encoded_len, Base64Encoder};
+
+    use std::io::{self, Read};
+
+    /// Returns at most `chunk` bytes per read.
+    struct ChunkedReader<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl Read for ChunkedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = std::cmp::min(buf.len(), self.chunk);
+            self.data.read(&mut buf[..len])
+        }
+    }
+
+    fn encode(data: &[u8], chunk: usize) -> String {
+        let mut encoded = String::new();
+        Base64Encoder::new(ChunkedReader { data, chunk })
+            .read_to_string(&mut encoded)
+            .unwrap();
+        encoded
+    }
+
+    #[test]
+    fn test_encode() {
+        for chunk in [1, 2, 5, 1024] {
+            assert_eq!(encode(b"", chunk), "");
+            assert_eq!(encode(b"f", chunk), "Zg==");
+            assert_eq!(encode(b"fo", chunk), "Zm8=");
+            assert_eq!(encode(b"foo", chunk), "Zm9v");
+            assert_eq!(encode(b"foob", chunk), "Zm9vYg==");
+            assert_eq!(encode(b"fooba", chunk), "Zm9vYmE=");
+            assert_eq!(encode(b"foobar", chunk), "Zm9vYmFy");
+        }
+    }
+
+    #[test]
+    fn test_line_breaks() {
+        let data: Vec<u8> = (0..=255).cycle().take(2000).collect();
+
+        for len in [0, 1, 56, 57, 58, 114, 1000, 2000] {
+            for chunk in [1, 7, 4096] {
+                let encoded = encode(&data[..len], chunk);
+                assert_eq!(encoded.len() as u64, encoded_len(len as u64), "len: {len}");
+
+                let lines: Vec<&str> = encoded.split("\r\n").collect();
+                assert!(lines.iter().all(|line| line.len() <= 76));
+                assert!(lines[..lines.len() - 1].iter().all(|line| line.len() == 76));
+            }
+        }
+    }
+}
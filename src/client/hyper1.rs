@@ -0,0 +1,132 @@
+//! Client-side integration with [Hyper 1.x](https://github.com/hyperium/hyper).
+//! Enabled with both the `hyper1` and `client-async` features.
+//!
+//! The async body of a lazy request, `AsyncPreparedFields`, implements `http_body::Body`, so it
+//! can be sent with `hyper::client::conn` or any other client built on `http::Request`:
+//!
+//! ```rust
+//! use mpart::client::lazy::Multipart;
+//!
+//! let mut multipart = Multipart::new();
+//! multipart.add_text("text", "some text");
+//!
+//! let req = multipart
+//!     .into_async_body()
+//!     .unwrap()
+//!     .into_request(hyper1::Request::post("http://localhost/upload"))
+//!     .unwrap();
+//!
+//! assert!(req.headers()["content-type"]
+//!     .to_str()
+//!     .unwrap()
+//!     .starts_with("multipart/form-data; boundary="));
+//! // pass `req` to `hyper::client::conn::http1::SendRequest::send_request()`
+//! ```
+use hyper1::body::{Body, Bytes, Frame, SizeHint};
+use hyper1::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper1::http::request::Builder;
+use hyper1::Request;
+
+use futures_core::Stream;
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::lazy::AsyncPreparedFields;
+
+impl<'d> AsyncPreparedFields<'d> {
+    /// #### Feature: `hyper1`
+    /// Set the `Content-Type` header, and `Content-Length` if the length of the body is known,
+    /// on `builder` and build the request with `self` as its body.
+    ///
+    /// ### Errors
+    /// If `builder` was given an invalid method, URI or header.
+    pub fn into_request(self, builder: Builder) -> hyper1::http::Result<Request<Self>> {
+        let builder = builder.header(CONTENT_TYPE, self.content_type());
+
+        let builder = match self.content_len() {
+            Some(len) => builder.header(CONTENT_LENGTH, len),
+            None => builder,
+        };
+
+        builder.body(self)
+    }
+}
+
+/// #### Feature: `hyper1`
+impl Body for AsyncPreparedFields<'_> {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<io::Result<Frame<Bytes>>>> {
+        self.poll_next(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data)))
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.content_len()
+            .map_or_else(SizeHint::default, SizeHint::with_exact)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::client::lazy::Multipart;
+
+    use http_body_util::BodyExt;
+    use hyper1::body::Body;
+    use hyper1::Request;
+
+    #[test]
+    fn test_hyper1_body() {
+        let mut multipart = Multipart::new();
+        multipart.add_text("text", "some text").add_async_stream(
+            "stream",
+            &b"stream data"[..],
+            Some("a.bin"),
+            None,
+        );
+
+        let body = multipart.into_async_body().unwrap();
+        let boundary = body.boundary().to_owned();
+
+        let req = body
+            .into_request(Request::post("http://localhost/upload"))
+            .unwrap();
+        assert_eq!(
+            req.headers()["content-type"],
+            format!("multipart/form-data; boundary={boundary}")
+        );
+        // a stream has no known length
+        assert!(req.headers().get("content-length").is_none());
+        assert_eq!(req.body().size_hint().exact(), None);
+
+        let body = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(req.into_body().collect())
+            .unwrap()
+            .to_bytes();
+
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("some text"));
+        assert!(body.contains("stream data"));
+        assert!(body.ends_with(&format!("--{boundary}--")));
+
+        // text fields alone have a known length
+        let mut multipart = Multipart::new();
+        multipart.add_text("text", "some text");
+        let body = multipart.into_async_body().unwrap();
+        let len = body.content_len().unwrap();
+
+        let req = body
+            .into_request(Request::post("http://localhost/upload"))
+            .unwrap();
+        assert_eq!(req.headers()["content-length"], len.to_string());
+        assert_eq!(req.body().size_hint().exact(), Some(len));
+    }
+}
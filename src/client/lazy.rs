@@ -8,6 +8,7 @@ use std::io::prelude::*;
 use std::io::Cursor;
 use std::{fmt, io};
 
+use super::base64::Base64Encoder;
 use super::{HttpRequest, HttpStream};
 
 macro_rules! try_lazy (
@@ -25,6 +26,12 @@ macro_rules! try_lazy (
     )
 );
 
+#[cfg(feature = "client-async")]
+mod async_body;
+
+#[cfg(feature = "client-async")]
+pub use self::async_body::AsyncPreparedFields;
+
 /// A `LazyError` wrapping `std::io::Error`.
 #[allow(clippy::module_name_repetitions)]
 pub type LazyIoError<'a> = LazyError<'a, io::Error>;
@@ -117,9 +124,33 @@ impl<'a, E: fmt::Display> fmt::Display for LazyError<'a, E> {
 /// #### Lifetimes
 /// * `'n`: Lifetime for field **n**ames; will only escape this struct in `LazyIoError<'n>`.
 /// * `'d`: Lifetime for **d**ata: will only escape this struct in `PreparedFields<'d>`.
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct Multipart<'n, 'd> {
     fields: Vec<Field<'n, 'd>>,
+    mime_guesser: Option<MimeGuesser<'d>>,
+}
+
+/// Guesses the `Content-Type` of a file added by path; see `Multipart::with_mime_guesser()`.
+type MimeGuesser<'d> = Box<dyn Fn(&Path) -> Mime + 'd>;
+
+/// The guesser set with `with_mime_guesser()`, or the built-in one.
+fn guess_mime<'a, 'd>(guesser: Option<&'a MimeGuesser<'d>>) -> &'a (dyn Fn(&Path) -> Mime + 'd) {
+    match guesser {
+        Some(guesser) => guesser,
+        None => &super::guess_mime,
+    }
+}
+
+impl<'n, 'd> fmt::Debug for Multipart<'n, 'd> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multipart")
+            .field("fields", &self.fields)
+            .field(
+                "mime_guesser",
+                &self.mime_guesser.as_ref().map(|_| "Box<Fn(&Path) -> Mime>"),
+            )
+            .finish()
+    }
 }
 
 impl<'n, 'd> Multipart<'n, 'd> {
@@ -129,6 +160,21 @@ impl<'n, 'd> Multipart<'n, 'd> {
         Multipart::default()
     }
 
+    /// Use `guesser` to determine the `Content-Type` of files added by path, e.g. with
+    /// `add_file()`, in place of the built-in mapping of file extensions, so extensions it
+    /// doesn't know can be given a type. The built-in mapping is available as
+    /// `mime_guess::from_path()`, to fall back on.
+    ///
+    /// The guesser is called when the request is serialized, for every file added by path.
+    #[must_use]
+    pub fn with_mime_guesser<G>(mut self, guesser: G) -> Self
+    where
+        G: Fn(&Path) -> Mime + 'd,
+    {
+        self.mime_guesser = Some(Box::new(guesser));
+        self
+    }
+
     /// Add a text field to this request.
     pub fn add_text<N, T>(&mut self, name: N, text: T) -> &mut Self
     where
@@ -137,24 +183,77 @@ impl<'n, 'd> Multipart<'n, 'd> {
     {
         self.fields.push(Field {
             name: name.into(),
+            disposition: super::FORM_DATA.into(),
             data: Data::Text(text.into()),
+            base64: false,
         });
 
         self
     }
 
-    /// Add a file field to this request.
+    /// Add a file field to this request, with its `Content-Type` guessed from its extension
+    /// or with the function given to `with_mime_guesser()`.
     ///
     /// ### Note
     /// Does not check if `path` exists.
     pub fn add_file<N, P>(&mut self, name: N, path: P) -> &mut Self
+    where
+        N: Into<Cow<'n, str>>,
+        P: IntoCowPath<'d>,
+    {
+        self.add_file_with_disposition(name, path, super::FORM_DATA)
+    }
+
+    /// Add a file field to this request with the given disposition type in place of `form-data`,
+    /// e.g. `attachment` or `inline` for consumers of `multipart/mixed` payloads.
+    ///
+    /// The field is then serialized with
+    /// `Content-Disposition: {disposition}; name="{name}"; filename="{filename}"`.
+    /// `disposition` must be a valid header token; otherwise serializing the request returns
+    /// an error of kind `InvalidInput` for this field.
+    ///
+    /// ### Note
+    /// Does not check if `path` exists.
+    pub fn add_file_with_disposition<N, P, D>(
+        &mut self,
+        name: N,
+        path: P,
+        disposition: D,
+    ) -> &mut Self
+    where
+        N: Into<Cow<'n, str>>,
+        P: IntoCowPath<'d>,
+        D: Into<Cow<'n, str>>,
+    {
+        self.fields.push(Field {
+            name: name.into(),
+            disposition: disposition.into(),
+            data: Data::File(path.into_cow_path()),
+            base64: false,
+        });
+
+        self
+    }
+
+    /// Add a file field to this request, encoding its contents as base64 and
+    /// setting `Content-Transfer-Encoding: base64`.
+    ///
+    /// The encoded data is broken into lines of 76 characters as per RFC 2045. This is only
+    /// necessary for servers which expect it; RFC 7578 deprecates `Content-Transfer-Encoding`
+    /// for `multipart/form-data` and it roughly adds a third to the size of the request.
+    ///
+    /// ### Note
+    /// Does not check if `path` exists.
+    pub fn add_file_base64<N, P>(&mut self, name: N, path: P) -> &mut Self
     where
         N: Into<Cow<'n, str>>,
         P: IntoCowPath<'d>,
     {
         self.fields.push(Field {
             name: name.into(),
+            disposition: super::FORM_DATA.into(),
             data: Data::File(path.into_cow_path()),
+            base64: true,
         });
 
         self
@@ -173,13 +272,97 @@ impl<'n, 'd> Multipart<'n, 'd> {
         R: Read + 'd,
         F: Into<Cow<'n, str>>,
     {
+        self.push_stream(name.into(), Box::new(stream), filename, mime, false)
+    }
+
+    /// Add a generic stream field to this request, encoding its contents as base64 and
+    /// setting `Content-Transfer-Encoding: base64`.
+    ///
+    /// See `add_file_base64()` for more info.
+    pub fn add_stream_base64<N, R, F>(
+        &mut self,
+        name: N,
+        stream: R,
+        filename: Option<F>,
+        mime: Option<Mime>,
+    ) -> &mut Self
+    where
+        N: Into<Cow<'n, str>>,
+        R: Read + 'd,
+        F: Into<Cow<'n, str>>,
+    {
+        self.push_stream(name.into(), Box::new(stream), filename, mime, true)
+    }
+
+    /// Add several files under one field, serialized as a nested `multipart/mixed` part with
+    /// its own boundary as described in [RFC 2388, Section 4.2][rfc2388-4.2]:
+    ///
+    /// ```text
+    /// Content-Disposition: form-data; name="{name}"
+    /// Content-Type: multipart/mixed; boundary={inner boundary}
+    ///
+    /// --{inner boundary}
+    /// Content-Disposition: file; filename="{filename}"
+    /// Content-Type: {mime}
+    ///
+    /// {contents}
+    /// --{inner boundary}--
+    /// ```
+    ///
+    /// `mime` defaults to `application/octet-stream`. As with `add_stream()`, the length of
+    /// the request is then not known up front.
+    ///
+    /// RFC 7578 deprecates this form, so only use it for servers which expect it. `files`
+    /// shouldn't be empty, as a nested part without any files isn't valid either
+    /// ([RFC 2046, Section 5.1.1](https://tools.ietf.org/html/rfc2046#section-5.1.1)).
+    ///
+    /// [rfc2388-4.2]: https://tools.ietf.org/html/rfc2388#section-4.2
+    pub fn add_file_group<N, F, R>(
+        &mut self,
+        name: N,
+        files: Vec<(F, R, Option<Mime>)>,
+    ) -> &mut Self
+    where
+        N: Into<Cow<'n, str>>,
+        F: Into<Cow<'n, str>>,
+        R: Read + 'd,
+    {
+        let files = files
+            .into_iter()
+            .map(|(filename, stream, mime)| Stream {
+                filename: Some(filename.into()),
+                content_type: mime.unwrap_or(mime::APPLICATION_OCTET_STREAM),
+                stream: Box::new(stream),
+            })
+            .collect();
+
         self.fields.push(Field {
             name: name.into(),
+            disposition: super::FORM_DATA.into(),
+            data: Data::Group(files),
+            base64: false,
+        });
+
+        self
+    }
+
+    fn push_stream<F: Into<Cow<'n, str>>>(
+        &mut self,
+        name: Cow<'n, str>,
+        stream: Box<dyn Read + 'd>,
+        filename: Option<F>,
+        mime: Option<Mime>,
+        base64: bool,
+    ) -> &mut Self {
+        self.fields.push(Field {
+            name,
+            disposition: super::FORM_DATA.into(),
             data: Data::Stream(Stream {
                 content_type: mime.unwrap_or(mime::APPLICATION_OCTET_STREAM),
-                filename: filename.map(|f| std::convert::Into::into(f)),
-                stream: Box::new(stream),
+                filename: filename.map(Into::into),
+                stream,
             }),
+            base64,
         });
 
         self
@@ -210,22 +393,40 @@ impl<'n, 'd> Multipart<'n, 'd> {
     ///
     /// During this step, if any files were added by path then they will be opened for reading
     /// and their length measured.
+    ///
+    /// Files and streams are not buffered; their contents are read on-demand as the adaptor
+    /// is read from, interleaved with the boundary and header bytes. Only the text fields
+    /// and the per-field headers are held in memory, so this is suitable for arbitrarily large
+    /// uploads, e.g. as a chunked body.
+    ///
+    /// The result also provides the `Content-Type` and `Content-Length` header values,
+    /// for sending the body without an `HttpRequest` implementation.
+    ///
+    /// Fields added with `add_async_stream()` are not supported and will return an error
+    /// of kind `Unsupported`; use `into_async_body()` instead.
     #[allow(clippy::missing_errors_doc)]
     pub fn prepare(&mut self) -> LazyIoResult<'n, PreparedFields<'d>> {
-        PreparedFields::from_fields(&mut self.fields)
+        let guess_mime = guess_mime(self.mime_guesser.as_ref());
+        PreparedFields::from_fields(&mut self.fields, guess_mime)
     }
 }
 
 #[derive(Debug)]
 struct Field<'n, 'd> {
     name: Cow<'n, str>,
+    disposition: Cow<'n, str>,
     data: Data<'n, 'd>,
+    base64: bool,
 }
 
 enum Data<'n, 'd> {
     Text(Cow<'d, str>),
     File(Cow<'d, Path>),
     Stream(Stream<'n, 'd>),
+    /// Files to be sent as a nested `multipart/mixed` part.
+    Group(Vec<Stream<'n, 'd>>),
+    #[cfg(feature = "client-async")]
+    AsyncStream(async_body::AsyncStream<'n, 'd>),
 }
 
 impl<'n, 'd> fmt::Debug for Data<'n, 'd> {
@@ -234,6 +435,9 @@ impl<'n, 'd> fmt::Debug for Data<'n, 'd> {
             Data::Text(ref text) => write!(f, "Data::Text({text:?})"),
             Data::File(ref path) => write!(f, "Data::File({path:?})"),
             Data::Stream(_) => f.write_str("Data::Stream(Box<Read>)"),
+            Data::Group(ref files) => write!(f, "Data::Group([Box<Read>; {}])", files.len()),
+            #[cfg(feature = "client-async")]
+            Data::AsyncStream(_) => f.write_str("Data::AsyncStream(Box<AsyncRead>)"),
         }
     }
 }
@@ -261,7 +465,10 @@ pub struct PreparedFields<'d> {
 }
 
 impl<'d> PreparedFields<'d> {
-    fn from_fields<'n>(fields: &mut Vec<Field<'n, 'd>>) -> Result<Self, LazyIoError<'n>> {
+    fn from_fields<'n>(
+        fields: &mut Vec<Field<'n, 'd>>,
+        guess_mime: &dyn Fn(&Path) -> Mime,
+    ) -> Result<Self, LazyIoError<'n>> {
         log::debug!("Field count: {}", fields.len());
 
         // One of the two RFCs specifies that any bytes before the first boundary are to be
@@ -274,16 +481,29 @@ impl<'d> PreparedFields<'d> {
         let mut use_len = true;
 
         for field in fields.drain(..) {
+            try_lazy!(field.name, super::check_disposition(&field.disposition));
+
             match field.data {
-                Data::Text(text) => write!(
-                    text_data,
-                    "{}\r\nContent-Disposition: form-data; \
-                     name=\"{}\"\r\n\r\n{}",
-                    boundary, field.name, text
-                )
-                .unwrap(),
+                Data::Text(text) => {
+                    write!(text_data, "{boundary}\r\n").unwrap();
+                    super::write_content_disposition(
+                        &mut text_data,
+                        &field.disposition,
+                        &field.name,
+                        None,
+                    )
+                    .unwrap();
+                    write!(text_data, "\r\n\r\n{text}").unwrap();
+                }
                 Data::File(file) => {
-                    let (stream, len) = PreparedField::from_path(field.name, &file, &boundary)?;
+                    let (stream, len) = PreparedField::from_path(
+                        field.name,
+                        &field.disposition,
+                        &file,
+                        guess_mime(&file),
+                        &boundary,
+                        field.base64,
+                    )?;
                     content_len += len;
                     streams.push(stream);
                 }
@@ -292,10 +512,32 @@ impl<'d> PreparedFields<'d> {
 
                     streams.push(PreparedField::from_stream(
                         &field.name,
+                        &field.disposition,
                         &boundary,
                         &stream.content_type,
                         stream.filename.as_deref(),
                         stream.stream,
+                        field.base64,
+                    ));
+                }
+                Data::Group(files) => {
+                    use_len = false;
+
+                    streams.push(PreparedField::from_group(
+                        &field.name,
+                        &field.disposition,
+                        &boundary,
+                        files,
+                    ));
+                }
+                #[cfg(feature = "client-async")]
+                Data::AsyncStream(_) => {
+                    return Err(LazyError::with_field(
+                        field.name,
+                        io::Error::new(
+                            io::ErrorKind::Unsupported,
+                            "fields added with `add_async_stream()` require `into_async_body()`",
+                        ),
                     ));
                 }
             }
@@ -308,7 +550,7 @@ impl<'d> PreparedFields<'d> {
             boundary.push_str("--");
         }
 
-        content_len += boundary.len() as u64;
+        content_len += text_data.len() as u64 + boundary.len() as u64;
 
         Ok(PreparedFields {
             text_data: Cursor::new(text_data),
@@ -333,6 +575,16 @@ impl<'d> PreparedFields<'d> {
         // Get just the bare boundary string
         &boundary[4..boundary.len() - 2]
     }
+
+    /// Get the value of the `Content-Type` header to send with this body,
+    /// i.e. `multipart/form-data; boundary={boundary}`.
+    ///
+    /// Together with `content_len()` for the `Content-Length` header, this is all that's needed
+    /// to send the request over any transport.
+    #[must_use]
+    pub fn content_type(&self) -> String {
+        super::form_data_content_type(self.boundary())
+    }
 }
 
 impl<'d> Read for PreparedFields<'d> {
@@ -374,15 +626,30 @@ struct PreparedField<'d> {
 impl<'d> PreparedField<'d> {
     fn from_path<'n>(
         name: Cow<'n, str>,
+        disposition: &str,
         path: &Path,
+        content_type: Mime,
         boundary: &str,
+        base64: bool,
     ) -> Result<(Self, u64), LazyIoError<'n>> {
-        let (content_type, filename) = super::mime_filename(path);
+        let filename = super::opt_filename(path);
 
         let file = try_lazy!(name, File::open(path));
-        let content_len = try_lazy!(name, file.metadata()).len();
+        let mut content_len = try_lazy!(name, file.metadata()).len();
 
-        let stream = Self::from_stream(&name, boundary, &content_type, filename, Box::new(file));
+        if base64 {
+            content_len = super::base64::encoded_len(content_len);
+        }
+
+        let stream = Self::from_stream(
+            &name,
+            disposition,
+            boundary,
+            &content_type,
+            filename,
+            Box::new(file),
+            base64,
+        );
 
         let content_len = content_len + (stream.header.get_ref().len() as u64);
 
@@ -391,30 +658,64 @@ impl<'d> PreparedField<'d> {
 
     fn from_stream(
         name: &str,
+        disposition: &str,
         boundary: &str,
         content_type: &Mime,
         filename: Option<&str>,
         stream: Box<dyn Read + 'd>,
+        base64: bool,
     ) -> Self {
-        let mut header = Vec::new();
+        let header = stream_header(name, disposition, boundary, content_type, filename, base64);
 
-        write!(
-            header,
-            "{boundary}\r\nContent-Disposition: form-data; name=\"{name}\""
-        )
-        .unwrap();
-
-        if let Some(filename) = filename {
-            write!(header, "; filename=\"{filename}\"").unwrap();
-        }
-
-        write!(header, "\r\nContent-Type: {content_type}\r\n\r\n").unwrap();
+        let stream = if base64 {
+            Box::new(Base64Encoder::new(stream))
+        } else {
+            stream
+        };
 
         PreparedField {
             header: Cursor::new(header),
             stream,
         }
     }
+
+    fn from_group(
+        name: &str,
+        disposition: &str,
+        boundary: &str,
+        files: Vec<Stream<'_, 'd>>,
+    ) -> Self {
+        let inner_boundary = super::gen_boundary();
+        let content_type = format!("multipart/mixed; boundary={inner_boundary}")
+            .parse()
+            .expect("generated boundary should be a valid `Mime` parameter");
+
+        let mut stream: Box<dyn Read + 'd> = Box::new(io::empty());
+        let mut delimiter = "";
+
+        for file in files {
+            let mut header = Vec::new();
+            write!(header, "{delimiter}--{inner_boundary}\r\n").unwrap();
+            super::write_file_disposition(&mut header, file.filename.as_deref()).unwrap();
+            write!(header, "\r\nContent-Type: {}\r\n\r\n", file.content_type).unwrap();
+
+            stream = Box::new(stream.chain(Cursor::new(header)).chain(file.stream));
+            delimiter = "\r\n";
+        }
+
+        let end = format!("{delimiter}--{inner_boundary}--");
+        let stream = Box::new(stream.chain(Cursor::new(end)));
+
+        Self::from_stream(
+            name,
+            disposition,
+            boundary,
+            &content_type,
+            None,
+            stream,
+            false,
+        )
+    }
 }
 
 impl<'d> Read for PreparedField<'d> {
@@ -475,6 +776,29 @@ impl<'a> IntoCowPath<'a> for &'a str {
     }
 }
 
+/// Write the boundary and headers preceding the data of a file or stream field.
+fn stream_header(
+    name: &str,
+    disposition: &str,
+    boundary: &str,
+    content_type: &Mime,
+    filename: Option<&str>,
+    base64: bool,
+) -> Vec<u8> {
+    let mut header = Vec::new();
+
+    write!(header, "{boundary}\r\n").unwrap();
+    super::write_content_disposition(&mut header, disposition, name, filename).unwrap();
+    write!(header, "\r\nContent-Type: {content_type}").unwrap();
+
+    if base64 {
+        header.extend_from_slice(b"\r\nContent-Transfer-Encoding: base64");
+    }
+
+    header.extend_from_slice(b"\r\n\r\n");
+    header
+}
+
 fn cursor_at_end<T: AsRef<[u8]>>(cursor: &Cursor<T>) -> bool {
     cursor.position() == (cursor.get_ref().as_ref().len() as u64)
 }
@@ -533,7 +857,6 @@ mod hyper {
     impl<'d> super::PreparedFields<'d> {
         /// #### Feature: `hyper`
         /// Convert `self` to `hyper::client::Body`.
-        #[cfg_attr(feature = "clippy", warn(wrong_self_convention))]
         pub fn to_body<'b>(&'b mut self) -> Body<'b>
         where
             'd: 'b,
@@ -546,3 +869,314 @@ mod hyper {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Multipart;
+
+    use std::cell::Cell;
+    use std::io::{self, Read};
+    use std::rc::Rc;
+
+    /// Yields `remaining` bytes without holding them in memory, tracking how many were produced.
+    struct FakeReader {
+        remaining: u64,
+        produced: Rc<Cell<u64>>,
+    }
+
+    impl Read for FakeReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = std::cmp::min(buf.len() as u64, self.remaining) as usize;
+            buf[..len].iter_mut().for_each(|b| *b = b'a');
+            self.remaining -= len as u64;
+            self.produced.set(self.produced.get() + len as u64);
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test_prepare_streams_lazily() {
+        const LEN: u64 = 64 * 1024 * 1024;
+        const CHUNK: usize = 4096;
+
+        let produced = Rc::new(Cell::new(0));
+
+        let mut multipart = Multipart::new();
+        multipart.add_text("text", "hello");
+        multipart.add_stream(
+            "file",
+            FakeReader {
+                remaining: LEN,
+                produced: produced.clone(),
+            },
+            Some("large.bin"),
+            None,
+        );
+
+        let mut reader = multipart.prepare().unwrap();
+        let mut buf = [0u8; CHUNK];
+        let mut consumed = 0u64;
+
+        loop {
+            let read = reader.read(&mut buf).unwrap();
+            if read == 0 {
+                break;
+            }
+            consumed += read as u64;
+            // the stream must never be read further ahead than what we've consumed
+            assert!(produced.get() <= consumed);
+        }
+
+        assert_eq!(produced.get(), LEN);
+        assert!(consumed > LEN);
+    }
+
+    #[test]
+    fn test_file_disposition() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.txt");
+        std::fs::write(&path, "contents").unwrap();
+
+        let mut multipart = Multipart::new();
+        multipart.add_file_with_disposition("report", &*path, "attachment");
+
+        let mut prepared = multipart.prepare().unwrap();
+        let mut body = String::new();
+        prepared.read_to_string(&mut body).unwrap();
+
+        assert!(body.contains(
+            "\r\nContent-Disposition: attachment; name=\"report\"; filename=\"report.txt\"\r\n"
+        ));
+
+        for disposition in ["", "attachment; x=y", "inline\r\nX-Injected: 1"] {
+            let mut multipart = Multipart::new();
+            multipart.add_file_with_disposition("report", &*path, disposition);
+
+            let err = multipart.prepare().err().unwrap();
+            assert_eq!(err.field_name.as_deref(), Some("report"));
+            assert_eq!(err.error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_mime_guesser() {
+        let dir = tempfile::tempdir().unwrap();
+        let proto = dir.path().join("message.proto");
+        let text = dir.path().join("notes.txt");
+        std::fs::write(&proto, "contents").unwrap();
+        std::fs::write(&text, "contents").unwrap();
+
+        let mut multipart = Multipart::new().with_mime_guesser(|path| {
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("proto") => "application/x-protobuf".parse().unwrap(),
+                _ => mime_guess::from_path(path).first_or_octet_stream(),
+            }
+        });
+        multipart.add_file("proto", &*proto);
+        multipart.add_file("text", &*text);
+
+        let mut prepared = multipart.prepare().unwrap();
+        let mut body = String::new();
+        prepared.read_to_string(&mut body).unwrap();
+
+        assert!(body.contains(
+            "\r\nContent-Disposition: form-data; name=\"proto\"; filename=\"message.proto\"\r\n\
+             Content-Type: application/x-protobuf\r\n"
+        ));
+        assert!(body.contains(
+            "\r\nContent-Disposition: form-data; name=\"text\"; filename=\"notes.txt\"\r\n\
+             Content-Type: text/plain\r\n"
+        ));
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_prepared_headers() {
+        use crate::server::Multipart as ServerMultipart;
+
+        let mut multipart = Multipart::new();
+        multipart.add_text("text", "hello");
+        multipart.add_stream("file", &b"file data"[..], Some("file.txt"), None);
+
+        let mut prepared = multipart.prepare().unwrap();
+
+        let content_type = prepared.content_type();
+        assert_eq!(
+            content_type,
+            format!("multipart/form-data; boundary={}", prepared.boundary())
+        );
+        let mime: mime::Mime = content_type.parse().unwrap();
+        let boundary = mime.get_param(mime::BOUNDARY).unwrap().to_string();
+
+        // a stream's length isn't known
+        assert_eq!(prepared.content_len(), None);
+
+        let mut body = Vec::new();
+        prepared.read_to_end(&mut body).unwrap();
+
+        let mut server = ServerMultipart::with_body(&body[..], boundary);
+        let mut fields = Vec::new();
+
+        server
+            .foreach_entry(|mut field| {
+                let mut data = String::new();
+                field.data.read_to_string(&mut data).unwrap();
+                fields.push((field.headers.name.to_string(), data));
+            })
+            .unwrap();
+
+        fields.sort();
+        assert_eq!(
+            fields,
+            [
+                ("file".to_owned(), "file data".to_owned()),
+                ("text".to_owned(), "hello".to_owned())
+            ]
+        );
+
+        let mut text_only = Multipart::new();
+        text_only.add_text("text", "hello");
+        let mut prepared = text_only.prepare().unwrap();
+        let content_len = prepared.content_len().unwrap();
+        assert_eq!(
+            content_len,
+            io::copy(&mut prepared, &mut io::sink()).unwrap()
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_base64_round_trip() {
+        use crate::server::base64::Base64Decoder;
+        use crate::server::Multipart as ServerMultipart;
+
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        std::fs::write(&path, &data).unwrap();
+
+        let mut multipart = Multipart::new();
+        multipart.add_file_base64("file", &*path);
+        multipart.add_stream_base64("stream", &data[..], Some("stream.bin"), None);
+
+        let mut prepared = multipart.prepare().unwrap();
+        let boundary = prepared.boundary().to_owned();
+        let mut body = Vec::new();
+        prepared.read_to_end(&mut body).unwrap();
+
+        let mut server = ServerMultipart::with_body(&body[..], &*boundary);
+        let mut names = Vec::new();
+
+        server
+            .foreach_entry(|mut field| {
+                let mut decoded = Vec::new();
+                field.data.read_to_end(&mut decoded).unwrap();
+                assert_eq!(decoded, data);
+                names.push(field.headers.name.to_string());
+            })
+            .unwrap();
+
+        names.sort();
+        assert_eq!(names, ["file", "stream"]);
+
+        let mut server = ServerMultipart::with_body(&body[..], boundary);
+        server.set_decode_transfer_encoding(false);
+
+        server
+            .foreach_entry(|mut field| {
+                let mut encoded = Vec::new();
+                field.data.read_to_end(&mut encoded).unwrap();
+
+                let mut decoder = Base64Decoder::default();
+                let mut decoded = Vec::new();
+                decoder.decode(&encoded, &mut decoded).unwrap();
+                decoder.finish().unwrap();
+                assert_eq!(decoded, data);
+            })
+            .unwrap();
+
+        // the encoded length of files is still known up front
+        let mut file_only = Multipart::new();
+        file_only.add_file_base64("file", &*path);
+        let mut prepared = file_only.prepare().unwrap();
+        let content_len = prepared.content_len().unwrap();
+        assert_eq!(
+            content_len,
+            io::copy(&mut prepared, &mut io::sink()).unwrap()
+        );
+
+        let body = String::from_utf8(body).unwrap();
+        assert_eq!(
+            body.matches("\r\nContent-Transfer-Encoding: base64\r\n")
+                .count(),
+            2
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_file_group_round_trip() {
+        use crate::server::boundary::BoundaryReader;
+        use crate::server::Multipart as ServerMultipart;
+
+        let mut multipart = Multipart::new();
+        multipart.add_text("text", "hello");
+        multipart.add_file_group(
+            "files",
+            vec![
+                ("file1.txt", &b"first file"[..], Some(mime::TEXT_PLAIN)),
+                ("file2.bin", &b"\r\nsecond file\r\n"[..], None),
+            ],
+        );
+
+        let mut prepared = multipart.prepare().unwrap();
+        assert_eq!(prepared.content_len(), None);
+
+        let boundary = prepared.boundary().to_owned();
+        let mut body = Vec::new();
+        prepared.read_to_end(&mut body).unwrap();
+
+        let mut server = ServerMultipart::with_body(&body[..], boundary);
+        let mut fields = Vec::new();
+
+        while let Some(field) = server.read_entry().unwrap() {
+            let name = field.headers.name.to_string();
+
+            if name == "text" {
+                fields.push(name);
+                continue;
+            }
+
+            let content_type = field.headers.content_type.clone().unwrap();
+            assert_eq!(content_type.essence_str(), "multipart/mixed");
+            let inner_boundary = content_type.get_param(mime::BOUNDARY).unwrap();
+
+            let mut inner = BoundaryReader::from_reader(field.data, inner_boundary.as_str());
+            let mut parts = Vec::new();
+
+            while inner.consume_boundary().unwrap() {
+                let mut part = String::new();
+                inner.read_to_string(&mut part).unwrap();
+                parts.push(part);
+            }
+
+            assert_eq!(
+                parts,
+                [
+                    "Content-Disposition: file; filename=\"file1.txt\"\r\n\
+                     Content-Type: text/plain\r\n\r\n\
+                     first file",
+                    "Content-Disposition: file; filename=\"file2.bin\"\r\n\
+                     Content-Type: application/octet-stream\r\n\r\n\
+                     \r\nsecond file\r\n",
+                ]
+            );
+
+            fields.push(name);
+        }
+
+        fields.sort();
+        assert_eq!(fields, ["files", "text"]);
+    }
+}
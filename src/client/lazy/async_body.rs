@@ -0,0 +1,467 @@
+//! Serializing a lazy request as an async body. Enabled with the `client-async` feature.
+use bytes::Bytes;
+use futures_core::Stream;
+use mime::Mime;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::{Data, Field, LazyError, LazyIoResult, Multipart};
+
+/// The size of the chunks yielded by `AsyncPreparedFields` as a `Stream`.
+const CHUNK_LEN: usize = 8 * 1024;
+
+type BoxAsyncRead<'d> = Pin<Box<dyn AsyncRead + Send + 'd>>;
+
+pub(super) struct AsyncStream<'n, 'd> {
+    filename: Option<Cow<'n, str>>,
+    content_type: Mime,
+    stream: BoxAsyncRead<'d>,
+}
+
+impl<'n, 'd> Multipart<'n, 'd> {
+    /// #### Feature: `client-async`
+    /// Add a field to this request which is read from an async stream, e.g. a `tokio::fs::File`
+    /// or the body of a response from another service.
+    ///
+    /// Such fields can only be serialized with `into_async_body()`.
+    pub fn add_async_stream<N, R, F>(
+        &mut self,
+        name: N,
+        stream: R,
+        filename: Option<F>,
+        mime: Option<Mime>,
+    ) -> &mut Self
+    where
+        N: Into<Cow<'n, str>>,
+        R: AsyncRead + Send + 'd,
+        F: Into<Cow<'n, str>>,
+    {
+        self.fields.push(Field {
+            name: name.into(),
+            disposition: super::super::FORM_DATA.into(),
+            data: Data::AsyncStream(AsyncStream {
+                content_type: mime.unwrap_or(mime::APPLICATION_OCTET_STREAM),
+                filename: filename.map(Into::into),
+                stream: Box::pin(stream),
+            }),
+            base64: false,
+        });
+
+        self
+    }
+
+    /// #### Feature: `client-async`
+    /// Consume this lazy request and return the serialized body as an adaptor implementing
+    /// `tokio::io::AsyncRead` and `Stream<Item = io::Result<Bytes>>`, to be used as the body
+    /// of an async HTTP client request.
+    ///
+    /// The boundary and header bytes are interleaved with reads of each field, so as with
+    /// `prepare()` only the text fields and headers are held in memory. Unlike
+    /// `prepare()`, the fields are serialized in the order they were added.
+    ///
+    /// Files added by path are opened now and read with `tokio::fs`, so the body must be
+    /// read from within a Tokio runtime.
+    ///
+    /// ### Errors
+    /// If a file added by path could not be opened, or a field was added with `add_stream()`,
+    /// `add_file_group()` or with base64 encoding, which are not supported here
+    /// (error kind `Unsupported`).
+    pub fn into_async_body(self) -> LazyIoResult<'n, AsyncPreparedFields<'d>> {
+        let guess_mime = super::guess_mime(self.mime_guesser.as_ref());
+        AsyncPreparedFields::from_fields(self.fields, guess_mime)
+    }
+}
+
+/// The result of [`Multipart::into_async_body()`](struct.Multipart.html#method.into_async_body).
+///
+/// Implements `tokio::io::AsyncRead` and `Stream<Item = io::Result<Bytes>>`, and contains
+/// the entire request body.
+pub struct AsyncPreparedFields<'d> {
+    parts: VecDeque<Part<'d>>,
+    boundary: String,
+    content_len: Option<u64>,
+    chunk: Vec<u8>,
+}
+
+enum Part<'d> {
+    Bytes(Bytes),
+    Stream(BoxAsyncRead<'d>),
+}
+
+impl<'d> AsyncPreparedFields<'d> {
+    fn from_fields<'n>(
+        fields: Vec<Field<'n, 'd>>,
+        guess_mime: &dyn Fn(&Path) -> Mime,
+    ) -> LazyIoResult<'n, Self> {
+        log::debug!("Field count: {}", fields.len());
+
+        let boundary = super::super::gen_boundary();
+        let field_boundary = format!("\r\n--{boundary}");
+
+        let mut parts = VecDeque::new();
+        // `None` once there is a field of unknown length
+        let mut content_len = Some(0u64);
+
+        fn push_bytes(
+            parts: &mut VecDeque<Part<'_>>,
+            content_len: &mut Option<u64>,
+            bytes: Vec<u8>,
+        ) {
+            *content_len = content_len.map(|len| len + bytes.len() as u64);
+            parts.push_back(Part::Bytes(bytes.into()));
+        }
+
+        let unsupported = |name, msg| {
+            Err(LazyError::with_field(
+                name,
+                io::Error::new(io::ErrorKind::Unsupported, msg),
+            ))
+        };
+
+        let is_empty = fields.is_empty();
+
+        for field in fields {
+            if field.base64 {
+                return unsupported(
+                    field.name,
+                    "base64 fields are not supported in async bodies",
+                );
+            }
+
+            try_lazy!(
+                field.name,
+                super::super::check_disposition(&field.disposition)
+            );
+
+            match field.data {
+                Data::Text(text) => {
+                    let mut bytes = Vec::new();
+                    write!(bytes, "{field_boundary}\r\n").unwrap();
+                    super::super::write_content_disposition(
+                        &mut bytes,
+                        &field.disposition,
+                        &field.name,
+                        None,
+                    )
+                    .unwrap();
+                    write!(bytes, "\r\n\r\n{text}").unwrap();
+                    push_bytes(&mut parts, &mut content_len, bytes);
+                }
+                Data::File(path) => {
+                    let content_type = guess_mime(&path);
+                    let filename = super::super::opt_filename(&path);
+
+                    let file = try_lazy!(field.name, File::open(&path));
+                    let len = try_lazy!(field.name, file.metadata()).len();
+
+                    push_bytes(
+                        &mut parts,
+                        &mut content_len,
+                        super::stream_header(
+                            &field.name,
+                            &field.disposition,
+                            &field_boundary,
+                            &content_type,
+                            filename,
+                            false,
+                        ),
+                    );
+
+                    content_len = content_len.map(|content_len| content_len + len);
+                    parts.push_back(Part::Stream(Box::pin(tokio::fs::File::from_std(file))));
+                }
+                Data::Stream(_) => {
+                    return unsupported(
+                        field.name,
+                        "fields added with `add_stream()` are not supported in async bodies; \
+                         use `add_async_stream()`",
+                    );
+                }
+                Data::Group(_) => {
+                    return unsupported(
+                        field.name,
+                        "fields added with `add_file_group()` are not supported in async bodies",
+                    );
+                }
+                Data::AsyncStream(stream) => {
+                    push_bytes(
+                        &mut parts,
+                        &mut content_len,
+                        super::stream_header(
+                            &field.name,
+                            &field.disposition,
+                            &field_boundary,
+                            &stream.content_type,
+                            stream.filename.as_deref(),
+                            false,
+                        ),
+                    );
+
+                    content_len = None;
+                    parts.push_back(Part::Stream(stream.stream));
+                }
+            }
+        }
+
+        // So we don't write a spurious end boundary
+        if !is_empty {
+            push_bytes(
+                &mut parts,
+                &mut content_len,
+                format!("{field_boundary}--").into_bytes(),
+            );
+        }
+
+        Ok(AsyncPreparedFields {
+            parts,
+            boundary,
+            content_len,
+            chunk: Vec::new(),
+        })
+    }
+
+    /// Get the content-length value for this set of fields, if applicable (no fields were
+    /// added with `add_async_stream()`).
+    #[must_use]
+    pub fn content_len(&self) -> Option<u64> {
+        self.content_len
+    }
+
+    /// Get the boundary that was used to serialize the request.
+    #[must_use]
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Get the value of the `Content-Type` header to send with this body;
+    /// see `PreparedFields::content_type()`.
+    #[must_use]
+    pub fn content_type(&self) -> String {
+        super::super::form_data_content_type(&self.boundary)
+    }
+}
+
+impl AsyncRead for AsyncPreparedFields<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        // `buf` may already contain data from the caller
+        let start = buf.filled().len();
+
+        while buf.remaining() > 0 {
+            let Some(part) = self.parts.front_mut() else {
+                break;
+            };
+
+            match part {
+                Part::Bytes(bytes) => {
+                    let len = bytes.len().min(buf.remaining());
+                    buf.put_slice(&bytes.split_to(len));
+
+                    if bytes.is_empty() {
+                        self.parts.pop_front();
+                    }
+                }
+                Part::Stream(stream) => {
+                    let filled = buf.filled().len();
+
+                    match stream.as_mut().poll_read(cx, buf) {
+                        // return what we have so far, if anything
+                        Poll::Pending if filled > start => break,
+                        Poll::Ready(Ok(())) if buf.filled().len() == filled => {
+                            self.parts.pop_front();
+                        }
+                        Poll::Ready(Ok(())) => break,
+                        res => return res,
+                    }
+                }
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Stream for AsyncPreparedFields<'_> {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            match this.parts.front_mut() {
+                None => return Poll::Ready(None),
+                Some(Part::Bytes(_)) => {
+                    let Some(Part::Bytes(bytes)) = this.parts.pop_front() else {
+                        unreachable!()
+                    };
+                    return Poll::Ready(Some(Ok(bytes)));
+                }
+                Some(Part::Stream(stream)) => {
+                    this.chunk.resize(CHUNK_LEN, 0);
+                    let mut buf = ReadBuf::new(&mut this.chunk);
+
+                    match stream.as_mut().poll_read(cx, &mut buf) {
+                        Poll::Ready(Ok(())) if buf.filled().is_empty() => {
+                            this.parts.pop_front();
+                        }
+                        Poll::Ready(Ok(())) => {
+                            return Poll::Ready(Some(Ok(Bytes::copy_from_slice(buf.filled()))))
+                        }
+                        Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e))),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for AsyncPreparedFields<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncPreparedFields")
+            .field("parts", &self.parts.len())
+            .field("boundary", &self.boundary)
+            .field("content_len", &self.content_len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::Multipart;
+    use super::AsyncPreparedFields;
+
+    use futures_core::Stream;
+    use tokio::io::AsyncReadExt;
+
+    use std::future::{poll_fn, Future};
+    use std::io::{self, Write};
+    use std::path::Path;
+    use std::pin::Pin;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn read_to_end(mut body: AsyncPreparedFields<'_>) -> Vec<u8> {
+        block_on(async move {
+            let mut data = Vec::new();
+            body.read_to_end(&mut data).await.unwrap();
+            data
+        })
+    }
+
+    fn collect_chunks(mut body: AsyncPreparedFields<'_>) -> Vec<u8> {
+        block_on(async move {
+            let mut data = Vec::new();
+            while let Some(chunk) = poll_fn(|cx| Pin::new(&mut body).poll_next(cx)).await {
+                data.extend_from_slice(&chunk.unwrap());
+            }
+            data
+        })
+    }
+
+    fn request(file: &Path) -> Multipart<'static, 'static> {
+        let mut request = Multipart::new();
+        request
+            .add_text("text", "text value")
+            .add_file("file", file.to_owned())
+            .add_async_stream(
+                "stream",
+                &b"async stream value"[..],
+                Some("stream.bin"),
+                None,
+            );
+        request
+    }
+
+    #[test]
+    fn test_async_body() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"file value").unwrap();
+
+        let collect: [for<'a> fn(AsyncPreparedFields<'a>) -> Vec<u8>; 2] =
+            [read_to_end, collect_chunks];
+
+        for collect in collect {
+            let body = request(file.path()).into_async_body().unwrap();
+            let boundary = body.boundary().to_owned();
+            assert_eq!(body.content_len(), None);
+
+            let body = String::from_utf8(collect(body)).unwrap();
+            assert!(body.starts_with(&format!("\r\n--{boundary}\r\n")));
+            assert!(body.ends_with(&format!("\r\n--{boundary}--")));
+
+            // fields are written in the order they were added
+            let text = body.find("text value").unwrap();
+            let file = body.find("file value").unwrap();
+            let stream = body.find("async stream value").unwrap();
+            assert!(text < file && file < stream);
+
+            assert!(body.contains(
+                "Content-Disposition: form-data; name=\"stream\"; filename=\"stream.bin\"\r\n\
+                 Content-Type: application/octet-stream\r\n\r\n"
+            ));
+
+            #[cfg(feature = "server")]
+            {
+                let mut multipart =
+                    crate::server::Multipart::with_body(body.as_bytes(), boundary.as_str());
+                let mut names = Vec::new();
+                multipart
+                    .foreach_entry(|field| names.push(field.headers.name.to_string()))
+                    .unwrap();
+                assert_eq!(names, ["text", "file", "stream"]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_content_len() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"file value").unwrap();
+
+        let mut request = Multipart::new();
+        request
+            .add_text("text", "text value")
+            .add_file("file", file.path().to_owned());
+
+        let body = request.into_async_body().unwrap();
+        let content_len = body.content_len().unwrap();
+        assert_eq!(read_to_end(body).len() as u64, content_len);
+
+        assert_eq!(
+            Multipart::new().into_async_body().unwrap().content_len(),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_unsupported_fields() {
+        let mut request = Multipart::new();
+        request.add_stream("sync", &b"value"[..], None::<&str>, None);
+        let err = request.into_async_body().unwrap_err();
+        assert_eq!(err.field_name.as_deref(), Some("sync"));
+        assert_eq!(err.error.kind(), io::ErrorKind::Unsupported);
+
+        let mut request = Multipart::new();
+        request.add_async_stream("async", &b"value"[..], None::<&str>, None);
+        let err = request.prepare().err().unwrap();
+        assert_eq!(err.field_name.as_deref(), Some("async"));
+        assert_eq!(err.error.kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -4,17 +4,26 @@
 use mime::Mime;
 
 use std::borrow::Cow;
+use std::fmt::Write as _;
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 
 use std::path::Path;
 
+#[cfg(feature = "client-async")]
+pub mod async_;
+
 #[cfg(feature = "hyper")]
 pub mod hyper;
 
+#[cfg(all(feature = "hyper1", feature = "client-async"))]
+pub mod hyper1;
+
 pub mod lazy;
 
+mod base64;
+
 mod sized;
 
 pub use self::sized::SizedRequest;
@@ -44,7 +53,6 @@ impl Multipart<()> {
     ///
     /// # Errors
     /// If `req.open_stream()` returns an error.
-
     pub fn from_request<R: HttpRequest>(req: R) -> Result<Multipart<R::Stream>, R::Error> {
         let (boundary, stream) = open_stream(req, None)?;
 
@@ -54,6 +62,17 @@ impl Multipart<()> {
     }
 }
 
+impl<S> Multipart<S> {
+    /// Get the boundary string used to separate the fields of this request.
+    ///
+    /// This is the same value passed to `HttpRequest::apply_headers()`, which is useful when
+    /// the `Content-Type` header has to be set on a transport this crate doesn't integrate with.
+    #[must_use]
+    pub fn boundary(&self) -> &str {
+        &self.writer.boundary
+    }
+}
+
 impl<S: HttpStream> Multipart<S> {
     /// Write a text field to this multipart request.
     /// `name` and `val` can be either owned `String` or `&str`.
@@ -126,6 +145,49 @@ impl<S: HttpStream> Multipart<S> {
         )
     }
 
+    /// Write several files under one field as a nested `multipart/mixed` part with its own
+    /// boundary, as described in [RFC 7578, Section 4.3][rfc7578-4.3]. `files` writes the
+    /// files with the `NestedMultipart` it is given, and the nested part is closed once it
+    /// returns:
+    ///
+    /// ```rust
+    /// use mpart::client::Multipart;
+    ///
+    /// let mut multipart = Multipart::from_request(()).unwrap();
+    ///
+    /// multipart
+    ///     .write_nested("files", |files| {
+    ///         files.write_stream(&mut &b"first"[..], Some("a.txt"), Some(mime::TEXT_PLAIN))?;
+    ///         files.write_stream(&mut &b"second"[..], Some("b.txt"), Some(mime::TEXT_PLAIN))?;
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// ```
+    ///
+    /// RFC 7578 deprecates this form, so only use it for servers which expect it. See
+    /// `lazy::Multipart::add_file_group()` for the same with the lazy API.
+    ///
+    /// Like the other methods here, this writes to the stream as it goes rather than adding a
+    /// field to send later, hence `write_` and not `add_`; `files` returning marks the end of
+    /// the nested part.
+    ///
+    /// `name` can be either `String` or `&str`.
+    ///
+    /// ## Errors
+    /// If `files` returned an error or wrote no files, which [RFC 2046, Section 5.1.1][rfc2046]
+    /// doesn't allow, or if something went wrong with the HTTP stream. The headers of the field
+    /// have been written by then, so the request can't be completed.
+    ///
+    /// [rfc7578-4.3]: https://tools.ietf.org/html/rfc7578#section-4.3
+    /// [rfc2046]: https://tools.ietf.org/html/rfc2046#section-5.1.1
+    pub fn write_nested<N, F>(&mut self, name: N, files: F) -> Result<&mut Self, S::Error>
+    where
+        N: AsRef<str>,
+        F: FnOnce(&mut NestedMultipart<'_, S>) -> io::Result<()>,
+    {
+        map_self!(self, self.writer.write_nested(name.as_ref(), files))
+    }
+
     /// Finalize the request and return the response from the server, or the last error if set.
     #[allow(clippy::missing_errors_doc, clippy::redundant_closure_for_method_calls)]
     pub fn send(self) -> Result<S::Response, S::Error> {
@@ -136,6 +198,66 @@ impl<S: HttpStream> Multipart<S> {
     }
 }
 
+/// The files of a nested `multipart/mixed` part, written by `Multipart::write_nested()`.
+///
+/// Each file is sent with `Content-Disposition: file` and its filename, without a field name of
+/// its own.
+pub struct NestedMultipart<'a, S> {
+    writer: MultipartWriter<'static, &'a mut S>,
+}
+
+impl<S: Write> NestedMultipart<'_, S> {
+    /// Open a file pointed to by `path` and write its contents to the nested part, supplying its
+    /// filename and guessing its `Content-Type` from its extension.
+    ///
+    /// ## Errors
+    /// If there was a problem opening the file, or if something went wrong with the HTTP stream.
+    pub fn write_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<&mut Self> {
+        let path = path.as_ref();
+        let (content_type, filename) = mime_filename(path);
+        let mut file = File::open(path)?;
+
+        self.write_stream(&mut file, filename, Some(content_type))
+    }
+
+    /// Write a byte stream to the nested part as a file, supplying `filename` if given (the
+    /// `filename` parameter is left out otherwise), and `content_type` if given or
+    /// `"application/octet-stream"` if not.
+    ///
+    /// The same warning about reading to EOF applies as for `Multipart::write_stream()`.
+    ///
+    /// ## Errors
+    /// If the reader returned an error, or if something went wrong with the HTTP stream.
+    pub fn write_stream<St: Read>(
+        &mut self,
+        stream: &mut St,
+        filename: Option<&str>,
+        content_type: Option<Mime>,
+    ) -> io::Result<&mut Self> {
+        let content_type = content_type.unwrap_or(mime::APPLICATION_OCTET_STREAM);
+        let writer = &mut self.writer;
+
+        writer.write_boundary()?;
+        writer.data_written = true;
+        write_file_disposition(&mut writer.inner, filename)?;
+        write!(writer.inner, "\r\nContent-Type: {content_type}\r\n\r\n")?;
+        io::copy(stream, &mut writer.inner)?;
+
+        Ok(self)
+    }
+
+    fn finish(self) -> io::Result<()> {
+        let writer = self.writer;
+
+        if writer.data_written {
+            writer.inner.write_all(b"\r\n")?;
+        }
+
+        // unlike the outer body, the next outer boundary follows directly
+        write!(writer.inner, "--{}--", writer.boundary)
+    }
+}
+
 impl<R: HttpRequest> Multipart<SizedRequest<R>>
 where
     <R::Stream as HttpStream>::Error: From<R::Error>,
@@ -209,6 +331,11 @@ fn gen_boundary() -> String {
     crate::random_alphanumeric(BOUNDARY_LEN)
 }
 
+/// The value of the `Content-Type` header for a body with `boundary`.
+fn form_data_content_type(boundary: &str) -> String {
+    format!("multipart/form-data; boundary={boundary}")
+}
+
 fn open_stream<R: HttpRequest>(
     mut req: R,
     content_len: Option<u64>,
@@ -267,6 +394,33 @@ impl<'a, W: Write> MultipartWriter<'a, W> {
         Ok(())
     }
 
+    fn write_nested<F>(&mut self, name: &str, files: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut NestedMultipart<'_, W>) -> io::Result<()>,
+    {
+        let boundary = gen_boundary();
+        let content_type = format!("multipart/mixed; boundary={boundary}")
+            .parse()
+            .expect("generated boundary should be a valid `Mime` parameter");
+
+        self.write_field_headers(name, None, Some(content_type))?;
+
+        let mut nested = NestedMultipart {
+            writer: MultipartWriter::new(&mut self.inner, boundary),
+        };
+
+        files(&mut nested)?;
+
+        if !nested.writer.data_written {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("nested part {name:?} has no files"),
+            ));
+        }
+
+        nested.finish()
+    }
+
     fn write_field_headers(
         &mut self,
         name: &str,
@@ -276,13 +430,7 @@ impl<'a, W: Write> MultipartWriter<'a, W> {
         // Write the first boundary, or the boundary for the previous field.
         self.write_boundary()?;
         self.data_written = true;
-        write!(
-            self.inner,
-            "Content-Disposition: form-data; name=\"{name}\""
-        )?;
-        filename.map_or(Ok(()), |filename| {
-            write!(self.inner, "; filename=\"{filename}\"")
-        })?;
+        write_content_disposition(&mut self.inner, FORM_DATA, name, filename)?;
         content_type.map_or(Ok(()), |content_type| {
             write!(self.inner, "\r\nContent-Type: {content_type}")
         })?;
@@ -303,11 +451,214 @@ impl<'a, W: Write> MultipartWriter<'a, W> {
 }
 
 fn mime_filename(path: &Path) -> (Mime, Option<&str>) {
-    let content_type = mime_guess::from_path(path);
-    let filename = opt_filename(path);
-    (content_type.first_or_octet_stream(), filename)
+    (guess_mime(path), opt_filename(path))
+}
+
+/// Guess the `Content-Type` of a file from its extension.
+fn guess_mime(path: &Path) -> Mime {
+    mime_guess::from_path(path).first_or_octet_stream()
 }
 
 fn opt_filename(path: &Path) -> Option<&str> {
     path.file_name().and_then(|filename| filename.to_str())
 }
+
+/// The default disposition type for fields.
+const FORM_DATA: &str = "form-data";
+
+/// Write the `Content-Disposition` header for a field, sans trailing CRLF.
+///
+/// `name` and `filename` are escaped the same way browsers do it: `"`, CR and LF are
+/// percent-encoded so they can't terminate the quoted value or inject a header.
+/// Filenames which are non-ASCII or had to be escaped are additionally emitted as an
+/// RFC 5987 `filename*` parameter, which servers decode to the exact filename.
+fn write_content_disposition<W: Write>(
+    w: &mut W,
+    disposition: &str,
+    name: &str,
+    filename: Option<&str>,
+) -> io::Result<()> {
+    check_disposition(disposition)?;
+
+    write!(
+        w,
+        "Content-Disposition: {}; name=\"{}\"",
+        disposition,
+        escape_param(name)
+    )?;
+
+    filename.map_or(Ok(()), |filename| write_filename(w, filename))
+}
+
+/// Return an error of kind `InvalidInput` unless `disposition` is a valid disposition type,
+/// i.e. an RFC 7230 `token`, so it can't inject parameters or headers.
+fn check_disposition(disposition: &str) -> io::Result<()> {
+    // `tchar` from RFC 7230, section 3.2.6
+    let is_tchar = |b: u8| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b);
+
+    if !disposition.is_empty() && disposition.bytes().all(is_tchar) {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid Content-Disposition type: {disposition:?}"),
+        ))
+    }
+}
+
+/// Write the `Content-Disposition` header for a file in a `multipart/mixed` group,
+/// sans trailing CRLF. These have no field name of their own.
+fn write_file_disposition<W: Write>(w: &mut W, filename: Option<&str>) -> io::Result<()> {
+    w.write_all(b"Content-Disposition: file")?;
+    filename.map_or(Ok(()), |filename| write_filename(w, filename))
+}
+
+fn write_filename<W: Write>(w: &mut W, filename: &str) -> io::Result<()> {
+    write!(w, "; filename=\"{}\"", escape_param(filename))?;
+
+    if !filename.is_ascii() || matches!(escape_param(filename), Cow::Owned(_)) {
+        write!(w, "; filename*=UTF-8''{}", encode_ext_value(filename))?;
+    }
+
+    Ok(())
+}
+
+/// Percent-encode the characters which may not appear in a quoted header parameter.
+fn escape_param(val: &str) -> Cow<'_, str> {
+    if !val.contains(['"', '\r', '\n']) {
+        return val.into();
+    }
+
+    val.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+        .into()
+}
+
+/// Percent-encode `val` as the value of an RFC 5987 extended parameter.
+fn encode_ext_value(val: &str) -> String {
+    let mut encoded = String::with_capacity(val.len() * 3);
+
+    for &byte in val.as_bytes() {
+        // `attr-char` from RFC 5987, section 3.2.1
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HttpRequest, Multipart, MultipartWriter};
+
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_escape_field_headers() {
+        let mut writer = MultipartWriter::new(Vec::new(), "boundary");
+        writer
+            .write_stream(&mut &b"data"[..], "a\"b\r\nc", Some("ß\".txt"), None)
+            .unwrap();
+        let body = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        assert_eq!(
+            body,
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a%22b%0D%0Ac\"; \
+             filename=\"ß%22.txt\"; filename*=UTF-8''%C3%9F%22.txt\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             data\r\n--boundary--\r\n"
+        );
+    }
+
+    #[test]
+    fn test_write_nested() {
+        let mut writer = MultipartWriter::new(Vec::new(), "boundary");
+        writer.write_text("text", "text").unwrap();
+        writer
+            .write_nested("files", |files| {
+                files.write_stream(&mut &b"first"[..], Some("a.txt"), Some(mime::TEXT_PLAIN))?;
+                files.write_stream(&mut &b"second"[..], None, None)?;
+                Ok(())
+            })
+            .unwrap();
+        writer
+            .write_nested("more", |files| {
+                files.write_stream(&mut &b"third"[..], Some("c.txt"), None)?;
+                Ok(())
+            })
+            .unwrap();
+        let body = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        let mut inner = body
+            .match_indices("multipart/mixed; boundary=")
+            .map(|(idx, prefix)| &body[idx + prefix.len()..][..super::BOUNDARY_LEN]);
+        let (first, second) = (inner.next().unwrap(), inner.next().unwrap());
+        assert_ne!(first, second);
+
+        assert_eq!(
+            body,
+            format!(
+                "--boundary\r\n\
+                 Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+                 text\r\n\
+                 --boundary\r\n\
+                 Content-Disposition: form-data; name=\"files\"\r\n\
+                 Content-Type: multipart/mixed; boundary={first}\r\n\r\n\
+                 --{first}\r\n\
+                 Content-Disposition: file; filename=\"a.txt\"\r\n\
+                 Content-Type: text/plain\r\n\r\n\
+                 first\r\n\
+                 --{first}\r\n\
+                 Content-Disposition: file\r\n\
+                 Content-Type: application/octet-stream\r\n\r\n\
+                 second\r\n\
+                 --{first}--\r\n\
+                 --boundary\r\n\
+                 Content-Disposition: form-data; name=\"more\"\r\n\
+                 Content-Type: multipart/mixed; boundary={second}\r\n\r\n\
+                 --{second}\r\n\
+                 Content-Disposition: file; filename=\"c.txt\"\r\n\
+                 Content-Type: application/octet-stream\r\n\r\n\
+                 third\r\n\
+                 --{second}--\r\n\
+                 --boundary--\r\n"
+            )
+        );
+
+        // a nested part needs at least one file
+        let mut writer = MultipartWriter::new(Vec::new(), "boundary");
+        let err = writer.write_nested("empty", |_| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    struct RecordBoundary(Rc<RefCell<String>>);
+
+    impl HttpRequest for RecordBoundary {
+        type Stream = io::Sink;
+        type Error = io::Error;
+
+        fn apply_headers(&mut self, boundary: &str, _: Option<u64>) -> bool {
+            self.0.borrow_mut().push_str(boundary);
+            true
+        }
+
+        fn open_stream(self) -> io::Result<io::Sink> {
+            Ok(io::sink())
+        }
+    }
+
+    #[test]
+    fn test_boundary_matches_headers() {
+        let applied = Rc::new(RefCell::new(String::new()));
+        let multipart = Multipart::from_request(RecordBoundary(applied.clone())).unwrap();
+
+        assert_eq!(multipart.boundary(), *applied.borrow());
+    }
+}
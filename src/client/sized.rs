@@ -16,7 +16,6 @@ use std::io::prelude::*;
 /// server doesn't support chunked requests or otherwise rejects them.
 ///
 /// [chunked-example]: http://en.wikipedia.org/wiki/Chunked_transfer_encoding#Example
-
 #[allow(clippy::module_name_repetitions)]
 pub struct SizedRequest<R> {
     inner: R,
@@ -77,8 +76,7 @@ where
         let content_len = self.buffer.len() as u64;
 
         if !self.inner.apply_headers(&self.boundary, Some(content_len)) {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
+            return Err(io::Error::other(
                 "SizedRequest failed to apply headers to wrapped request.",
             )
             .into());
@@ -5,23 +5,68 @@
 //!
 //! * `client`: The client-side abstractions for generating multipart requests.
 //!
+//! * `client-async`: Fields read from a Tokio `AsyncRead` and serializing a request as an
+//!   async body. See `client::lazy::Multipart::into_async_body()`, or
+//!   [`client::async_`](client/async_/index.html) for writing to a Tokio `AsyncWrite`.
+//!
 //! * `server`: The server-side abstractions for parsing multipart requests.
 //!
+//! * `server-async`: Parsing a request body which is a `Stream` of `Bytes` chunks, as handed
+//!   out by async web stacks. See the [`server::async_`](server/async_/index.html) module.
+//!
+//! * `bytes`: Converting in-memory saved fields to [`bytes::Bytes`](https://crates.io/crates/bytes).
+//!   See `server::save::SavedData::into_bytes()`.
+//!
+//! * `charset`: Decoding of text fields in character encodings other than UTF-8, as declared
+//!   by their `charset` parameter or a `_charset_` field. See `MultipartField::read_text()`.
+//!   Also available as `encoding`.
+//!
 //! * `mock`: Provides mock implementations of core `client` and `server` traits for debugging
-//! or non-standard use.
+//!   or non-standard use, and `server::Entries::builder()` for constructing `Entries` in tests.
 //!
 //! * `hyper`: Integration with the [Hyper](https://crates.io/crates/hyper) HTTP library
-//! for client and/or server depending on which other feature flags are set.
+//!   for client and/or server depending on which other feature flags are set.
+//!
+//! * `hashing`: Computing a digest of each field while saving it, with any hash function
+//!   implementing the traits from the [`digest`](https://crates.io/crates/digest) crate.
+//!   See `server::save::SaveBuilder::hash_with()`.
+//!
+//! * `checksums`: Verifying fields against the MD5 or SHA-256 checksums sent in their headers
+//!   while saving them. See `server::save::SaveBuilder::verify_checksums()`.
+//!
+//! * `derive`: `#[derive(MultipartForm)]` for filling a struct from the fields of a request.
+//!   See the [`server::extract`](server/extract/index.html) module.
+//!
+//! * `serde`: Deserializing the fields of a request into a struct; see the
+//!   [`server::form`](server/form/index.html) module. Also exporting the metadata of each field
+//!   as JSON lines; see `server::Multipart::export_metadata_jsonl()`.
+//!
+//! * `axum`: Server-side integration with [Axum](https://crates.io/crates/axum), extracting
+//!   requests as an async `Multipart`. See the [`server::axum`](server/axum/index.html) module.
+//!
+//! * `hyper1`: Server-side integration with Hyper 1.x, or anything else using `http::Request`
+//!   with an `http_body::Body`. See the [`server::hyper1`](server/hyper1/index.html) module for
+//!   more information. With `client-async`, also client-side integration; see the
+//!   [`client::hyper1`](client/hyper1/index.html) module.
 //!
 //! * `iron`: Integration with the [Iron](http://crates.io/crates/iron) web application
-//! framework. See the [`server::iron`](server/iron/index.html) module for more information.
+//!   framework. See the [`server::iron`](server/iron/index.html) module for more information.
 //!
 //! * `nickel` (returning in 0.14!): Integration with the [Nickel](https://crates.io/crates/nickel)
-//! web application framework. See the [`server::nickel`](server/nickel/index.html) module for more
-//! information.
+//!   web application framework. See the [`server::nickel`](server/nickel/index.html) module for
+//!   more information.
+//!
+//! * `rocket`: Integration with [Rocket](https://crates.io/crates/rocket) 0.5, saving requests
+//!   with a data guard. See the [`server::rocket`](server/rocket/index.html) module.
+//!
+//! * `tide`: Integration with [Tide](https://crates.io/crates/tide), parsing requests as an
+//!   async `Multipart` on `async-std`. See the [`server::tide`](server/tide/index.html) module.
 //!
 //! * `tiny_http`: Integration with the [`tiny_http`](https://crates.io/crates/tiny_http)
-//! crate. See the [`server::tiny_http`](server/tiny_http/index.html) module for more information.
+//!   crate. See the [`server::tiny_http`](server/tiny_http/index.html) module for more information.
+//!
+//! * `warp`: Integration with the [Warp](https://crates.io/crates/warp) web framework.
+//!   See the [`server::warp`](server/warp/index.html) module for more information.
 //!
 //! ### Note: Work in Progress
 //! I have left a number of Request-for-Comments (RFC) questions on various APIs and other places
@@ -31,9 +76,17 @@
 //! [on Github](https://github.com/abonander/multipart/issues/96). Please quote the RFC-statement
 //! (and/or link to its source line) and provide your feedback there.
 #![deny(rust_2018_idioms)]
+#![cfg_attr(all(test, feature = "bench"), feature(test))]
 
 use rand::Rng;
 
+#[cfg(all(test, feature = "bench"))]
+extern crate test;
+
+// the code generated by `mpart-derive` refers to `::mpart`
+#[cfg(all(test, feature = "derive"))]
+extern crate self as mpart;
+
 #[cfg(feature = "client")]
 pub mod client;
 #[cfg(feature = "server")]
@@ -0,0 +1,529 @@
+//! Parsing of a body which arrives as an async stream of chunks.
+//! Enabled with the `server-async` feature.
+//!
+//! Async web stacks such as Hyper 1.x, Axum and Warp hand out the request body as a stream of
+//! `Bytes` chunks; this `Multipart` parses it as the chunks arrive, without blocking a thread
+//! on a `Read` adapter or collecting the whole body first.
+//!
+//! ```rust
+//! use bytes::Bytes;
+//! use mpart::server::async_::Multipart;
+//!
+//! # fn stream() -> impl futures_core::Stream<Item = Result<Bytes, std::io::Error>> + Unpin {
+//! #     struct Once(Option<Bytes>);
+//! #     impl futures_core::Stream for Once {
+//! #         type Item = Result<Bytes, std::io::Error>;
+//! #         fn poll_next(
+//! #             mut self: std::pin::Pin<&mut Self>,
+//! #             _: &mut std::task::Context<'_>,
+//! #         ) -> std::task::Poll<Option<Self::Item>> {
+//! #             std::task::Poll::Ready(self.0.take().map(Ok))
+//! #         }
+//! #     }
+//! #     Once(Some(Bytes::from_static(
+//! #         b"--boundary\r\nContent-Disposition: form-data; name=\"text\"\r\n\r\nsome text\r\n\
+//! #           --boundary--",
+//! #     )))
+//! # }
+//! async fn print_fields() -> std::io::Result<()> {
+//!     let mut multipart = Multipart::with_body(stream(), "boundary");
+//!
+//!     while let Some(mut field) = multipart.next_field().await? {
+//!         let mut len = 0;
+//!
+//!         while let Some(chunk) = field.chunk().await? {
+//!             len += chunk.len();
+//!         }
+//!
+//!         println!("{}: {} bytes", field.headers.name, len);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap()
+//! #     .block_on(print_fields()).unwrap();
+//! ```
+use bytes::Bytes;
+use futures_core::Stream;
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::boundary::{validate_boundary, BoundaryValidation, InvalidBoundary};
+use super::field::{FieldHeaders, LineEndingPolicy};
+use super::push::{PushEvent, PushParser};
+
+/// A `PushEvent` which doesn't borrow from the parser.
+#[derive(Debug)]
+enum Event {
+    Start(Box<FieldHeaders>),
+    Data(Bytes),
+    End,
+}
+
+/// The async counterpart of `server::Multipart`, parsing a body which is a `Stream` of `Bytes`
+/// chunks.
+///
+/// As with `Multipart::read_entry()`, each field borrows the `Multipart` while it is read, so
+/// fields are returned one at a time by `next_field()` rather than by implementing `Stream`;
+/// the data of a field is itself a `Stream` of chunks. Any data of a field left unread is
+/// skipped when the next one is requested.
+///
+/// Chunks are parsed by `push::PushParser`, so the same rules about the preamble, epilogue and
+/// the size of the field headers apply.
+pub struct Multipart<S> {
+    stream: S,
+    parser: PushParser,
+    /// Events found in the chunks so far which weren't returned yet.
+    events: VecDeque<Event>,
+    /// An error from the parser, returned once `events` is empty.
+    error: Option<io::Error>,
+    /// Whether `stream` has ended.
+    eof: bool,
+    /// Whether the data of a field is being returned.
+    in_field: bool,
+    /// The boundary was quoted in the request's `Content-Type`, for `boundary_was_quoted()`.
+    boundary_quoted: bool,
+}
+
+impl<S, E> Multipart<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    /// Parse `stream` as a multipart body with the given boundary, without the leading `--`.
+    ///
+    /// Errors returned by `stream` are passed on wrapped in an `io::Error` of kind `Other`.
+    pub fn with_body<B: Into<String>>(stream: S, boundary: B) -> Self {
+        let boundary = boundary.into();
+
+        log::info!("Multipart::with_body(_, {:?})", boundary);
+
+        Multipart {
+            stream,
+            parser: PushParser::new(boundary),
+            events: VecDeque::new(),
+            error: None,
+            eof: false,
+            in_field: false,
+            boundary_quoted: false,
+        }
+    }
+
+    /// Like `with_body()`, but check the boundary with `boundary::validate_boundary()` first,
+    /// e.g. to reject a request with an empty boundary or one containing control characters
+    /// before reading any of it. With `BoundaryValidation::Lenient`, the trimmed boundary is used.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the boundary is invalid; `stream` is dropped.
+    pub fn try_with_body<B: AsRef<str>>(
+        stream: S,
+        boundary: B,
+        validation: BoundaryValidation,
+    ) -> Result<Self, InvalidBoundary> {
+        let boundary = validate_boundary(boundary.as_ref(), validation)?;
+        Ok(Multipart::with_body(stream, boundary))
+    }
+
+    /// Whether the boundary was quoted in the request's `Content-Type` header, as with
+    /// `server::Multipart::boundary_was_quoted()`.
+    ///
+    /// Captured by the `axum`, `hyper1`, `tide` and `warp` integrations; always `false` for a
+    /// `Multipart` constructed from a boundary directly.
+    pub fn boundary_was_quoted(&self) -> bool {
+        self.boundary_quoted
+    }
+
+    /// Set what to do with field headers mixing CRLF and LF line endings, as with
+    /// `server::Multipart::set_line_ending_policy()`. The default is `LineEndingPolicy::Lenient`.
+    pub fn set_line_ending_policy(&mut self, policy: LineEndingPolicy) {
+        self.parser.set_line_ending_policy(policy);
+    }
+
+    /// Set the value returned by `boundary_was_quoted()`, for the integrations.
+    #[cfg_attr(
+        not(any(
+            feature = "axum",
+            feature = "hyper1",
+            feature = "tide",
+            feature = "warp"
+        )),
+        allow(dead_code)
+    )]
+    pub(super) fn with_boundary_quoted(self, boundary_quoted: bool) -> Self {
+        Multipart {
+            boundary_quoted,
+            ..self
+        }
+    }
+
+    /// Poll for the headers of the next field, skipping any data left in the current one.
+    ///
+    /// Returns `Ok(None)` after the closing boundary once the body has ended.
+    ///
+    /// # Errors
+    ///
+    /// If the body could not be parsed or reading from the stream failed. If the body ends
+    /// before the closing boundary, the error is of kind `io::ErrorKind::UnexpectedEof`.
+    pub fn poll_next_field(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<Option<FieldHeaders>>> {
+        loop {
+            match self.poll_event(cx) {
+                Poll::Ready(Ok(Some(Event::Start(headers)))) => {
+                    self.in_field = true;
+                    return Poll::Ready(Ok(Some(*headers)));
+                }
+                Poll::Ready(Ok(Some(Event::Data(_)))) => (),
+                Poll::Ready(Ok(Some(Event::End))) => self.in_field = false,
+                Poll::Ready(Ok(None)) => return Poll::Ready(Ok(None)),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    /// Get the next field, skipping any data left in the current one.
+    ///
+    /// Returns `Ok(None)` after the closing boundary once the body has ended.
+    ///
+    /// # Errors
+    ///
+    /// As for `poll_next_field()`.
+    pub async fn next_field(&mut self) -> io::Result<Option<Field<'_, S>>> {
+        let headers = poll_fn(|cx| self.poll_next_field(cx)).await?;
+
+        Ok(headers.map(move |headers| Field {
+            headers,
+            multipart: self,
+        }))
+    }
+
+    /// Poll for the next chunk of data of the current field, or `None` at its end.
+    fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Option<Bytes>>> {
+        if !self.in_field {
+            return Poll::Ready(Ok(None));
+        }
+
+        match self.poll_event(cx) {
+            Poll::Ready(Ok(Some(Event::Data(data)))) => Poll::Ready(Ok(Some(data))),
+            Poll::Ready(Ok(Some(Event::End))) => {
+                self.in_field = false;
+                Poll::Ready(Ok(None))
+            }
+            // can't happen before `End`, but keep it for `poll_next_field()`
+            Poll::Ready(Ok(Some(event @ Event::Start(_)))) => {
+                self.in_field = false;
+                self.events.push_front(event);
+                Poll::Ready(Ok(None))
+            }
+            Poll::Ready(Ok(None)) => Poll::Ready(Ok(None)),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    /// Poll for the next event, feeding chunks from the stream to the parser as needed.
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Option<Event>>> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Poll::Ready(Ok(Some(event)));
+            }
+
+            if let Some(e) = self.error.take() {
+                // the parser returns nothing more after an error
+                self.eof = true;
+                return Poll::Ready(Err(e));
+            }
+
+            if self.eof {
+                return Poll::Ready(self.parser.finish().map(|()| None));
+            }
+
+            let chunk = match Pin::new(&mut self.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => chunk,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(io::Error::other(e))),
+                Poll::Ready(None) => {
+                    self.eof = true;
+                    continue;
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+
+            for event in self.parser.feed(&chunk) {
+                self.events.push_back(match event {
+                    Ok(PushEvent::FieldStart(headers)) => Event::Start(headers),
+                    Ok(PushEvent::FieldData(data)) => Event::Data(Bytes::copy_from_slice(data)),
+                    Ok(PushEvent::FieldEnd) => Event::End,
+                    Ok(PushEvent::Done) => continue,
+                    Err(e) => {
+                        // returned after the events preceding it
+                        self.error = Some(e);
+                        break;
+                    }
+                });
+            }
+        }
+    }
+}
+
+impl<S> fmt::Debug for Multipart<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multipart")
+            .field("parser", &self.parser)
+            .field("eof", &self.eof)
+            .field("in_field", &self.in_field)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A field of an async `Multipart`, whose data is read as a `Stream` of `Bytes` chunks.
+pub struct Field<'a, S> {
+    /// The headers of this field, including its name.
+    pub headers: FieldHeaders,
+    multipart: &'a mut Multipart<S>,
+}
+
+impl<S, E> Field<'_, S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    /// Get the next chunk of the data of this field, or `None` at its end.
+    ///
+    /// # Errors
+    ///
+    /// As for `Multipart::poll_next_field()`.
+    pub async fn chunk(&mut self) -> io::Result<Option<Bytes>> {
+        poll_fn(|cx| self.multipart.poll_chunk(cx)).await
+    }
+
+    /// Read the rest of the data of this field into memory.
+    ///
+    /// There's no limit on the size here; for fields from untrusted clients, read `chunk()`
+    /// in a loop and stop at a limit instead.
+    ///
+    /// # Errors
+    ///
+    /// As for `Multipart::poll_next_field()`.
+    pub async fn bytes(mut self) -> io::Result<Bytes> {
+        let first = match self.chunk().await? {
+            Some(chunk) => chunk,
+            None => return Ok(Bytes::new()),
+        };
+
+        // avoid a copy for a field which arrived in one chunk
+        let second = match self.chunk().await? {
+            Some(chunk) => chunk,
+            None => return Ok(first),
+        };
+
+        let mut data = Vec::with_capacity(first.len() + second.len());
+        data.extend_from_slice(&first);
+        data.extend_from_slice(&second);
+
+        while let Some(chunk) = self.chunk().await? {
+            data.extend_from_slice(&chunk);
+        }
+
+        Ok(data.into())
+    }
+}
+
+impl<S, E> Stream for Field<'_, S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .multipart
+            .poll_chunk(cx)
+            .map(Result::transpose)
+    }
+}
+
+impl<S> fmt::Debug for Field<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Field")
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Multipart;
+
+    use bytes::Bytes;
+    use futures_core::Stream;
+
+    use std::collections::VecDeque;
+    use std::future::{poll_fn, Future};
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    const BODY: &[u8] = b"preamble\r\n--boundary\r\n\
+        Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+        some text\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\
+        Content-Type: application/octet-stream\r\n\r\n\
+        file\r\ndata\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"skipped\"\r\n\r\n\
+        never read\r\n\
+        --boundary--\r\nepilogue";
+
+    /// Returns its chunks one by one, and `Pending` before each of them.
+    struct Chunks {
+        chunks: VecDeque<io::Result<Bytes>>,
+        pending: bool,
+    }
+
+    impl Stream for Chunks {
+        type Item = io::Result<Bytes>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.pending = !self.pending;
+
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            Poll::Ready(self.chunks.pop_front())
+        }
+    }
+
+    fn chunked(body: &[u8], chunk_size: usize) -> Chunks {
+        Chunks {
+            chunks: body
+                .chunks(chunk_size)
+                .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+                .collect(),
+            pending: false,
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_async_multipart() {
+        for chunk_size in [1, 2, 3, 7, 16, BODY.len()] {
+            block_on(async {
+                let mut multipart = Multipart::with_body(chunked(BODY, chunk_size), "boundary");
+
+                let field = multipart.next_field().await.unwrap().unwrap();
+                assert_eq!(&*field.headers.name, "text");
+                assert_eq!(field.bytes().await.unwrap(), "some text");
+
+                let mut field = multipart.next_field().await.unwrap().unwrap();
+                assert_eq!(&*field.headers.name, "file");
+                assert_eq!(field.headers.filename.as_deref(), Some("file.bin"));
+
+                let mut data = Vec::new();
+                while let Some(chunk) = poll_fn(|cx| Pin::new(&mut field).poll_next(cx)).await {
+                    data.extend_from_slice(&chunk.unwrap());
+                }
+                assert_eq!(data, b"file\r\ndata", "chunk size {chunk_size}");
+
+                // the end of a field is sticky
+                assert!(field.chunk().await.unwrap().is_none());
+
+                // unread data is skipped
+                let mut field = multipart.next_field().await.unwrap().unwrap();
+                assert_eq!(&*field.headers.name, "skipped");
+                let _ = field.chunk().await.unwrap();
+
+                assert!(multipart.next_field().await.unwrap().is_none());
+                assert!(multipart.next_field().await.unwrap().is_none());
+            });
+        }
+    }
+
+    #[test]
+    fn test_async_multipart_errors() {
+        block_on(async {
+            // the body ends before the closing boundary
+            let end = BODY.windows(4).position(|w| w == b"some").unwrap() + 4;
+            let mut multipart = Multipart::with_body(chunked(&BODY[..end], 8), "boundary");
+            let field = multipart.next_field().await.unwrap().unwrap();
+            assert_eq!(
+                field.bytes().await.err().unwrap().kind(),
+                io::ErrorKind::UnexpectedEof
+            );
+
+            // an error from the stream is passed on
+            let mut stream = chunked(BODY, 16);
+            stream.chunks.insert(
+                2,
+                Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset")),
+            );
+            let mut multipart = Multipart::with_body(stream, "boundary");
+            let err = multipart.next_field().await.err().unwrap();
+            assert_eq!(err.to_string(), "reset");
+
+            // a malformed body
+            let body = b"--boundary\r\nContent-Disposition: form-data; name=\"text\"\r\n\r\n\
+                         text\r\n--boundaryXX";
+            let mut multipart = Multipart::with_body(chunked(body, 8), "boundary");
+            let field = multipart.next_field().await.unwrap().unwrap();
+            assert_eq!(
+                field.bytes().await.err().unwrap().kind(),
+                io::ErrorKind::InvalidData
+            );
+
+            // an empty body has no fields
+            let mut multipart = Multipart::with_body(chunked(b"", 8), "boundary");
+            assert!(multipart.next_field().await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn test_try_with_body() {
+        use crate::server::boundary::{BoundaryValidation, InvalidBoundary};
+
+        for boundary in ["boundary", " boundary\t"] {
+            let mut multipart =
+                Multipart::try_with_body(chunked(BODY, 16), boundary, BoundaryValidation::Lenient)
+                    .ok()
+                    .unwrap();
+
+            block_on(async {
+                let field = multipart.next_field().await.unwrap().unwrap();
+                assert_eq!(&*field.headers.name, "text");
+            });
+        }
+
+        let invalid = |boundary, validation| {
+            Multipart::try_with_body(chunked(BODY, 16), boundary, validation)
+                .err()
+                .unwrap()
+        };
+
+        assert_eq!(
+            invalid("", BoundaryValidation::Lenient),
+            InvalidBoundary::Empty
+        );
+        assert_eq!(
+            invalid("bound\0ary", BoundaryValidation::Strict),
+            InvalidBoundary::InvalidChar { ch: '\0', index: 5 }
+        );
+    }
+}
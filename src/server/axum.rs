@@ -0,0 +1,172 @@
+//! Server-side integration with [Axum](https://github.com/tokio-rs/axum).
+//! Enabled with the `axum` feature.
+//!
+//! `MpartMultipart` implements `FromRequest`, so handlers can take it as an argument. Unlike
+//! the other integrations the body isn't buffered: fields are parsed as the body arrives, using
+//! the async [`Multipart`](../async_/struct.Multipart.html) of the `server-async` feature.
+//!
+//! As with Axum's own `Multipart` extractor, the body is limited by `DefaultBodyLimit`, 2 MB
+//! unless the layer changes it; reading past the limit returns an error.
+//!
+//! ```rust,no_run
+//! use mpart::server::axum::MpartMultipart;
+//!
+//! async fn upload(mut multipart: MpartMultipart) -> String {
+//!     let mut files = 0;
+//!
+//!     loop {
+//!         match multipart.next_field().await {
+//!             Ok(Some(field)) if field.headers.filename.is_some() => files += 1,
+//!             Ok(Some(_)) => (),
+//!             Ok(None) => return format!("received {} files", files),
+//!             Err(e) => return format!("error reading request: {}", e),
+//!         }
+//!     }
+//! }
+//!
+//! # fn main() {}
+//! // `axum::Router::new().route("/upload", axum::routing::post(upload))`
+//! ```
+use std::error::Error;
+use std::fmt;
+
+use axum_core::body::BodyDataStream;
+use axum_core::extract::{FromRequest, Request};
+use axum_core::response::{IntoResponse, Response};
+use axum_core::RequestExt;
+use http::header::CONTENT_TYPE;
+use http::StatusCode;
+
+use super::async_::Multipart;
+
+/// The async `Multipart` extracted from a request by Axum.
+pub type MpartMultipart = Multipart<BodyDataStream>;
+
+/// The rejection of `MpartMultipart` for requests which are not `multipart/form-data` or don't
+/// specify a boundary, responding with `400 Bad Request`.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct NotMultipart;
+
+impl fmt::Display for NotMultipart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("request is not multipart/form-data")
+    }
+}
+
+impl Error for NotMultipart {}
+
+impl IntoResponse for NotMultipart {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+    }
+}
+
+impl<S: Send + Sync> FromRequest<S> for MpartMultipart {
+    type Rejection = NotMultipart;
+
+    async fn from_request(req: Request, _: &S) -> Result<Self, Self::Rejection> {
+        let content_type = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|val| val.to_str().ok());
+        let boundary = content_type
+            .and_then(super::form_data_boundary)
+            .map(str::to_owned)
+            .ok_or(NotMultipart)?;
+        let boundary_quoted = content_type.is_some_and(super::boundary_quoted);
+
+        // limited by `DefaultBodyLimit`, as with Axum's own `Multipart`
+        let body = req.with_limited_body().into_body();
+
+        Ok(Multipart::with_body(body.into_data_stream(), boundary)
+            .with_boundary_quoted(boundary_quoted))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MpartMultipart;
+
+    use axum_core::body::Body;
+    use axum_core::extract::{FromRequest, Request};
+    use axum_core::response::IntoResponse;
+    use http::StatusCode;
+
+    use std::future::Future;
+
+    const BODY: &str = "--boundary\r\n\
+        Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+        some text\r\n\
+        --boundary--";
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn request(content_type: &str) -> Request {
+        Request::builder()
+            .header("content-type", content_type)
+            .body(Body::from(BODY))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_axum_extractor() {
+        block_on(async {
+            let req = request("multipart/form-data; boundary=boundary");
+            let mut multipart = MpartMultipart::from_request(req, &()).await.unwrap();
+
+            let field = multipart.next_field().await.unwrap().unwrap();
+            assert_eq!(&*field.headers.name, "text");
+            assert_eq!(field.bytes().await.unwrap(), "some text");
+            assert!(multipart.next_field().await.unwrap().is_none());
+
+            let req = request("text/plain");
+            let rejection = MpartMultipart::from_request(req, &()).await.err().unwrap();
+            assert_eq!(rejection.into_response().status(), StatusCode::BAD_REQUEST);
+        });
+    }
+
+    #[test]
+    fn test_boundary_quoted() {
+        block_on(async {
+            let req = request("multipart/form-data; boundary=boundary");
+            let multipart = MpartMultipart::from_request(req, &()).await.unwrap();
+            assert!(!multipart.boundary_was_quoted());
+
+            let req = request("multipart/form-data; boundary=\"boundary\"");
+            let multipart = MpartMultipart::from_request(req, &()).await.unwrap();
+            assert!(multipart.boundary_was_quoted());
+        });
+    }
+
+    #[test]
+    fn test_default_body_limit() {
+        // larger than Axum's default limit of 2 MB
+        let data = "x".repeat(3 * 1024 * 1024);
+        let body = format!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"\r\n\r\n\
+             {data}\r\n\
+             --boundary--"
+        );
+
+        block_on(async {
+            let req = Request::builder()
+                .header("content-type", "multipart/form-data; boundary=boundary")
+                .body(Body::from(body))
+                .unwrap();
+            let mut multipart = MpartMultipart::from_request(req, &()).await.unwrap();
+
+            let res = async {
+                let field = multipart.next_field().await?.unwrap();
+                field.bytes().await
+            };
+            res.await.unwrap_err();
+        });
+    }
+}
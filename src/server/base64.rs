@@ -0,0 +1,152 @@
+//! Incremental base64 decoding for `Content-Transfer-Encoding: base64` fields and checksum
+//! headers.
+use std::io;
+
+/// Decodes base64 split across any number of chunks, skipping ASCII whitespace.
+///
+/// Padding is optional, but if present it must complete the last quantum and end the data.
+#[derive(Debug, Default)]
+pub(crate) struct Base64Decoder {
+    /// The bits not yet making up a byte, and how many there are.
+    bits: u32,
+    bit_count: u8,
+    /// The number of `=` seen.
+    padding: u8,
+}
+
+impl Base64Decoder {
+    /// Decode `input` into `out`.
+    pub fn decode(&mut self, input: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        for &byte in input {
+            if byte.is_ascii_whitespace() {
+                continue;
+            }
+
+            if byte == b'=' {
+                // one `=` completes 3 characters (2 bits left over), two complete 2 (4 bits)
+                let max_padding = match self.bit_count {
+                    2 => 1,
+                    4 => 2,
+                    _ => 0,
+                };
+
+                if self.padding >= max_padding {
+                    return Err(invalid("misplaced padding in base64 data"));
+                }
+
+                self.padding += 1;
+                continue;
+            }
+
+            let sextet = match sextet(byte) {
+                Some(sextet) if self.padding == 0 => sextet,
+                Some(_) => return Err(invalid("base64 data continues after padding")),
+                None => return Err(invalid(format!("invalid byte in base64 data: {byte:#04x}"))),
+            };
+
+            self.bits = (self.bits << 6) | u32::from(sextet);
+            self.bit_count += 6;
+
+            if self.bit_count >= 8 {
+                self.bit_count -= 8;
+                out.push((self.bits >> self.bit_count) as u8);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the data decoded so far didn't end in the middle of a quantum.
+    pub fn finish(&self) -> io::Result<()> {
+        let complete = match (self.bit_count, self.padding) {
+            (0, 0) | (2, 0 | 1) | (4, 0 | 2) => true,
+            // a single character of the last quantum, or only one `=` where two are needed
+            _ => false,
+        };
+
+        if complete {
+            Ok(())
+        } else {
+            Err(invalid("truncated base64 data"))
+        }
+    }
+}
+
+/// Decode all of `encoded`, which may not contain whitespace, or return `None` if it isn't
+/// valid base64.
+pub(crate) fn decode(encoded: &str) -> Option<Vec<u8>> {
+    if encoded.bytes().any(|b| b.is_ascii_whitespace()) {
+        return None;
+    }
+
+    let mut decoder = Base64Decoder::default();
+    let mut decoded = Vec::with_capacity(encoded.len() * 3 / 4);
+    decoder.decode(encoded.as_bytes(), &mut decoded).ok()?;
+    decoder.finish().ok()?;
+    Some(decoded)
+}
+
+fn sextet(byte: u8) -> Option<u8> {
+    match byte {
+        b'A'..=b'Z' => Some(byte - b'A'),
+        b'a'..=b'z' => Some(byte - b'a' + 26),
+        b'0'..=b'9' => Some(byte - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(msg: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, Base64Decoder};
+
+    #[test]
+    fn test_padding() {
+        assert_eq!(decode("").unwrap(), b"");
+        assert_eq!(decode("Zm9v").unwrap(), b"foo");
+        assert_eq!(decode("Zm9vYg==").unwrap(), b"foob");
+        assert_eq!(decode("Zm9vYmE=").unwrap(), b"fooba");
+        // padding is optional
+        assert_eq!(decode("Zm9vYg").unwrap(), b"foob");
+        assert_eq!(decode("Zm9vYmE").unwrap(), b"fooba");
+
+        for invalid in [
+            "Zm9v=",
+            "Zm9vYg=",
+            "Zm9vYmE==",
+            "Zm9vYg=x",
+            "Zm9v=Yg==",
+            "=",
+            "Zm 9v",
+        ] {
+            assert_eq!(decode(invalid), None, "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_truncated() {
+        assert_eq!(decode("Zm9vY"), None);
+        assert_eq!(decode("Z"), None);
+        assert_eq!(decode("Zm9vY==="), None);
+    }
+
+    #[test]
+    fn test_chunks() {
+        let mut decoder = Base64Decoder::default();
+        let mut out = Vec::new();
+
+        for chunk in ["Zm", "9v\r\n", "Y", "mE", "=", ""] {
+            decoder.decode(chunk.as_bytes(), &mut out).unwrap();
+        }
+
+        decoder.finish().unwrap();
+        assert_eq!(out, b"fooba");
+
+        decoder.decode(b"x", &mut out).unwrap_err();
+    }
+}
@@ -1,18 +1,120 @@
 //! Boundary parsing for `multipart` requests.
+//!
+//! `BoundaryReader` splits a multipart body on its boundaries without interpreting the parts,
+//! so it can be used on its own for any `multipart/*` type, e.g. to rewrite parts in a proxy.
+//! `Multipart` is built on top of it and parses the headers of each part.
 
 use ::safemem;
 
-use buf_redux::policy::MinBuffered;
-use buf_redux::BufReader;
+use buf_redux::policy::{DoRead, MinBuffered, ReaderPolicy, StdPolicy};
+use buf_redux::{BufReader, Buffer};
 
-use std::cmp;
 use std::io;
 use std::io::prelude::*;
+use std::time::Instant;
+use std::{cmp, fmt};
+
+use super::error::{malformed_boundary, truncated};
+use super::parse::{after_boundary, find_boundary, find_delimiter, AfterBoundary};
+use super::pool::MultipartPool;
+use super::timeout::DeadlineExceeded;
+
+use thiserror::Error;
 
 use self::State::{AtEnd, BoundaryRead, Searching};
 
+/// The minimum number of bytes `BoundaryReader` keeps buffered while searching for a boundary.
 pub const MIN_BUF_SIZE: usize = 1024;
 
+/// The default size the buffer is grown to for skipping the unread rest of a large field.
+pub const SKIP_BUF_SIZE: usize = 64 * 1024;
+
+/// `Seek::seek()` of the buffered source, which can't be called in `consume_boundary()` since
+/// `R: Seek` isn't required there.
+type SeekFn<R> = fn(&mut BufReader<R, FillPolicy>, io::SeekFrom) -> io::Result<u64>;
+
+/// The maximum length of a boundary according to RFC 2046.
+pub const MAX_BOUNDARY_LEN: usize = 70;
+
+/// How strictly `validate_boundary()` checks a boundary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundaryValidation {
+    /// Only accept boundaries following RFC 2046 exactly: 1 to 70 characters from its `bchars`
+    /// set (letters, digits and `'()+_,-./:=?` and space), not ending in a space.
+    Strict,
+    /// Trim whitespace around the boundary and accept any printable ASCII character and any
+    /// length, as some clients overstep the RFC. Empty boundaries and control or non-ASCII
+    /// characters are still rejected since they can't be matched reliably.
+    Lenient,
+}
+
+/// Why `validate_boundary()` rejected a boundary.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum InvalidBoundary {
+    /// The boundary is empty, which would match almost anywhere in the body.
+    #[error("the multipart boundary is empty")]
+    Empty,
+    /// The boundary is longer than `MAX_BOUNDARY_LEN`.
+    #[error("the multipart boundary is {} bytes long, the maximum is {}", .0, MAX_BOUNDARY_LEN)]
+    TooLong(usize),
+    /// The boundary contains a character which is not allowed.
+    #[error("the multipart boundary contains the invalid character {:?} at byte {}", .ch, .index)]
+    InvalidChar {
+        /// The character.
+        ch: char,
+        /// The byte index of the character in the boundary.
+        index: usize,
+    },
+    /// The boundary ends in a space.
+    #[error("the multipart boundary ends in a space")]
+    TrailingSpace,
+}
+
+/// Check that `boundary` (without the leading `--`) can be matched reliably, returning it,
+/// trimmed for `BoundaryValidation::Lenient`.
+///
+/// `Multipart::try_with_body()` calls this; `Multipart::with_body()` and `BoundaryReader`
+/// accept any boundary.
+///
+/// # Errors
+///
+/// Will return `Error` if the boundary is not valid according to `validation`.
+pub fn validate_boundary(
+    boundary: &str,
+    validation: BoundaryValidation,
+) -> Result<&str, InvalidBoundary> {
+    let boundary = match validation {
+        BoundaryValidation::Strict => boundary,
+        BoundaryValidation::Lenient => boundary.trim(),
+    };
+
+    if boundary.is_empty() {
+        return Err(InvalidBoundary::Empty);
+    }
+
+    let is_valid = |ch: char| match validation {
+        BoundaryValidation::Strict => ch.is_ascii_alphanumeric() || "'()+_,-./:=? ".contains(ch),
+        BoundaryValidation::Lenient => ch == ' ' || ch.is_ascii_graphic(),
+    };
+
+    if let Some((index, ch)) = boundary.char_indices().find(|&(_, ch)| !is_valid(ch)) {
+        return Err(InvalidBoundary::InvalidChar { ch, index });
+    }
+
+    if validation == BoundaryValidation::Strict {
+        if boundary.len() > MAX_BOUNDARY_LEN {
+            return Err(InvalidBoundary::TooLong(boundary.len()));
+        }
+
+        if boundary.ends_with(' ') {
+            return Err(InvalidBoundary::TrailingSpace);
+        }
+    }
+
+    Ok(boundary)
+}
+
 #[derive(Debug, PartialEq, Eq)]
 enum State {
     Searching,
@@ -21,35 +123,267 @@ enum State {
 }
 
 /// A struct implementing `Read` and `BufRead` that will yield bytes until it sees a given sequence.
+///
+/// The reader yields the bytes of one part at a time, i.e. everything between two boundaries
+/// (the CRLF preceding a boundary belongs to the boundary). Once the part is exhausted, reads
+/// return `Ok(0)` until `consume_boundary()` is called to move on to the next part;
+/// any unread bytes of the current part are skipped.
+///
+/// The data before the first boundary (the preamble) is skipped by the first call to
+/// `consume_boundary()` and nothing is read past the closing boundary.
+///
+/// ```rust
+/// use mpart::server::boundary::BoundaryReader;
+/// use std::io::Read;
+///
+/// let body: &[u8] = b"preamble\r\n--boundary\r\n\
+///     Content-Type: text/plain\r\n\r\n\
+///     first part\r\n\
+///     --boundary\r\n\r\n\
+///     second part\r\n\
+///     --boundary--";
+///
+/// let mut reader = BoundaryReader::from_reader(body, "boundary");
+/// let mut parts = Vec::new();
+///
+/// while reader.consume_boundary().unwrap() {
+///     let mut part = String::new();
+///     reader.read_to_string(&mut part).unwrap();
+///     parts.push(part);
+/// }
+///
+/// assert_eq!(parts, ["Content-Type: text/plain\r\n\r\nfirst part", "\r\nsecond part"]);
+/// ```
 #[derive(Debug)]
 #[allow(clippy::module_name_repetitions)]
 pub struct BoundaryReader<R> {
-    source: BufReader<R, MinBuffered>,
+    source: Source<R>,
     boundary: Vec<u8>,
+    /// The boundary with its mandatory preceding CRLF, searched for in strict mode.
+    delimiter: Vec<u8>,
+    strict: bool,
+    /// Reject a preamble, an epilogue and dashes after the closing boundary.
+    no_extras: bool,
     search_idx: usize,
     state: State,
+    /// Number of bytes consumed from `source` since the start of the body.
+    consumed: u64,
+    /// The position and declared length of the current field's body, if it declared one.
+    expected_len: Option<(u64, u64)>,
+    /// The declared and actual length of the last field's body, if they didn't match.
+    len_mismatch: Option<(u64, u64)>,
+    /// The number of boundaries consumed so far, including the opening one.
+    boundaries_found: u64,
+    deadline: Option<Instant>,
+    /// The length of the body, past which nothing is parsed.
+    limit: Option<u64>,
+    skip_buf_size: usize,
+    /// Set to seek past the unread rest of fields which declared their length.
+    seek: Option<SeekFn<R>>,
 }
 
 impl<R> BoundaryReader<R>
 where
     R: Read,
 {
-    /// Internal API
+    /// Split the body read from `reader` on the given boundary, without the leading `--`.
+    ///
+    /// The data is read through an internal buffer.
     pub fn from_reader<B: Into<Vec<u8>>>(reader: R, boundary: B) -> BoundaryReader<R> {
-        let mut boundary = boundary.into();
+        let source = BufReader::new(reader).set_policy(FillPolicy::default());
+        Self::with_source(Source::Owned(source), boundary.into())
+    }
+
+    /// Like `from_reader()`, but the internal buffer is taken from `pool` and returned to it
+    /// when this reader is unwrapped with `into_inner()`.
+    pub fn from_reader_pooled<B: Into<Vec<u8>>>(
+        reader: R,
+        boundary: B,
+        pool: &MultipartPool,
+    ) -> BoundaryReader<R> {
+        let source = BufReader::with_buffer(pool.take(), reader).set_policy(FillPolicy::default());
+
+        let source = Pooled {
+            reader: source,
+            pool: pool.clone(),
+        };
+
+        Self::with_source(Source::Pooled(source), boundary.into())
+    }
+
+    /// Split the body read from `reader` on the given boundary, without the leading `--`.
+    ///
+    /// Scans the buffer of `reader` directly instead of copying the data into a buffer of its own.
+    pub fn from_buf_read<B: Into<Vec<u8>>>(reader: R, boundary: B) -> BoundaryReader<R>
+    where
+        R: BufRead,
+    {
+        let source = Borrowed {
+            inner: reader,
+            fill_buf: R::fill_buf,
+            consume: R::consume,
+            carry: Vec::new(),
+            pos: 0,
+            min_buf_size: MIN_BUF_SIZE,
+            deadline: None,
+            remaining: None,
+            #[cfg(test)]
+            copied: 0,
+        };
+
+        Self::with_source(Source::Borrowed(source), boundary.into())
+    }
+
+    fn with_source(source: Source<R>, mut boundary: Vec<u8>) -> BoundaryReader<R> {
         safemem::prepend(b"--", &mut boundary);
-        let source = BufReader::new(reader).set_policy(MinBuffered(MIN_BUF_SIZE));
+        let mut delimiter = boundary.clone();
+        safemem::prepend(b"\r\n", &mut delimiter);
 
         BoundaryReader {
             source,
             boundary,
+            delimiter,
+            strict: false,
+            no_extras: false,
             search_idx: 0,
             state: Searching,
+            consumed: 0,
+            expected_len: None,
+            len_mismatch: None,
+            boundaries_found: 0,
+            deadline: None,
+            limit: None,
+            skip_buf_size: SKIP_BUF_SIZE,
+            seek: None,
+        }
+    }
+
+    /// The number of bytes consumed from the underlying reader so far, i.e. the offset of the
+    /// next byte to be read relative to the start of the body.
+    pub fn position(&self) -> u64 {
+        self.consumed
+    }
+
+    /// The offset of the next boundary (including its preceding CRLF) relative to the start
+    /// of the body, if it has been found yet.
+    pub fn boundary_position(&self) -> Option<u64> {
+        if self.state == BoundaryRead {
+            Some(self.consumed + self.search_idx as u64)
+        } else {
+            None
         }
     }
 
+    /// The boundary being searched for, without the leading `--`.
+    pub fn boundary(&self) -> &[u8] {
+        &self.boundary[2..]
+    }
+
+    /// The number of boundaries consumed so far, including the opening boundary.
+    pub fn boundaries_found(&self) -> u64 {
+        self.boundaries_found
+    }
+
+    /// `true` once the closing boundary has been consumed or the body turned out to be empty.
+    pub fn is_at_end(&self) -> bool {
+        self.state == AtEnd
+    }
+
+    /// Unwrap the underlying reader, discarding any buffered data.
+    pub fn into_inner(self) -> R {
+        self.source.into_inner()
+    }
+
+    /// Unwrap the underlying reader along with any data buffered but not yet consumed.
+    pub fn into_buf_reader(self) -> BufReader<R> {
+        self.source.into_buf_reader()
+    }
+
+    /// In strict mode, only recognize the boundary if it is preceded by CRLF (or is at the very
+    /// start of the body) and followed by CRLF or `--`, as RFC 2046 requires. Anything else
+    /// which looks like the boundary is treated as data.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// In addition to `set_strict()`, reject anything RFC 7578 doesn't allow around the
+    /// boundaries: data before the opening boundary (the preamble), data after the closing
+    /// boundary except for a CRLF (the epilogue) and extra dashes after the closing boundary.
+    /// These are an error of kind `io::ErrorKind::InvalidData` from `consume_boundary()`.
+    ///
+    /// The source isn't read past the closing boundary to look for an epilogue: only data
+    /// which was already buffered with it is checked, unless a limit is set with
+    /// `set_limit()`, in which case everything up to the limit is. Set a limit so that an
+    /// epilogue is reliably rejected.
+    ///
+    /// Setting this to `false` leaves strict mode as it is.
+    pub fn set_strict_rfc7578(&mut self, strict: bool) {
+        if strict {
+            self.strict = true;
+        }
+
+        self.no_extras = strict;
+    }
+
+    pub(crate) fn is_strict_rfc7578(&self) -> bool {
+        self.no_extras
+    }
+
+    pub(crate) fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Record the declared length of the body starting at the current position, to be checked
+    /// against the actual length when the next boundary is consumed.
+    pub fn expect_body_len(&mut self, len: Option<u64>) {
+        self.expected_len = len.map(|len| (self.consumed, len));
+    }
+
+    /// Set the size the buffer is grown to when skipping the unread rest of a field which doesn't
+    /// fit in the buffer, so it's read in fewer, larger chunks. The default is `SKIP_BUF_SIZE`.
+    ///
+    /// The buffer keeps this size afterwards. Has no effect with `from_buf_read()`, which uses
+    /// the caller's buffer.
+    pub fn set_skip_buf_size(&mut self, skip_buf_size: usize) {
+        self.skip_buf_size = skip_buf_size;
+    }
+
+    /// The declared and actual length of the body before the last boundary consumed, if they
+    /// didn't match.
+    pub(crate) fn take_len_mismatch(&mut self) -> Option<(u64, u64)> {
+        self.len_mismatch.take()
+    }
+
+    /// Fail all further reads once `deadline` has passed. See `Multipart::set_deadline()`.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+        self.source.set_deadline(deadline);
+    }
+
+    /// Treat the body as ending after `limit` bytes, e.g. the `Content-Length` of the request,
+    /// so anything after that is never parsed. If the closing boundary hasn't been found by
+    /// then, `consume_boundary()` returns an error of kind `io::ErrorKind::UnexpectedEof`.
+    ///
+    /// The buffer is not topped up past `limit`, so a read from the underlying reader never
+    /// waits for bytes beyond it, but a single read may still return some which are then
+    /// discarded. Wrap the reader in `Read::take()` if they must be left in it.
+    pub fn set_limit(&mut self, limit: Option<u64>) {
+        self.limit = limit;
+    }
+
+    /// The deadline, unless the end has been reached so no more reads are needed.
+    fn active_deadline(&self) -> Option<Instant> {
+        self.deadline.filter(|_| self.state != AtEnd)
+    }
+
+    /// The number of bytes of the body left before the limit, if there is one.
+    fn remaining(&self) -> Option<u64> {
+        self.limit.map(|limit| limit.saturating_sub(self.consumed))
+    }
+
     fn read_to_boundary(&mut self) -> io::Result<&[u8]> {
-        let buf = self.source.fill_buf()?;
+        let deadline = self.active_deadline();
+        let buf = self.source.fill_buf_until(deadline, self.remaining())?;
 
         log::trace!("Buf: {:?}", String::from_utf8_lossy(buf));
 
@@ -67,8 +401,15 @@ where
         if self.state == Searching && self.search_idx < buf.len() {
             let lookahead = &buf[self.search_idx..];
 
+            let found = if self.strict {
+                let at_start = self.consumed == 0 && self.search_idx == 0;
+                find_delimiter(lookahead, &self.boundary, &self.delimiter, at_start)
+            } else {
+                find_boundary(lookahead, &self.boundary)
+            };
+
             // Look for the boundary, or if it isn't found, stop near the end.
-            match find_boundary(lookahead, &self.boundary) {
+            match found {
                 Ok(found_idx) => {
                     self.search_idx += found_idx;
                     self.state = BoundaryRead;
@@ -110,38 +451,182 @@ where
         Ok(ret_buf)
     }
 
+    /// Set the minimum number of bytes to keep buffered while searching for the boundary.
+    ///
+    /// This is at least twice the length of the boundary regardless of the value given.
     pub fn set_min_buf_size(&mut self, min_buf_size: usize) {
         // ensure the minimum buf size is at least enough to find a boundary with some extra
         let min_buf_size = cmp::max(self.boundary.len() * 2, min_buf_size);
 
-        self.source.policy_mut().0 = min_buf_size;
+        self.source.set_min_buf_size(min_buf_size);
     }
 
-    #[allow(clippy::missing_errors_doc)]
+    /// If seeking is enabled and the current field declared its length, seek to the end of its
+    /// body instead of reading the rest of it. If the boundary isn't there, the declared length
+    /// is wrong, so seek back to read it after all.
+    fn seek_to_body_end(&mut self) -> io::Result<()> {
+        let (Some(seek), Some((start, len))) = (self.seek, self.expected_len) else {
+            return Ok(());
+        };
+
+        let before = self.consumed;
+        let end = start.saturating_add(len);
+
+        // not worth it if the rest is buffered already
+        if end <= before + self.source.buffer().len() as u64 {
+            return Ok(());
+        }
+
+        // the boundary can't be found there anyway
+        if self.limit.is_some_and(|limit| end > limit) {
+            return Ok(());
+        }
+
+        let Ok(offset) = i64::try_from(end - before) else {
+            return Ok(());
+        };
+
+        if !self.source.seek_relative(seek, offset)? {
+            return Ok(());
+        }
+
+        self.consumed = end;
+        self.search_idx = 0;
+
+        let remaining = self.remaining();
+        let found = match self.source.fill_buf_until(None, remaining) {
+            Ok(buf) => buf.starts_with(&self.delimiter),
+            Err(e) => {
+                // go back so a retry starts from the same place
+                self.source.seek_relative(seek, -offset)?;
+                self.consumed = before;
+                return Err(e);
+            }
+        };
+
+        if found {
+            log::debug!("Seeked past {} bytes of the field", offset);
+            return Ok(());
+        }
+
+        log::debug!(
+            "Boundary not found at the end of the field's declared length; reading it instead"
+        );
+
+        self.source.seek_relative(seek, -offset)?;
+        self.consumed = before;
+
+        Ok(())
+    }
+
+    /// Skip the rest of the current part (or the preamble) and consume the next boundary.
+    ///
+    /// Returns `true` if a part follows the boundary, or `false` if it was the closing boundary
+    /// or the body is completely empty.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the body ends before the closing boundary, the boundary is
+    /// followed by something other than CRLF or `--`, if the deadline set with `set_deadline()`
+    /// has passed, or on an error from the underlying reader.
     pub fn consume_boundary(&mut self) -> io::Result<bool> {
+        log::debug!(
+            "consume_boundary(): looking for {:?} at byte {}, {} boundaries found so far",
+            String::from_utf8_lossy(&self.boundary),
+            self.consumed,
+            self.boundaries_found
+        );
+
         if self.state == AtEnd {
+            log::debug!("Already at the end of the body");
             return Ok(false);
         }
 
+        if deadline_passed(self.active_deadline()) {
+            return Err(DeadlineExceeded.into());
+        }
+
+        if self.state == Searching && self.boundaries_found > 0 {
+            self.seek_to_body_end()?;
+        }
+
+        let mut skipped = 0;
+
         while self.state == Searching {
             log::debug!("Boundary not found yet");
 
+            // the rest of the field is larger than the buffer, so it may be large
+            if skipped > 0 {
+                self.source.reserve(self.skip_buf_size);
+            }
+
             let buf_len = self.read_to_boundary()?.len();
 
             if buf_len == 0 && self.state == Searching {
-                return Err(io::Error::new(
-                    io::ErrorKind::UnexpectedEof,
-                    "unexpected end of request body",
-                ));
+                // a completely empty body has no fields, rather than being truncated
+                let remaining = self.remaining();
+                if self.consumed == 0 && self.source.fill_buf_until(None, remaining)?.is_empty() {
+                    log::debug!("Empty request body");
+                    self.state = AtEnd;
+                    return Ok(false);
+                }
+
+                if self.remaining() == Some(0) {
+                    return Err(truncated(format!(
+                        "request body reached its length of {} bytes before the closing boundary",
+                        self.consumed
+                    )));
+                }
+
+                if self.boundaries_found == 0 {
+                    log::debug!(
+                        "Reached the end of the body after {} bytes without finding the opening \
+                         boundary {:?}; the boundary is probably wrong",
+                        self.consumed,
+                        String::from_utf8_lossy(&self.boundary)
+                    );
+                } else {
+                    log::debug!(
+                        "Reached the end of the body after {} bytes without finding the closing \
+                         boundary; the body is probably truncated",
+                        self.consumed
+                    );
+                }
+
+                return Err(truncated("unexpected end of request body"));
             }
 
-            log::debug!("Discarding {} bytes", buf_len);
+            if self.boundaries_found == 0 {
+                if self.no_extras && buf_len > 0 {
+                    return Err(malformed_boundary("data before the opening boundary"));
+                }
+
+                log::debug!("Discarding {} bytes of preamble", buf_len);
+            } else {
+                log::debug!("Discarding {} bytes", buf_len);
+            }
 
             self.consume(buf_len);
+            skipped += buf_len;
         }
 
         let consume_amt = {
-            let buf = self.source.fill_buf()?;
+            let deadline = self.active_deadline();
+            let buf = self.source.fill_buf_until(deadline, self.remaining())?;
+
+            // only after the fill, so a retry after a failed read still checks the length
+            if let Some((start, expected)) = self.expected_len.take() {
+                let actual = self.consumed + self.search_idx as u64 - start;
+
+                if actual != expected {
+                    log::warn!(
+                        "Field declared Content-Length: {} but its body was {} bytes long",
+                        expected,
+                        actual
+                    );
+                    self.len_mismatch = Some((expected, actual));
+                }
+            }
 
             // if the boundary is found we should have at least this much in-buffer
             let mut consume_amt = self.search_idx + self.boundary.len();
@@ -160,32 +645,30 @@ where
                 debug_assert_eq!(*self.boundary, bnd_segment[..self.boundary.len()]);
             }
 
-            // include the trailing CRLF or --
-            consume_amt += 2;
-
-            if buf.len() < consume_amt {
-                return Err(io::Error::new(
-                    io::ErrorKind::UnexpectedEof,
-                    "not enough bytes to verify boundary",
-                ));
-            }
-
-            // we have enough bytes to verify
-            self.state = Searching;
-
-            let last_two = &buf[consume_amt - 2..consume_amt];
-
-            match last_two {
-                b"\r\n" => self.state = Searching,
-                b"--" => self.state = AtEnd,
-                _ => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        format!(
-                            "unexpected bytes following multipart boundary: {:X} {:X}",
-                            last_two[0], last_two[1]
-                        ),
-                    ))
+            match after_boundary(&buf[consume_amt..]) {
+                AfterBoundary::Part(len) => {
+                    consume_amt += len;
+                    self.state = Searching;
+                }
+                AfterBoundary::Closing(len) => {
+                    // only the first two dashes are part of the boundary
+                    if self.no_extras && buf[consume_amt + 2..consume_amt + len].contains(&b'-') {
+                        return Err(malformed_boundary(
+                            "extra dashes after the closing boundary",
+                        ));
+                    }
+
+                    consume_amt += len;
+                    self.state = AtEnd;
+                }
+                AfterBoundary::Incomplete => {
+                    return Err(truncated("not enough bytes to verify boundary"));
+                }
+                AfterBoundary::Invalid(first, second) => {
+                    return Err(malformed_boundary(format!(
+                        "unexpected bytes following multipart boundary: {:X} {:X}",
+                        first, second
+                    )));
                 }
             }
 
@@ -199,9 +682,34 @@ where
         );
 
         self.source.consume(consume_amt);
+        self.consumed += consume_amt as u64;
+        self.boundaries_found += 1;
 
         self.search_idx = 0;
 
+        if self.no_extras && self.state == AtEnd {
+            // without a limit, reading on could block on a keep-alive connection, or take the
+            // start of a pipelined request, so only what is already buffered is checked
+            let epilogue = match self.remaining() {
+                Some(remaining) => self.source.fill_buf_until(None, Some(remaining))?,
+                None => self.source.buffer(),
+            };
+
+            if !epilogue.is_empty() {
+                return Err(malformed_boundary("data after the closing boundary"));
+            }
+        }
+
+        log::debug!(
+            "Found {} boundary, now at byte {}",
+            match (self.boundaries_found, self.state == AtEnd) {
+                (_, true) => "the closing",
+                (1, false) => "the opening",
+                _ => "a",
+            },
+            self.consumed
+        );
+
         log::trace!(
             "Consumed boundary (state: {:?}), remaining buf: {:?}",
             self.state,
@@ -212,22 +720,388 @@ where
     }
 }
 
-/// Find the boundary occurrence or the highest length to safely yield
-fn find_boundary(buf: &[u8], boundary: &[u8]) -> Result<usize, usize> {
-    if let Some(idx) = twoway::find_bytes(buf, boundary) {
-        return Ok(idx);
+impl<R> BoundaryReader<R>
+where
+    R: Read + Seek,
+{
+    /// Seek past the unread rest of fields which declared their length with `Content-Length`
+    /// when moving on to the next part, instead of reading and discarding it.
+    ///
+    /// Only for trusted bodies: the declared length is only checked to end at a boundary, so a
+    /// length spanning several parts skips all of them. If no boundary is found at the declared
+    /// end, the rest is read after all. Has no effect with `from_buf_read()`.
+    pub fn set_trusted_seek_skip(&mut self, seek_skip: bool) {
+        self.seek = if seek_skip {
+            Some(<BufReader<R, FillPolicy> as Seek>::seek)
+        } else {
+            None
+        };
+    }
+
+    /// Seek the underlying reader back to the start and reset the boundary search state.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.source.rewind()?;
+        self.search_idx = 0;
+        self.state = Searching;
+        self.consumed = 0;
+        self.expected_len = None;
+        self.len_mismatch = None;
+        self.boundaries_found = 0;
+        Ok(())
+    }
+
+    /// Count the occurrences of the boundary in the rest of the body without consuming it.
+    ///
+    /// Scans the buffered data and then the remainder of the underlying reader, seeking it back
+    /// afterwards so the parse state is unaffected.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn count_boundaries(&mut self) -> io::Result<usize> {
+        let mut window = self.source.buffer().to_vec();
+        let inner = self.source.get_mut();
+        let start = inner.stream_position()?;
+
+        let mut count = 0;
+        let mut chunk = vec![0; 8 * 1024];
+
+        loop {
+            let mut search = &window[..];
+            while let Ok(idx) = find_boundary(search, &self.boundary) {
+                count += 1;
+                search = &search[idx + self.boundary.len()..];
+            }
+
+            // keep a tail too short to contain a whole boundary, in case one is split across reads
+            let keep = cmp::min(search.len(), self.boundary.len() - 1);
+            let tail_start = window.len() - keep;
+            window.drain(..tail_start);
+
+            let read = match inner.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            window.extend_from_slice(&chunk[..read]);
+        }
+
+        inner.seek(io::SeekFrom::Start(start))?;
+
+        Ok(count)
+    }
+}
+
+/// The buffered source of a `BoundaryReader`.
+///
+/// Either way, `fill_buf()` returns at least `min_buf_size` bytes unless the end of the body
+/// is near, as the boundary search and header parsing rely on that. The exception is a fill
+/// cut short by the deadline, which `fill_buf_until()` turns into an error.
+#[derive(Debug)]
+enum Source<R> {
+    /// The body is buffered by us.
+    Owned(BufReader<R, FillPolicy>),
+    /// The body is buffered by us in a buffer from a `MultipartPool`.
+    Pooled(Pooled<R>),
+    /// The body is already buffered by the caller and we scan its buffer directly.
+    Borrowed(Borrowed<R>),
+}
+
+impl<R: Read> Source<R> {
+    /// Like `fill_buf()`, but fail if `deadline` passed before or during the fill, and if
+    /// `remaining` is given, return no more than that and don't top up the buffer past it.
+    fn fill_buf_until(
+        &mut self,
+        deadline: Option<Instant>,
+        remaining: Option<u64>,
+    ) -> io::Result<&[u8]> {
+        if deadline_passed(deadline) {
+            return Err(DeadlineExceeded.into());
+        }
+
+        self.set_remaining(remaining);
+        let buf = self.fill_buf()?;
+
+        // the fill may have been cut short, which would look like the end of the body
+        if deadline_passed(deadline) {
+            return Err(DeadlineExceeded.into());
+        }
+
+        match remaining {
+            Some(remaining) if buf.len() as u64 > remaining => Ok(&buf[..remaining as usize]),
+            _ => Ok(buf),
+        }
+    }
+
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match *self {
+            Source::Owned(ref mut source) => source.fill_buf(),
+            Source::Pooled(ref mut source) => source.reader.fill_buf(),
+            Source::Borrowed(ref mut source) => source.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match *self {
+            Source::Owned(ref mut source) => source.consume(amt),
+            Source::Pooled(ref mut source) => source.reader.consume(amt),
+            Source::Borrowed(ref mut source) => source.consume(amt),
+        }
+    }
+
+    /// The data buffered but not consumed yet, without reading.
+    fn buffer(&self) -> &[u8] {
+        match *self {
+            Source::Owned(ref source) => source.buffer(),
+            Source::Pooled(ref source) => source.reader.buffer(),
+            Source::Borrowed(ref source) => &source.carry[source.pos..],
+        }
+    }
+
+    /// Grow the buffer to at least `size` bytes.
+    fn reserve(&mut self, size: usize) {
+        let reader = match *self {
+            Source::Owned(ref mut source) => source,
+            Source::Pooled(ref mut source) => &mut source.reader,
+            Source::Borrowed(_) => return,
+        };
+
+        if reader.capacity() < size {
+            log::debug!("Growing the buffer to {} bytes for skipping", size);
+            let additional = size - reader.buf_len();
+            reader.reserve(additional);
+        }
+    }
+
+    /// Seek `offset` bytes from the current position with `seek`, discarding the buffer.
+    /// Returns `false` if the source can't seek.
+    fn seek_relative(&mut self, seek: SeekFn<R>, offset: i64) -> io::Result<bool> {
+        let reader = match *self {
+            Source::Owned(ref mut source) => source,
+            Source::Pooled(ref mut source) => &mut source.reader,
+            Source::Borrowed(_) => return Ok(false),
+        };
+
+        seek(reader, io::SeekFrom::Current(offset))?;
+        Ok(true)
+    }
+
+    fn set_min_buf_size(&mut self, min_buf_size: usize) {
+        match *self {
+            Source::Owned(ref mut source) => source.policy_mut().min.0 = min_buf_size,
+            Source::Pooled(ref mut source) => source.reader.policy_mut().min.0 = min_buf_size,
+            Source::Borrowed(ref mut source) => source.min_buf_size = min_buf_size,
+        }
+    }
+
+    fn set_deadline(&mut self, deadline: Option<Instant>) {
+        match *self {
+            Source::Owned(ref mut source) => source.policy_mut().deadline = deadline,
+            Source::Pooled(ref mut source) => source.reader.policy_mut().deadline = deadline,
+            Source::Borrowed(ref mut source) => source.deadline = deadline,
+        }
+    }
+
+    fn set_remaining(&mut self, remaining: Option<u64>) {
+        match *self {
+            Source::Owned(ref mut source) => source.policy_mut().remaining = remaining,
+            Source::Pooled(ref mut source) => source.reader.policy_mut().remaining = remaining,
+            Source::Borrowed(ref mut source) => source.remaining = remaining,
+        }
+    }
+
+    fn get_mut(&mut self) -> &mut R {
+        match *self {
+            Source::Owned(ref mut source) => source.get_mut(),
+            Source::Pooled(ref mut source) => source.reader.get_mut(),
+            Source::Borrowed(ref mut source) => &mut source.inner,
+        }
+    }
+
+    fn into_inner(self) -> R {
+        match self {
+            Source::Owned(source) => source.into_inner(),
+            Source::Pooled(source) => source.into_inner(),
+            Source::Borrowed(source) => source.inner,
+        }
+    }
+
+    fn into_buf_reader(self) -> BufReader<R> {
+        match self {
+            Source::Owned(source) => source.set_policy(StdPolicy),
+            Source::Pooled(source) => source.reader.set_policy(StdPolicy),
+            Source::Borrowed(source) => {
+                let carry = &source.carry[source.pos..];
+                let mut buffer = Buffer::with_capacity(carry.len());
+                buffer.copy_from_slice(carry);
+                BufReader::with_buffer(buffer, source.inner)
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek> Source<R> {
+    fn rewind(&mut self) -> io::Result<()> {
+        match *self {
+            Source::Owned(ref mut source) => source.seek(io::SeekFrom::Start(0)).map(drop),
+            Source::Pooled(ref mut source) => source.reader.seek(io::SeekFrom::Start(0)).map(drop),
+            Source::Borrowed(ref mut source) => {
+                source.carry.clear();
+                source.pos = 0;
+                source.inner.seek(io::SeekFrom::Start(0)).map(drop)
+            }
+        }
     }
+}
 
-    let search_start = buf.len().saturating_sub(boundary.len());
+/// Keeps at least `min` bytes buffered like `MinBuffered`, but stops topping up the buffer
+/// once `deadline` has passed, so a trickling body can't hold up `fill_buf()` indefinitely.
+/// `BoundaryReader` then fails the next read.
+///
+/// Also stops once the `remaining` bytes of a body with a limit are buffered.
+#[derive(Debug)]
+struct FillPolicy {
+    min: MinBuffered,
+    deadline: Option<Instant>,
+    remaining: Option<u64>,
+}
 
-    // search for just the boundary fragment
-    for i in search_start..buf.len() {
-        if boundary.starts_with(&buf[i..]) {
-            return Err(i);
+impl Default for FillPolicy {
+    fn default() -> Self {
+        FillPolicy {
+            min: MinBuffered(MIN_BUF_SIZE),
+            deadline: None,
+            remaining: None,
         }
     }
+}
 
-    Err(buf.len())
+impl ReaderPolicy for FillPolicy {
+    fn before_read(&mut self, buffer: &mut Buffer) -> DoRead {
+        if !buffer.is_empty() && deadline_passed(self.deadline) {
+            return DoRead(false);
+        }
+
+        if self
+            .remaining
+            .is_some_and(|remaining| buffer.len() as u64 >= remaining)
+        {
+            return DoRead(false);
+        }
+
+        self.min.before_read(buffer)
+    }
+}
+
+fn deadline_passed(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// An owned source whose buffer is returned to `pool` when it's unwrapped with `into_inner()`.
+///
+/// There is deliberately no `Drop` impl, which would make every `Multipart<R>` require its
+/// body to outlive it; a buffer which isn't returned is simply deallocated.
+#[derive(Debug)]
+struct Pooled<R> {
+    reader: BufReader<R, FillPolicy>,
+    pool: MultipartPool,
+}
+
+impl<R> Pooled<R> {
+    fn into_inner(self) -> R {
+        let (inner, buffer) = self.reader.into_inner_with_buffer();
+        self.pool.put(buffer);
+        inner
+    }
+}
+
+/// A source which is already `BufRead`.
+///
+/// Only when less than `min_buf_size` bytes are left in the inner buffer, e.g. at the end of it
+/// or because a boundary or headers straddle two fills, are they copied into `carry` and topped
+/// up from the next fill so the data is contiguous.
+struct Borrowed<R> {
+    inner: R,
+    // `R: BufRead` is only known when this is constructed.
+    fill_buf: fn(&mut R) -> io::Result<&[u8]>,
+    consume: fn(&mut R, usize),
+    carry: Vec<u8>,
+    pos: usize,
+    min_buf_size: usize,
+    /// Stop topping up `carry` once passed, like `FillPolicy`.
+    deadline: Option<Instant>,
+    /// Stop topping up `carry` once this much is buffered, like `FillPolicy`.
+    remaining: Option<u64>,
+    /// The number of bytes copied into `carry`.
+    #[cfg(test)]
+    copied: usize,
+}
+
+impl<R> Borrowed<R> {
+    /// Whether `buffered` bytes are all that is left of a body with a limit.
+    fn has_remaining(&self, buffered: usize) -> bool {
+        self.remaining
+            .is_some_and(|remaining| buffered as u64 >= remaining)
+    }
+
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.carry.len() {
+            self.carry.clear();
+            self.pos = 0;
+
+            // the buffer is returned from a second call to satisfy the borrow checker;
+            // `fill_buf()` doesn't read again if the buffer isn't empty
+            let available = (self.fill_buf)(&mut self.inner)?.len();
+            if available == 0 || available >= self.min_buf_size || self.has_remaining(available) {
+                return (self.fill_buf)(&mut self.inner);
+            }
+        }
+
+        while self.carry.len() - self.pos < self.min_buf_size {
+            if self.carry.len() > self.pos && deadline_passed(self.deadline) {
+                break;
+            }
+
+            if self.has_remaining(self.carry.len() - self.pos) {
+                break;
+            }
+
+            let buf = (self.fill_buf)(&mut self.inner)?;
+
+            if buf.is_empty() {
+                break;
+            }
+
+            let copy_amt = cmp::min(buf.len(), self.min_buf_size - (self.carry.len() - self.pos));
+            self.carry.extend_from_slice(&buf[..copy_amt]);
+            (self.consume)(&mut self.inner, copy_amt);
+
+            #[cfg(test)]
+            {
+                self.copied += copy_amt;
+            }
+        }
+
+        Ok(&self.carry[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.pos < self.carry.len() {
+            self.pos = cmp::min(self.pos + amt, self.carry.len());
+        } else {
+            (self.consume)(&mut self.inner, amt);
+        }
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for Borrowed<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Borrowed")
+            .field("inner", &self.inner)
+            .field("carry", &&self.carry[self.pos..])
+            .field("min_buf_size", &self.min_buf_size)
+            .finish()
+    }
 }
 
 impl<R> Read for BoundaryReader<R>
@@ -261,18 +1135,19 @@ where
 
         self.source.consume(true_amt);
         self.search_idx -= true_amt;
+        self.consumed += true_amt as u64;
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::BoundaryReader;
+    use super::{BoundaryReader, MIN_BUF_SIZE, SKIP_BUF_SIZE};
 
     use std::io;
     use std::io::prelude::*;
 
-    const BOUNDARY: &'static str = "boundary";
-    const TEST_VAL: &'static str = "--boundary\r\n\
+    const BOUNDARY: &str = "boundary";
+    const TEST_VAL: &str = "--boundary\r\n\
                                     dashed-value-1\r\n\
                                     --boundary\r\n\
                                     dashed-value-2\r\n\
@@ -301,10 +1176,7 @@ mod test {
         fn split(data: &'a [u8], at: usize) -> SplitReader<'a> {
             let (left, right) = data.split_at(at);
 
-            SplitReader {
-                left: left,
-                right: right,
-            }
+            SplitReader { left, right }
         }
     }
 
@@ -342,6 +1214,104 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_split_boundary_strict() {
+        crate::init_log();
+
+        let mut buf = String::new();
+
+        for split_at in 0..TEST_VAL.len() {
+            log::debug!("Testing split at: {}", split_at);
+
+            let src = SplitReader::split(TEST_VAL.as_bytes(), split_at);
+            let mut reader = BoundaryReader::from_reader(src, BOUNDARY);
+            reader.set_strict(true);
+            test_boundary_reader(&mut reader, &mut buf);
+        }
+    }
+
+    #[test]
+    fn test_opening_boundary_without_crlf() {
+        crate::init_log();
+
+        // no preamble, so nothing precedes the opening boundary; later boundaries need the CRLF
+        // in strict mode
+        let body = b"--boundary\r\nfirst--boundary\r\n--boundary\r\nsecond\r\n--boundary--";
+
+        for strict in [false, true] {
+            for split_at in 0..body.len() {
+                let src = SplitReader::split(&body[..], split_at);
+                let mut reader = BoundaryReader::from_reader(src, BOUNDARY);
+                reader.set_strict(strict);
+
+                let mut parts = Vec::new();
+
+                while reader.consume_boundary().unwrap() {
+                    let mut part = String::new();
+                    reader.read_to_string(&mut part).unwrap();
+                    parts.push(part);
+                }
+
+                if strict {
+                    assert_eq!(parts, ["first--boundary", "second"]);
+                } else {
+                    assert_eq!(parts, ["first", "", "second"]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_buffered_boundary() {
+        crate::init_log();
+
+        let mut buf = String::new();
+
+        // capacities below the minimum buffer size force everything through the carry buffer
+        for capacity in [1, 3, 16, 64, 8192] {
+            for split_at in 0..TEST_VAL.len() {
+                log::debug!("Testing capacity: {} split at: {}", capacity, split_at);
+
+                let src = SplitReader::split(TEST_VAL.as_bytes(), split_at);
+                let src = io::BufReader::with_capacity(capacity, src);
+                let mut reader = BoundaryReader::from_buf_read(src, BOUNDARY);
+                test_boundary_reader(&mut reader, &mut buf);
+            }
+        }
+    }
+
+    #[test]
+    fn test_buffered_copies() {
+        crate::init_log();
+
+        const FIELD_LEN: usize = 1024 * 1024;
+
+        let mut body = b"--boundary\r\n".to_vec();
+        body.resize(body.len() + FIELD_LEN, b'a');
+        body.extend_from_slice(b"\r\n--boundary--");
+
+        let src = io::BufReader::with_capacity(64 * 1024, &body[..]);
+        let mut reader = BoundaryReader::from_buf_read(src, BOUNDARY);
+
+        reader.consume_boundary().unwrap();
+        let read = io::copy(&mut reader, &mut io::sink()).unwrap();
+        assert_eq!(read, FIELD_LEN as u64);
+        assert!(!reader.consume_boundary().unwrap());
+
+        match reader.source {
+            super::Source::Borrowed(ref source) => {
+                // only the ends of the 16 fills of the inner buffer were copied
+                assert!(
+                    source.copied <= 16 * MIN_BUF_SIZE,
+                    "copied: {}",
+                    source.copied
+                );
+                assert!(source.carry.capacity() <= 2 * MIN_BUF_SIZE);
+            }
+            _ => unreachable!(),
+        }
+    }
+
     fn test_boundary_reader<R: Read>(reader: &mut BoundaryReader<R>, buf: &mut String) {
         buf.clear();
 
@@ -381,11 +1351,11 @@ mod test {
         // empty body contains closing boundary only
         let mut body: &[u8] = b"--boundary--";
 
-        let ref mut buf = String::new();
+        let buf = &mut String::new();
         let mut reader = BoundaryReader::from_reader(&mut body, BOUNDARY);
 
         log::debug!("Consume 1");
-        assert_eq!(reader.consume_boundary().unwrap(), false);
+        assert!(!reader.consume_boundary().unwrap());
 
         log::debug!("Read 1");
         let _ = reader.read_to_string(buf).unwrap();
@@ -393,7 +1363,7 @@ mod test {
         buf.clear();
 
         log::debug!("Consume 2");
-        assert_eq!(reader.consume_boundary().unwrap(), false);
+        assert!(!reader.consume_boundary().unwrap());
     }
 
     #[test]
@@ -404,11 +1374,11 @@ mod test {
                          asdf1234\
                          \r\n\r\n--boundary--";
 
-        let ref mut buf = String::new();
+        let buf = &mut String::new();
         let mut reader = BoundaryReader::from_reader(&mut body, BOUNDARY);
 
         log::debug!("Consume 1");
-        assert_eq!(reader.consume_boundary().unwrap(), true);
+        assert!(reader.consume_boundary().unwrap());
 
         log::debug!("Read 1");
         let _ = reader.read_to_string(buf).unwrap();
@@ -416,7 +1386,7 @@ mod test {
         buf.clear();
 
         log::debug!("Consume 2");
-        assert_eq!(reader.consume_boundary().unwrap(), false);
+        assert!(!reader.consume_boundary().unwrap());
 
         log::debug!("Read 2 (empty)");
         let _ = reader.read_to_string(buf).unwrap();
@@ -432,11 +1402,11 @@ mod test {
                          \r\n\r\n--boundary\r\n\
                          hjkl5678\r\n--boundary--";
 
-        let ref mut buf = String::new();
+        let buf = &mut String::new();
         let mut reader = BoundaryReader::from_reader(&mut body, BOUNDARY);
 
         log::debug!("Consume 1");
-        assert_eq!(reader.consume_boundary().unwrap(), true);
+        assert!(reader.consume_boundary().unwrap());
 
         log::debug!("Read 1");
 
@@ -451,7 +1421,7 @@ mod test {
         buf.clear();
 
         log::debug!("Consume 2");
-        assert_eq!(reader.consume_boundary().unwrap(), true);
+        assert!(reader.consume_boundary().unwrap());
 
         log::debug!("Read 2");
         let _ = reader.read_to_string(buf).unwrap();
@@ -459,7 +1429,7 @@ mod test {
         buf.clear();
 
         log::debug!("Consume 3");
-        assert_eq!(reader.consume_boundary().unwrap(), false);
+        assert!(!reader.consume_boundary().unwrap());
 
         log::debug!("Read 3 (empty)");
         let _ = reader.read_to_string(buf).unwrap();
@@ -476,11 +1446,11 @@ mod test {
                          \n\n\r\n--boundary\r\n\
                          hjkl5678\r\n--boundary--";
 
-        let ref mut buf = String::new();
+        let buf = &mut String::new();
         let mut reader = BoundaryReader::from_reader(&mut body, BOUNDARY);
 
         log::debug!("Consume 1");
-        assert_eq!(reader.consume_boundary().unwrap(), true);
+        assert!(reader.consume_boundary().unwrap());
 
         log::debug!("Read 1");
 
@@ -494,7 +1464,7 @@ mod test {
         buf.clear();
 
         log::debug!("Consume 2");
-        assert_eq!(reader.consume_boundary().unwrap(), true);
+        assert!(reader.consume_boundary().unwrap());
 
         log::debug!("Read 2");
         let _ = reader.read_to_string(buf).unwrap();
@@ -502,7 +1472,7 @@ mod test {
         buf.clear();
 
         log::debug!("Consume 3");
-        assert_eq!(reader.consume_boundary().unwrap(), false);
+        assert!(!reader.consume_boundary().unwrap());
 
         log::debug!("Read 3 (empty)");
         let _ = reader.read_to_string(buf).unwrap();
@@ -519,11 +1489,11 @@ mod test {
                          \n\n\r\n--boundary\r\n\
                          hjkl5678  ";
 
-        let ref mut buf = String::new();
+        let buf = &mut String::new();
         let mut reader = BoundaryReader::from_reader(&mut body, BOUNDARY);
 
         log::debug!("Consume 1");
-        assert_eq!(reader.consume_boundary().unwrap(), true);
+        assert!(reader.consume_boundary().unwrap());
 
         log::debug!("Read 1");
 
@@ -537,7 +1507,7 @@ mod test {
         buf.clear();
 
         log::debug!("Consume 2");
-        assert_eq!(reader.consume_boundary().unwrap(), true);
+        assert!(reader.consume_boundary().unwrap());
 
         log::debug!("Read 2");
         let _ = reader.read_to_string(buf).unwrap();
@@ -562,19 +1532,209 @@ mod test {
         reader.consume_boundary().unwrap_err();
     }
 
+    #[test]
+    fn test_validate_boundary() {
+        use super::BoundaryValidation::{Lenient, Strict};
+        use super::{validate_boundary, InvalidBoundary};
+
+        let chrome = "----WebKitFormBoundary7MA4YWxkTrZu0gW";
+        assert_eq!(validate_boundary(chrome, Strict), Ok(chrome));
+        assert_eq!(validate_boundary(chrome, Lenient), Ok(chrome));
+        assert_eq!(
+            validate_boundary("a'()+_,-./:=? b", Strict),
+            Ok("a'()+_,-./:=? b")
+        );
+
+        for validation in [Strict, Lenient] {
+            assert_eq!(
+                validate_boundary("", validation),
+                Err(InvalidBoundary::Empty)
+            );
+            assert_eq!(
+                validate_boundary("bound\r\nary", validation),
+                Err(InvalidBoundary::InvalidChar { ch: '\r', index: 5 })
+            );
+            assert_eq!(
+                validate_boundary("boundäry", validation),
+                Err(InvalidBoundary::InvalidChar { ch: 'ä', index: 5 })
+            );
+        }
+
+        assert_eq!(
+            validate_boundary("  ", Lenient),
+            Err(InvalidBoundary::Empty)
+        );
+        assert_eq!(
+            validate_boundary("bound@ry", Strict),
+            Err(InvalidBoundary::InvalidChar { ch: '@', index: 5 })
+        );
+        assert_eq!(validate_boundary("bound@ry", Lenient), Ok("bound@ry"));
+        assert_eq!(
+            validate_boundary("boundary ", Strict),
+            Err(InvalidBoundary::TrailingSpace)
+        );
+        assert_eq!(validate_boundary(" boundary ", Lenient), Ok("boundary"));
+
+        let long = "x".repeat(71);
+        assert_eq!(
+            validate_boundary(&long, Strict),
+            Err(InvalidBoundary::TooLong(71))
+        );
+        assert_eq!(validate_boundary(&long, Lenient), Ok(&*long));
+    }
+
+    #[test]
+    fn test_skip_buf_size() {
+        let body = |len| {
+            let mut body = b"--boundary\r\n".to_vec();
+            body.resize(body.len() + len, b'x');
+            body.extend_from_slice(b"\r\n--boundary\r\nsecond\r\n--boundary--");
+            body
+        };
+
+        let skip_first = |body: &[u8], skip_buf_size| {
+            let mut reader = BoundaryReader::from_reader(body, BOUNDARY);
+            reader.set_skip_buf_size(skip_buf_size);
+            assert!(reader.consume_boundary().unwrap());
+            assert!(reader.consume_boundary().unwrap());
+
+            let mut second = String::new();
+            reader.read_to_string(&mut second).unwrap();
+            assert_eq!(second, "second");
+
+            reader.into_buf_reader().capacity()
+        };
+
+        // only grown for fields larger than the buffer
+        assert!(skip_first(&body(16), SKIP_BUF_SIZE) < SKIP_BUF_SIZE);
+        assert!(skip_first(&body(256 * 1024), SKIP_BUF_SIZE) >= SKIP_BUF_SIZE);
+        assert!(skip_first(&body(256 * 1024), 128 * 1024) >= 128 * 1024);
+    }
+
+    #[test]
+    fn test_transport_padding() {
+        let mut body: &[u8] = b"--boundary \t\r\nfield1\r\n--boundary  \r\nfield2\r\n--boundary--";
+        let mut reader = BoundaryReader::from_reader(&mut body, "boundary");
+        let buf = &mut String::new();
+
+        assert!(reader.consume_boundary().unwrap());
+        reader.read_to_string(buf).unwrap();
+        assert_eq!(buf, "field1");
+        buf.clear();
+
+        assert!(reader.consume_boundary().unwrap());
+        reader.read_to_string(buf).unwrap();
+        assert_eq!(buf, "field2");
+
+        assert!(!reader.consume_boundary().unwrap());
+
+        // padding must still be followed by CRLF
+        let mut body: &[u8] = b"--boundary  x\r\nfield1\r\n--boundary--";
+        let mut reader = BoundaryReader::from_reader(&mut body, "boundary");
+        let err = reader.consume_boundary().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
     #[test]
     fn test_skip_field() {
         let mut body: &[u8] = b"--boundary\r\nfield1\r\n--boundary\r\nfield2\r\n--boundary--";
         let mut reader = BoundaryReader::from_reader(&mut body, "boundary");
 
-        assert_eq!(reader.consume_boundary().unwrap(), true);
+        assert!(reader.consume_boundary().unwrap());
         // skip `field1`
-        assert_eq!(reader.consume_boundary().unwrap(), true);
+        assert!(reader.consume_boundary().unwrap());
 
         let mut buf = String::new();
         reader.read_to_string(&mut buf).unwrap();
         assert_eq!(buf, "field2");
 
-        assert_eq!(reader.consume_boundary().unwrap(), false);
+        assert!(!reader.consume_boundary().unwrap());
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod bench {
+    use super::BoundaryReader;
+
+    use rand::RngCore;
+
+    use std::io;
+
+    use test::Bencher;
+
+    const BOUNDARY: &str = "boundary";
+
+    const LEN: usize = 200 * 1024 * 1024;
+
+    /// A body with a single field of 200 MB of random binary data.
+    fn large_field() -> Vec<u8> {
+        let mut body = format!("--{BOUNDARY}\r\n\r\n").into_bytes();
+        let start = body.len();
+        body.resize(start + LEN, 0);
+        rand::thread_rng().fill_bytes(&mut body[start..]);
+        body.extend_from_slice(format!("\r\n--{BOUNDARY}--").as_bytes());
+        body
+    }
+
+    fn read_field(mut reader: BoundaryReader<&[u8]>) {
+        assert!(reader.consume_boundary().unwrap());
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+        assert!(!reader.consume_boundary().unwrap());
+    }
+
+    #[bench]
+    fn bench_large_field(b: &mut Bencher) {
+        let body = large_field();
+        b.bytes = body.len() as u64;
+        b.iter(|| read_field(BoundaryReader::from_reader(&body[..], BOUNDARY)));
+    }
+
+    /// Consume the boundary after the large field without reading it.
+    fn skip_field<R: io::Read>(mut reader: BoundaryReader<R>) {
+        assert!(reader.consume_boundary().unwrap());
+        // the part starts with the empty line ending its (lack of) headers
+        reader.expect_body_len(Some(LEN as u64 + 2));
+        assert!(!reader.consume_boundary().unwrap());
+    }
+
+    #[bench]
+    fn bench_skip_large_field(b: &mut Bencher) {
+        let body = large_field();
+        b.bytes = body.len() as u64;
+        b.iter(|| skip_field(BoundaryReader::from_reader(&body[..], BOUNDARY)));
+    }
+
+    #[bench]
+    fn bench_skip_large_field_small_buf(b: &mut Bencher) {
+        let body = large_field();
+        b.bytes = body.len() as u64;
+        b.iter(|| {
+            let mut reader = BoundaryReader::from_reader(&body[..], BOUNDARY);
+            // the default buffer size of `BufReader`, i.e. without growing it
+            reader.set_skip_buf_size(8 * 1024);
+            skip_field(reader)
+        });
+    }
+
+    #[bench]
+    fn bench_skip_large_field_seek(b: &mut Bencher) {
+        let body = large_field();
+        b.bytes = body.len() as u64;
+        b.iter(|| {
+            let mut reader = BoundaryReader::from_reader(io::Cursor::new(&body[..]), BOUNDARY);
+            reader.set_trusted_seek_skip(true);
+            skip_field(reader)
+        });
+    }
+
+    #[bench]
+    fn bench_large_field_strict(b: &mut Bencher) {
+        let body = large_field();
+        b.bytes = body.len() as u64;
+        b.iter(|| {
+            let mut reader = BoundaryReader::from_reader(&body[..], BOUNDARY);
+            reader.set_strict(true);
+            read_field(reader)
+        });
     }
 }
@@ -0,0 +1,243 @@
+//! A decoder for request bodies which still have HTTP/1.1 chunked framing.
+use std::io::{self, BufRead, BufReader, Read};
+
+use super::error::truncated;
+
+/// The maximum length of a chunk-size line or trailer line, including any chunk extensions.
+const MAX_LINE_LEN: u64 = 4096;
+
+#[derive(Debug, PartialEq, Eq)]
+enum State {
+    /// Expecting a chunk-size line.
+    Size,
+    /// In the middle of a chunk with this many bytes left.
+    Data(u64),
+    /// Expecting the CRLF after a chunk.
+    DataEnd,
+    /// The last chunk and trailers have been read.
+    Done,
+}
+
+/// A `Read` adapter which decodes a body sent with `Transfer-Encoding: chunked`.
+///
+/// Most HTTP libraries decode the chunked framing themselves, so this is only needed if
+/// the body reader yields the raw framed bytes. Use `Multipart::with_chunked_body()` to
+/// parse such a body.
+///
+/// Chunk extensions and trailers are read and discarded. Nothing is read from the inner
+/// reader past the end of the chunked body, beyond what is held in this reader's buffer.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct ChunkedReader<R> {
+    inner: BufReader<R>,
+    state: State,
+}
+
+impl<R: Read> ChunkedReader<R> {
+    /// Wrap `inner`, which should be positioned at the start of the first chunk.
+    pub fn new(inner: R) -> Self {
+        ChunkedReader {
+            inner: BufReader::new(inner),
+            state: State::Size,
+        }
+    }
+
+    /// Unwrap the inner reader, discarding any buffered data.
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+
+    fn read_line(&mut self, line: &mut Vec<u8>) -> io::Result<()> {
+        line.clear();
+        (&mut self.inner)
+            .take(MAX_LINE_LEN)
+            .read_until(b'\n', line)?;
+
+        if line.last() != Some(&b'\n') {
+            return Err(if line.len() as u64 == MAX_LINE_LEN {
+                invalid_data("chunked body line too long")
+            } else {
+                truncated("unexpected end of chunked body")
+            });
+        }
+
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+
+        Ok(())
+    }
+
+    fn read_size(&mut self) -> io::Result<u64> {
+        let mut line = Vec::new();
+        self.read_line(&mut line)?;
+
+        // chunk extensions follow a semicolon
+        let size = line.split(|&b| b == b';').next().unwrap_or_default();
+
+        std::str::from_utf8(size)
+            .ok()
+            .map(str::trim)
+            .filter(|size| !size.is_empty())
+            .and_then(|size| u64::from_str_radix(size, 16).ok())
+            .ok_or_else(|| {
+                invalid_data(format!(
+                    "invalid chunk size: {:?}",
+                    String::from_utf8_lossy(&line)
+                ))
+            })
+    }
+
+    fn skip_trailers(&mut self) -> io::Result<()> {
+        let mut line = Vec::new();
+
+        loop {
+            self.read_line(&mut line)?;
+
+            if line.is_empty() {
+                return Ok(());
+            }
+
+            log::trace!("Skipping trailer: {:?}", String::from_utf8_lossy(&line));
+        }
+    }
+}
+
+impl<R: Read> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.state {
+                State::Size => {
+                    let size = self.read_size()?;
+                    log::trace!("Chunk size: {}", size);
+
+                    if size == 0 {
+                        self.skip_trailers()?;
+                        self.state = State::Done;
+                    } else {
+                        self.state = State::Data(size);
+                    }
+                }
+                State::Data(remaining) => {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+
+                    let max = usize::try_from(remaining).map_or(buf.len(), |r| r.min(buf.len()));
+                    let read = self.inner.read(&mut buf[..max])?;
+
+                    if read == 0 {
+                        return Err(truncated("unexpected end of chunked body"));
+                    }
+
+                    let remaining = remaining - read as u64;
+                    self.state = if remaining == 0 {
+                        State::DataEnd
+                    } else {
+                        State::Data(remaining)
+                    };
+
+                    return Ok(read);
+                }
+                State::DataEnd => {
+                    let mut line = Vec::new();
+                    self.read_line(&mut line)?;
+
+                    if !line.is_empty() {
+                        return Err(invalid_data("expected CRLF after chunk data"));
+                    }
+
+                    self.state = State::Size;
+                }
+                State::Done => return Ok(0),
+            }
+        }
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(msg: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod test {
+    use super::ChunkedReader;
+
+    use crate::server::Multipart;
+
+    use std::io::{self, Read};
+
+    /// Encode `data` as chunks of the given size, with a chunk extension and a trailer.
+    fn chunked(data: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut body = Vec::new();
+
+        for chunk in data.chunks(chunk_size) {
+            body.extend_from_slice(format!("{:X};ext=1\r\n", chunk.len()).as_bytes());
+            body.extend_from_slice(chunk);
+            body.extend_from_slice(b"\r\n");
+        }
+
+        body.extend_from_slice(b"0\r\nTrailer: value\r\n\r\n");
+        body
+    }
+
+    #[test]
+    fn test_chunked_body() {
+        let data = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+            first value\r\n\
+            --boundary\r\n\
+            Content-Disposition: form-data; name=\"second\"\r\n\r\n\
+            second value\r\n\
+            --boundary--";
+
+        for chunk_size in [1, 3, 7, 16, 1024] {
+            let body = chunked(data, chunk_size);
+            let mut multipart = Multipart::with_chunked_body(&body[..], "boundary");
+
+            let mut fields = Vec::new();
+            multipart
+                .foreach_entry(|mut field| {
+                    let mut value = String::new();
+                    field.data.read_to_string(&mut value).unwrap();
+                    fields.push((field.headers.name.to_string(), value));
+                })
+                .unwrap();
+
+            assert_eq!(
+                fields,
+                [
+                    ("first".to_string(), "first value".to_string()),
+                    ("second".to_string(), "second value".to_string())
+                ],
+                "chunk size: {chunk_size}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_chunks() {
+        let read = |body: &'static [u8]| {
+            let mut data = Vec::new();
+            ChunkedReader::new(body)
+                .read_to_end(&mut data)
+                .map(|_| data)
+        };
+
+        assert_eq!(read(b"3\r\nabc\r\n0\r\n\r\n").unwrap(), b"abc");
+
+        let err = read(b"x\r\nabc\r\n0\r\n\r\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = read(b"3\r\nabcd\r\n0\r\n\r\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = read(b"5\r\nabc").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // missing the last chunk
+        let err = read(b"3\r\nabc\r\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,111 @@
+//! A typed classification of the errors returned while reading a request.
+//!
+//! The reading methods of `Multipart`, `PushParser` and the async `Multipart` return
+//! `io::Error`, so errors from the body pass through unchanged. Convert such an error into a
+//! `MultipartError` to tell what went wrong without matching on its message:
+//!
+//! ```rust
+//! use mpart::server::{Multipart, MultipartError};
+//!
+//! let body = "--boundary\r\nContent-Disposition: form-data; name=\"text\"\r\n\r\ntext";
+//! let mut multipart = Multipart::with_body(body.as_bytes(), "boundary");
+//!
+//! let err = multipart.foreach_entry(|_| ()).unwrap_err();
+//! assert!(matches!(MultipartError::from(err), MultipartError::Truncated(_)));
+//! ```
+use std::io;
+
+use thiserror::Error;
+
+use super::limits::LimitError;
+
+/// What went wrong while reading a request.
+///
+/// Converts from the `io::Error` returned by the reading methods, and back into an
+/// `io::Error` of the same kind.
+#[derive(Debug, Error)]
+#[allow(clippy::module_name_repetitions)]
+pub enum MultipartError {
+    /// A boundary isn't followed by a line ending or `--`, or a strict `Multipart` (see
+    /// `Multipart::set_strict()`) found data before the opening or after the closing boundary.
+    ///
+    /// Of kind `io::ErrorKind::InvalidData`.
+    #[error("{0}")]
+    MalformedBoundary(String),
+    /// The body ended before the closing boundary.
+    ///
+    /// Of kind `io::ErrorKind::UnexpectedEof`.
+    #[error("{0}")]
+    Truncated(String),
+    /// The headers of a field are missing, invalid or too long.
+    ///
+    /// Of kind `io::ErrorKind::InvalidData`.
+    #[error("{0}")]
+    InvalidHeaders(String),
+    /// A limit set with `Multipart::with_limits()` was exceeded.
+    ///
+    /// Of kind `io::ErrorKind::InvalidData`.
+    #[error(transparent)]
+    Limit(LimitError),
+    /// Any other error, e.g. from reading the body or saving a field, of the original kind.
+    #[error(transparent)]
+    Io(io::Error),
+}
+
+impl MultipartError {
+    /// The kind of the `io::Error` this converts into.
+    #[must_use]
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            MultipartError::Truncated(_) => io::ErrorKind::UnexpectedEof,
+            MultipartError::Io(e) => e.kind(),
+            _ => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl From<io::Error> for MultipartError {
+    fn from(err: io::Error) -> Self {
+        if let Some(limit) = err.get_ref().and_then(|e| e.downcast_ref::<LimitError>()) {
+            return MultipartError::Limit(limit.clone());
+        }
+
+        if err.get_ref().is_some_and(|e| e.is::<MultipartError>()) {
+            return *err
+                .into_inner()
+                .and_then(|e| e.downcast().ok())
+                .expect("checked the type above");
+        }
+
+        MultipartError::Io(err)
+    }
+}
+
+impl From<LimitError> for MultipartError {
+    fn from(err: LimitError) -> Self {
+        MultipartError::Limit(err)
+    }
+}
+
+impl From<MultipartError> for io::Error {
+    fn from(err: MultipartError) -> Self {
+        match err {
+            // keep wrapping the `LimitError` itself, as documented on it
+            MultipartError::Limit(e) => e.into(),
+            MultipartError::Io(e) => e,
+            e => io::Error::new(e.kind(), e),
+        }
+    }
+}
+
+pub(crate) fn malformed_boundary<S: Into<String>>(msg: S) -> io::Error {
+    MultipartError::MalformedBoundary(msg.into()).into()
+}
+
+pub(crate) fn truncated<S: Into<String>>(msg: S) -> io::Error {
+    MultipartError::Truncated(msg.into()).into()
+}
+
+pub(crate) fn invalid_headers<S: Into<String>>(msg: S) -> io::Error {
+    MultipartError::InvalidHeaders(msg.into()).into()
+}
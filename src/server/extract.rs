@@ -0,0 +1,313 @@
+//! Filling a struct directly from the fields of a request.
+//!
+//! Implement `MultipartForm` for a struct to read it from a `Multipart` in one call, with each
+//! field of the struct converted from the field of the request with the same name by
+//! `FromField`. With the `derive` feature, `#[derive(MultipartForm)]` writes the implementation:
+//!
+#![cfg_attr(feature = "derive", doc = "```rust")]
+#![cfg_attr(not(feature = "derive"), doc = "```rust,ignore")]
+//! use mpart::server::extract::MultipartForm;
+//! use mpart::server::{Multipart, SavedField};
+//!
+//! #[derive(MultipartForm)]
+//! struct Upload {
+//!     title: String,
+//!     // at most 1 MiB
+//!     #[mpart(limit = 1024 * 1024)]
+//!     avatar: SavedField,
+//!     #[mpart(rename = "tag-line")]
+//!     tag_line: Option<String>,
+//!     #[mpart(default)]
+//!     notes: Vec<u8>,
+//! }
+//!
+//! let body = "--boundary\r\n\
+//!     Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+//!     Holiday\r\n\
+//!     --boundary\r\n\
+//!     Content-Disposition: form-data; name=\"avatar\"; filename=\"avatar.png\"\r\n\
+//!     Content-Type: image/png\r\n\r\n\
+//!     not really a png\r\n\
+//!     --boundary--";
+//!
+//! let mut multipart = Multipart::with_body(body.as_bytes(), "boundary");
+//! let upload = Upload::from_multipart(&mut multipart).unwrap();
+//!
+//! assert_eq!(upload.title, "Holiday");
+//! assert_eq!(upload.avatar.headers.filename.as_deref(), Some("avatar.png"));
+//! assert_eq!(upload.tag_line, None);
+//! assert!(upload.notes.is_empty());
+//! ```
+//!
+//! The derived implementation takes these options in `#[mpart(...)]` attributes on fields:
+//!
+//! * `rename = "name"`: the name of the field in the request, instead of the name in the struct.
+//! * `limit = <expr>`: the maximum size of the field's data in bytes, as a `u64`.
+//! * `default`: use `Default::default()` if the request doesn't have the field.
+//!
+//! Fields of type `Option<T>` are `None` if the request doesn't have them; any other field
+//! missing from the request is an error, as is a field sent more than once. Fields of the
+//! request which aren't in the struct are skipped.
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+
+use super::field::{MultipartField, ReadEntry};
+use super::limits::LimitError;
+use super::save::{PartialReason, SaveResult, SavedField};
+use super::Multipart;
+
+/// The derive macro for `MultipartForm`. Requires the `derive` feature.
+#[cfg(feature = "derive")]
+pub use mpart_derive::MultipartForm;
+
+/// A type which can be filled from the fields of a request.
+///
+/// Usually derived; see the [module docs](index.html).
+#[allow(clippy::module_name_repetitions)]
+pub trait MultipartForm: Sized {
+    /// Read all the remaining fields of `multipart` into a `Self`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if reading any field fails, or with `io::ErrorKind::InvalidData`
+    /// wrapping an `ExtractError` or `LimitError` if the fields don't fit `Self`.
+    fn from_multipart<R: Read>(multipart: &mut Multipart<R>) -> io::Result<Self>;
+}
+
+/// A type which a single field of a request can be converted into, for `MultipartForm`.
+pub trait FromField: Sized {
+    /// Read `field` into a `Self`, failing if its data is larger than `limit` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if reading the field fails, or with `io::ErrorKind::InvalidData`
+    /// wrapping a `LimitError` if it is too large.
+    fn from_field<M: ReadEntry>(field: MultipartField<M>, limit: Option<u64>) -> io::Result<Self>;
+}
+
+/// Reads the data of the field into memory.
+impl FromField for Vec<u8> {
+    fn from_field<M: ReadEntry>(
+        mut field: MultipartField<M>,
+        limit: Option<u64>,
+    ) -> io::Result<Self> {
+        read_limited(&mut field, limit)
+    }
+}
+
+/// Reads the data of the field into memory, decoding it with the charset
+/// `MultipartField::read_text()` uses with the `charset` feature, or as UTF-8 otherwise.
+///
+/// Fails with `io::ErrorKind::InvalidData` if the data isn't valid in that charset; unlike
+/// `read_text()`, malformed sequences are never replaced.
+impl FromField for String {
+    fn from_field<M: ReadEntry>(
+        mut field: MultipartField<M>,
+        limit: Option<u64>,
+    ) -> io::Result<Self> {
+        let data = read_limited(&mut field, limit)?;
+        field.decode_text(data)
+    }
+}
+
+/// Saves the field as `MultipartField::into_owned()` does, with `limit` as the size limit if
+/// set, instead of the default of `SaveBuilder`.
+impl FromField for SavedField {
+    fn from_field<M: ReadEntry>(
+        mut field: MultipartField<M>,
+        limit: Option<u64>,
+    ) -> io::Result<Self> {
+        let mut save = field.data.save();
+
+        if let Some(limit) = limit {
+            save = save.size_limit(limit);
+        }
+
+        let data = match save.temp() {
+            SaveResult::Full(data) => data,
+            SaveResult::Partial(_, PartialReason::FieldSizeLimit { limit, .. }) => {
+                return Err(too_large(&field, limit))
+            }
+            SaveResult::Partial(_, PartialReason::IoError(e)) | SaveResult::Error(e) => {
+                return Err(e)
+            }
+            SaveResult::Partial(_, reason) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, reason))
+            }
+        };
+
+        Ok(SavedField::new(field.headers, data))
+    }
+}
+
+fn read_limited<M: ReadEntry>(
+    field: &mut MultipartField<M>,
+    limit: Option<u64>,
+) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+
+    match limit {
+        // one more byte tells if the field is over the limit
+        Some(limit) => {
+            (&mut field.data)
+                .take(limit.saturating_add(1))
+                .read_to_end(&mut data)?;
+
+            if data.len() as u64 > limit {
+                return Err(too_large(field, limit));
+            }
+        }
+        None => {
+            field.data.read_to_end(&mut data)?;
+        }
+    }
+
+    Ok(data)
+}
+
+fn too_large<M: ReadEntry>(field: &MultipartField<M>, max: u64) -> io::Error {
+    let field_name = field.headers.name.clone();
+
+    if field.headers.filename.is_some() {
+        LimitError::FileTooLarge {
+            field: field_name,
+            max,
+        }
+    } else {
+        LimitError::FieldTooLarge {
+            field: field_name,
+            max,
+        }
+    }
+    .into()
+}
+
+/// The error from filling a `MultipartForm`, wrapped in the `io::Error` returned by
+/// `MultipartForm::from_multipart()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::module_name_repetitions)]
+pub enum ExtractError {
+    /// A required field isn't in the request.
+    MissingField {
+        /// The name of the field in the request.
+        field: &'static str,
+    },
+    /// A field was sent more than once.
+    DuplicateField {
+        /// The name of the field in the request.
+        field: &'static str,
+    },
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::MissingField { field } => write!(f, "missing field `{field}`"),
+            ExtractError::DuplicateField { field } => {
+                write!(f, "field `{field}` was sent more than once")
+            }
+        }
+    }
+}
+
+impl Error for ExtractError {}
+
+impl From<ExtractError> for io::Error {
+    fn from(err: ExtractError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod test {
+    use super::{ExtractError, MultipartForm};
+
+    use crate::server::limits::LimitError;
+    use crate::server::{Multipart, SavedField};
+
+    use std::io;
+
+    #[derive(MultipartForm)]
+    struct Upload {
+        title: String,
+        #[mpart(limit = 16)]
+        file: SavedField,
+        #[mpart(rename = "tag-line")]
+        tag_line: Option<String>,
+        #[mpart(default, limit = 4)]
+        r#type: Vec<u8>,
+    }
+
+    fn text(name: &str, value: &str) -> String {
+        format!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"{name}\"\r\n\r\n\
+             {value}\r\n"
+        )
+    }
+
+    fn file(data: &str) -> String {
+        format!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\r\n\
+             {data}\r\n"
+        )
+    }
+
+    fn extract(parts: &[String]) -> io::Result<Upload> {
+        let body = parts.concat() + "--boundary--";
+        let mut multipart = Multipart::with_body(body.as_bytes(), "boundary");
+        Upload::from_multipart(&mut multipart)
+    }
+
+    fn error<E: std::error::Error + Clone + 'static>(parts: &[String]) -> E {
+        let err = extract(parts).err().expect("extracting should fail");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        err.get_ref().unwrap().downcast_ref::<E>().unwrap().clone()
+    }
+
+    #[test]
+    fn test_derive_multipart_form() {
+        let upload = extract(&[
+            text("title", "title"),
+            text("unknown", "skipped"),
+            file("file data"),
+            text("tag-line", "tag line"),
+        ])
+        .unwrap();
+
+        assert_eq!(upload.title, "title");
+        assert_eq!(upload.file.headers.filename.as_deref(), Some("file.bin"));
+        assert_eq!(upload.file.data.size(), 9);
+        assert_eq!(upload.tag_line.as_deref(), Some("tag line"));
+        assert!(upload.r#type.is_empty());
+
+        let upload = extract(&[file(""), text("type", "type"), text("title", "")]).unwrap();
+        assert_eq!(upload.tag_line, None);
+        assert_eq!(upload.r#type, b"type");
+
+        assert_eq!(
+            error::<ExtractError>(&[file("")]),
+            ExtractError::MissingField { field: "title" }
+        );
+        assert_eq!(
+            error::<ExtractError>(&[text("title", "a"), text("title", "b"), file("")]),
+            ExtractError::DuplicateField { field: "title" }
+        );
+        assert_eq!(
+            error::<LimitError>(&[text("title", ""), file("seventeen bytes!!")]),
+            LimitError::FileTooLarge {
+                field: "file".into(),
+                max: 16
+            }
+        );
+        assert_eq!(
+            error::<LimitError>(&[text("title", ""), file(""), text("type", "types")]),
+            LimitError::FieldTooLarge {
+                field: "type".into(),
+                max: 4
+            }
+        );
+    }
+}
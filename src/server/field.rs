@@ -3,11 +3,17 @@ use mime::Mime;
 
 use self::ReadEntryResult::{End, Entry, Error};
 use httparse::{self, Error as HttparseError, Header, Status, EMPTY_HEADER};
-use std::io::{self, BufRead, Read};
+use std::io::{self, BufRead, Read, Write};
+use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 use std::{fmt, str};
 
-use super::save::SaveBuilder;
+use super::base64::Base64Decoder;
+use super::error::invalid_headers;
+use super::parse::{FormDataDisposition, MAX_HEADERS};
+use super::save::{PartialReason, SaveBuilder, SaveResult, SavedField};
+use super::warning::{ParseWarning, WarningReason};
+use super::Multipart;
 use thiserror::Error;
 
 const EMPTY_STR_HEADER: StrHeader<'static> = StrHeader { name: "", val: "" };
@@ -40,13 +46,15 @@ impl<'s, 'a: 's> fmt::Display for DisplayHeaders<'s, 'a> {
     }
 }
 
-fn with_headers<R, F, Ret>(r: &mut R, closure: F) -> Result<Ret, ParseHeaderError>
+fn with_headers<R, F, Ret>(
+    r: &mut R,
+    lines: HeaderLines,
+    mut closure: F,
+) -> Result<Ret, ParseHeaderError>
 where
     R: BufRead,
-    F: FnOnce(&[StrHeader<'_>]) -> Ret,
+    F: FnOnce(&[StrHeader<'_>], bool) -> Ret,
 {
-    const HEADER_LEN: usize = 4;
-
     let consume;
     let ret;
 
@@ -58,22 +66,32 @@ where
 
         // buffer has stopped growing
         if buf.len() == last_len {
+            // If the client didn't send a CRLF for an empty body, the one ending the headers
+            // was taken as the one preceding the boundary.
+            if buf.ends_with(b"\r\n") {
+                let terminated = [buf, b"\r\n"].concat();
+
+                if let Ok(Ok((_, ret_))) = parse_complete(&terminated, lines, closure) {
+                    consume = buf.len();
+                    ret = ret_;
+                    break;
+                }
+            }
+
             return Err(ParseHeaderError::TooLarge);
         }
 
-        let mut raw_headers = [EMPTY_HEADER; HEADER_LEN];
-
-        match httparse::parse_headers(buf, &mut raw_headers)? {
-            // read more and try again
-            Status::Partial => last_len = buf.len(),
-            Status::Complete((consume_, raw_headers)) => {
-                let mut headers = [EMPTY_STR_HEADER; HEADER_LEN];
-                let headers = copy_headers(raw_headers, &mut headers)?;
-                log::debug!("Parsed headers: {:?}", headers);
+        match parse_complete(buf, lines, closure)? {
+            Ok((consume_, ret_)) => {
                 consume = consume_;
-                ret = closure(headers);
+                ret = ret_;
                 break;
             }
+            // read more and try again
+            Err(closure_) => {
+                closure = closure_;
+                last_len = buf.len();
+            }
         }
     }
 
@@ -81,6 +99,79 @@ where
     Ok(ret)
 }
 
+/// Which lines to accept in a field's headers.
+#[derive(Clone, Copy, Debug)]
+struct HeaderLines {
+    /// Reject any line ending without CR.
+    crlf_only: bool,
+    /// What to do with a headers section mixing CRLF and LF.
+    mixed: LineEndingPolicy,
+    /// The maximum length of a line in bytes, not counting its line ending.
+    max_len: usize,
+}
+
+/// Parse the headers at the start of `buf` and pass them to `closure` along with whether
+/// they mix CRLF and LF line endings, returning the length of the headers section and the
+/// result, or `closure` back if the headers are incomplete.
+fn parse_complete<F, Ret>(
+    buf: &[u8],
+    lines: HeaderLines,
+    closure: F,
+) -> Result<Result<(usize, Ret), F>, ParseHeaderError>
+where
+    F: FnOnce(&[StrHeader<'_>], bool) -> Ret,
+{
+    let mut raw_headers = [EMPTY_HEADER; MAX_HEADERS];
+
+    let Status::Complete((len, raw_headers)) = httparse::parse_headers(buf, &mut raw_headers)?
+    else {
+        // a line without its ending yet may only get longer
+        if has_long_line(buf, lines.max_len) {
+            return Err(ParseHeaderError::LineTooLong { max: lines.max_len });
+        }
+
+        return Ok(Err(closure));
+    };
+
+    if has_long_line(&buf[..len], lines.max_len) {
+        return Err(ParseHeaderError::LineTooLong { max: lines.max_len });
+    }
+
+    if lines.crlf_only && has_bare_lf(&buf[..len]) {
+        return Err(ParseHeaderError::BareLineFeed);
+    }
+
+    let mixed = mixes_line_endings(&buf[..len]);
+
+    if mixed && lines.mixed == LineEndingPolicy::Strict {
+        return Err(ParseHeaderError::MixedLineEndings);
+    }
+
+    let mut headers = [EMPTY_STR_HEADER; MAX_HEADERS];
+    let headers = copy_headers(raw_headers, &mut headers)?;
+    log::debug!("Parsed headers: {:?}", headers);
+
+    Ok(Ok((len, closure(headers, mixed))))
+}
+
+/// Whether any line in `buf`, including an unfinished one at the end, is longer than `max_len`
+/// without its line ending.
+fn has_long_line(buf: &[u8], max_len: usize) -> bool {
+    let line_len = |line: &[u8]| line.strip_suffix(b"\r").unwrap_or(line).len();
+    buf.split(|&b| b == b'\n')
+        .any(|line| line_len(line) > max_len)
+}
+
+/// Whether any line in `buf` ends with LF alone instead of CRLF.
+fn has_bare_lf(buf: &[u8]) -> bool {
+    memchr::memchr_iter(b'\n', buf).any(|idx| idx == 0 || buf[idx - 1] != b'\r')
+}
+
+/// Whether the lines of the headers section `buf` end with both CRLF and a bare LF.
+pub(crate) fn mixes_line_endings(buf: &[u8]) -> bool {
+    has_bare_lf(buf) && memchr::memmem::find(buf, b"\r\n").is_some()
+}
+
 fn copy_headers<'h, 'b: 'h>(
     raw: &[Header<'b>],
     headers: &'h mut [StrHeader<'b>],
@@ -98,16 +189,27 @@ fn copy_headers<'h, 'b: 'h>(
 /// ### Warning: Values are Client-Provided
 /// Everything in this struct are values from the client and should be considered **untrustworthy**.
 /// This crate makes no effort to validate or sanitize any client inputs.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[allow(clippy::module_name_repetitions)]
+#[non_exhaustive]
 pub struct FieldHeaders {
     /// The field's name from the form.
     pub name: Arc<str>,
 
     /// The filename of this entry, if supplied. This is not guaranteed to match the original file
     /// or even to be a valid filename for the current platform.
+    ///
+    /// An extended `filename*` parameter ([RFC 5987](https://tools.ietf.org/html/rfc5987),
+    /// e.g. `filename*=UTF-8''%C3%9F.txt`, or split into RFC 2231 continuations) takes precedence
+    /// over `filename`, if it's in UTF-8 or ISO-8859-1.
+    ///
+    /// Filenames longer than the limit set with `Multipart::set_max_filename_len()`
+    /// (255 bytes by default) are truncated or rejected; see `filename_truncated`.
     pub filename: Option<String>,
 
+    /// `true` if `filename` was truncated to the maximum filename length.
+    pub filename_truncated: bool,
+
     /// The MIME type (`Content-Type` value) of this file, if supplied by the client.
     ///
     /// If this is not supplied, the content-type of the field should default to `text/plain` as
@@ -115,76 +217,361 @@ pub struct FieldHeaders {
     /// should not be implicitly trusted. This crate makes no attempt to identify or validate
     /// the content-type of the actual field data.
     pub content_type: Option<Mime>,
+
+    /// The length of the field's data in bytes, if the client sent a `Content-Length` header
+    /// for this part.
+    ///
+    /// This is only a hint, e.g. for pre-allocating buffers or rejecting a field early;
+    /// the boundary is authoritative for where the field actually ends. A mismatch between
+    /// the two is logged as a warning and otherwise ignored.
+    pub content_length: Option<u64>,
+
+    /// The checksum of the field's data, if the client sent one in a recognized header;
+    /// see `Checksum` for which. Check it with `SaveBuilder::verify_checksums()`.
+    pub checksum: Option<Checksum>,
+
+    /// The value of the `Content-Disposition` header as the client sent it, only without
+    /// surrounding whitespace, e.g. to re-emit the part verbatim or to see why `name` or
+    /// `filename` came out wrong.
+    ///
+    /// This is always set for fields read from a request; it's an `Option` only for
+    /// `FieldHeaders` constructed by hand.
+    pub content_disposition_raw: Option<String>,
+
+    /// The size of the file in bytes, if the client sent the non-standard `size` parameter
+    /// of `Content-Disposition` (as some do). It's ignored if it isn't a number.
+    ///
+    /// Like `content_length`, this is only a hint, e.g. for checking against a limit before
+    /// reading the field; the boundary is authoritative for where the field actually ends.
+    pub declared_size: Option<u64>,
+
+    /// The `Content-Transfer-Encoding` of the field's data, if it's one this crate decodes.
+    ///
+    /// Reading `MultipartField::data` yields the decoded data unless decoding was turned off
+    /// with `Multipart::set_decode_transfer_encoding()`. `PushParser` and the async `Multipart`
+    /// always return the data as sent.
+    pub transfer_encoding: Option<TransferEncoding>,
+}
+
+/// A `Content-Transfer-Encoding` of a field's data which is decoded when reading it,
+/// as some mail gateways and legacy clients still send binary parts encoded
+/// ([RFC 2045, Section 6](https://tools.ietf.org/html/rfc2045#section-6)).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TransferEncoding {
+    /// `base64`. Line breaks and other whitespace in the data are skipped.
+    Base64,
+    /// `quoted-printable`. Soft line breaks are removed, as is whitespace at the end of lines;
+    /// an `=` which doesn't start a valid escape is kept as it is.
+    QuotedPrintable,
+}
+
+impl TransferEncoding {
+    fn from_header(encoding: &str) -> Option<Self> {
+        if encoding.eq_ignore_ascii_case("base64") {
+            Some(TransferEncoding::Base64)
+        } else if encoding.eq_ignore_ascii_case("quoted-printable") {
+            Some(TransferEncoding::QuotedPrintable)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for TransferEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TransferEncoding::Base64 => "base64",
+            TransferEncoding::QuotedPrintable => "quoted-printable",
+        })
+    }
+}
+
+/// A checksum of a field's data sent by the client in the field's headers.
+///
+/// The recognized headers are:
+///
+/// * `X-Checksum-SHA256`: SHA-256, as 64 hex digits or in base64.
+/// * `Content-MD5`: MD5, in base64 as per [RFC 1864](https://tools.ietf.org/html/rfc1864).
+///
+/// If both are present, only SHA-256 is used. Headers whose value is not a digest of the right
+/// length are logged as a warning and ignored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Checksum {
+    /// The hash function the checksum was computed with.
+    pub algorithm: ChecksumAlgorithm,
+    /// The decoded digest.
+    pub digest: Vec<u8>,
+}
+
+/// A hash function for a `Checksum`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChecksumAlgorithm {
+    /// MD5, from a `Content-MD5` header.
+    Md5,
+    /// SHA-256, from an `X-Checksum-SHA256` header.
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    /// The length of a digest in bytes.
+    fn digest_len(self) -> usize {
+        match self {
+            ChecksumAlgorithm::Md5 => 16,
+            ChecksumAlgorithm::Sha256 => 32,
+        }
+    }
+}
+
+impl fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ChecksumAlgorithm::Md5 => "MD5",
+            ChecksumAlgorithm::Sha256 => "SHA-256",
+        })
+    }
 }
 
 impl FieldHeaders {
+    /// Construct headers for a field named `name` with no other headers, e.g. for tests or
+    /// for mocking a request. The other fields can be set afterwards.
+    ///
+    /// ```rust
+    /// use mpart::server::field::FieldHeaders;
+    ///
+    /// let mut headers = FieldHeaders::new("file");
+    /// headers.filename = Some("file.txt".into());
+    /// headers.content_type = Some(mime::TEXT_PLAIN);
+    ///
+    /// assert_eq!(&*headers.name, "file");
+    /// assert_eq!(headers.content_disposition_raw, None);
+    /// ```
+    pub fn new<N: Into<Arc<str>>>(name: N) -> Self {
+        FieldHeaders {
+            name: name.into(),
+            filename: None,
+            filename_truncated: false,
+            content_type: None,
+            content_length: None,
+            checksum: None,
+            content_disposition_raw: None,
+            declared_size: None,
+            transfer_encoding: None,
+        }
+    }
+
     /// Parse the field headers from the passed `BufRead`, consuming the relevant bytes.
-    fn read_from<R: BufRead>(r: &mut R) -> Result<Self, ParseHeaderError> {
-        with_headers(r, Self::parse)?
+    ///
+    /// Anything unusual which doesn't prevent parsing is added to `warnings`.
+    ///
+    /// Lines are checked against `lines` as they are read.
+    ///
+    /// `nested_name` is the name of the field whose data is being parsed as nested
+    /// `multipart/mixed`, which the parts are named after.
+    fn read_from<R: BufRead>(
+        r: &mut R,
+        limit: FilenameLimit,
+        lines: HeaderLines,
+        nested_name: Option<&Arc<str>>,
+        warnings: &mut Vec<WarningReason>,
+    ) -> Result<Self, ParseHeaderError> {
+        with_headers(r, lines, |headers, mixed| {
+            if mixed && lines.mixed == LineEndingPolicy::Lenient {
+                log::warn!("Field headers mix CRLF and LF line endings");
+                warnings.push(WarningReason::MixedLineEndings);
+            }
+
+            Self::parse(headers, limit, nested_name, warnings)
+        })?
+    }
+
+    /// Parse the field headers at the start of `buf`, returning them with the length of the
+    /// headers section including the empty line that ends it, or `None` if `buf` ends first.
+    ///
+    /// Errors are of kind `io::ErrorKind::InvalidData`, as from `read_entry()`.
+    pub(crate) fn parse_slice(
+        buf: &[u8],
+        limit: FilenameLimit,
+        mixed: LineEndingPolicy,
+        warnings: &mut Vec<WarningReason>,
+    ) -> io::Result<Option<(Self, usize)>> {
+        let mut raw_headers = [EMPTY_HEADER; MAX_HEADERS];
+
+        let (len, raw_headers) = match httparse::parse_headers(buf, &mut raw_headers) {
+            Ok(Status::Complete(complete)) => complete,
+            Ok(Status::Partial) => return Ok(None),
+            Err(e) => return Err(ParseHeaderError::from(e).into()),
+        };
+
+        if mixes_line_endings(&buf[..len]) {
+            match mixed {
+                LineEndingPolicy::Strict => return Err(ParseHeaderError::MixedLineEndings.into()),
+                LineEndingPolicy::Lenient => {
+                    log::warn!("Field headers mix CRLF and LF line endings");
+                    warnings.push(WarningReason::MixedLineEndings);
+                }
+            }
+        }
+
+        let mut headers = [EMPTY_STR_HEADER; MAX_HEADERS];
+        let headers = copy_headers(raw_headers, &mut headers)?;
+
+        Ok(Some((Self::parse(headers, limit, None, warnings)?, len)))
     }
 
-    fn parse(headers: &[StrHeader<'_>]) -> Result<FieldHeaders, ParseHeaderError> {
-        let cont_disp = ContentDisp::parse_required(headers)?;
+    fn parse(
+        headers: &[StrHeader<'_>],
+        limit: FilenameLimit,
+        nested_name: Option<&Arc<str>>,
+        warnings: &mut Vec<WarningReason>,
+    ) -> Result<FieldHeaders, ParseHeaderError> {
+        let cont_disp = ContentDisp::parse_required(headers, nested_name.is_some())?;
+
+        if let Some(param) = cont_disp.duplicate_param {
+            log::warn!("Duplicate Content-Disposition parameter: {:?}", param);
+            warnings.push(WarningReason::DuplicateParam(param));
+        }
+
+        let mut filename = cont_disp.filename;
+        let mut filename_truncated = false;
+
+        if let Some(ref mut filename) = filename {
+            if filename.len() > limit.max_len {
+                match limit.on_exceed {
+                    LongFilename::Reject => {
+                        return Err(ParseHeaderError::FilenameTooLong {
+                            len: filename.len(),
+                            max: limit.max_len,
+                        })
+                    }
+                    LongFilename::Truncate => {
+                        let mut end = limit.max_len;
+                        while !filename.is_char_boundary(end) {
+                            end -= 1;
+                        }
+                        warnings.push(WarningReason::FilenameTruncated {
+                            len: filename.len(),
+                        });
+                        filename.truncate(end);
+                        filename_truncated = true;
+                    }
+                }
+            }
+        }
+
+        let transfer_encoding = parse_transfer_encoding(headers, warnings);
 
         Ok(FieldHeaders {
-            name: cont_disp.field_name.into(),
-            filename: cont_disp.filename,
+            name: nested_name.map_or_else(|| cont_disp.field_name.into(), Arc::clone),
+            filename,
+            filename_truncated,
             content_type: parse_content_type(headers)?,
+            content_length: parse_content_length(headers, warnings),
+            checksum: parse_checksum(headers, warnings),
+            content_disposition_raw: Some(cont_disp.raw),
+            declared_size: cont_disp.size,
+            transfer_encoding,
         })
     }
 }
 
+/// What to do with a filename longer than the limit set with `Multipart::set_max_filename_len()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LongFilename {
+    /// Truncate the filename to the limit (on a UTF-8 character boundary) and set
+    /// `FieldHeaders::filename_truncated`.
+    Truncate,
+    /// Fail reading the field with an error of kind `io::ErrorKind::InvalidData`.
+    Reject,
+}
+
+/// The default maximum length of a line of field headers in bytes, not counting its line
+/// ending. See `Multipart::set_max_header_line_len()`.
+pub const DEFAULT_MAX_HEADER_LINE_LEN: usize = 8 * 1024;
+
+/// The longest headers section of a field which is buffered before giving up on it, for
+/// parsers which buffer it themselves rather than relying on the size of a `BufRead`.
+pub(crate) const MAX_HEADERS_LEN: usize = 8 * 1024;
+
+/// What to do with a field's headers whose lines end with both CRLF and a bare LF.
+/// Set with `Multipart::set_line_ending_policy()`, `PushParser::set_line_ending_policy()` or
+/// `Parts::line_ending_policy()`.
+///
+/// Headers using LF throughout are accepted by both of these; use
+/// `Multipart::strict_rfc7578()` to require CRLF.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEndingPolicy {
+    /// Fail reading the field with an error of kind `io::ErrorKind::InvalidData`.
+    Strict,
+    /// Accept the headers, treating both as line endings. `Multipart` adds a
+    /// `WarningReason::MixedLineEndings`.
+    #[default]
+    Lenient,
+}
+
+/// The maximum length of a filename in bytes, and what to do when it's exceeded.
+#[derive(Clone, Copy, Debug)]
+pub struct FilenameLimit {
+    pub(crate) max_len: usize,
+    pub(crate) on_exceed: LongFilename,
+}
+
+impl Default for FilenameLimit {
+    fn default() -> Self {
+        FilenameLimit {
+            // the limit of most filesystems
+            max_len: 255,
+            on_exceed: LongFilename::Truncate,
+        }
+    }
+}
+
 /// The `Content-Disposition` header.
 struct ContentDisp {
     /// The name of the `multipart/form-data` field.
     field_name: String,
     /// The optional filename for this field.
     filename: Option<String>,
+    /// The unparsed header value.
+    raw: String,
+    /// The `size` parameter.
+    size: Option<u64>,
+    /// A parameter which appeared more than once.
+    duplicate_param: Option<&'static str>,
 }
 
 impl ContentDisp {
-    fn parse_required(headers: &[StrHeader<'_>]) -> Result<ContentDisp, ParseHeaderError> {
+    /// With `nested`, any disposition type is accepted and the name is optional.
+    fn parse_required(
+        headers: &[StrHeader<'_>],
+        nested: bool,
+    ) -> Result<ContentDisp, ParseHeaderError> {
         let Some(header) = find_header(headers, "Content-Disposition") else {
             return Err(ParseHeaderError::MissingContentDisposition(
                 DisplayHeaders(headers).to_string(),
             ));
         };
 
-        // Content-Disposition: ?
-        let after_disp_type = match split_once(header.val, ';') {
-            Some((disp_type, after_disp_type)) => {
-                // assert Content-Disposition: form-data
-                // but needs to be parsed out to trim the spaces (allowed by spec IIRC)
-                if disp_type.trim() != "form-data" {
-                    invalid_cont_disp!("unexpected Content-Disposition value", disp_type);
-                }
-                after_disp_type
-            }
-            None => invalid_cont_disp!(
-                "expected additional data after Content-Disposition type",
-                header.val
-            ),
+        let disp = if nested {
+            Ok(FormDataDisposition::parse_nested(header.val))
+        } else {
+            FormDataDisposition::parse(header.val)
         };
 
-        // Content-Disposition: form-data; name=?
-        let (field_name, filename) = match get_str_after("name=", ';', after_disp_type) {
-            None => invalid_cont_disp!(
-                "expected field name and maybe filename, got",
-                after_disp_type
-            ),
-            // Content-Disposition: form-data; name={field_name}; filename=?
-            Some((field_name, after_field_name)) => {
-                let field_name = trim_quotes(field_name);
-                let filename = get_str_after("filename=", ';', after_field_name)
-                    .map(|(filename, _)| trim_quotes(filename).to_owned());
-                (field_name, filename)
-            }
-        };
-
-        Ok(ContentDisp {
-            field_name: field_name.to_owned(),
-            filename,
-        })
+        match disp {
+            Ok(disp) => Ok(ContentDisp {
+                field_name: disp.name.to_owned(),
+                filename: disp
+                    .filename_ext
+                    .or_else(|| disp.filename.map(str::to_owned)),
+                raw: header.val.to_owned(),
+                size: disp.size,
+                duplicate_param: disp.duplicate_param,
+            }),
+            Err((reason, cause)) => invalid_cont_disp!(reason, cause),
+        }
     }
 }
 
@@ -200,6 +587,92 @@ fn parse_content_type(headers: &[StrHeader<'_>]) -> Result<Option<Mime>, ParseHe
     }
 }
 
+fn parse_content_length(
+    headers: &[StrHeader<'_>],
+    warnings: &mut Vec<WarningReason>,
+) -> Option<u64> {
+    let header = find_header(headers, "Content-Length")?;
+
+    match header.val.trim().parse() {
+        Ok(len) => Some(len),
+        Err(_) => {
+            log::warn!("Ignoring invalid Content-Length on field: {:?}", header.val);
+            warnings.push(WarningReason::InvalidContentLength(header.val.into()));
+            None
+        }
+    }
+}
+
+fn parse_transfer_encoding(
+    headers: &[StrHeader<'_>],
+    warnings: &mut Vec<WarningReason>,
+) -> Option<TransferEncoding> {
+    let header = find_header(headers, "Content-Transfer-Encoding")?;
+    let encoding = header.val.trim();
+
+    if ["7bit", "8bit", "binary"]
+        .iter()
+        .any(|identity| encoding.eq_ignore_ascii_case(identity))
+    {
+        return None;
+    }
+
+    let decoded = TransferEncoding::from_header(encoding);
+
+    if decoded.is_none() {
+        log::warn!("Not decoding Content-Transfer-Encoding: {:?}", encoding);
+        warnings.push(WarningReason::UnsupportedTransferEncoding(encoding.into()));
+    }
+
+    decoded
+}
+
+fn parse_checksum(
+    headers: &[StrHeader<'_>],
+    warnings: &mut Vec<WarningReason>,
+) -> Option<Checksum> {
+    [
+        ("X-Checksum-SHA256", ChecksumAlgorithm::Sha256),
+        ("Content-MD5", ChecksumAlgorithm::Md5),
+    ]
+    .into_iter()
+    .find_map(|(name, algorithm)| {
+        let header = find_header(headers, name)?;
+        let val = header.val.trim();
+
+        let digest = if val.len() == algorithm.digest_len() * 2 {
+            decode_hex(val)
+        } else {
+            super::base64::decode(val)
+        };
+
+        match digest {
+            Some(digest) if digest.len() == algorithm.digest_len() => {
+                Some(Checksum { algorithm, digest })
+            }
+            _ => {
+                log::warn!("Ignoring invalid {} on field: {:?}", name, header.val);
+                warnings.push(WarningReason::InvalidChecksum {
+                    header: name,
+                    value: header.val.into(),
+                });
+                None
+            }
+        }
+    })
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
 /// A field in a multipart request with its associated headers and data.
 #[derive(Debug)]
 #[allow(clippy::module_name_repetitions)]
@@ -213,9 +686,20 @@ pub struct MultipartField<M: ReadEntry> {
 
     /// The field's data.
     pub data: MultipartData<M>,
+
+    /// The offset of the start of the body, if the source is positional.
+    body_start: Option<u64>,
 }
 
 impl<M: ReadEntry> MultipartField<M> {
+    /// Clone the headers of this field, e.g. to keep them after the field has been read
+    /// or dropped.
+    ///
+    /// This is cheap as the name is reference-counted.
+    pub fn headers(&self) -> FieldHeaders {
+        self.headers.clone()
+    }
+
     /// Returns `true` if this field has no content-type or the content-type is `text/...`.
     ///
     /// This typically means it can be read to a string, but it could still be using an unsupported
@@ -225,11 +709,374 @@ impl<M: ReadEntry> MultipartField<M> {
     /// The `.save()` adapter can be used to enforce a size limit.
     ///
     /// Detecting character encodings by any means is (currently) beyond the scope of this crate.
+    ///
+    /// This looks at the content-type only and is *not* the inverse of `is_file()`; a file
+    /// upload of a `.txt` file is both a file and text.
     pub fn is_text(&self) -> bool {
         self.headers
             .content_type
             .as_ref()
-            .map_or(true, |ct| ct.type_() == mime::TEXT)
+            .is_none_or(|ct| ct.type_() == mime::TEXT)
+    }
+
+    /// Returns `true` if this field is a file upload, i.e. its `Content-Disposition` header has
+    /// a `filename` parameter (even an empty one, which browsers send for a file input with no
+    /// file selected). Fields without it are plain form values.
+    ///
+    /// This is the same convention browsers and most frameworks use. It does not look at the
+    /// content-type; use `is_text()` to decide how to interpret the data.
+    pub fn is_file(&self) -> bool {
+        self.headers.filename.is_some()
+    }
+
+    /// The byte offsets `(start, end)` of this field's body within the request body,
+    /// relative to where the body reader was when parsing started.
+    ///
+    /// `end` is exclusive and is only known once the following boundary has been found,
+    /// which is guaranteed after the data has been read to EOF. Returns `None` if the end is not
+    /// known yet, or if the source does not track its position.
+    pub fn body_range(&self) -> Option<(u64, u64)> {
+        let start = self.body_start?;
+        let end = self.data.inner.as_ref()?.boundary_position()?;
+        Some((start, end))
+    }
+
+    /// Also collect the raw bytes of this field's body, exactly as they appear in the request,
+    /// as its data is read, up to `limit` bytes. Retrieve them afterwards with `raw_bytes()`.
+    ///
+    /// This is for integrations which need to preserve the exact wire bytes, e.g. to forward
+    /// them verbatim, while also consuming the data. Only data read after this call is captured,
+    /// so call it before reading anything. A reasonable `limit` is the `memory_threshold` the
+    /// field would otherwise be saved with; if the body is longer, the capture is dropped
+    /// rather than held in memory.
+    #[must_use]
+    pub fn with_raw_capture(mut self, limit: usize) -> Self {
+        self.data.raw = Some(RawCapture {
+            buf: Vec::new(),
+            limit,
+            overflowed: false,
+        });
+        self
+    }
+
+    /// The raw bytes of the body read so far, if `with_raw_capture()` was called.
+    ///
+    /// Returns `None` if capturing wasn't enabled or the body exceeded its limit.
+    pub fn raw_bytes(&self) -> Option<&[u8]> {
+        self.data
+            .raw
+            .as_ref()
+            .filter(|raw| !raw.overflowed)
+            .map(|raw| &*raw.buf)
+    }
+
+    /// Copy the rest of this field's data into `dest`, returning the number of bytes copied.
+    ///
+    /// This streams the data straight through without an intermediate buffer, which is useful for
+    /// forwarding a field into a hasher, a compressor or another upload.
+    ///
+    /// The default size limit of `SaveBuilder` applies, as do any `Limits` set on the
+    /// `Multipart`. To copy with a different limit, use
+    /// `self.data.save().size_limit(limit).write_to(dest)` instead, which reports
+    /// `PartialReason::FieldSizeLimit` when the limit is reached.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if reading the field or writing to `dest` fails, or if the field
+    /// is larger than the size limit; some of the data may have been written to `dest` by then.
+    pub fn save_to<W: Write>(&mut self, dest: &mut W) -> io::Result<u64> {
+        self.data.save().write_to(dest).into_full_result()
+    }
+
+    /// Iterate over the rest of this field's data in chunks of `chunk_size` bytes.
+    ///
+    /// Every chunk is exactly `chunk_size` bytes long except for the last one, which may be
+    /// shorter. Iteration stops at the end of the field or after the first error.
+    ///
+    /// This is useful for forwarding a large field into a channel or another sink which
+    /// takes ownership of its input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn chunks(&mut self, chunk_size: usize) -> impl Iterator<Item = io::Result<Vec<u8>>> + '_ {
+        assert!(chunk_size != 0, "chunk_size must be nonzero");
+
+        let mut done = false;
+
+        std::iter::from_fn(move || {
+            if done {
+                return None;
+            }
+
+            let mut chunk = Vec::with_capacity(chunk_size);
+
+            match (&mut self.data)
+                .take(chunk_size as u64)
+                .read_to_end(&mut chunk)
+            {
+                Ok(0) => {
+                    done = true;
+                    None
+                }
+                Ok(_) => Some(Ok(chunk)),
+                Err(e) => {
+                    done = true;
+                    Some(Err(e))
+                }
+            }
+        })
+    }
+
+    /// Read this field's data to completion, returning the headers and data as an owned value
+    /// which no longer borrows the request.
+    ///
+    /// This uses the default settings of `SaveBuilder`: data up to the memory threshold is kept
+    /// in memory (as a string if it is valid UTF-8), anything larger is written to a file
+    /// with a random name in the OS temporary directory. That file is *not* deleted automatically.
+    /// For more control, use `self.data.save()` instead.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if reading the field or writing the file fails, or with
+    /// `io::ErrorKind::InvalidData` if the field exceeds the default size limit.
+    pub fn into_owned(mut self) -> io::Result<SavedField> {
+        let data = match self.data.save().temp() {
+            SaveResult::Full(data) => data,
+            SaveResult::Partial(_, PartialReason::IoError(e)) | SaveResult::Error(e) => {
+                return Err(e)
+            }
+            SaveResult::Partial(_, reason) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "field {:?} could not be read: {:?}",
+                        self.headers.name, reason
+                    ),
+                ))
+            }
+        };
+
+        Ok(SavedField::new(self.headers, data))
+    }
+
+    /// Get a builder type for saving this field's data to memory or the filesystem.
+    ///
+    /// Shorthand for `self.data.save()`; see [`SaveBuilder`](../save/struct.SaveBuilder.html).
+    ///
+    /// To stream a field to a path of your choosing while iterating the request, e.g. routing
+    /// an `avatar` field to its final location, set a memory threshold of `0` so the file is
+    /// always written even if the field is small:
+    ///
+    /// ```rust,no_run
+    /// # use mpart::server::Multipart;
+    /// # fn route(multipart: &mut Multipart<&[u8]>) -> std::io::Result<()> {
+    /// multipart.foreach_entry(|mut field| {
+    ///     if &*field.headers.name == "avatar" {
+    ///         let _ = field
+    ///             .save()
+    ///             .memory_threshold(0)
+    ///             .create_dirs(false)
+    ///             .with_path("/uploads/user/avatar.png");
+    ///     }
+    /// })
+    /// # }
+    /// ```
+    pub fn save(&mut self) -> SaveBuilder<&mut MultipartData<M>> {
+        self.data.save()
+    }
+
+    /// If this field's content-type is `multipart/mixed` with a boundary, as legacy clients send
+    /// several files under one field ([RFC 2388, Section 4.2][rfc2388-4.2]), return a
+    /// `Multipart` reading the files from its data. Returns `None` otherwise.
+    ///
+    /// The files are read as fields named after this one, with the filename and content-type of
+    /// their own headers. Their disposition type, usually `file` or `attachment`, isn't checked.
+    ///
+    /// The returned `Multipart` is configured like the one this field was read from: the files
+    /// count towards the same `set_max_fields()` limit, also for the fields read after this one,
+    /// and the filename limit, strict modes, header line limit, line-ending policy and
+    /// transfer-encoding decoding carry over.
+    ///
+    /// The size `Limits` aren't inherited: they apply to the data of this field as a whole,
+    /// which the files are read from, but not to each file.
+    ///
+    /// ```rust
+    /// use mpart::server::Multipart;
+    ///
+    /// let body = "--outer\r\n\
+    ///     Content-Disposition: form-data; name=\"files\"\r\n\
+    ///     Content-Type: multipart/mixed; boundary=inner\r\n\r\n\
+    ///     --inner\r\n\
+    ///     Content-Disposition: file; filename=\"a.txt\"\r\n\r\n\
+    ///     first\r\n\
+    ///     --inner\r\n\
+    ///     Content-Disposition: file; filename=\"b.txt\"\r\n\r\n\
+    ///     second\r\n\
+    ///     --inner--\r\n\
+    ///     --outer--";
+    ///
+    /// let mut multipart = Multipart::with_body(body.as_bytes(), "outer");
+    /// let mut field = multipart.read_entry().unwrap().unwrap();
+    /// let mut files = field.nested().unwrap();
+    ///
+    /// let mut filenames = Vec::new();
+    /// files
+    ///     .foreach_entry(|file| filenames.push(file.headers.filename.unwrap()))
+    ///     .unwrap();
+    /// assert_eq!(filenames, ["a.txt", "b.txt"]);
+    /// ```
+    ///
+    /// [rfc2388-4.2]: https://tools.ietf.org/html/rfc2388#section-4.2
+    pub fn nested(&mut self) -> Option<Multipart<&mut MultipartData<M>>> {
+        let content_type = self.headers.content_type.as_ref()?;
+
+        if content_type.type_() != mime::MULTIPART || content_type.subtype() != "mixed" {
+            return None;
+        }
+
+        let boundary = content_type.get_param(mime::BOUNDARY)?;
+
+        let outer = self.data.inner_mut();
+        let filename_limit = outer.filename_limit();
+        let max_fields = outer.max_fields();
+        let fields_read = outer.fields_read().unwrap_or_default();
+        let line_ending_policy = outer.line_ending_policy();
+        let max_header_line_len = outer.max_header_line_len();
+        let decode_transfer_encoding = outer.decode_transfer_encoding();
+        let strict = outer.is_strict();
+        let strict_rfc7578 = outer.strict_rfc7578();
+        #[cfg(feature = "charset")]
+        let default_charset = outer.default_charset();
+
+        let mut nested = Multipart {
+            filename_limit,
+            max_fields,
+            fields_read,
+            line_ending_policy,
+            max_header_line_len,
+            nested_name: Some(self.headers.name.clone()),
+            decode_transfer_encoding,
+            #[cfg(feature = "charset")]
+            default_charset,
+            ..Multipart::with_body(&mut self.data, boundary.as_str())
+        };
+        nested.reader.set_strict(strict);
+        nested.reader.set_strict_rfc7578(strict_rfc7578);
+
+        Some(nested)
+    }
+
+    /// Read the rest of this field's data to a `String`, decoding it with the character encoding
+    /// given by the `charset` parameter of its content-type.
+    ///
+    /// Without a `charset` parameter, the encoding is the value of a `_charset_` field earlier in
+    /// the request, as [RFC 7578, Section 4.6][rfc7578-4.6] describes for the hidden field
+    /// browsers send. That field is only picked up if it's read with this method too; see also
+    /// `Multipart::set_default_charset()`. If there is neither, the encoding is UTF-8.
+    ///
+    /// Malformed sequences in the data are replaced with U+FFFD REPLACEMENT CHARACTER rather
+    /// than returning an error, as browsers do. A leading byte-order mark overrides the declared
+    /// charset and is stripped.
+    ///
+    /// No size limit is applied; the whole field is read into memory. Check `is_text()` first
+    /// and use `self.data.save()` to enforce a limit if the client is untrusted.
+    ///
+    /// Requires the `charset` feature.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if reading the field fails, or with `io::ErrorKind::InvalidData`
+    /// if the `charset` parameter names an encoding which is not recognized.
+    ///
+    /// [rfc7578-4.6]: https://tools.ietf.org/html/rfc7578#section-4.6
+    #[cfg(feature = "charset")]
+    pub fn read_text(&mut self) -> io::Result<String> {
+        let mut bytes = Vec::new();
+        self.data.read_to_end(&mut bytes)?;
+
+        let (text, _, _) = self.text_encoding()?.decode(&bytes);
+        self.set_charset_from(&text);
+
+        Ok(text.into_owned())
+    }
+
+    /// Decode data read from this field to a `String` as `read_text()` does, or as UTF-8 without
+    /// the `charset` feature. Either way, malformed sequences are an error of kind
+    /// `io::ErrorKind::InvalidData` instead of being replaced.
+    pub(crate) fn decode_text(&mut self, bytes: Vec<u8>) -> io::Result<String> {
+        #[cfg(feature = "charset")]
+        {
+            let declared = self.text_encoding()?;
+            let (encoding, bom_len) =
+                encoding_rs::Encoding::for_bom(&bytes).unwrap_or((declared, 0));
+
+            let text = encoding
+                .decode_without_bom_handling_and_without_replacement(&bytes[bom_len..])
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "field {:?} is not valid {}",
+                            self.headers.name,
+                            encoding.name()
+                        ),
+                    )
+                })?;
+            self.set_charset_from(&text);
+
+            Ok(text.into_owned())
+        }
+
+        #[cfg(not(feature = "charset"))]
+        {
+            String::from_utf8(bytes).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("field {:?} is not valid UTF-8", self.headers.name),
+                )
+            })
+        }
+    }
+
+    /// If this is a `_charset_` field, make its value the default charset of the request.
+    #[cfg(feature = "charset")]
+    fn set_charset_from(&mut self, text: &str) {
+        if &*self.headers.name != "_charset_" {
+            return;
+        }
+
+        match encoding_rs::Encoding::for_label(text.trim().as_bytes()) {
+            Some(encoding) => {
+                log::debug!("Default charset of the request: {}", encoding.name());
+                self.data.inner_mut().set_default_charset(encoding);
+            }
+            None => log::warn!("Ignoring unrecognized _charset_: {:?}", text),
+        }
+    }
+
+    #[cfg(feature = "charset")]
+    fn text_encoding(&mut self) -> io::Result<&'static encoding_rs::Encoding> {
+        let charset = self
+            .headers
+            .content_type
+            .as_ref()
+            .and_then(|ct| ct.get_param(mime::CHARSET));
+
+        match charset {
+            Some(charset) => encoding_rs::Encoding::for_label(charset.as_str().as_bytes())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unsupported charset: {:?}", charset.as_str()),
+                    )
+                }),
+            None => Ok(self
+                .data
+                .inner_mut()
+                .default_charset()
+                .unwrap_or(encoding_rs::UTF_8)),
+        }
     }
 
     /// Read the next entry in the request.
@@ -268,9 +1115,163 @@ impl<M: ReadEntry> MultipartField<M> {
 /// The data of a field in a `multipart/form-data` request.
 ///
 /// You can read it to EOF, or use the `save()` adaptor to save it to disk/memory.
+///
+/// If the field has a `FieldHeaders::transfer_encoding`, the data read is decoded from it.
 #[derive(Debug)]
 pub struct MultipartData<M> {
     inner: Option<M>,
+    raw: Option<RawCapture>,
+    decoder: Option<Decoder>,
+}
+
+/// The raw bytes of a field collected by `MultipartField::with_raw_capture()`.
+#[derive(Debug)]
+struct RawCapture {
+    buf: Vec<u8>,
+    limit: usize,
+    overflowed: bool,
+}
+
+impl RawCapture {
+    fn extend(&mut self, data: &[u8]) {
+        if self.overflowed {
+            return;
+        }
+
+        if self.buf.len() + data.len() > self.limit {
+            log::debug!("Raw capture exceeded limit of {} bytes", self.limit);
+            self.overflowed = true;
+            self.buf = Vec::new();
+        } else {
+            self.buf.extend_from_slice(data);
+        }
+    }
+}
+
+/// The longest run of whitespace held back while decoding quoted-printable to see whether it
+/// ends the line; the longest line RFC 5322 allows.
+const QP_MAX_HELD: usize = 998;
+
+/// Decodes the data of a field with a `TransferEncoding` as it is read.
+#[derive(Debug)]
+struct Decoder {
+    encoding: TransferEncoding,
+    base64: Base64Decoder,
+    /// The end of quoted-printable data which could be part of an escape, soft line break or
+    /// trailing whitespace continuing in the next chunk.
+    held: Vec<u8>,
+    /// Decoded data not consumed yet, from `pos`.
+    out: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl Decoder {
+    fn new(encoding: TransferEncoding) -> Self {
+        Decoder {
+            encoding,
+            base64: Base64Decoder::default(),
+            held: Vec::new(),
+            out: Vec::new(),
+            pos: 0,
+            done: false,
+        }
+    }
+
+    /// Decode `input` into `out`; `input` is empty at the end of the data.
+    fn decode(&mut self, input: &[u8]) -> io::Result<()> {
+        let eof = input.is_empty();
+
+        match self.encoding {
+            TransferEncoding::Base64 if eof => self.base64.finish()?,
+            TransferEncoding::Base64 => self.base64.decode(input, &mut self.out)?,
+            TransferEncoding::QuotedPrintable if self.held.is_empty() => {
+                let used = decode_quoted_printable(input, eof, &mut self.out);
+                self.held.extend_from_slice(&input[used..]);
+            }
+            TransferEncoding::QuotedPrintable => {
+                let mut input = [&self.held, input].concat();
+                let used = decode_quoted_printable(&input, eof, &mut self.out);
+                input.drain(..used);
+                self.held = input;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Decode as much quoted-printable as possible from `input` into `out`, returning the number of
+/// bytes decoded. Unless `eof`, this stops before anything which may continue past the end
+/// of `input`.
+fn decode_quoted_printable(input: &[u8], eof: bool, out: &mut Vec<u8>) -> usize {
+    let whitespace = |data: &[u8]| {
+        data.iter()
+            .take_while(|&&byte| byte == b' ' || byte == b'\t')
+            .count()
+    };
+
+    let mut i = 0;
+
+    while i < input.len() {
+        match input[i] {
+            b'=' => {
+                let rest = &input[i + 1..];
+                let ws = whitespace(rest);
+
+                match rest[ws..] {
+                    // a soft line break, which may have trailing whitespace too
+                    [b'\r', b'\n', ..] => {
+                        i += 1 + ws + 2;
+                        continue;
+                    }
+                    [b'\n', ..] => {
+                        i += 1 + ws + 1;
+                        continue;
+                    }
+                    [] | [b'\r'] if !eof && ws < QP_MAX_HELD => return i,
+                    _ => (),
+                }
+
+                match *rest {
+                    [hi, lo, ..] if ws == 0 => {
+                        let hex = |digit: u8| (digit as char).to_digit(16);
+
+                        if let (Some(hi), Some(lo)) = (hex(hi), hex(lo)) {
+                            out.push(((hi << 4) | lo) as u8);
+                            i += 3;
+                            continue;
+                        }
+                    }
+                    [] | [_] if !eof => return i,
+                    _ => (),
+                }
+
+                // not an escape, so keep it as RFC 2045 suggests
+                out.push(b'=');
+                i += 1;
+            }
+            b' ' | b'\t' => {
+                let ws = whitespace(&input[i..]);
+
+                match input[i + ws..] {
+                    // whitespace at the end of a line was added in transport
+                    [b'\r', b'\n', ..] | [b'\n', ..] => (),
+                    [] if eof => (),
+                    [] | [b'\r'] if !eof && ws < QP_MAX_HELD => return i,
+                    _ => out.extend_from_slice(&input[i..i + ws]),
+                }
+
+                i += ws;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    i
 }
 
 const DATA_INNER_ERR: &str = "MultipartFile::inner taken and not replaced; this is likely \
@@ -308,6 +1309,11 @@ where
         self.inner.as_mut().expect(DATA_INNER_ERR)
     }
 
+    /// Notify the observer of the `Multipart`, if any, of the digest of this field's data.
+    pub(crate) fn observe_digest(&mut self, digest: &str) {
+        self.inner_mut().observe_digest(digest);
+    }
+
     fn take_inner(&mut self) -> M {
         self.inner.take().expect(DATA_INNER_ERR)
     }
@@ -319,7 +1325,25 @@ where
 
 impl<M: ReadEntry> Read for MultipartData<M> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner_mut().source_mut().read(buf)
+        if self.decoder.is_some() {
+            let decoded = self.fill_buf()?;
+            let read = decoded.len().min(buf.len());
+            buf[..read].copy_from_slice(&decoded[..read]);
+            self.consume(read);
+            return Ok(read);
+        }
+
+        self.inner_mut().check_limits()?;
+
+        let read = self.inner_mut().source_mut().read(buf)?;
+        self.inner_mut().observe_bytes(read as u64);
+
+        if let Some(raw) = self.raw.as_mut() {
+            raw.extend(&buf[..read]);
+        }
+
+        self.inner_mut().check_limits()?;
+        Ok(read)
     }
 }
 
@@ -328,40 +1352,64 @@ impl<M: ReadEntry> Read for MultipartData<M> {
 /// Use `set_min_buf_size()` if you require a minimum buffer length.
 impl<M: ReadEntry> BufRead for MultipartData<M> {
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
-        self.inner_mut().source_mut().fill_buf()
+        // what was consumed is counted, so a limit is exceeded by at most one buffer
+        self.inner_mut().check_limits()?;
+
+        let MultipartData {
+            inner,
+            raw,
+            decoder,
+        } = self;
+        let inner = inner.as_mut().expect(DATA_INNER_ERR);
+
+        let Some(decoder) = decoder.as_mut() else {
+            return inner.source_mut().fill_buf();
+        };
+
+        // limits, the observer and raw capture see the data as sent
+        while decoder.pos == decoder.out.len() && !decoder.done {
+            decoder.out.clear();
+            decoder.pos = 0;
+
+            let input = inner.source_mut().fill_buf()?;
+            let len = input.len();
+            decoder.decode(input)?;
+
+            if let Some(raw) = raw.as_mut() {
+                raw.extend(input);
+            }
+
+            decoder.done = len == 0;
+            inner.source_mut().consume(len);
+            inner.observe_bytes(len as u64);
+            inner.check_limits()?;
+        }
+
+        Ok(&decoder.out[decoder.pos..])
     }
 
     fn consume(&mut self, amt: usize) {
-        self.inner_mut().source_mut().consume(amt);
-    }
-}
+        if let Some(decoder) = self.decoder.as_mut() {
+            decoder.pos = (decoder.pos + amt).min(decoder.out.len());
+            return;
+        }
 
-fn split_once(s: &str, delim: char) -> Option<(&str, &str)> {
-    s.find(delim).map(|idx| s.split_at(idx))
-}
+        if amt > 0 && self.raw.is_some() {
+            // the bytes being consumed are still buffered so this doesn't read anything
+            let source = self.inner.as_mut().expect(DATA_INNER_ERR).source_mut();
 
-fn trim_quotes(s: &str) -> &str {
-    s.trim_matches('"')
-}
+            if let (Ok(buf), Some(raw)) = (source.fill_buf(), self.raw.as_mut()) {
+                raw.extend(&buf[..amt.min(buf.len())]);
+            }
+        }
 
-/// Get the string after `needle` in `haystack`, stopping before `end_val_delim`
-fn get_str_after<'a>(
-    needle: &str,
-    end_val_delim: char,
-    haystack: &'a str,
-) -> Option<(&'a str, &'a str)> {
-    let val_start_idx = haystack.find(needle)? + needle.len();
-    let val_end_idx = haystack[val_start_idx..]
-        .find(end_val_delim)
-        .map_or(haystack.len(), |end_idx| end_idx + val_start_idx);
-    Some((
-        &haystack[val_start_idx..val_end_idx],
-        &haystack[val_end_idx..],
-    ))
+        self.inner_mut().source_mut().consume(amt);
+        self.inner_mut().observe_bytes(amt as u64);
+    }
 }
 
 fn io_str_utf8(buf: &[u8]) -> io::Result<&str> {
-    str::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    str::from_utf8(buf).map_err(|e| invalid_headers(format!("field header is not UTF-8: {e}")))
 }
 
 fn find_header<'a, 'b>(headers: &'a [StrHeader<'b>], name: &str) -> Option<&'a StrHeader<'b>> {
@@ -380,8 +1428,21 @@ pub trait ReadEntry: PrivReadEntry + Sized {
 
         log::debug!("ReadEntry::read_entry()");
 
-        if self.consume_boundary().is_err() {
-            return ReadEntryResult::End(self);
+        // the boundary was already consumed by a call which failed reading the headers
+        if !self.take_headers_pending() {
+            match self.consume_boundary() {
+                Ok(true) => (),
+                Ok(false) => {
+                    self.observe_end(Ok(()));
+                    return End(self);
+                }
+                Err(e) => {
+                    if !is_retryable(&e) {
+                        self.observe_end(Err(&e));
+                    }
+                    return Error(self, e);
+                }
+            }
         }
 
         match self.read_headers() {
@@ -400,12 +1461,32 @@ pub trait ReadEntry: PrivReadEntry + Sized {
                     }
                 }
 
+                self.expect_body_len(headers.content_length);
+                self.observe_field_start(&headers);
+
+                let decoder = headers
+                    .transfer_encoding
+                    .filter(|_| self.decode_transfer_encoding())
+                    .map(Decoder::new);
+
                 Entry(MultipartField {
                     headers,
-                    data: MultipartData { inner: Some(self) },
+                    body_start: self.position(),
+                    data: MultipartData {
+                        inner: Some(self),
+                        raw: None,
+                        decoder,
+                    },
                 })
             }
-            Err(e) => ReadEntryResult::Error(self, e),
+            Err(e) => {
+                if is_retryable(&e) {
+                    self.set_headers_pending();
+                } else {
+                    self.observe_end(Err(&e));
+                }
+                ReadEntryResult::Error(self, e)
+            }
         }
     }
     /// Equivalent to `read_entry()` but takes `&mut self`
@@ -416,6 +1497,14 @@ pub trait ReadEntry: PrivReadEntry + Sized {
 
 impl<T> ReadEntry for T where T: PrivReadEntry {}
 
+/// Errors after which `read_entry()` can be called again to pick up where it left off.
+fn is_retryable(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+    )
+}
+
 /// Public trait but not re-exported.
 pub trait PrivReadEntry {
     type Source: BufRead;
@@ -432,10 +1521,167 @@ pub trait PrivReadEntry {
     /// Will throw `error` if there is error in consuming next boundary
     fn consume_boundary(&mut self) -> io::Result<bool>;
 
+    /// The number of bytes read from the start of the body, if the source tracks it.
+    fn position(&self) -> Option<u64> {
+        None
+    }
+
+    /// The offset of the next boundary from the start of the body, if it has been found
+    /// and the source tracks it.
+    fn boundary_position(&self) -> Option<u64> {
+        None
+    }
+
+    /// The limit on filename length to apply when parsing field headers.
+    fn filename_limit(&self) -> FilenameLimit {
+        FilenameLimit::default()
+    }
+
+    /// Called with the declared `Content-Length` of a field whose body is about to be read,
+    /// so the source can check it against the actual length.
+    fn expect_body_len(&mut self, _len: Option<u64>) {}
+
+    /// Notify the observer, if any, that a field was found.
+    fn observe_field_start(&mut self, _headers: &FieldHeaders) {}
+
+    /// Notify the observer, if any, that `n` bytes of field data were read.
+    fn observe_bytes(&mut self, _n: u64) {}
+
+    /// Fail if the field data read so far exceeds a limit set with `Multipart::set_limits()`.
+    fn check_limits(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Notify the observer, if any, that there are no more fields, or reading the next one failed.
+    fn observe_end(&mut self, _res: Result<(), &io::Error>) {}
+
+    /// Notify the observer, if any, of the digest of the current field's data.
+    fn observe_digest(&mut self, _digest: &str) {}
+
+    /// Record a non-fatal anomaly, if warnings are being collected.
+    fn warn(&mut self, _warning: ParseWarning) {}
+
+    /// Whether to reject anything RFC 7578 doesn't allow instead of tolerating it.
+    fn strict_rfc7578(&self) -> bool {
+        false
+    }
+
+    /// Whether boundaries are only recognized where RFC 2046 allows them.
+    fn is_strict(&self) -> bool {
+        false
+    }
+
+    /// The `Multipart::set_max_fields()` limit.
+    fn max_fields(&self) -> usize {
+        usize::MAX
+    }
+
+    /// The number of fields read so far, counted against `max_fields()`, to be shared with
+    /// nested `Multipart`s.
+    fn fields_read(&self) -> Option<Arc<AtomicUsize>> {
+        None
+    }
+
+    /// What to do with field headers mixing CRLF and LF line endings.
+    fn line_ending_policy(&self) -> LineEndingPolicy {
+        LineEndingPolicy::default()
+    }
+
+    /// The maximum length of a line of field headers.
+    fn max_header_line_len(&self) -> usize {
+        DEFAULT_MAX_HEADER_LINE_LEN
+    }
+
+    /// The name of the field whose data is being read as nested `multipart/mixed`, if it is.
+    fn nested_name(&self) -> Option<Arc<str>> {
+        None
+    }
+
+    /// Whether to decode the data of fields with a `TransferEncoding`.
+    fn decode_transfer_encoding(&self) -> bool {
+        true
+    }
+
+    /// The encoding of text fields without a `charset` parameter, if not UTF-8.
+    #[cfg(feature = "charset")]
+    fn default_charset(&self) -> Option<&'static encoding_rs::Encoding> {
+        None
+    }
+
+    /// Set the encoding of text fields without a `charset` parameter, from a `_charset_` field.
+    #[cfg(feature = "charset")]
+    fn set_default_charset(&mut self, _encoding: &'static encoding_rs::Encoding) {}
+
+    /// Remember that a boundary was consumed but reading the headers after it failed with a
+    /// retryable error, so the next `read_entry()` starts with the headers.
+    fn set_headers_pending(&mut self) {}
+
+    /// Whether `set_headers_pending()` was called since the last call to this, clearing it.
+    fn take_headers_pending(&mut self) -> bool {
+        false
+    }
+
     #[allow(clippy::missing_errors_doc)]
     fn read_headers(&mut self) -> Result<FieldHeaders, io::Error> {
-        FieldHeaders::read_from(self.source_mut())
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        let limit = self.filename_limit();
+        let strict = self.strict_rfc7578();
+        let lines = HeaderLines {
+            crlf_only: strict,
+            mixed: self.line_ending_policy(),
+            max_len: self.max_header_line_len(),
+        };
+        let mut warnings = Vec::new();
+
+        let nested_name = self.nested_name();
+        let headers = FieldHeaders::read_from(
+            self.source_mut(),
+            limit,
+            lines,
+            nested_name.as_ref(),
+            &mut warnings,
+        )?;
+
+        if let Some(encoding) = headers.transfer_encoding {
+            if !self.decode_transfer_encoding() {
+                warnings.push(WarningReason::UnsupportedTransferEncoding(
+                    encoding.to_string(),
+                ));
+            }
+        }
+
+        if strict {
+            if let Some(encoding) = headers.transfer_encoding {
+                return Err(invalid_headers(format!(
+                    "field {:?}: Content-Transfer-Encoding {:?} is not allowed",
+                    headers.name,
+                    encoding.to_string()
+                )));
+            }
+
+            let violation = warnings.iter().find(|reason| {
+                matches!(
+                    reason,
+                    WarningReason::DuplicateParam(_)
+                        | WarningReason::UnsupportedTransferEncoding(_)
+                )
+            });
+
+            if let Some(reason) = violation {
+                return Err(invalid_headers(format!(
+                    "field {:?}: {}",
+                    headers.name, reason
+                )));
+            }
+        }
+
+        for reason in warnings {
+            self.warn(ParseWarning {
+                reason,
+                field: Some(headers.name.clone()),
+            });
+        }
+
+        Ok(headers)
     }
 
     #[allow(clippy::missing_errors_doc)]
@@ -449,7 +1695,7 @@ pub trait PrivReadEntry {
     }
 }
 
-impl<'a, M: ReadEntry> PrivReadEntry for &'a mut M {
+impl<M: ReadEntry> PrivReadEntry for &mut M {
     type Source = M::Source;
 
     fn source_mut(&mut self) -> &mut M::Source {
@@ -463,6 +1709,96 @@ impl<'a, M: ReadEntry> PrivReadEntry for &'a mut M {
     fn consume_boundary(&mut self) -> io::Result<bool> {
         (**self).consume_boundary()
     }
+
+    fn position(&self) -> Option<u64> {
+        (**self).position()
+    }
+
+    fn boundary_position(&self) -> Option<u64> {
+        (**self).boundary_position()
+    }
+
+    fn filename_limit(&self) -> FilenameLimit {
+        (**self).filename_limit()
+    }
+
+    fn expect_body_len(&mut self, len: Option<u64>) {
+        (**self).expect_body_len(len);
+    }
+
+    fn observe_field_start(&mut self, headers: &FieldHeaders) {
+        (**self).observe_field_start(headers);
+    }
+
+    fn observe_bytes(&mut self, n: u64) {
+        (**self).observe_bytes(n);
+    }
+
+    fn check_limits(&mut self) -> io::Result<()> {
+        (**self).check_limits()
+    }
+
+    fn observe_end(&mut self, res: Result<(), &io::Error>) {
+        (**self).observe_end(res);
+    }
+
+    fn observe_digest(&mut self, digest: &str) {
+        (**self).observe_digest(digest);
+    }
+
+    fn warn(&mut self, warning: ParseWarning) {
+        (**self).warn(warning);
+    }
+
+    fn strict_rfc7578(&self) -> bool {
+        (**self).strict_rfc7578()
+    }
+
+    fn is_strict(&self) -> bool {
+        (**self).is_strict()
+    }
+
+    fn max_fields(&self) -> usize {
+        (**self).max_fields()
+    }
+
+    fn fields_read(&self) -> Option<Arc<AtomicUsize>> {
+        (**self).fields_read()
+    }
+
+    fn line_ending_policy(&self) -> LineEndingPolicy {
+        (**self).line_ending_policy()
+    }
+
+    fn max_header_line_len(&self) -> usize {
+        (**self).max_header_line_len()
+    }
+
+    fn nested_name(&self) -> Option<Arc<str>> {
+        (**self).nested_name()
+    }
+
+    fn decode_transfer_encoding(&self) -> bool {
+        (**self).decode_transfer_encoding()
+    }
+
+    #[cfg(feature = "charset")]
+    fn default_charset(&self) -> Option<&'static encoding_rs::Encoding> {
+        (**self).default_charset()
+    }
+
+    #[cfg(feature = "charset")]
+    fn set_default_charset(&mut self, encoding: &'static encoding_rs::Encoding) {
+        (**self).set_default_charset(encoding);
+    }
+
+    fn set_headers_pending(&mut self) {
+        (**self).set_headers_pending();
+    }
+
+    fn take_headers_pending(&mut self) -> bool {
+        (**self).take_headers_pending()
+    }
 }
 
 /// Ternary result type returned by `ReadEntry::next_entry()`,
@@ -548,10 +1884,83 @@ enum ParseHeaderError {
     MimeError(String),
     #[error("field headers section ridiculously long or missing trailing CRLF-CRLF")]
     TooLarge,
+    #[error("field headers contain a line ending without CR")]
+    BareLineFeed,
+    #[error("field headers mix CRLF and LF line endings")]
+    MixedLineEndings,
+    #[error("field headers contain a line longer than {} bytes", .max)]
+    LineTooLong { max: usize },
+    #[error("filename is {} bytes long, the maximum is {}", .len, .max)]
+    FilenameTooLong { len: usize, max: usize },
     #[error("an IO error has occured {}", .0)]
     Io(#[from] io::Error),
 }
 
+impl From<ParseHeaderError> for io::Error {
+    fn from(err: ParseHeaderError) -> io::Error {
+        match err {
+            // keep the kind of errors from the body, e.g. `TimedOut`
+            ParseHeaderError::Io(e) => e,
+            e => invalid_headers(e.to_string()),
+        }
+    }
+}
+
+/// The error for a headers section which is longer than the buffer or `MAX_HEADERS_LEN`.
+pub(crate) fn headers_too_large() -> io::Error {
+    ParseHeaderError::TooLarge.into()
+}
+
+#[test]
+fn test_content_disp_unquoted() {
+    let parse = |val| {
+        let headers = [StrHeader {
+            name: "Content-Disposition",
+            val,
+        }];
+        let disp = ContentDisp::parse_required(&headers, false).unwrap();
+        (disp.field_name, disp.filename)
+    };
+
+    let expected = ("field1".to_owned(), Some("file 1.txt".to_owned()));
+
+    assert_eq!(
+        parse("form-data; name=\"field1\"; filename=\"file 1.txt\""),
+        expected
+    );
+    assert_eq!(parse("form-data; name=field1"), ("field1".into(), None));
+    assert_eq!(
+        parse("form-data; name = field1 ; filename = file 1.txt "),
+        expected
+    );
+    assert_eq!(
+        parse("form-data; name=field1; filename=\"file 1.txt\""),
+        expected
+    );
+    assert_eq!(
+        parse("form-data; name=\"field1\"; filename=file 1.txt"),
+        expected
+    );
+
+    // `filename` doesn't match `name` and may come first
+    assert_eq!(
+        parse("form-data; filename=\"file 1.txt\"; name=field1"),
+        expected
+    );
+
+    // quoted values may contain `;`
+    assert_eq!(
+        parse("form-data; name=\"a;b\"; filename=\"c;d\""),
+        ("a;b".into(), Some("c;d".into()))
+    );
+
+    let headers = [StrHeader {
+        name: "Content-Disposition",
+        val: "form-data; filename=\"file.txt\"",
+    }];
+    assert!(ContentDisp::parse_required(&headers, false).is_err());
+}
+
 #[test]
 fn test_find_header() {
     let headers = [
@@ -584,3 +1993,38 @@ fn test_find_header() {
         "binary"
     );
 }
+
+#[test]
+fn test_decode_split_input() {
+    fn decode(encoding: TransferEncoding, chunks: &[&[u8]]) -> Vec<u8> {
+        let mut decoder = Decoder::new(encoding);
+
+        for chunk in chunks.iter().chain(&[&[][..]]) {
+            decoder.decode(chunk).unwrap();
+        }
+
+        decoder.out
+    }
+
+    let cases: [(TransferEncoding, &[u8], &[u8]); 2] = [
+        (TransferEncoding::Base64, b"aGVs\r\nbG8=", b"hello"),
+        (
+            TransferEncoding::QuotedPrintable,
+            b"a=3Db \t\r\nc=\r\nd = e=  \r\nf=4 \t",
+            b"a=b\r\ncd = ef=4",
+        ),
+    ];
+
+    for (encoding, encoded, decoded) in cases {
+        assert_eq!(decode(encoding, &[encoded]), decoded);
+
+        // escapes, soft line breaks and trailing whitespace split between chunks
+        let bytes: Vec<_> = encoded.chunks(1).collect();
+        assert_eq!(decode(encoding, &bytes), decoded);
+
+        for mid in 0..encoded.len() {
+            let (first, second) = encoded.split_at(mid);
+            assert_eq!(decode(encoding, &[first, second]), decoded);
+        }
+    }
+}
@@ -0,0 +1,616 @@
+//! Deserializing the fields of a request into a struct with [Serde](https://serde.rs), like
+//! `serde_urlencoded` does for `application/x-www-form-urlencoded` bodies.
+//! Enabled with the `serde` feature.
+//!
+//! Each field of the struct is deserialized from the fields of the request with the same name:
+//!
+//! * text fields parse into strings, numbers, `bool`s and unit enum variants;
+//! * fields sent more than once deserialize into a `Vec` or other sequence;
+//! * `Option` fields are `None` if the request doesn't have them;
+//! * file fields deserialize into `FilePart`, or anything deserialized from bytes.
+//!
+//! ```rust
+//! use mpart::server::form::{from_multipart, FilePart};
+//! use mpart::server::Multipart;
+//!
+//! #[derive(serde::Deserialize)]
+//! struct Upload {
+//!     title: String,
+//!     tags: Vec<String>,
+//!     rating: Option<u8>,
+//!     file: FilePart,
+//! }
+//!
+//! let body = "--boundary\r\n\
+//!     Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+//!     Holiday\r\n\
+//!     --boundary\r\n\
+//!     Content-Disposition: form-data; name=\"tags\"\r\n\r\n\
+//!     beach\r\n\
+//!     --boundary\r\n\
+//!     Content-Disposition: form-data; name=\"tags\"\r\n\r\n\
+//!     sun\r\n\
+//!     --boundary\r\n\
+//!     Content-Disposition: form-data; name=\"file\"; filename=\"photo.jpg\"\r\n\
+//!     Content-Type: image/jpeg\r\n\r\n\
+//!     not really a jpeg\r\n\
+//!     --boundary--";
+//!
+//! let mut multipart = Multipart::with_body(body.as_bytes(), "boundary");
+//! let upload: Upload = from_multipart(&mut multipart).unwrap();
+//!
+//! assert_eq!(upload.title, "Holiday");
+//! assert_eq!(upload.tags, ["beach", "sun"]);
+//! assert_eq!(upload.rating, None);
+//! assert_eq!(upload.file.filename.as_deref(), Some("photo.jpg"));
+//! assert_eq!(upload.file.data, b"not really a jpeg");
+//! ```
+//!
+//! All fields are read into memory first, files included, so set `Limits` on the `Multipart`
+//! for requests from untrusted clients.
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use mime::Mime;
+use serde::de::value::{MapDeserializer, SeqDeserializer, StringDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, SeqAccess, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer};
+
+use super::Multipart;
+
+/// The struct name `FilePart` asks the deserializer for, so it can be given the whole field.
+const FILE_PART: &str = "$mpart::FilePart";
+
+/// Read all the remaining fields of `multipart` and deserialize them into a `T`.
+///
+/// See the [module docs](index.html) for how fields are mapped.
+///
+/// # Errors
+///
+/// Will return `Error` if reading any field fails, or with `io::ErrorKind::InvalidData`
+/// wrapping a `FormError` if the fields don't deserialize into a `T`.
+pub fn from_multipart<T: DeserializeOwned, R: Read>(multipart: &mut Multipart<R>) -> io::Result<T> {
+    let mut fields: Vec<(String, Vec<Part>)> = Vec::new();
+    // the index of each name in `fields`, which keeps the order the names first appeared in
+    let mut indices: HashMap<Arc<str>, usize> = HashMap::new();
+
+    while let Some(mut field) = multipart.read_entry()? {
+        let mut data = Vec::new();
+        field.data.read_to_end(&mut data)?;
+
+        let headers = field.headers;
+        let part = Part {
+            name: headers.name.clone(),
+            filename: headers.filename,
+            content_type: headers.content_type,
+            data,
+        };
+
+        match indices.get(&headers.name) {
+            Some(&idx) => fields[idx].1.push(part),
+            None => {
+                indices.insert(headers.name.clone(), fields.len());
+                fields.push((headers.name.to_string(), vec![part]));
+            }
+        }
+    }
+
+    let map = fields.into_iter().map(|(name, parts)| (name, Parts(parts)));
+    T::deserialize(MapDeserializer::new(map)).map_err(io::Error::from)
+}
+
+/// A file field of a request, deserialized by `from_multipart()`.
+///
+/// Text fields deserialize into a `FilePart` as well, without a filename.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct FilePart {
+    /// The filename of the field, if supplied.
+    pub filename: Option<String>,
+    /// The `Content-Type` of the field, if supplied.
+    pub content_type: Option<Mime>,
+    /// The data of the field.
+    pub data: Vec<u8>,
+}
+
+impl<'de> Deserialize<'de> for FilePart {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct(
+            FILE_PART,
+            &["filename", "content_type", "data"],
+            FilePartVisitor,
+        )
+    }
+}
+
+struct FilePartVisitor;
+
+impl<'de> Visitor<'de> for FilePartVisitor {
+    type Value = FilePart;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a field of a multipart request")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<FilePart, A::Error> {
+        let missing =
+            || de::Error::custom("`FilePart` can only be deserialized by `from_multipart()`");
+
+        let filename: Option<String> = seq.next_element()?.ok_or_else(missing)?;
+        let content_type: Option<String> = seq.next_element()?.ok_or_else(missing)?;
+        let ByteBuf(data) = seq.next_element()?.ok_or_else(missing)?;
+
+        Ok(FilePart {
+            filename,
+            // this was parsed from the field headers already
+            content_type: content_type.and_then(|ct| ct.parse().ok()),
+            data,
+        })
+    }
+}
+
+struct ByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ByteBufVisitor;
+
+        impl Visitor<'_> for ByteBufVisitor {
+            type Value = ByteBuf;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("bytes")
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, data: Vec<u8>) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(data))
+            }
+
+            fn visit_bytes<E: de::Error>(self, data: &[u8]) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(data.to_vec()))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(ByteBufVisitor)
+    }
+}
+
+/// The error from deserializing the fields of a request with `from_multipart()`, wrapped in
+/// the `io::Error` it returns.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct FormError(String);
+
+impl fmt::Display for FormError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for FormError {}
+
+impl de::Error for FormError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        FormError(msg.to_string())
+    }
+}
+
+impl From<FormError> for io::Error {
+    fn from(err: FormError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// A field of the request, with its data in memory.
+struct Part {
+    name: Arc<str>,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+    data: Vec<u8>,
+}
+
+impl Part {
+    fn text(self) -> Result<String, FormError> {
+        let name = self.name;
+
+        String::from_utf8(self.data)
+            .map_err(|_| FormError(format!("field `{name}` is not valid UTF-8")))
+    }
+
+    fn parse<T: FromStr>(self) -> Result<T, FormError>
+    where
+        T::Err: fmt::Display,
+    {
+        let name = self.name.clone();
+        let text = self.text()?;
+
+        text.parse()
+            .map_err(|e| FormError(format!("field `{name}`: invalid value {text:?}: {e}")))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormError> {
+            visitor.$visit(self.parse()?)
+        }
+    )*};
+}
+
+impl<'de> Deserializer<'de> for Part {
+    type Error = FormError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormError> {
+        if self.filename.is_some() {
+            visitor.visit_byte_buf(self.data)
+        } else {
+            visitor.visit_string(self.text()?)
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormError> {
+        visitor.visit_string(self.text()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormError> {
+        visitor.visit_string(self.text()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormError> {
+        visitor.visit_byte_buf(self.data)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormError> {
+        visitor.visit_byte_buf(self.data)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, FormError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, FormError> {
+        let variant: StringDeserializer<FormError> = self.text()?.into_deserializer();
+        visitor.visit_enum(variant)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, FormError> {
+        if name != FILE_PART {
+            return Err(FormError(format!(
+                "field `{}` can't be deserialized into a struct with fields {:?}",
+                self.name, fields
+            )));
+        }
+
+        let content_type = self.content_type.map(|ct| ct.to_string());
+
+        visitor.visit_seq(SeqDeserializer::new(
+            [
+                FilePartValue::Text(self.filename),
+                FilePartValue::Text(content_type),
+                FilePartValue::Data(self.data),
+            ]
+            .into_iter(),
+        ))
+    }
+
+    forward_to_deserialize_any! {
+        unit unit_struct seq tuple tuple_struct map identifier ignored_any
+    }
+}
+
+/// All the fields of the request with the same name.
+struct Parts(Vec<Part>);
+
+impl Parts {
+    fn single(self) -> Result<Part, FormError> {
+        let mut parts = self.0;
+
+        if parts.len() > 1 {
+            return Err(FormError(format!(
+                "field `{}` was sent {} times but only one value was expected",
+                parts[0].name,
+                parts.len()
+            )));
+        }
+
+        Ok(parts.pop().expect("fields have at least one part"))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, FormError> for Parts {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_single {
+    ($($method:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormError> {
+            self.single()?.$method(visitor)
+        }
+    )*};
+}
+
+impl<'de> Deserializer<'de> for Parts {
+    type Error = FormError;
+
+    deserialize_single! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32
+        deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char
+        deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_unit deserialize_map deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormError> {
+        let mut seq = SeqDeserializer::new(self.0.into_iter());
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, FormError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, FormError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, FormError> {
+        self.single()?.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, FormError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, FormError> {
+        self.single()?.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, FormError> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+}
+
+impl<'de> IntoDeserializer<'de, FormError> for Part {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// A member of a `FilePart`, as given to `FilePartVisitor`.
+enum FilePartValue {
+    Text(Option<String>),
+    Data(Vec<u8>),
+}
+
+impl<'de> IntoDeserializer<'de, FormError> for FilePartValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for FilePartValue {
+    type Error = FormError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormError> {
+        match self {
+            FilePartValue::Text(Some(text)) => visitor.visit_some(text.into_deserializer()),
+            FilePartValue::Text(None) => visitor.visit_none(),
+            FilePartValue::Data(data) => visitor.visit_byte_buf(data),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_multipart, FilePart, FormError};
+
+    use crate::server::Multipart;
+
+    use serde::Deserialize;
+
+    use std::io;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Size {
+        Small,
+        Large,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Form {
+        name: String,
+        count: u32,
+        agree: bool,
+        size: Size,
+        tags: Vec<String>,
+        note: Option<String>,
+        file: FilePart,
+        text_as_file: FilePart,
+    }
+
+    const BODY: &str = "--boundary\r\n\
+        Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+        name\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"count\"\r\n\r\n\
+        42\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"agree\"\r\n\r\n\
+        true\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"size\"\r\n\r\n\
+        large\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"tags\"\r\n\r\n\
+        a\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"tags\"\r\n\r\n\
+        b\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"unknown\"\r\n\r\n\
+        ignored\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\
+        Content-Type: application/octet-stream\r\n\r\n\
+        \x00\x01\x02\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"text_as_file\"\r\n\r\n\
+        text\r\n\
+        --boundary--";
+
+    fn deserialize<T: serde::de::DeserializeOwned>(body: &str) -> io::Result<T> {
+        from_multipart(&mut Multipart::with_body(body.as_bytes(), "boundary"))
+    }
+
+    #[test]
+    fn test_from_multipart() {
+        let form: Form = deserialize(BODY).unwrap();
+
+        assert_eq!(
+            form,
+            Form {
+                name: "name".into(),
+                count: 42,
+                agree: true,
+                size: Size::Large,
+                tags: vec!["a".into(), "b".into()],
+                note: None,
+                file: FilePart {
+                    filename: Some("file.bin".into()),
+                    content_type: Some(mime::APPLICATION_OCTET_STREAM),
+                    data: vec![0, 1, 2],
+                },
+                text_as_file: FilePart {
+                    filename: None,
+                    content_type: None,
+                    data: b"text".to_vec(),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_multipart_errors() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Count {
+            count: u32,
+        }
+
+        fn error(body: &str) -> String {
+            let err = deserialize::<Count>(body).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.get_ref().unwrap().is::<FormError>());
+            err.to_string()
+        }
+
+        let field = |value: &str| {
+            format!(
+                "--boundary\r\n\
+                 Content-Disposition: form-data; name=\"count\"\r\n\r\n\
+                 {value}\r\n"
+            )
+        };
+
+        assert_eq!(
+            error(&format!("{}--boundary--", field("many"))),
+            "field `count`: invalid value \"many\": invalid digit found in string"
+        );
+        assert_eq!(error("--boundary--"), "missing field `count`");
+        assert_eq!(
+            error(&format!("{}{}--boundary--", field("1"), field("2"))),
+            "field `count` was sent 2 times but only one value was expected"
+        );
+
+        // errors reading the request pass through
+        let err = deserialize::<Count>(&field("1")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
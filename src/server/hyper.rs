@@ -3,7 +3,7 @@
 //!
 //! Also contains an implementation of [`HttpRequest`](../trait.HttpRequest.html)
 //! for `hyper::server::Request` and `&mut hyper::server::Request`.
-use hyper::header::ContentType;
+use hyper::header::{ContentType, Headers};
 use hyper::method::Method;
 use hyper::net::Fresh;
 use hyper::server::{Handler, Request, Response};
@@ -15,6 +15,8 @@ use hyper::mime::{Attr, Mime, SubLevel, TopLevel, Value};
 
 use super::{HttpRequest, Multipart};
 
+use std::str;
+
 /// A container that implements `hyper::server::Handler` which will switch
 /// the handler implementation depending on if the incoming request is multipart or not.
 ///
@@ -80,6 +82,41 @@ where
     }
 }
 
+/// The first `boundary` parameter of a `multipart/form-data` type, if it isn't empty.
+fn mime_boundary(mime: &Mime) -> Option<&str> {
+    let Mime(TopLevel::Multipart, SubLevel::FormData, _) = *mime else {
+        return None;
+    };
+
+    any_multipart_boundary(mime)
+}
+
+/// The first `boundary` parameter of any `multipart/*` type, if it isn't empty.
+fn any_multipart_boundary(mime: &Mime) -> Option<&str> {
+    let Mime(TopLevel::Multipart, _, ref params) = *mime else {
+        return None;
+    };
+
+    params
+        .iter()
+        .find(|&(name, _)| matches!(*name, Attr::Boundary))
+        .and_then(|(_, val)| match *val {
+            Value::Ext(ref val) => Some(&**val),
+            Value::Utf8 => None,
+        })
+        .filter(|boundary| !boundary.is_empty())
+}
+
+/// Whether the boundary is quoted in the `Content-Type` header as received, since `Mime`
+/// doesn't keep track of it.
+fn raw_boundary_quoted(headers: &Headers) -> bool {
+    headers
+        .get_raw("Content-Type")
+        .and_then(|raw| raw.first())
+        .and_then(|raw| str::from_utf8(raw).ok())
+        .is_some_and(super::boundary_quoted)
+}
+
 impl<'a, 'b> HttpRequest for HyperRequest<'a, 'b> {
     type Body = Self;
 
@@ -88,20 +125,23 @@ impl<'a, 'b> HttpRequest for HyperRequest<'a, 'b> {
             return None;
         }
 
-        self.headers.get::<ContentType>().and_then(|ct| {
-            let ContentType(ref mime) = *ct;
-            let Mime(TopLevel::Multipart, SubLevel::FormData, ref params) = *mime else {
-                return None;
-            };
-
-            params
-                .iter()
-                .find(|&(name, _)| matches!(*name, Attr::Boundary))
-                .and_then(|(_, val)| match *val {
-                    Value::Ext(ref val) => Some(&**val),
-                    Value::Utf8 => None,
-                })
-        })
+        self.headers
+            .get::<ContentType>()
+            .and_then(|ct| mime_boundary(&ct.0))
+    }
+
+    fn multipart_boundary_any_subtype(&self) -> Option<&str> {
+        if self.method != Method::Post {
+            return None;
+        }
+
+        self.headers
+            .get::<ContentType>()
+            .and_then(|ct| any_multipart_boundary(&ct.0))
+    }
+
+    fn multipart_boundary_quoted(&self) -> bool {
+        raw_boundary_quoted(&self.headers)
     }
 
     fn body(self) -> Self {
@@ -117,23 +157,87 @@ impl<'r, 'a, 'b> HttpRequest for &'r mut HyperRequest<'a, 'b> {
             return None;
         }
 
-        self.headers.get::<ContentType>().and_then(|ct| {
-            let ContentType(ref mime) = *ct;
-            let Mime(TopLevel::Multipart, SubLevel::FormData, ref params) = *mime else {
-                return None;
-            };
-
-            params
-                .iter()
-                .find(|&(name, _)| matches!(*name, Attr::Boundary))
-                .and_then(|(_, val)| match *val {
-                    Value::Ext(ref val) => Some(&**val),
-                    Value::Utf8 => None,
-                })
-        })
+        self.headers
+            .get::<ContentType>()
+            .and_then(|ct| mime_boundary(&ct.0))
+    }
+
+    fn multipart_boundary_any_subtype(&self) -> Option<&str> {
+        if self.method != Method::Post {
+            return None;
+        }
+
+        self.headers
+            .get::<ContentType>()
+            .and_then(|ct| any_multipart_boundary(&ct.0))
+    }
+
+    fn multipart_boundary_quoted(&self) -> bool {
+        raw_boundary_quoted(&self.headers)
     }
 
     fn body(self) -> Self::Body {
         self
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{any_multipart_boundary, mime_boundary, raw_boundary_quoted};
+
+    use hyper::header::Headers;
+    use hyper::mime::Mime;
+
+    #[test]
+    fn test_mime_boundary() {
+        let boundary = |content_type: &str| {
+            let mime: Mime = content_type.parse().unwrap();
+            mime_boundary(&mime).map(str::to_owned)
+        };
+
+        assert_eq!(
+            boundary(crate::server::test::DUPLICATE_BOUNDARY).as_deref(),
+            Some("first")
+        );
+        assert_eq!(boundary("multipart/form-data; boundary=").as_deref(), None);
+        assert_eq!(
+            boundary("multipart/mixed; boundary=abc123").as_deref(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_any_multipart_boundary() {
+        let boundary = |content_type: &str| {
+            let mime: Mime = content_type.parse().unwrap();
+            any_multipart_boundary(&mime).map(str::to_owned)
+        };
+
+        assert_eq!(
+            boundary("multipart/form-data; boundary=abc123").as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(
+            boundary("multipart/related; boundary=abc123").as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(
+            boundary("multipart/mixed; boundary=abc123").as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(boundary("multipart/related; boundary=").as_deref(), None);
+        assert_eq!(boundary("text/plain; boundary=abc123").as_deref(), None);
+    }
+    #[test]
+    fn test_raw_boundary_quoted() {
+        let quoted = |content_type: &str| {
+            let mut headers = Headers::new();
+            headers.set_raw("Content-Type", vec![content_type.as_bytes().to_vec()]);
+            raw_boundary_quoted(&headers)
+        };
+
+        assert!(quoted("multipart/form-data; boundary=\"abc123\""));
+        assert!(!quoted("multipart/form-data; boundary=abc123"));
+        assert!(!raw_boundary_quoted(&Headers::new()));
+    }
+}
@@ -0,0 +1,279 @@
+//! Server-side integration with [Hyper 1.x](https://github.com/hyperium/hyper).
+//! Enabled with the `hyper1` feature.
+//!
+//! The legacy `hyper` feature targets the synchronous API of Hyper 0.10 and earlier;
+//! this module works with the `http::Request` type used by Hyper 1.x and any request body
+//! implementing `http_body::Body`, such as `hyper::body::Incoming`.
+//!
+//! With [`from_request()`](fn.from_request.html), the request body is collected into memory
+//! before parsing begins, so you should wrap it in
+//! [`http_body_util::Limited`](https://docs.rs/http-body-util/0.1/http_body_util/struct.Limited.html)
+//! to cap its size:
+//!
+//! ```rust,no_run
+//! use std::convert::Infallible;
+//!
+//! use http_body_util::Limited;
+//! use hyper1::body::Incoming;
+//! use hyper1::service::service_fn;
+//! use hyper1::{Request, Response};
+//!
+//! async fn upload(req: Request<Incoming>) -> Result<Response<String>, Infallible> {
+//!     let req = req.map(|body| Limited::new(body, 16 * 1024 * 1024));
+//!
+//!     let mut multipart = match mpart::server::hyper1::from_request(req).await {
+//!         Ok(multipart) => multipart,
+//!         Err(e) => return Ok(Response::new(format!("bad request: {}", e))),
+//!     };
+//!
+//!     let mut fields = 0;
+//!
+//!     let res = multipart.foreach_entry(|_| fields += 1);
+//!
+//!     Ok(Response::new(match res {
+//!         Ok(()) => format!("received {} fields", fields),
+//!         Err(e) => format!("error reading request: {}", e),
+//!     }))
+//! }
+//!
+//! # fn main() {
+//! // pass this to `hyper::server::conn::http1::Builder::serve_connection()`
+//! let service = service_fn(upload);
+//! # let _ = service;
+//! # }
+//! ```
+//!
+//! [`from_request_stream()`](fn.from_request_stream.html) instead returns the async
+//! `Multipart` of the `server-async` feature, which parses the body as it arrives.
+use std::error::Error;
+use std::fmt;
+use std::io::Cursor;
+use std::pin::Pin;
+
+use http_body_util::{BodyDataStream, BodyExt};
+use hyper1::body::{Body, Bytes};
+use hyper1::header::CONTENT_TYPE;
+use hyper1::Request;
+
+use super::{async_, Multipart};
+
+/// The `Multipart` type returned by [`from_request()`](fn.from_request.html).
+pub type Hyper1Multipart = Multipart<Cursor<Bytes>>;
+
+/// The async `Multipart` type returned by [`from_request_stream()`](fn.from_request_stream.html).
+pub type Hyper1StreamMultipart<B> = async_::Multipart<Pin<Box<BodyDataStream<B>>>>;
+
+/// The error returned by [`from_request()`](fn.from_request.html).
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub enum FromRequestError<E> {
+    /// The request's `Content-Type` isn't `multipart/form-data` with a boundary.
+    NotMultipart,
+    /// An error occurred while reading the request body.
+    Body(E),
+}
+
+impl<E: fmt::Display> fmt::Display for FromRequestError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            FromRequestError::NotMultipart => f.write_str("request is not multipart/form-data"),
+            FromRequestError::Body(ref e) => write!(f, "error reading request body: {e}"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for FromRequestError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            FromRequestError::NotMultipart => None,
+            FromRequestError::Body(ref e) => Some(e),
+        }
+    }
+}
+
+/// If `req` is a `multipart/form-data` request, collect its body and wrap it in a `Multipart`.
+///
+/// The request is checked before any of the body is read.
+///
+/// # Errors
+///
+/// Will return `FromRequestError::NotMultipart` if the request's `Content-Type` isn't
+/// `multipart/form-data` with a boundary, or `FromRequestError::Body` if reading the body fails.
+pub async fn from_request<B: Body>(
+    req: Request<B>,
+) -> Result<Hyper1Multipart, FromRequestError<B::Error>> {
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|val| val.to_str().ok());
+    let boundary = content_type
+        .and_then(super::form_data_boundary)
+        .map(str::to_owned)
+        .ok_or(FromRequestError::NotMultipart)?;
+    let boundary_quoted = content_type.is_some_and(super::boundary_quoted);
+
+    let body = req
+        .into_body()
+        .collect()
+        .await
+        .map_err(FromRequestError::Body)?
+        .to_bytes();
+
+    Ok(Multipart {
+        boundary_quoted,
+        ..Multipart::with_body(Cursor::new(body), boundary)
+    })
+}
+
+/// If `req` is a `multipart/form-data` request, wrap its body, e.g. `hyper::body::Incoming`,
+/// in an async `Multipart` which parses it as it arrives.
+///
+/// Errors reading the body are returned by the `Multipart`, wrapped in an `io::Error`.
+///
+/// # Errors
+///
+/// Will return `FromRequestError::NotMultipart` if the request's `Content-Type` isn't
+/// `multipart/form-data` with a boundary; `FromRequestError::Body` is never returned here.
+pub fn from_request_stream<B>(
+    req: Request<B>,
+) -> Result<Hyper1StreamMultipart<B>, FromRequestError<B::Error>>
+where
+    B: Body<Data = Bytes>,
+    B::Error: Into<Box<dyn Error + Send + Sync>>,
+{
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|val| val.to_str().ok());
+    let boundary = content_type
+        .and_then(super::form_data_boundary)
+        .map(str::to_owned)
+        .ok_or(FromRequestError::NotMultipart)?;
+    let boundary_quoted = content_type.is_some_and(super::boundary_quoted);
+
+    Ok(
+        async_::Multipart::with_body(Box::pin(req.into_body().into_data_stream()), boundary)
+            .with_boundary_quoted(boundary_quoted),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_request, from_request_stream, FromRequestError};
+
+    use http_body_util::Full;
+    use hyper1::body::Bytes;
+    use hyper1::Request;
+
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    /// The bodies used here are already complete, so the future resolves on the first poll.
+    fn poll_once<F: Future>(fut: F) -> F::Output {
+        match pin!(fut).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(out) => out,
+            Poll::Pending => panic!("future was not ready"),
+        }
+    }
+
+    #[test]
+    fn test_from_request() {
+        let req = Request::builder()
+            .header("content-type", "multipart/form-data; boundary=boundary")
+            .body(Full::new(Bytes::from_static(
+                b"--boundary\r\n\
+                  Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+                  value\r\n\
+                  --boundary--",
+            )))
+            .unwrap();
+
+        let mut multipart = poll_once(from_request(req)).unwrap();
+        assert!(!multipart.boundary_was_quoted());
+        let field = multipart.read_entry().unwrap().unwrap();
+        assert_eq!(&*field.headers.name, "field");
+    }
+
+    #[test]
+    fn test_from_request_stream() {
+        let req = Request::builder()
+            .header("content-type", "multipart/form-data; boundary=boundary")
+            .body(Full::new(Bytes::from_static(
+                b"--boundary\r\n\
+                  Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+                  value\r\n\
+                  --boundary--",
+            )))
+            .unwrap();
+
+        let mut multipart = from_request_stream(req).unwrap();
+
+        poll_once(async {
+            let field = multipart.next_field().await.unwrap().unwrap();
+            assert_eq!(&*field.headers.name, "field");
+            assert_eq!(field.bytes().await.unwrap(), "value");
+            assert!(multipart.next_field().await.unwrap().is_none());
+        });
+
+        let req = Request::builder()
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from_static(b"{}")))
+            .unwrap();
+
+        assert!(matches!(
+            from_request_stream(req),
+            Err(FromRequestError::NotMultipart)
+        ));
+    }
+
+    #[test]
+    fn test_boundary_quoted() {
+        let req = Request::builder()
+            .header("content-type", "multipart/form-data; boundary=\"boundary\"")
+            .body(Full::new(Bytes::from_static(b"--boundary--")))
+            .unwrap();
+
+        assert!(poll_once(from_request(req)).unwrap().boundary_was_quoted());
+    }
+
+    #[test]
+    fn test_not_multipart() {
+        let req = Request::builder()
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from_static(b"{}")))
+            .unwrap();
+
+        assert!(matches!(
+            poll_once(from_request(req)),
+            Err(FromRequestError::NotMultipart)
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_boundary() {
+        let req = Request::builder()
+            .header("content-type", crate::server::test::DUPLICATE_BOUNDARY)
+            .body(Full::new(Bytes::from_static(
+                b"--first\r\n\
+                  Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+                  --second\r\n\
+                  --first--",
+            )))
+            .unwrap();
+
+        let mut multipart = poll_once(from_request(req)).unwrap();
+        let field = multipart.read_entry().unwrap().unwrap();
+        assert_eq!(&*field.headers.name, "field");
+
+        let req = Request::builder()
+            .header("content-type", "multipart/form-data; boundary=")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+
+        assert!(matches!(
+            poll_once(from_request(req)),
+            Err(FromRequestError::NotMultipart)
+        ));
+    }
+}
@@ -17,7 +17,10 @@ impl<'r, 'a, 'b> HttpRequest for &'r mut IronRequest<'a, 'b> {
     fn multipart_boundary(&self) -> Option<&str> {
         let content_type = try_opt!(self.headers.get::<ContentType>());
         if let Mime(TopLevel::Multipart, SubLevel::FormData, _) = **content_type {
-            content_type.get_param("boundary").map(|b| b.as_str())
+            content_type
+                .get_param("boundary")
+                .map(|b| b.as_str())
+                .filter(|b| !b.is_empty())
         } else {
             None
         }
@@ -166,10 +169,10 @@ impl Intercept {
             Partial(_, PartialReason::IoError(err)) => {
                 Err(io_to_iron(err, "Error midway through request"))
             }
-            Partial(_, PartialReason::CountLimit) => {
+            Partial(_, PartialReason::CountLimit { .. }) => {
                 Err(FileCountLimitError(self.file_count_limit).into())
             }
-            Partial(partial, PartialReason::SizeLimit) => {
+            Partial(partial, PartialReason::FieldSizeLimit { .. }) => {
                 let partial = partial.partial.expect(EXPECT_PARTIAL_FILE);
                 Err(FileSizeLimitError {
                     field: partial.source.headers,
@@ -0,0 +1,173 @@
+//! Hard limits on the fields of a request, enforced by `Multipart` while parsing, and the error
+//! returned once one is exceeded.
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+use super::field::FieldHeaders;
+
+/// Limits on the fields of a request, set with `Multipart::with_limits()` or
+/// `Multipart::set_limits()`. `None` means no limit, which is the default for all of them.
+///
+/// Unlike the limits of `SaveBuilder`, these apply to every way of reading fields, and reading
+/// fails as soon as one is exceeded, with an error of kind `io::ErrorKind::InvalidData` wrapping
+/// a `LimitError`. The error is returned again by any further reads, so the request can't be
+/// read past it.
+///
+/// Sizes count the data of fields as it is read; the data of fields skipped unread isn't
+/// buffered and doesn't count. Use `Multipart::with_content_length_limit()` to cap the size
+/// of the whole body.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum number of fields, as for `Multipart::set_max_fields()`.
+    pub max_fields: Option<usize>,
+    /// The maximum size of the data of a field without a filename.
+    pub max_field_size: Option<u64>,
+    /// The maximum size of the data of a field with a filename.
+    pub max_file_size: Option<u64>,
+    /// The maximum size of the data of all fields together.
+    pub max_total_size: Option<u64>,
+}
+
+/// The limit which was exceeded, wrapped in the `io::Error` returned by `Multipart` and
+/// recoverable with `io::Error::get_ref()` and `downcast_ref()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::module_name_repetitions)]
+pub enum LimitError {
+    /// The request has more fields than `Limits::max_fields`.
+    TooManyFields {
+        /// The limit.
+        max: usize,
+    },
+    /// The data of a field without a filename is larger than `Limits::max_field_size`.
+    FieldTooLarge {
+        /// The name of the field.
+        field: Arc<str>,
+        /// The limit.
+        max: u64,
+    },
+    /// The data of a field with a filename is larger than `Limits::max_file_size`.
+    FileTooLarge {
+        /// The name of the field.
+        field: Arc<str>,
+        /// The limit.
+        max: u64,
+    },
+    /// The data of all fields together is larger than `Limits::max_total_size`.
+    TotalTooLarge {
+        /// The limit.
+        max: u64,
+    },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::TooManyFields { max } => {
+                write!(f, "request has more than the maximum of {max} fields")
+            }
+            LimitError::FieldTooLarge { field, max } => {
+                write!(
+                    f,
+                    "field `{field}` is larger than the maximum of {max} bytes"
+                )
+            }
+            LimitError::FileTooLarge { field, max } => {
+                write!(
+                    f,
+                    "file `{field}` is larger than the maximum of {max} bytes"
+                )
+            }
+            LimitError::TotalTooLarge { max } => {
+                write!(f, "request data is larger than the maximum of {max} bytes")
+            }
+        }
+    }
+}
+
+impl Error for LimitError {}
+
+impl From<LimitError> for io::Error {
+    fn from(err: LimitError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// The counts checked against the `Limits` of a `Multipart`.
+#[derive(Debug, Default)]
+pub(crate) struct LimitState {
+    pub(crate) limits: Limits,
+    /// The name of the current field and whether it has a filename.
+    field: Option<(Arc<str>, bool)>,
+    field_bytes: u64,
+    total_bytes: u64,
+    /// The limit which was exceeded, returned by every read after it.
+    exceeded: Option<LimitError>,
+}
+
+impl LimitState {
+    pub(crate) fn new(limits: Limits) -> Self {
+        LimitState {
+            limits,
+            ..LimitState::default()
+        }
+    }
+
+    pub(crate) fn field_start(&mut self, headers: &FieldHeaders) {
+        self.field = Some((headers.name.clone(), headers.filename.is_some()));
+        self.field_bytes = 0;
+    }
+
+    pub(crate) fn count(&mut self, n: u64) {
+        self.field_bytes += n;
+        self.total_bytes += n;
+    }
+
+    /// Fail if the limits are exceeded by the data read so far, or were exceeded before.
+    pub(crate) fn check(&mut self) -> io::Result<()> {
+        if self.exceeded.is_none() {
+            self.exceeded = self.find_exceeded();
+        }
+
+        self.check_exceeded()
+    }
+
+    /// Record `err` to be returned by every read from now on.
+    pub(crate) fn exceed(&mut self, err: LimitError) -> io::Error {
+        self.exceeded = Some(err.clone());
+        err.into()
+    }
+
+    /// Fail if a limit was exceeded before.
+    pub(crate) fn check_exceeded(&self) -> io::Result<()> {
+        match self.exceeded {
+            Some(ref err) => Err(err.clone().into()),
+            None => Ok(()),
+        }
+    }
+
+    fn find_exceeded(&self) -> Option<LimitError> {
+        let over = |max: Option<u64>, len: u64| max.filter(|&max| len > max);
+
+        if let Some(max) = over(self.limits.max_total_size, self.total_bytes) {
+            return Some(LimitError::TotalTooLarge { max });
+        }
+
+        let (field, is_file) = self.field.as_ref()?;
+
+        if *is_file {
+            over(self.limits.max_file_size, self.field_bytes).map(|max| LimitError::FileTooLarge {
+                field: field.clone(),
+                max,
+            })
+        } else {
+            over(self.limits.max_field_size, self.field_bytes).map(|max| {
+                LimitError::FieldTooLarge {
+                    field: field.clone(),
+                    max,
+                }
+            })
+        }
+    }
+}
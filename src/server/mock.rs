@@ -0,0 +1,221 @@
+//! Constructing `Entries` by hand, e.g. as fixtures in tests of code which consumes them,
+//! without parsing a request. Enabled with the `mock` feature.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::server::field::FieldHeaders;
+use crate::server::save::{Entries, SaveDir, SavedData, SavedField};
+
+impl Entries {
+    /// #### Feature: `mock`
+    /// Start building an `Entries` from fields given by hand. See `EntriesBuilder`.
+    #[must_use]
+    pub fn builder() -> EntriesBuilder {
+        EntriesBuilder {
+            entries: Entries::new(SaveDir::Perm(env::temp_dir())),
+        }
+    }
+}
+
+/// A builder for `Entries`, returned by `Entries::builder()`.
+///
+/// The headers of each field are those the client in this crate would send for it, so a field
+/// built here compares equal to the same field sent by `client::lazy::Multipart` and saved in
+/// memory:
+///
+/// ```
+/// # use mpart::server::Entries;
+/// let entries = Entries::builder()
+///     .text("name", "value")
+///     .file("upload", "data.bin", vec![0, 1, 2])
+///     .build();
+///
+/// assert_eq!(entries.fields_count(), 2);
+/// assert_eq!(entries.fields["upload"][0].data.size(), 3);
+/// ```
+#[derive(Debug)]
+pub struct EntriesBuilder {
+    entries: Entries,
+}
+
+impl EntriesBuilder {
+    /// Add a text field.
+    #[must_use]
+    pub fn text<N: AsRef<str>, V: Into<String>>(self, name: N, value: V) -> Self {
+        let headers = headers(name.as_ref(), None);
+        self.push(headers, SavedData::Text(value.into()))
+    }
+
+    /// Add a file field with the given filename, with its `Content-Type` guessed from the
+    /// filename's extension.
+    ///
+    /// `data` is either the contents, as `Vec<u8>` or `&[u8]`, or the path of a file on disk
+    /// as `PathBuf` or `&Path`. The file doesn't have to exist; if it doesn't, its size is 0.
+    #[must_use]
+    pub fn file<N, F, D>(self, name: N, filename: F, data: D) -> Self
+    where
+        N: AsRef<str>,
+        F: Into<String>,
+        D: Into<MockFile>,
+    {
+        let headers = headers(name.as_ref(), Some(filename.into()));
+
+        let data = match data.into() {
+            MockFile::Bytes(bytes) => bytes.into(),
+            MockFile::Path(path) => {
+                let size = fs::metadata(&path).map_or(0, |meta| meta.len());
+                SavedData::File(path, size)
+            }
+        };
+
+        self.push(headers, data)
+    }
+
+    /// Set the `save_dir` of the `Entries`. The default is a permanent `SaveDir` of the OS
+    /// temporary directory, so nothing is deleted when it's dropped.
+    #[must_use]
+    pub fn save_dir(mut self, save_dir: SaveDir) -> Self {
+        self.entries.save_dir = save_dir;
+        self
+    }
+
+    /// Finish building the `Entries`.
+    #[must_use]
+    pub fn build(self) -> Entries {
+        self.entries
+    }
+
+    fn push(mut self, headers: FieldHeaders, data: SavedData) -> Self {
+        self.entries.push_field(SavedField::new(headers, data));
+        self
+    }
+}
+
+/// The data of a file field for `EntriesBuilder::file()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MockFile {
+    /// The contents of the file, to be kept in memory.
+    Bytes(Vec<u8>),
+    /// The path of the file on disk.
+    Path(PathBuf),
+}
+
+impl From<Vec<u8>> for MockFile {
+    fn from(bytes: Vec<u8>) -> Self {
+        MockFile::Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for MockFile {
+    fn from(bytes: &[u8]) -> Self {
+        MockFile::Bytes(bytes.to_vec())
+    }
+}
+
+impl From<PathBuf> for MockFile {
+    fn from(path: PathBuf) -> Self {
+        MockFile::Path(path)
+    }
+}
+
+impl From<&Path> for MockFile {
+    fn from(path: &Path) -> Self {
+        MockFile::Path(path.to_path_buf())
+    }
+}
+
+fn headers(name: &str, filename: Option<String>) -> FieldHeaders {
+    let mut disposition = format!("form-data; name=\"{name}\"");
+    let content_type = filename.as_ref().map(|filename| {
+        disposition += &format!("; filename=\"{filename}\"");
+        mime_guess::from_path(filename).first_or_octet_stream()
+    });
+
+    FieldHeaders {
+        name: name.into(),
+        filename,
+        filename_truncated: false,
+        content_type,
+        content_length: None,
+        checksum: None,
+        content_disposition_raw: Some(disposition),
+        declared_size: None,
+        transfer_encoding: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MockFile;
+    use crate::server::save::{Entries, SavedData};
+
+    use std::path::Path;
+
+    #[test]
+    fn test_entries_builder() {
+        let entries = Entries::builder()
+            .text("text", "first")
+            .text("text", "second")
+            .file("upload", "data.bin", &b"\xff\x00"[..])
+            .file("path", "notes.txt", Path::new("/nonexistent/notes.txt"))
+            .build();
+
+        assert_eq!(entries.fields_count(), 4);
+        assert_eq!(
+            entries.text_fields_sorted()["text"],
+            ["first".to_owned(), "second".to_owned()]
+        );
+
+        let upload = &entries.fields["upload"][0];
+        assert_eq!(upload.headers.filename.as_deref(), Some("data.bin"));
+        assert_eq!(
+            upload.headers.content_type,
+            Some(mime::APPLICATION_OCTET_STREAM)
+        );
+        assert_eq!(upload.data, SavedData::from(b"\xff\x00".to_vec()));
+
+        let path = &entries.fields["path"][0];
+        assert_eq!(path.headers.content_type, Some(mime::TEXT_PLAIN));
+        assert_eq!(
+            path.data,
+            SavedData::File("/nonexistent/notes.txt".into(), 0)
+        );
+
+        assert_eq!(MockFile::from(Path::new("a")), MockFile::Path("a".into()));
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_same_as_parsed() {
+        use crate::client::lazy::Multipart as Client;
+        use crate::server::Multipart;
+
+        use std::io::Read;
+
+        let mut client = Client::new();
+        client.add_text("text", "value");
+        client.add_stream(
+            "upload",
+            &b"\xff\x00"[..],
+            Some("data.bin"),
+            Some(mime::APPLICATION_OCTET_STREAM),
+        );
+
+        let mut prepared = client.prepare().unwrap();
+        let boundary = prepared.boundary().to_owned();
+        let mut body = Vec::new();
+        prepared.read_to_end(&mut body).unwrap();
+
+        let parsed = Multipart::with_body(&body[..], boundary)
+            .into_entries()
+            .unwrap();
+
+        let expected = Entries::builder()
+            .text("text", "value")
+            .file("upload", "data.bin", &b"\xff\x00"[..])
+            .build();
+
+        assert_eq!(parsed, expected);
+    }
+}
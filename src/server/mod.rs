@@ -7,30 +7,118 @@
 
 use std::io;
 use std::io::prelude::*;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
-use crate::server::boundary::BoundaryReader;
-use crate::server::field::PrivReadEntry;
+use crate::server::boundary::{validate_boundary, BoundaryReader};
+use crate::server::boundary::{BoundaryValidation, InvalidBoundary};
+use crate::server::chunked::ChunkedReader;
+use crate::server::field::{FieldHeaders, FilenameLimit, LineEndingPolicy, LongFilename};
 use crate::server::field::{MultipartField, ReadEntry, ReadEntryResult};
-use crate::server::save::SaveBuilder;
+use crate::server::field::{PrivReadEntry, DEFAULT_MAX_HEADER_LINE_LEN};
+use crate::server::limits::{LimitError, LimitState};
+use crate::server::observer::Observer;
+use crate::server::save::{BufReader, EntriesSaveResult, SaveBuilder, SaveConfig};
+use crate::server::warning::{ParseWarning, WarningReason};
 
+pub use crate::server::error::MultipartError;
+pub use crate::server::limits::Limits;
+pub use crate::server::observer::MultipartObserver;
+pub use crate::server::pool::MultipartPool;
+pub use crate::server::push::PushParser;
 pub use crate::server::save::{Entries, SaveResult, SavedField};
 
+#[cfg(feature = "server-async")]
+pub mod async_;
+
+#[cfg(feature = "axum")]
+pub mod axum;
+
 pub mod boundary;
+pub mod chunked;
+pub mod error;
+pub mod extract;
 pub mod field;
 
+#[cfg(feature = "serde")]
+pub mod form;
+
 #[cfg(feature = "hyper")]
 pub mod hyper;
 
+#[cfg(feature = "hyper1")]
+pub mod hyper1;
+
+#[cfg(feature = "rocket")]
+pub mod rocket;
+
+#[cfg(feature = "tide")]
+pub mod tide;
+
 #[cfg(feature = "tiny_http")]
 pub mod tiny_http;
 
+#[cfg(feature = "warp")]
+pub mod warp;
+
+#[cfg(feature = "mock")]
+pub mod mock;
+
+pub(crate) mod base64;
+
+pub mod limits;
+
+pub mod observer;
+
+pub mod parse;
+
+pub mod pool;
+
+pub mod push;
+
+pub mod retry;
+
 pub mod save;
 
+pub mod timeout;
+
+pub mod warning;
+
 /// The server-side implementation of `multipart/form-data` requests.
 ///
 /// Implements `Borrow<R>` to allow access to the request body, if desired.
+///
+/// For a body which is already in memory, `parse::parse_multipart()` parses it without `Read`.
 pub struct Multipart<R> {
     reader: BoundaryReader<R>,
+    filename_limit: FilenameLimit,
+    max_fields: usize,
+    /// Shared with the `Multipart`s returned by `MultipartField::nested()`, whose fields count
+    /// towards `max_fields` too.
+    fields_read: Arc<AtomicUsize>,
+    limits: LimitState,
+    observer: Option<Observer>,
+    /// `Some` during `export_metadata_jsonl()`.
+    #[cfg(feature = "serde")]
+    metadata: Option<observer::MetadataRecorder>,
+    /// `Some` if warnings are being collected.
+    warnings: Option<Vec<ParseWarning>>,
+    /// The name of the field being read, to attribute warnings found at its end.
+    current_field: Option<Arc<str>>,
+    /// A boundary was consumed but reading the following headers failed with a retryable error.
+    headers_pending: bool,
+    /// The boundary was quoted in the request's `Content-Type`, for `boundary_was_quoted()`.
+    boundary_quoted: bool,
+    line_ending_policy: LineEndingPolicy,
+    max_header_line_len: usize,
+    /// The name of the field this reads the data of, for `MultipartField::nested()`.
+    nested_name: Option<Arc<str>>,
+    decode_transfer_encoding: bool,
+    /// The encoding of text fields without a `charset` parameter, if not UTF-8.
+    #[cfg(feature = "charset")]
+    default_charset: Option<&'static encoding_rs::Encoding>,
 }
 
 impl Multipart<()> {
@@ -38,18 +126,57 @@ impl Multipart<()> {
     /// return the request body wrapped in the multipart reader. Otherwise,
     /// returns the original request.
     ///
+    /// A request whose boundary fails `BoundaryValidation::Lenient` is not considered multipart.
+    ///
     /// # Errors
     ///
     /// Will return `Error` if the `req` is not in proper multipart format
     ///
     pub fn from_request<R: HttpRequest>(req: R) -> Result<Multipart<R::Body>, R> {
+        Self::from_request_with(req, R::multipart_boundary)
+    }
+
+    /// Like `from_request()`, but accept a request with any `multipart/*` content-type,
+    /// e.g. `multipart/related` or `multipart/mixed`, as long as it has a boundary.
+    ///
+    /// The parts are still read as `Multipart` reads form data, so every part needs a
+    /// `Content-Disposition` header with a `name`; check the content-type of the request to see
+    /// what the parts mean. Only requests which implement
+    /// `HttpRequest::multipart_boundary_any_subtype()` accept other subtypes: those of the
+    /// `hyper` and `tiny_http` integrations do, while for the others, e.g. `iron`, this is the
+    /// same as `from_request()`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the `req` is not in proper multipart format
+    pub fn from_request_any_subtype<R: HttpRequest>(req: R) -> Result<Multipart<R::Body>, R> {
+        Self::from_request_with(req, R::multipart_boundary_any_subtype)
+    }
+
+    fn from_request_with<R: HttpRequest>(
+        req: R,
+        boundary: fn(&R) -> Option<&str>,
+    ) -> Result<Multipart<R::Body>, R> {
         //FIXME: move `map` expr to `Some` arm when nonlexical borrow scopes land.
 
-        let Some(boundary) = req.multipart_boundary().map(String::from) else {
+        let Some(boundary) = boundary(&req).map(String::from) else {
             return Err(req);
         };
 
-        Ok(Multipart::with_body(req.body(), boundary))
+        let boundary = match validate_boundary(&boundary, BoundaryValidation::Lenient) {
+            Ok(boundary) => boundary.to_owned(),
+            Err(e) => {
+                log::warn!("Rejecting multipart request: {}", e);
+                return Err(req);
+            }
+        };
+
+        let boundary_quoted = req.multipart_boundary_quoted();
+
+        Ok(Multipart {
+            boundary_quoted,
+            ..Multipart::with_body(req.body(), boundary)
+        })
     }
 }
 
@@ -62,27 +189,354 @@ impl<R: Read> Multipart<R> {
     /// Simply pass the value of the `boundary` key from the `Content-Type` header in the
     /// request (or use `Multipart::from_request()`, if supported).
     ///
+    /// The boundary is not validated; a malformed one may never match. Use `try_with_body()`
+    /// for a boundary from an untrusted client.
+    ///
+    /// A read from `body` returning `Ok(0)` is taken as the end of the body. For a reader which
+    /// may return it before then, see `retry::RetryReader`.
+    ///
     /// [rfc1341-7.2.1]: https://tools.ietf.org/html/rfc1341#page-30
     pub fn with_body<Bnd: Into<String>>(body: R, boundary: Bnd) -> Self {
         let boundary = boundary.into();
 
         log::info!("Multipart::with_boundary(_, {:?})", boundary);
 
+        Self::from_reader(BoundaryReader::from_reader(body, boundary))
+    }
+
+    /// Construct a `Multipart` with the default settings around `reader`, for all constructors.
+    fn from_reader(reader: BoundaryReader<R>) -> Self {
         Multipart {
-            reader: BoundaryReader::from_reader(body, boundary),
+            reader,
+            filename_limit: FilenameLimit::default(),
+            max_fields: usize::MAX,
+            fields_read: Arc::default(),
+            limits: LimitState::default(),
+            observer: None,
+            #[cfg(feature = "serde")]
+            metadata: None,
+            warnings: None,
+            current_field: None,
+            headers_pending: false,
+            boundary_quoted: false,
+            line_ending_policy: LineEndingPolicy::default(),
+            max_header_line_len: DEFAULT_MAX_HEADER_LINE_LEN,
+            nested_name: None,
+            decode_transfer_encoding: true,
+            #[cfg(feature = "charset")]
+            default_charset: None,
+        }
+    }
+
+    /// Like `with_body()`, but enforce `limits` on the number and size of fields while parsing.
+    ///
+    /// See `Limits` for more info.
+    pub fn with_limits<Bnd: Into<String>>(body: R, boundary: Bnd, limits: Limits) -> Self {
+        let mut multipart = Self::with_body(body, boundary);
+        multipart.set_limits(limits);
+        multipart
+    }
+
+    /// Like `with_body()`, but check the boundary with `boundary::validate_boundary()` first,
+    /// e.g. to reject a request with an empty boundary or one containing control characters
+    /// before reading any of it. With `BoundaryValidation::Lenient`, the trimmed boundary is used.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the boundary is invalid; `body` is dropped.
+    pub fn try_with_body<Bnd: AsRef<str>>(
+        body: R,
+        boundary: Bnd,
+        validation: BoundaryValidation,
+    ) -> Result<Self, InvalidBoundary> {
+        let boundary = validate_boundary(boundary.as_ref(), validation)?;
+        Ok(Multipart::with_body(body, boundary))
+    }
+
+    /// Like `with_body()`, but the read buffer is taken from `pool` instead of being allocated,
+    /// and is returned to it by `release()` or `into_inner()`.
+    ///
+    /// See `MultipartPool` for more info.
+    pub fn with_body_pooled<Bnd: Into<String>>(
+        body: R,
+        boundary: Bnd,
+        pool: &MultipartPool,
+    ) -> Self {
+        let boundary = boundary.into();
+
+        log::info!("Multipart::with_body_pooled(_, {:?})", boundary);
+
+        Self::from_reader(BoundaryReader::from_reader_pooled(body, boundary, pool))
+    }
+
+    /// Set the maximum length of field filenames in bytes, and whether longer ones are truncated
+    /// or cause an error. The default is 255 bytes, truncating.
+    ///
+    /// Pass `usize::MAX` to lift the limit.
+    pub fn set_max_filename_len(&mut self, max_len: usize, on_exceed: LongFilename) {
+        self.filename_limit = FilenameLimit { max_len, on_exceed };
+    }
+
+    /// Set what to do with field headers whose lines end with both CRLF and LF. The default is
+    /// `LineEndingPolicy::Lenient`, accepting them with a warning.
+    ///
+    /// See `field::LineEndingPolicy` for more info.
+    pub fn set_line_ending_policy(&mut self, policy: LineEndingPolicy) {
+        self.line_ending_policy = policy;
+    }
+
+    /// Set the maximum length in bytes of a line of field headers, not counting its line ending.
+    /// A longer line is an error of kind `io::ErrorKind::InvalidData` from `read_entry()` as
+    /// soon as that much of it has been read, even before its line ending arrives.
+    /// The default is `field::DEFAULT_MAX_HEADER_LINE_LEN` (8 KiB).
+    ///
+    /// The whole headers section of a field must also fit in the read buffer.
+    pub fn set_max_header_line_len(&mut self, max_len: usize) {
+        self.max_header_line_len = max_len;
+    }
+
+    /// Set whether to decode the data of fields sent with a `Content-Transfer-Encoding` of
+    /// `base64` or `quoted-printable` as it is read. The default is `true`.
+    ///
+    /// With `false`, the data is read as sent, with a `WarningReason::UnsupportedTransferEncoding`
+    /// as for any other encoding. `FieldHeaders::transfer_encoding` is set either way.
+    pub fn set_decode_transfer_encoding(&mut self, decode: bool) {
+        self.decode_transfer_encoding = decode;
+    }
+
+    /// Set the character encoding of text fields without a `charset` parameter in their
+    /// content-type for `MultipartField::read_text()`, as a label such as `"iso-8859-1"`,
+    /// e.g. from the `accept-charset` of the form. The default is UTF-8.
+    ///
+    /// Reading a `_charset_` field with `read_text()` sets this too.
+    ///
+    /// Requires the `charset` feature.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error of kind `io::ErrorKind::InvalidInput` if `label` is not recognized.
+    #[cfg(feature = "charset")]
+    pub fn set_default_charset(&mut self, label: &str) -> io::Result<()> {
+        let encoding =
+            encoding_rs::Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported charset: {label:?}"),
+                )
+            })?;
+
+        self.default_charset = Some(encoding);
+        Ok(())
+    }
+
+    /// Set the maximum number of fields to read. Once that many have been read,
+    /// `read_entry()` returns an error of kind `io::ErrorKind::InvalidData` wrapping
+    /// `LimitError::TooManyFields` instead of another field. The default is no limit.
+    ///
+    /// This applies to every way of reading fields, including `save()`, which has its own
+    /// `count_limit()` as well.
+    pub fn set_max_fields(&mut self, max_fields: usize) {
+        self.max_fields = max_fields;
+    }
+
+    /// Set the limits on the number and size of fields, replacing any set before, including
+    /// with `set_max_fields()`.
+    ///
+    /// See `Limits` for more info.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.max_fields = limits.max_fields.unwrap_or(usize::MAX);
+        self.limits = LimitState::new(limits);
+    }
+
+    /// Set the size the read buffer is grown to when `read_entry()` skips the unread rest of
+    /// a large field, so it's read in fewer, larger chunks. The default is 64 KiB.
+    ///
+    /// See `BoundaryReader::set_skip_buf_size()` for more info.
+    pub fn set_skip_buf_size(&mut self, skip_buf_size: usize) {
+        self.reader.set_skip_buf_size(skip_buf_size);
+    }
+
+    /// Fail all further reads once `deadline` has passed, with an error of kind
+    /// `io::ErrorKind::TimedOut` wrapping `timeout::DeadlineExceeded`. This applies to
+    /// `read_entry()`, reading field data, and `save()`.
+    ///
+    /// The clock is checked before each read from the request body, so a read which is already
+    /// blocked is not interrupted; combine with `timeout::TimeoutReader` if the body may stall.
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.reader.set_deadline(Some(deadline));
+    }
+
+    /// Set an observer to be notified of fields, bytes read, and the end of the request or
+    /// errors, e.g. to collect metrics. Replaces any previous observer.
+    ///
+    /// See `MultipartObserver` for more info.
+    pub fn set_observer(&mut self, observer: Box<dyn MultipartObserver>) {
+        self.observer = Some(Observer {
+            inner: observer,
+            finished: false,
+        });
+    }
+
+    /// Collect warnings about anything unusual in the request which doesn't stop it from being
+    /// parsed, e.g. headers which are ignored because they're invalid or truncated filenames.
+    /// Read them with `warnings()`.
+    ///
+    /// Parsing is unaffected, these are only recorded in addition to being logged.
+    pub fn with_warnings(mut self) -> Self {
+        self.warnings = Some(Vec::new());
+        self
+    }
+
+    /// Parse no more than `len` bytes of the body, e.g. the `Content-Length` of the request,
+    /// so a body which is longer than declared can't smuggle in more fields. If the closing
+    /// boundary hasn't been found within `len` bytes, `read_entry()` or reading the last field
+    /// returns an error of kind `io::ErrorKind::UnexpectedEof`.
+    ///
+    /// See `BoundaryReader::set_limit()` for more info.
+    pub fn with_content_length_limit(mut self, len: u64) -> Self {
+        self.reader.set_limit(Some(len));
+        self
+    }
+
+    /// Whether the boundary was quoted in the request's `Content-Type` header, e.g.
+    /// `boundary="abc123"` rather than `boundary=abc123`, for reconstructing an equivalent
+    /// header when forwarding the body.
+    ///
+    /// Captured by `from_request()` (see `HttpRequest::multipart_boundary_quoted()`) and the
+    /// `axum`, `hyper1`, `rocket`, `tide` and `warp` integrations; always `false` for a
+    /// `Multipart` constructed from a boundary directly.
+    pub fn boundary_was_quoted(&self) -> bool {
+        self.boundary_quoted
+    }
+
+    /// The warnings recorded so far, if enabled with `with_warnings()`; empty otherwise.
+    ///
+    /// Warnings about a field's body are recorded once it has been read to the end,
+    /// so check this after reading all the fields.
+    pub fn warnings(&self) -> &[ParseWarning] {
+        self.warnings.as_deref().unwrap_or_default()
+    }
+
+    /// Describe the state of the parser, e.g. to find out why a request yields no fields.
+    ///
+    /// The result contains no field data so it is safe to log.
+    pub fn debug_state(&self) -> MultipartDebug {
+        MultipartDebug {
+            boundary: String::from_utf8_lossy(self.reader.boundary()).into_owned(),
+            consumed: self.reader.position(),
+            opening_boundary_found: self.reader.boundaries_found() > 0,
+            at_end: self.reader.is_at_end(),
+            fields_read: self.fields_read.load(Ordering::Relaxed),
         }
     }
 
+    /// Unwrap the request body reader, e.g. to drain or forward the rest of the body after
+    /// finding the field you wanted.
+    ///
+    /// ### Note: Buffered Data Is Lost
+    /// The body is read in chunks, so the returned reader is positioned at the end of the last
+    /// chunk read, somewhere at or past the parser's position. Whatever was buffered in between
+    /// is discarded. Use `into_buf_reader()` to keep it.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    /// Drop this `Multipart` along with the request body, returning the read buffer to its pool
+    /// if it was created with `with_body_pooled()`. Otherwise this is the same as dropping it.
+    pub fn release(self) {
+        drop(self.reader.into_inner());
+    }
+
+    /// Unwrap the request body reader along with the data which has been buffered but not
+    /// consumed yet, as a `BufRead`.
+    ///
+    /// Reading from the returned reader yields the rest of the raw body starting exactly at the
+    /// parser's position: the unread data of the current field followed by its boundary,
+    /// or the next boundary if the current field was read to the end. The boundary-framing
+    /// is not interpreted anymore.
+    pub fn into_buf_reader(self) -> BufReader<R> {
+        self.reader.into_buf_reader()
+    }
+
+    /// Only recognize the boundary when it is delimited exactly as RFC 2046 requires: preceded by
+    /// CRLF (unless it opens the body) and followed by CRLF, or `--` for the closing boundary.
+    ///
+    /// By default, any occurrence of the boundary string ends the current field, so with a short
+    /// boundary, field data which happens to contain it somewhere in a line will be cut short.
+    /// Strict mode treats such occurrences as data instead, at the cost of rejecting requests
+    /// from clients which are sloppy about line endings.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.reader.set_strict(strict);
+    }
+
+    /// Reject anything RFC 7578 doesn't allow instead of tolerating it, for a predictable parse
+    /// of untrusted requests. Defaults to `false`, which accepts what common clients send.
+    ///
+    /// In addition to `set_strict()`, this makes the following an error of kind
+    /// `io::ErrorKind::InvalidData` from `read_entry()`:
+    ///
+    /// * data before the opening boundary (the preamble) or after the closing boundary
+    ///   (the epilogue), except for a CRLF ending the body, and extra dashes after the
+    ///   closing boundary. The body isn't read past the closing boundary, so only an epilogue
+    ///   which is already buffered, or within `with_content_length_limit()`, is found;
+    /// * header lines ending without CR;
+    /// * a `Content-Disposition` parameter appearing more than once
+    ///   (`WarningReason::DuplicateParam` in lenient mode);
+    /// * a `Content-Transfer-Encoding` other than `7bit`, `8bit` or `binary`, which
+    ///   [RFC 7578, Section 4.7][rfc7578-4.7] deprecates. In lenient mode, `base64` and
+    ///   `quoted-printable` are decoded unless turned off with `set_decode_transfer_encoding()`,
+    ///   and anything not decoded gets a `WarningReason::UnsupportedTransferEncoding`.
+    ///
+    /// A field without a `name` and a body without the closing boundary are errors either way.
+    ///
+    /// See `BoundaryReader::set_strict_rfc7578()` for more info.
+    ///
+    /// [rfc7578-4.7]: https://tools.ietf.org/html/rfc7578#section-4.7
+    pub fn strict_rfc7578(mut self, strict: bool) -> Self {
+        self.reader.set_strict_rfc7578(strict);
+        self
+    }
+
+    /// Construct a new `Multipart` with a body which is already buffered, e.g. wrapped in
+    /// `std::io::BufReader`, and the given boundary.
+    ///
+    /// `with_body()` would add a second layer of buffering on top; this scans the body's own
+    /// buffer for boundaries instead. Only when a boundary or the field headers straddle the end
+    /// of that buffer are the few bytes involved copied, so the body's buffer should be
+    /// comfortably larger than a few kilobytes (the default of `std::io::BufReader` is fine).
+    ///
+    /// See `with_body()` for the format of `boundary`.
+    pub fn with_buffered_body<Bnd: Into<String>>(body: R, boundary: Bnd) -> Self
+    where
+        R: BufRead,
+    {
+        let boundary = boundary.into();
+
+        log::info!("Multipart::with_buffered_body(_, {:?})", boundary);
+
+        Self::from_reader(BoundaryReader::from_buf_read(body, boundary))
+    }
+
     /// Read the next entry from this multipart request, returning a struct with the field's name and
     /// data. See `MultipartField` for more info.
     ///
+    /// Returns `Ok(None)` after the closing boundary, or immediately if the request body is
+    /// completely empty (which is treated as having no fields). A body which ends before the
+    /// closing boundary is an error.
+    ///
     /// ## Warning: Risk of Data Loss
     /// If the previously returned entry had contents of type `MultipartField::File`,
     /// calling this again will discard any unread contents of that entry.
     ///
     /// # Errors
     ///
-    /// Will return `Error` if there is error in reading `entry`
+    /// Will return `Error` if there is error in reading `entry`. Convert it into a
+    /// `MultipartError` to tell a malformed or truncated request from a failure to read the body.
+    ///
+    /// An error of kind `io::ErrorKind::WouldBlock` or `io::ErrorKind::Interrupted` from the
+    /// body doesn't lose any data: call `read_entry()` again once the body is readable and it
+    /// picks up where it left off. The same goes for reading a field's data. After any other
+    /// error, the request should be abandoned.
     pub fn read_entry(&mut self) -> io::Result<Option<MultipartField<&mut Self>>> {
         self.read_entry_mut().into_result()
     }
@@ -116,12 +570,297 @@ impl<R: Read> Multipart<R> {
         }
     }
 
+    /// Like `foreach_entry()`, but `f` can stop the iteration by returning
+    /// `ControlFlow::Break(())`, e.g. once it has found what it was looking for.
+    /// The rest of the body is then left unread.
+    ///
+    /// Returns `Ok(())` when all fields have been read or `f` broke off, or the first error.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if reading any field before `f` broke off fails.
+    pub fn foreach_entry_ctl<F>(&mut self, mut foreach: F) -> io::Result<()>
+    where
+        F: FnMut(MultipartField<&mut Self>) -> ControlFlow<()>,
+    {
+        loop {
+            match self.read_entry() {
+                Ok(Some(field)) => {
+                    if foreach(field).is_break() {
+                        return Ok(());
+                    }
+                }
+                Ok(None) => return Ok(()),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Skip to the next field named `name` (compared exactly), e.g. when only the `file`
+    /// field of a form is of interest.
+    ///
+    /// Returns `Ok(None)` if the rest of the request has no field by that name.
+    ///
+    /// ## Warning: Skipped Fields Are Gone
+    /// Every field before the match is read past and its data discarded; there is no way to get
+    /// it back (short of `rewind()`ing a seekable body). Use `read_entry()` or `save()` to keep
+    /// other fields as well.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if reading any field up to and including the match fails.
+    pub fn find_field(&mut self, name: &str) -> io::Result<Option<MultipartField<&mut Self>>> {
+        let mut multipart = self;
+
+        loop {
+            match ReadEntry::read_entry(multipart) {
+                ReadEntryResult::Entry(field) if *field.headers.name == *name => {
+                    return Ok(Some(field))
+                }
+                ReadEntryResult::Entry(field) => {
+                    log::debug!("Skipping field {:?}", field.headers.name);
+                    multipart = field.data.into_inner();
+                }
+                ReadEntryResult::End(_) => return Ok(None),
+                ReadEntryResult::Error(_, err) => return Err(err),
+            }
+        }
+    }
+
+    /// Read past all the remaining fields in the request, discarding their data, and return how
+    /// many there were, e.g. to log the fields that weren't handled.
+    ///
+    /// This also consumes the body up to the closing boundary so a keep-alive connection stays
+    /// in sync. The rest of a field which was being read is discarded but not counted.
+    ///
+    /// Field data isn't copied anywhere; see `set_skip_buf_size()` to make skipping large fields
+    /// faster, and `set_trusted_seek_skip()` for seekable bodies.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if reading any field fails.
+    pub fn drain_remaining(&mut self) -> io::Result<usize> {
+        let mut count = 0;
+
+        while let Some(field) = self.read_entry()? {
+            log::debug!("Discarding field {:?}", field.headers.name);
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Write the metadata of each field read by `read` to `w` as a line of JSON, for logging
+    /// uploads. `read` reads the request as usual, e.g. with `read_entry()` or `save()`, and
+    /// its result is returned.
+    ///
+    /// Each line is an object with the field's `name`, `filename` and `content_type` (`null` if
+    /// not sent), the `size` of its data in bytes as read, and the `hash` computed while saving
+    /// it with `SaveBuilder::hash_with()` (`null` if none was), e.g.:
+    ///
+    /// ```json
+    /// {"content_type":"image/png","filename":"avatar.png","hash":null,"name":"avatar","size":1024}
+    /// ```
+    ///
+    /// The lines are written once `read` returns, for the fields it started reading. Data
+    /// skipped by `read_entry()` isn't counted in `size`. An observer set with `set_observer()`
+    /// is notified as usual meanwhile.
+    ///
+    /// ```rust
+    /// use mpart::server::Multipart;
+    ///
+    /// let body = "--boundary\r\n\
+    ///     Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+    ///     value\r\n\
+    ///     --boundary--";
+    ///
+    /// let mut multipart = Multipart::with_body(body.as_bytes(), "boundary");
+    /// let mut log = Vec::new();
+    ///
+    /// let entries = multipart
+    ///     .export_metadata_jsonl(&mut log, |multipart| multipart.save().temp().into_result())
+    ///     .unwrap()
+    ///     .unwrap();
+    /// assert_eq!(entries.fields_count(), 1);
+    /// assert!(log.ends_with(b"\"size\":5}\n"));
+    /// ```
+    ///
+    /// Requires the `serde` feature.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if writing to `w` fails.
+    #[cfg(feature = "serde")]
+    pub fn export_metadata_jsonl<W, F, T>(&mut self, w: &mut W, read: F) -> io::Result<T>
+    where
+        W: Write,
+        F: FnOnce(&mut Self) -> T,
+    {
+        let outer = self.metadata.replace(observer::MetadataRecorder::default());
+        let ret = read(self);
+        let recorded = std::mem::replace(&mut self.metadata, outer).unwrap_or_default();
+
+        recorded.write_jsonl(w)?;
+        Ok(ret)
+    }
+
+    /// Read all remaining fields in the request into owned values which can be collected
+    /// and processed later, trading streaming for convenience.
+    ///
+    /// See `MultipartField::into_owned()` for how each field's data is stored.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if reading any field fails; fields read up to that point are dropped.
+    pub fn collect_owned(&mut self) -> io::Result<Vec<SavedField>> {
+        let mut fields = Vec::new();
+
+        while let Some(field) = self.read_entry()? {
+            fields.push(field.into_owned()?);
+        }
+
+        Ok(fields)
+    }
+
     /// Get a builder type for saving the files in this request to the filesystem.
     ///
     /// See [`SaveBuilder`](save/struct.SaveBuilder.html) for more information.
     pub fn save(&mut self) -> SaveBuilder<&mut Self> {
         SaveBuilder::new(self)
     }
+
+    /// Get a builder type for saving the files in this request, starting from the options in
+    /// `config` instead of the defaults.
+    ///
+    /// Setters called on the builder override the options from `config` for this request only.
+    pub fn save_from(&mut self, config: &SaveConfig) -> SaveBuilder<&mut Self> {
+        SaveBuilder::from_config(self, config.clone())
+    }
+
+    /// Save all the remaining fields with the options in `config`, which can be shared
+    /// between requests.
+    ///
+    /// Equivalent to `self.save_from(config).with_config_dir()`; see `save::SaveConfig` for
+    /// more info.
+    pub fn save_with(&mut self, config: &SaveConfig) -> EntriesSaveResult<&mut Self> {
+        self.save_from(config).with_config_dir()
+    }
+
+    /// Save all the remaining fields with the default settings of `save()` to a new temporary
+    /// directory; the shortcut for simple upload handling.
+    ///
+    /// Use `save()` for control over limits and where files go.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` unless all fields were saved. If saving stopped partway through, the
+    /// error wraps the `save::PartialReason` (retrievable with `get_ref()` and `downcast_ref()`)
+    /// unless it was an I/O error, which is returned as-is; the fields saved so far are dropped.
+    pub fn read_entries(&mut self) -> io::Result<Entries> {
+        self.save().temp().into_full_result()
+    }
+
+    /// Like `read_entries()`, but consumes `self`.
+    ///
+    /// # Errors
+    ///
+    /// See `read_entries()`.
+    pub fn into_entries(mut self) -> io::Result<Entries> {
+        self.read_entries()
+    }
+}
+
+impl<R: Read + Seek> Multipart<R> {
+    /// Seek the request body back to the start so the request can be parsed again from the
+    /// first field.
+    ///
+    /// Only available for seekable bodies, e.g. ones buffered in memory as `Cursor<Vec<u8>>`;
+    /// a body being streamed from the network cannot be re-read.
+    ///
+    /// Note that this seeks to offset `0` of the underlying reader, not the position
+    /// it was at when this `Multipart` was constructed. Any warnings collected so far are
+    /// cleared.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if seeking the underlying reader fails.
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.reader.rewind()?;
+        self.fields_read.store(0, Ordering::Relaxed);
+        self.limits = LimitState::new(self.limits.limits);
+        self.current_field = None;
+        self.headers_pending = false;
+
+        if let Some(warnings) = self.warnings.as_mut() {
+            warnings.clear();
+        }
+
+        if let Some(observer) = self.observer.as_mut() {
+            observer.finished = false;
+        }
+
+        Ok(())
+    }
+
+    /// Seek past the unread rest of a field instead of reading it when moving on to the next
+    /// one, if the field declared its length with a `Content-Length` header. This makes skipping
+    /// large files, e.g. with `find_field()`, nearly free for bodies that are files on disk.
+    ///
+    /// ### Warning: Only for Trusted Bodies
+    /// The declared length is only checked to end right before a boundary. A length which
+    /// spans the following parts up to a later boundary skips those fields without them ever
+    /// being seen, so only enable this for bodies you produced or otherwise trust, never for
+    /// bodies straight from clients. If no boundary is at the declared end, the rest of the
+    /// field is read as usual.
+    pub fn set_trusted_seek_skip(&mut self, seek_skip: bool) {
+        self.reader.set_trusted_seek_skip(seek_skip);
+    }
+
+    /// Cheaply estimate how many fields remain in the request by scanning the rest of the body
+    /// for occurrences of the boundary, without parsing any fields.
+    ///
+    /// This is intended for rejecting requests with an absurd number of fields before doing any
+    /// real work. The estimate will be too high if field data happens to contain the boundary
+    /// string, and is not a substitute for the count limit when saving.
+    ///
+    /// Returns `None` if the body cannot actually be seeked (e.g. a pipe) or reading fails.
+    pub fn field_count_hint(&mut self) -> Option<usize> {
+        match self.reader.count_boundaries() {
+            // the closing boundary doesn't start a field
+            Ok(count) => Some(count.saturating_sub(1)),
+            Err(e) => {
+                log::debug!("failed to scan body for boundaries: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl<R: Read> Multipart<ChunkedReader<R>> {
+    /// Construct a new `Multipart` with a body which still has the framing of
+    /// `Transfer-Encoding: chunked`, decoding it before parsing.
+    ///
+    /// Only use this if the body reader of your HTTP library yields the raw chunked bytes;
+    /// most decode the framing themselves, in which case use `with_body()`.
+    ///
+    /// See `with_body()` for the format of `boundary`.
+    pub fn with_chunked_body<Bnd: Into<String>>(body: R, boundary: Bnd) -> Self {
+        Multipart::with_body(ChunkedReader::new(body), boundary)
+    }
+}
+
+impl Multipart<io::Cursor<Vec<u8>>> {
+    /// Construct a new `Multipart` with a body which is already in memory, e.g. as `Vec<u8>`
+    /// (which is not copied) or `&[u8]`.
+    ///
+    /// The body is seekable, so `rewind()` and `field_count_hint()` are available. To parse it
+    /// without copying any field data, see `parse::parse_multipart()`; to save all the fields
+    /// in one go, see `Entries::from_bytes()`.
+    ///
+    /// See `with_body()` for the format of `boundary`.
+    pub fn from_bytes<B: Into<Vec<u8>>, Bnd: Into<String>>(body: B, boundary: Bnd) -> Self {
+        Multipart::with_body(io::Cursor::new(body.into()), boundary)
+    }
 }
 
 impl<R: Read> PrivReadEntry for Multipart<R> {
@@ -139,24 +878,3851 @@ impl<R: Read> PrivReadEntry for Multipart<R> {
     /// Returns `true` if a field should follow this boundary, `false` otherwise.
     fn consume_boundary(&mut self) -> io::Result<bool> {
         log::debug!("Consume boundary!");
-        self.reader.consume_boundary()
-    }
-}
 
-/// A server-side HTTP request that may or may not be multipart.
-///
-/// May be implemented by mutable references if providing the request or body by-value is
-/// undesirable.
-pub trait HttpRequest {
-    /// The body of this request.
-    type Body: Read;
-    /// Get the boundary string of this request if it is a POST request
-    /// with the `Content-Type` header set to `multipart/form-data`.
-    ///
-    /// The boundary string should be supplied as an extra value of the `Content-Type` header, e.g.
-    /// `Content-Type: multipart/form-data; boundary={boundary}`.
-    fn multipart_boundary(&self) -> Option<&str>;
+        self.limits.check_exceeded()?;
 
-    /// Return the request body for reading.
-    fn body(self) -> Self::Body;
+        let more = self.reader.consume_boundary()?;
+
+        if let Some((declared, actual)) = self.reader.take_len_mismatch() {
+            let field = self.current_field.take();
+
+            self.warn(ParseWarning {
+                reason: WarningReason::ContentLengthMismatch { declared, actual },
+                field,
+            });
+        }
+
+        if !more {
+            return Ok(false);
+        }
+
+        if self.fields_read.load(Ordering::Relaxed) >= self.max_fields {
+            return Err(self.limits.exceed(LimitError::TooManyFields {
+                max: self.max_fields,
+            }));
+        }
+
+        self.fields_read.fetch_add(1, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    fn position(&self) -> Option<u64> {
+        Some(self.reader.position())
+    }
+
+    fn boundary_position(&self) -> Option<u64> {
+        self.reader.boundary_position()
+    }
+
+    fn filename_limit(&self) -> FilenameLimit {
+        self.filename_limit
+    }
+
+    fn expect_body_len(&mut self, len: Option<u64>) {
+        self.reader.expect_body_len(len);
+    }
+
+    fn observe_field_start(&mut self, headers: &FieldHeaders) {
+        self.limits.field_start(headers);
+
+        if self.warnings.is_some() {
+            self.current_field = Some(headers.name.clone());
+        }
+
+        if let Some(observer) = self.observer.as_mut() {
+            observer.inner.on_field_start(headers);
+        }
+
+        #[cfg(feature = "serde")]
+        if let Some(metadata) = self.metadata.as_mut() {
+            metadata.field_start(headers);
+        }
+    }
+
+    fn observe_bytes(&mut self, n: u64) {
+        self.limits.count(n);
+
+        if let Some(observer) = self.observer.as_mut() {
+            observer.inner.on_bytes(n);
+        }
+
+        #[cfg(feature = "serde")]
+        if let Some(metadata) = self.metadata.as_mut() {
+            metadata.bytes(n);
+        }
+    }
+
+    fn check_limits(&mut self) -> io::Result<()> {
+        self.limits.check()
+    }
+
+    fn warn(&mut self, warning: ParseWarning) {
+        if let Some(warnings) = self.warnings.as_mut() {
+            warnings.push(warning);
+        }
+    }
+
+    fn strict_rfc7578(&self) -> bool {
+        self.reader.is_strict_rfc7578()
+    }
+
+    fn is_strict(&self) -> bool {
+        self.reader.is_strict()
+    }
+
+    fn max_fields(&self) -> usize {
+        self.max_fields
+    }
+
+    fn fields_read(&self) -> Option<Arc<AtomicUsize>> {
+        Some(self.fields_read.clone())
+    }
+
+    fn line_ending_policy(&self) -> LineEndingPolicy {
+        self.line_ending_policy
+    }
+
+    fn max_header_line_len(&self) -> usize {
+        self.max_header_line_len
+    }
+
+    fn nested_name(&self) -> Option<Arc<str>> {
+        self.nested_name.clone()
+    }
+
+    fn decode_transfer_encoding(&self) -> bool {
+        self.decode_transfer_encoding
+    }
+
+    #[cfg(feature = "charset")]
+    fn default_charset(&self) -> Option<&'static encoding_rs::Encoding> {
+        self.default_charset
+    }
+
+    #[cfg(feature = "charset")]
+    fn set_default_charset(&mut self, encoding: &'static encoding_rs::Encoding) {
+        self.default_charset = Some(encoding);
+    }
+
+    fn set_headers_pending(&mut self) {
+        self.headers_pending = true;
+    }
+
+    fn take_headers_pending(&mut self) -> bool {
+        std::mem::take(&mut self.headers_pending)
+    }
+
+    fn observe_end(&mut self, res: Result<(), &io::Error>) {
+        let Some(observer) = self.observer.as_mut() else {
+            return;
+        };
+
+        match res {
+            Ok(()) if !observer.finished => {
+                observer.finished = true;
+                observer.inner.on_complete(
+                    self.fields_read.load(Ordering::Relaxed),
+                    self.reader.position(),
+                );
+            }
+            Ok(()) => (),
+            Err(e) => observer.inner.on_error(e),
+        }
+    }
+
+    fn observe_digest(&mut self, digest: &str) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.inner.on_field_digest(digest);
+        }
+
+        #[cfg(feature = "serde")]
+        if let Some(metadata) = self.metadata.as_mut() {
+            metadata.digest(digest);
+        }
+    }
+}
+
+/// The state of a `Multipart` parser, as returned by `Multipart::debug_state()`.
+///
+/// If a request unexpectedly yields no fields:
+///
+/// * If `opening_boundary_found` is `false` after `read_entry()` returned an error, the body
+///   doesn't contain `boundary`, so the boundary passed in doesn't match the one the body was
+///   generated with.
+/// * If `at_end` is `true` and `consumed` is `0`, the body was empty.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultipartDebug {
+    /// The boundary being searched for, without the leading `--`.
+    pub boundary: String,
+    /// The number of bytes of the body consumed so far.
+    pub consumed: u64,
+    /// Whether the opening boundary has been found yet.
+    pub opening_boundary_found: bool,
+    /// Whether the closing boundary has been found, or the body was empty.
+    pub at_end: bool,
+    /// The number of fields read so far.
+    pub fields_read: usize,
+}
+
+/// A server-side HTTP request that may or may not be multipart.
+///
+/// May be implemented by mutable references if providing the request or body by-value is
+/// undesirable.
+pub trait HttpRequest {
+    /// The body of this request.
+    type Body: Read;
+    /// Get the boundary string of this request if it is a POST request
+    /// with the `Content-Type` header set to `multipart/form-data`.
+    ///
+    /// The boundary string should be supplied as an extra value of the `Content-Type` header, e.g.
+    /// `Content-Type: multipart/form-data; boundary={boundary}`.
+    fn multipart_boundary(&self) -> Option<&str>;
+
+    /// Like `multipart_boundary()`, but for any `multipart/*` content-type, for
+    /// `Multipart::from_request_any_subtype()`.
+    ///
+    /// Defaults to `multipart_boundary()`, i.e. only `multipart/form-data`.
+    fn multipart_boundary_any_subtype(&self) -> Option<&str> {
+        self.multipart_boundary()
+    }
+
+    /// Whether the boundary was double-quoted in the `Content-Type` header, as recorded by
+    /// `Multipart::boundary_was_quoted()`. Defaults to `false`.
+    fn multipart_boundary_quoted(&self) -> bool {
+        false
+    }
+
+    /// Return the request body for reading.
+    fn body(self) -> Self::Body;
+}
+
+/// An object-safe version of `HttpRequest`, for storing requests of different types as
+/// `Box<dyn DynHttpRequest>`.
+///
+/// Implemented for every `HttpRequest` with a `'static` body. `Box<dyn DynHttpRequest>` in turn
+/// implements `HttpRequest`, so it can be passed to `Multipart::from_request()`.
+pub trait DynHttpRequest {
+    /// Get the boundary string of this request; see `HttpRequest::multipart_boundary()`.
+    fn multipart_boundary(&self) -> Option<&str>;
+
+    /// See `HttpRequest::multipart_boundary_any_subtype()`.
+    fn multipart_boundary_any_subtype(&self) -> Option<&str>;
+
+    /// See `HttpRequest::multipart_boundary_quoted()`.
+    fn multipart_boundary_quoted(&self) -> bool;
+
+    /// Return the request body for reading.
+    fn into_body(self: Box<Self>) -> Box<dyn Read>;
+}
+
+impl<R> DynHttpRequest for R
+where
+    R: HttpRequest,
+    R::Body: 'static,
+{
+    fn multipart_boundary(&self) -> Option<&str> {
+        HttpRequest::multipart_boundary(self)
+    }
+
+    fn multipart_boundary_any_subtype(&self) -> Option<&str> {
+        HttpRequest::multipart_boundary_any_subtype(self)
+    }
+
+    fn multipart_boundary_quoted(&self) -> bool {
+        HttpRequest::multipart_boundary_quoted(self)
+    }
+
+    fn into_body(self: Box<Self>) -> Box<dyn Read> {
+        Box::new((*self).body())
+    }
+}
+
+impl HttpRequest for Box<dyn DynHttpRequest> {
+    type Body = Box<dyn Read>;
+
+    fn multipart_boundary(&self) -> Option<&str> {
+        (**self).multipart_boundary()
+    }
+
+    fn multipart_boundary_any_subtype(&self) -> Option<&str> {
+        (**self).multipart_boundary_any_subtype()
+    }
+
+    fn multipart_boundary_quoted(&self) -> bool {
+        (**self).multipart_boundary_quoted()
+    }
+
+    fn body(self) -> Box<dyn Read> {
+        self.into_body()
+    }
+}
+
+/// Find the value of the `boundary` parameter in a `Content-Type` header value.
+///
+/// The parameter name is matched case-insensitively, whitespace around the `=` is ignored and
+/// a double-quoted value is unquoted.
+///
+/// If the parameter appears more than once, the first one is used (as `mime` does). An empty
+/// boundary is rejected with `None` since nothing could be parsed with it.
+#[cfg_attr(
+    not(any(feature = "tiny_http", feature = "hyper1", feature = "warp")),
+    allow(dead_code)
+)]
+fn boundary_param(content_type: &str) -> Option<&str> {
+    raw_boundary_param(content_type)
+        .map(|val| unquote(val).unwrap_or(val))
+        .filter(|val| !val.is_empty())
+}
+
+/// Whether the `boundary` parameter found by `boundary_param()` is double-quoted.
+#[cfg_attr(
+    not(any(
+        feature = "hyper",
+        feature = "tiny_http",
+        feature = "axum",
+        feature = "hyper1",
+        feature = "tide",
+        feature = "warp"
+    )),
+    allow(dead_code)
+)]
+fn boundary_quoted(content_type: &str) -> bool {
+    raw_boundary_param(content_type)
+        .and_then(unquote)
+        .is_some_and(|val| !val.is_empty())
+}
+
+/// The trimmed value of the first `boundary` parameter, still quoted if it was.
+fn raw_boundary_param(content_type: &str) -> Option<&str> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("boundary"))
+        .map(|(_, val)| val.trim())
+}
+
+fn unquote(val: &str) -> Option<&str> {
+    val.strip_prefix('"').and_then(|val| val.strip_suffix('"'))
+}
+
+/// Find the boundary of a `multipart/form-data` `Content-Type` header value,
+/// following the same rules as `boundary_param()`.
+#[cfg(any(
+    feature = "axum",
+    feature = "hyper1",
+    feature = "rocket",
+    feature = "tide",
+    feature = "warp"
+))]
+fn form_data_boundary(content_type: &str) -> Option<&str> {
+    let mime = content_type.parse::<mime::Mime>().ok()?;
+
+    if mime.type_() != mime::MULTIPART || mime.subtype() != mime::FORM_DATA {
+        return None;
+    }
+
+    boundary_param(content_type)
+}
+
+/// Find the boundary of a `Content-Type` header value of any `multipart/*` type, following the
+/// same rules as `boundary_param()`.
+#[cfg(feature = "tiny_http")]
+fn any_multipart_boundary(content_type: &str) -> Option<&str> {
+    let mime = content_type.parse::<mime::Mime>().ok()?;
+
+    if mime.type_() != mime::MULTIPART {
+        return None;
+    }
+
+    boundary_param(content_type)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    #[cfg(feature = "checksums")]
+    use super::field::ChecksumAlgorithm;
+    use super::save::{PartialReason, SaveConfig, SaveResult, SavedData, TextPolicy};
+    use super::warning::{ParseWarning, WarningReason};
+    use super::{boundary_param, parse, push, Multipart};
+
+    use std::fs;
+    use std::io::prelude::*;
+    use std::io::{self, Cursor};
+
+    const BOUNDARY: &str = "boundary";
+
+    /// A `Content-Type` with two `boundary` parameters, of which the first should be used.
+    pub(crate) const DUPLICATE_BOUNDARY: &str =
+        "multipart/form-data; boundary=first; charset=utf-8; boundary=second";
+
+    fn multipart(body: &'static str) -> Multipart<&'static [u8]> {
+        Multipart::with_body(body.as_bytes(), BOUNDARY)
+    }
+
+    #[test]
+    fn test_save_to() {
+        let mut multipart = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\r\n\
+             hello, world!\r\n\
+             --boundary--",
+        );
+
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        let mut dest = Vec::new();
+
+        assert_eq!(field.save_to(&mut dest).unwrap(), 13);
+        assert_eq!(dest, b"hello, world!");
+        assert!(multipart.read_entry().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_save_to_size_limit() {
+        let body = format!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\r\n\
+             {}\r\n\
+             --boundary--",
+            "a".repeat(8 * 1024 * 1024 + 1)
+        );
+        let mut multipart = Multipart::with_body(body.as_bytes(), BOUNDARY);
+
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        let mut dest = Vec::new();
+
+        field.save_to(&mut dest).unwrap_err();
+        assert!(dest.len() <= 8 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_read_entry_boundary_errors() {
+        // the closing boundary ends the request instead of being read as a field
+        let mut request = multipart("--boundary--");
+        assert!(request.read_entry().unwrap().is_none());
+
+        // an error reading the boundary is returned rather than taken for the end
+        let mut request = Multipart::with_body(ErrorReader, BOUNDARY);
+        assert_eq!(
+            request.read_entry().err().unwrap().kind(),
+            io::ErrorKind::ConnectionReset
+        );
+
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             text\r\n\
+             --boundaryXX",
+        );
+        drop(request.read_entry().unwrap().unwrap());
+        assert_eq!(
+            request.read_entry().err().unwrap().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_boundary_param() {
+        let cases = [
+            "multipart/form-data; boundary=abc123",
+            "multipart/form-data; BOUNDARY=\"abc123\"",
+            "multipart/form-data; Boundary = abc123 ;",
+            "multipart/form-data;charset=utf-8;boundary=\"abc123\";",
+        ];
+
+        for case in &cases {
+            assert_eq!(boundary_param(case), Some("abc123"), "{case:?}");
+        }
+
+        assert_eq!(boundary_param("multipart/form-data"), None);
+        assert_eq!(boundary_param("boundary=abc123"), None);
+
+        assert_eq!(boundary_param(DUPLICATE_BOUNDARY), Some("first"));
+        assert_eq!(boundary_param("multipart/form-data; boundary="), None);
+        assert_eq!(boundary_param("multipart/form-data; boundary=\"\""), None);
+        // the first boundary is empty so the second isn't considered
+        assert_eq!(
+            boundary_param("multipart/form-data; boundary=; boundary=abc123"),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "tiny_http")]
+    fn test_any_multipart_boundary() {
+        use super::any_multipart_boundary;
+
+        assert_eq!(
+            any_multipart_boundary("multipart/related; boundary=\"abc123\""),
+            Some("abc123")
+        );
+        assert_eq!(
+            any_multipart_boundary("multipart/form-data; boundary=abc123"),
+            Some("abc123")
+        );
+        assert_eq!(any_multipart_boundary("text/plain; boundary=abc123"), None);
+        assert_eq!(any_multipart_boundary("multipart/mixed"), None);
+    }
+
+    #[test]
+    fn test_boundary_quoted() {
+        use super::boundary_quoted;
+
+        assert!(boundary_quoted("multipart/form-data; boundary=\"abc123\""));
+        assert!(boundary_quoted(
+            "multipart/form-data; Boundary = \"abc123\" ;"
+        ));
+        assert!(!boundary_quoted("multipart/form-data; boundary=abc123"));
+        assert!(!boundary_quoted("multipart/form-data; boundary=\"\""));
+        assert!(!boundary_quoted("multipart/form-data"));
+        // only the first boundary counts, as for `boundary_param()`
+        assert!(!boundary_quoted(
+            "multipart/form-data; boundary=abc123; boundary=\"abc123\""
+        ));
+    }
+
+    #[cfg(any(
+        feature = "axum",
+        feature = "hyper1",
+        feature = "rocket",
+        feature = "tide",
+        feature = "warp"
+    ))]
+    #[test]
+    fn test_form_data_boundary() {
+        use super::form_data_boundary;
+
+        assert_eq!(form_data_boundary(DUPLICATE_BOUNDARY), Some("first"));
+        assert_eq!(form_data_boundary("multipart/form-data; boundary="), None);
+        assert_eq!(form_data_boundary("multipart/mixed; boundary=abc123"), None);
+        assert_eq!(form_data_boundary("text/plain; boundary=abc123"), None);
+    }
+
+    #[test]
+    fn test_rewind() {
+        let body = "--boundary\r\n\
+                    Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+                    some text\r\n\
+                    --boundary\r\n\
+                    Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\r\n\
+                    file contents\r\n\
+                    --boundary--";
+
+        let mut multipart = Multipart::with_body(Cursor::new(body.as_bytes().to_vec()), BOUNDARY);
+
+        let read_all = |multipart: &mut Multipart<_>| {
+            let mut fields = Vec::new();
+            multipart
+                .foreach_entry(|mut field| {
+                    let mut data = String::new();
+                    field.data.read_to_string(&mut data).unwrap();
+                    fields.push((field.headers.name, field.headers.filename, data));
+                })
+                .unwrap();
+            fields
+        };
+
+        let first = read_all(&mut multipart);
+        multipart.rewind().unwrap();
+        let second = read_all(&mut multipart);
+
+        assert_eq!(first.len(), 2);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_body_range() {
+        let body = "--boundary\r\n\
+                    Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+                    first value\r\n\
+                    --boundary\r\n\
+                    Content-Disposition: form-data; name=\"second\"\r\n\r\n\
+                    second value\r\n\
+                    --boundary--";
+
+        let mut multipart = multipart(body);
+
+        for val in ["first value", "second value"] {
+            let mut field = multipart.read_entry().unwrap().unwrap();
+            io::copy(&mut field.data, &mut io::sink()).unwrap();
+
+            let start = body.find(val).unwrap() as u64;
+            let end = start + val.len() as u64;
+            assert_eq!(field.body_range(), Some((start, end)));
+        }
+    }
+
+    #[test]
+    fn test_field_count_hint() {
+        let mut body = String::new();
+        for i in 0..50 {
+            body.push_str(&format!(
+                "--boundary\r\n\
+                 Content-Disposition: form-data; name=\"field{i}\"\r\n\r\n\
+                 value {i}\r\n"
+            ));
+        }
+        body.push_str("--boundary--");
+
+        let mut multipart = Multipart::with_body(Cursor::new(body.into_bytes()), BOUNDARY);
+        assert_eq!(multipart.field_count_hint(), Some(50));
+
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        let mut data = String::new();
+        field.data.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "value 0");
+
+        assert_eq!(multipart.field_count_hint(), Some(49));
+
+        // the scan must not disturb parsing
+        let field = multipart.read_entry().unwrap().unwrap();
+        assert_eq!(&*field.headers.name, "field1");
+    }
+
+    #[test]
+    fn test_collect_owned() {
+        let mut multipart = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             some text\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             \x00\x01\x02\r\n\
+             --boundary--",
+        );
+
+        let fields = multipart.collect_owned().unwrap();
+        assert_eq!(fields.len(), 2);
+
+        assert_eq!(&*fields[0].headers.name, "text");
+        assert!(matches!(fields[0].data, SavedData::Text(ref text) if text == "some text"));
+
+        assert_eq!(&*fields[1].headers.name, "file");
+        assert_eq!(fields[1].headers.filename.as_deref(), Some("file.bin"));
+        assert_eq!(fields[1].data.size(), 3);
+    }
+
+    #[cfg(feature = "charset")]
+    #[test]
+    fn test_read_text_latin1() {
+        let body: &[u8] = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"text\"\r\n\
+            Content-Type: text/plain; charset=ISO-8859-1\r\n\r\n\
+            caf\xe9 cr\xe8me br\xfbl\xe9e\r\n\
+            --boundary--";
+
+        let mut multipart = Multipart::with_body(body, "boundary");
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        assert_eq!(field.read_text().unwrap(), "café crème brûlée");
+    }
+
+    #[cfg(feature = "charset")]
+    #[test]
+    fn test_read_text_default_charset() {
+        let body: &[u8] = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"_charset_\"\r\n\r\n\
+            iso-8859-1\r\n\
+            --boundary\r\n\
+            Content-Disposition: form-data; name=\"latin1\"\r\n\r\n\
+            caf\xe9\r\n\
+            --boundary\r\n\
+            Content-Disposition: form-data; name=\"utf16\"\r\n\
+            Content-Type: text/plain; charset=UTF-16LE\r\n\r\n\
+            c\0a\0f\0\xe9\0\r\n\
+            --boundary--";
+
+        let mut multipart = Multipart::with_body(body, "boundary");
+        let mut texts = Vec::new();
+
+        multipart
+            .foreach_entry(|mut field| texts.push(field.read_text().unwrap()))
+            .unwrap();
+        assert_eq!(texts, ["iso-8859-1", "café", "café"]);
+
+        // skipping `_charset_` leaves UTF-8
+        let mut multipart = Multipart::with_body(body, "boundary");
+        drop(multipart.read_entry().unwrap().unwrap());
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        assert_eq!(field.read_text().unwrap(), "caf\u{fffd}");
+        drop(field);
+
+        let mut multipart = Multipart::with_body(body, "boundary");
+        assert!(multipart.set_default_charset("no such charset").is_err());
+        multipart.set_default_charset("latin1").unwrap();
+        drop(multipart.read_entry().unwrap().unwrap());
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        assert_eq!(field.read_text().unwrap(), "café");
+    }
+
+    #[test]
+    fn test_string_from_field() {
+        use super::extract::FromField;
+
+        let body: &[u8] = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+            caf\xc3\xa9\r\n\
+            --boundary\r\n\
+            Content-Disposition: form-data; name=\"invalid\"\r\n\r\n\
+            caf\xe9\r\n\
+            --boundary--";
+
+        let mut request = Multipart::with_body(body, BOUNDARY);
+        let field = request.read_entry().unwrap().unwrap();
+        assert_eq!(String::from_field(field, None).unwrap(), "café");
+
+        // an error with or without the `charset` feature
+        let field = request.read_entry().unwrap().unwrap();
+        let err = String::from_field(field, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    struct ErrorReader;
+
+    impl Read for ErrorReader {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "connection reset",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_cleanup_on_error() {
+        let mut body = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\r\n"
+            .to_vec();
+        // enough data that the error isn't hit while reading the headers
+        body.resize(body.len() + 64 * 1024, b'x');
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.bin");
+
+        let mut multipart = Multipart::with_body(Cursor::new(body).chain(ErrorReader), "boundary");
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        let res = field.data.save().memory_threshold(0).with_path(&path);
+
+        assert_eq!(
+            res.into_result_strict().unwrap_err().kind(),
+            io::ErrorKind::ConnectionReset
+        );
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_entries_cleanup() {
+        let mut multipart = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\r\n\
+             some data\r\n\
+             --boundary--",
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut entries = multipart
+            .save()
+            .memory_threshold(0)
+            .with_dir(dir.path())
+            .into_result_strict()
+            .unwrap();
+
+        let path = match entries.fields["file"][0].data {
+            SavedData::File(ref path, _) => path.clone(),
+            ref other => panic!("expected file, got {:?}", other),
+        };
+        assert!(path.exists());
+
+        entries.cleanup();
+        assert!(!path.exists());
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_chunks() {
+        let mut multipart = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\r\n\
+             0123456789\r\n\
+             --boundary--",
+        );
+
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        let chunks = field.chunks(4).collect::<io::Result<Vec<_>>>().unwrap();
+
+        assert_eq!(chunks, [&b"0123"[..], b"4567", b"89"]);
+    }
+
+    #[test]
+    fn test_strict_boundary_in_data() {
+        let body: &[u8] = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\r\n\
+            \x00\x01--boundary\x02\r\n--boundary\xff\xfe\r\n--boundaryX\r\n\
+            --boundary\r\n\
+            Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+            text\r\n\
+            --boundary--";
+
+        let mut multipart = Multipart::with_body(body, "boundary");
+        multipart.set_strict(true);
+
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        let mut data = Vec::new();
+        field.data.read_to_end(&mut data).unwrap();
+        assert_eq!(
+            data,
+            b"\x00\x01--boundary\x02\r\n--boundary\xff\xfe\r\n--boundaryX"
+        );
+
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        assert_eq!(&*field.headers.name, "text");
+        let mut text = String::new();
+        field.data.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "text");
+
+        assert!(multipart.read_entry().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_strict_rfc7578() {
+        fn parse_all(body: &'static str, strict: bool) -> io::Result<usize> {
+            let mut multipart = multipart(body).strict_rfc7578(strict);
+            let mut fields = 0;
+
+            while let Some(mut field) = multipart.read_entry()? {
+                io::copy(&mut field.data, &mut io::sink())?;
+                fields += 1;
+            }
+
+            Ok(fields)
+        }
+
+        // accepted in lenient mode, rejected in strict mode
+        let lenient_only = [
+            // a bare LF in the headers
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\n\n\
+             value\r\n\
+             --boundary--",
+            // a boundary not preceded by CRLF
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\r\n\
+             value--boundary--",
+            // an encoding which is not decoded
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\
+             Content-Transfer-Encoding: x-uuencode\r\n\r\n\
+             value\r\n\
+             --boundary--",
+            // an encoding which is decoded
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\
+             Content-Transfer-Encoding: base64\r\n\r\n\
+             dmFsdWU=\r\n\
+             --boundary--",
+            // a duplicate parameter
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"; name=\"b\"\r\n\r\n\
+             value\r\n\
+             --boundary--",
+            // a preamble
+            "preamble\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\r\n\
+             value\r\n\
+             --boundary--",
+            // an epilogue
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\r\n\
+             value\r\n\
+             --boundary--\r\n\
+             epilogue",
+            // extra dashes after the closing boundary
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\r\n\
+             value\r\n\
+             --boundary----",
+        ];
+
+        for body in lenient_only {
+            assert_eq!(parse_all(body, false).unwrap(), 1, "{body:?}");
+            assert!(parse_all(body, true).is_err(), "{body:?}");
+        }
+
+        // rejected either way
+        let invalid = [
+            // no name
+            "--boundary\r\n\
+             Content-Disposition: form-data; filename=\"a.txt\"\r\n\r\n\
+             value\r\n\
+             --boundary--",
+            // no closing boundary
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\r\n\
+             value\r\n",
+        ];
+
+        for body in invalid {
+            assert!(parse_all(body, false).is_err(), "{body:?}");
+            assert!(parse_all(body, true).is_err(), "{body:?}");
+        }
+
+        // a CRLF ending the body is fine
+        let valid = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\r\n\
+             value\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"b\"\r\n\r\n\
+             value\r\n\
+             --boundary--\r\n";
+        assert_eq!(parse_all(valid, true).unwrap(), 2);
+
+        // the epilogue check doesn't read further than lenient mode, which could block on a
+        // keep-alive connection
+        struct Counted<'a>(&'a [u8], usize);
+
+        impl Read for Counted<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.1 += 1;
+                self.0.read(buf)
+            }
+        }
+
+        let reads = [false, true].map(|strict| {
+            let mut request =
+                Multipart::with_body(Counted(valid.as_bytes(), 0), BOUNDARY).strict_rfc7578(strict);
+
+            while let Some(mut field) = request.read_entry().unwrap() {
+                io::copy(&mut field.data, &mut io::sink()).unwrap();
+            }
+
+            request.into_inner().1
+        });
+        assert_eq!(reads[0], reads[1]);
+
+        // nor into the next request on the connection within the length limit
+        let pipelined = format!("{valid}GET / HTTP/1.1\r\n\r\n");
+        let mut request = Multipart::with_body(pipelined.as_bytes(), BOUNDARY)
+            .strict_rfc7578(true)
+            .with_content_length_limit(valid.len() as u64);
+
+        while let Some(mut field) = request.read_entry().unwrap() {
+            io::copy(&mut field.data, &mut io::sink()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_line_ending_policy() {
+        use super::field::LineEndingPolicy;
+
+        const MIXED: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\n\
+             Content-Type: text/plain\r\n\r\n\
+             value\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"b\"\n\n\
+             value\r\n\
+             --boundary--";
+
+        let read_all = |policy| {
+            let mut multipart = multipart(MIXED).with_warnings();
+            multipart.set_line_ending_policy(policy);
+
+            let mut fields = Vec::new();
+            multipart.foreach_entry(|mut field| {
+                let mut data = String::new();
+                field.data.read_to_string(&mut data).unwrap();
+                assert_eq!(data, "value");
+                fields.push((field.headers.name, field.headers.content_type));
+            })?;
+
+            io::Result::Ok((fields, multipart.warnings().to_vec()))
+        };
+
+        // the first field mixes line endings, the second uses LF throughout
+        let expected = [("a".into(), Some(mime::TEXT_PLAIN)), ("b".into(), None)];
+
+        let (fields, warnings) = read_all(LineEndingPolicy::Lenient).unwrap();
+        assert_eq!(fields, expected);
+        assert_eq!(
+            warnings,
+            [ParseWarning {
+                reason: WarningReason::MixedLineEndings,
+                field: Some("a".into()),
+            }]
+        );
+
+        let err = read_all(LineEndingPolicy::Strict).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("mix CRLF and LF"), "{err}");
+
+        // consistent line endings are fine in strict mode
+        let mut multipart = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\n\n\
+             value\r\n\
+             --boundary--",
+        );
+        multipart.set_line_ending_policy(LineEndingPolicy::Strict);
+        assert_eq!(multipart.drain_remaining().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_max_header_line_len() {
+        // a header line which never ends
+        let body = Read::chain(
+            &b"--boundary\r\n\
+               Content-Disposition: form-data; name=\"a\"\r\n\
+               X-Long: "[..],
+            io::repeat(b'a'),
+        );
+
+        // below the size of the read buffer, which bounds the whole headers section
+        let mut request = Multipart::with_body(body, BOUNDARY);
+        request.set_max_header_line_len(4096);
+        let err = request.read_entry().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            err.to_string().contains("line longer than 4096 bytes"),
+            "{err}"
+        );
+
+        let body = format!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\
+             X-Long: {}\r\n\r\n\
+             value\r\n\
+             --boundary--",
+            "a".repeat(100)
+        );
+        let mut request = Multipart::with_body(body.as_bytes(), BOUNDARY);
+        assert_eq!(request.drain_remaining().unwrap(), 1);
+
+        let mut request = Multipart::with_body(body.as_bytes(), BOUNDARY);
+        request.set_max_header_line_len(64);
+        let err = request.read_entry().err().unwrap();
+        assert!(
+            err.to_string().contains("line longer than 64 bytes"),
+            "{err}"
+        );
+
+        // the line ending doesn't count
+        let mut request = Multipart::with_body(body.as_bytes(), BOUNDARY);
+        request.set_max_header_line_len(108);
+        assert_eq!(request.drain_remaining().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_is_file() {
+        let mut multipart = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             value\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"notes.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             notes\r\n\
+             --boundary--",
+        );
+
+        let field = multipart.read_entry().unwrap().unwrap();
+        assert!(!field.is_file());
+        assert!(field.is_text());
+
+        let field = multipart.read_entry().unwrap().unwrap();
+        assert!(field.is_file());
+        assert!(field.is_text());
+    }
+
+    #[test]
+    fn test_save_field_to_path() {
+        let mut multipart = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"avatar\"; filename=\"me.png\"\r\n\r\n\
+             png\r\n\
+             --boundary--",
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("user").join("avatar.png");
+
+        let mut field = multipart.read_entry().unwrap().unwrap();
+
+        let err = field
+            .save()
+            .memory_threshold(0)
+            .create_dirs(false)
+            .with_path(&path)
+            .into_result_strict()
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        let saved = field
+            .save()
+            .memory_threshold(0)
+            .with_path(&path)
+            .into_result_strict()
+            .unwrap();
+        assert_eq!(saved.size(), 3);
+        assert_eq!(std::fs::read(&path).unwrap(), b"png");
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_hash_with() {
+        use sha2::{Digest, Sha256};
+
+        // SHA-256 of "hello world"
+        const DIGEST: &str = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             hello world\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"hello.bin\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             hello world\r\n\
+             --boundary--";
+
+        let mut single = multipart(BODY);
+
+        let mut field = single.read_entry().unwrap().unwrap();
+        let mut saver = field.save().hash_with(Sha256::new());
+        let data = saver.temp().into_result_strict().unwrap();
+        assert!(data.is_memory());
+        assert_eq!(saver.take_digest().as_deref(), Some(DIGEST));
+
+        let entries = multipart(BODY)
+            .save()
+            .hash_with(Sha256::new())
+            .memory_threshold(0)
+            .text_policy(TextPolicy::ToDisk)
+            .temp()
+            .into_result_strict()
+            .unwrap();
+
+        for name in ["text", "file"] {
+            let field = &entries.fields[name][0];
+            assert!(!field.data.is_memory());
+            assert_eq!(field.digest.as_deref(), Some(DIGEST));
+        }
+    }
+
+    #[cfg(feature = "checksums")]
+    #[test]
+    fn test_verify_checksums() {
+        let entries = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"md5\"\r\n\
+             Content-MD5: HlAhCgICSX+3m8OLat5sNA==\r\n\r\n\
+             some data\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"sha256\"; filename=\"file.txt\"\r\n\
+             X-Checksum-SHA256: 86f3c70fb6673cf303d2206db5f23c237b665d5df9d3e44efef5114845fc9f59\r\n\r\n\
+             file data\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"unchecked\"\r\n\r\n\
+             more data\r\n\
+             --boundary--",
+        )
+        .save()
+        .verify_checksums(true)
+        .memory_threshold(0)
+        .temp()
+        .into_result_strict()
+        .unwrap();
+
+        assert_eq!(entries.fields_count(), 3);
+
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"md5\"\r\n\
+             Content-MD5: HlAhCgICSX+3m8OLat5sNA==\r\n\r\n\
+             some data\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"sha256\"\r\n\
+             X-Checksum-SHA256: hvPHD7ZnPPMD0iBttfI8I3tmXV350+RO/vURSEX8n1k=\r\n\r\n\
+             corrupted data\r\n\
+             --boundary--",
+        );
+
+        let SaveResult::Partial(partial, reason) = request.save().verify_checksums(true).temp()
+        else {
+            panic!("expected `Partial`");
+        };
+
+        match reason {
+            PartialReason::ChecksumMismatch {
+                field,
+                algorithm,
+                expected,
+                ..
+            } => {
+                assert_eq!(&*field, "sha256");
+                assert_eq!(algorithm, ChecksumAlgorithm::Sha256);
+                assert_eq!(
+                    expected,
+                    "86f3c70fb6673cf303d2206db5f23c237b665d5df9d3e44efef5114845fc9f59"
+                );
+            }
+            other => panic!("expected `PartialReason::ChecksumMismatch`, got {other:?}"),
+        }
+
+        assert!(partial.entries.fields.contains_key("md5"));
+        let partial = partial.partial.unwrap();
+        assert_eq!(&*partial.source.headers.name, "sha256");
+        assert!(
+            matches!(partial.dest, Some(SavedData::Text(ref text)) if text == "corrupted data")
+        );
+
+        // not checked unless enabled
+        multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"md5\"\r\n\
+             Content-MD5: HlAhCgICSX+3m8OLat5sNA==\r\n\r\n\
+             other data\r\n\
+             --boundary--",
+        )
+        .save()
+        .temp()
+        .into_result_strict()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_content_length() {
+        let mut multipart = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"exact\"\r\n\
+             Content-Length: 5\r\n\r\n\
+             hello\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"wrong\"\r\n\
+             Content-Length: 100\r\n\r\n\
+             hello\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"none\"\r\n\r\n\
+             hello\r\n\
+             --boundary--",
+        );
+
+        for expected in [Some(5), Some(100), None] {
+            let mut field = multipart.read_entry().unwrap().unwrap();
+            assert_eq!(field.headers.content_length, expected);
+
+            // the boundary wins over the declared length
+            let mut data = String::new();
+            field.data.read_to_string(&mut data).unwrap();
+            assert_eq!(data, "hello");
+        }
+
+        assert!(multipart.read_entry().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_skip_empty_files() {
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             value\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             \r\n\
+             --boundary--";
+
+        let entries = multipart(BODY).save().temp().into_result_strict().unwrap();
+        assert_eq!(entries.fields_count(), 2);
+        assert_eq!(entries.fields["file"][0].data.size(), 0);
+
+        let entries = multipart(BODY)
+            .save()
+            .skip_empty_files(true)
+            .temp()
+            .into_result_strict()
+            .unwrap();
+        assert_eq!(entries.fields_count(), 1);
+        assert!(!entries.fields.contains_key("file"));
+    }
+
+    #[test]
+    fn test_into_buf_reader() {
+        let mut multipart = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+             first\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"second\"\r\n\r\n\
+             second\r\n\
+             --boundary--",
+        );
+
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        let mut buf = [0; 3];
+        field.data.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"fir");
+
+        let mut rest = String::new();
+        multipart
+            .into_buf_reader()
+            .read_to_string(&mut rest)
+            .unwrap();
+
+        assert_eq!(
+            rest,
+            "st\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"second\"\r\n\r\n\
+             second\r\n\
+             --boundary--"
+        );
+    }
+
+    #[test]
+    fn test_max_filename_len() {
+        use super::field::LongFilename;
+
+        let body = format!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"{}.txt\"\r\n\r\n\
+             data\r\n\
+             --boundary--",
+            "é".repeat(200)
+        );
+
+        let mut multipart = Multipart::with_body(body.as_bytes(), "boundary");
+        let field = multipart.read_entry().unwrap().unwrap();
+        let filename = field.headers.filename.as_ref().unwrap();
+        assert!(field.headers.filename_truncated);
+        // truncated on a character boundary
+        assert_eq!(filename.len(), 254);
+        assert!(filename.chars().all(|c| c == 'é'));
+
+        let mut multipart = Multipart::with_body(body.as_bytes(), "boundary");
+        multipart.set_max_filename_len(255, LongFilename::Reject);
+        let err = multipart.read_entry().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut multipart = Multipart::with_body(body.as_bytes(), "boundary");
+        multipart.set_max_filename_len(usize::MAX, LongFilename::Reject);
+        let field = multipart.read_entry().unwrap().unwrap();
+        assert!(!field.headers.filename_truncated);
+        assert_eq!(field.headers.filename.unwrap().len(), 404);
+    }
+
+    #[test]
+    fn test_with_buffered_body() {
+        let body = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+             first\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"second\"; filename=\"second.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             second\r\n\
+             --boundary--";
+
+        for capacity in [1, 10, 100, 8192] {
+            let body = io::BufReader::with_capacity(capacity, body.as_bytes());
+            let mut multipart = Multipart::with_buffered_body(body, "boundary");
+
+            let mut fields = Vec::new();
+            multipart
+                .foreach_entry(|mut field| {
+                    let mut data = String::new();
+                    field.data.read_to_string(&mut data).unwrap();
+                    fields.push((field.headers.name.to_string(), data));
+                })
+                .unwrap();
+
+            assert_eq!(
+                fields,
+                [
+                    ("first".to_string(), "first".to_string()),
+                    ("second".to_string(), "second".to_string())
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_allowed_content_types() {
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"avatar\"; filename=\"avatar.png\"\r\n\
+             Content-Type: image/png\r\n\r\n\
+             PNG\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"script\"; filename=\"evil.js\"\r\n\
+             Content-Type: application/javascript\r\n\r\n\
+             alert(1)\r\n\
+             --boundary--";
+
+        let allowed = ["image/*".parse().unwrap()];
+
+        let mut request = multipart(BODY);
+        let res = request.save().allowed_content_types(&allowed).temp();
+
+        match res {
+            SaveResult::Partial(
+                partial,
+                PartialReason::ContentType {
+                    field,
+                    content_type,
+                },
+            ) => {
+                assert_eq!(&*field, "script");
+                assert_eq!(content_type, mime::APPLICATION_JAVASCRIPT);
+                assert_eq!(partial.entries.fields_count(), 1);
+                assert_eq!(partial.entries.fields["avatar"][0].data.size(), 3);
+
+                let partial = partial.partial.unwrap();
+                assert_eq!(&*partial.source.headers.name, "script");
+                assert!(partial.dest.is_none());
+            }
+            _ => panic!("expected `PartialReason::ContentType`"),
+        }
+
+        let allowed = [mime::IMAGE_STAR, mime::APPLICATION_JAVASCRIPT];
+
+        let entries = multipart(BODY)
+            .save()
+            .allowed_content_types(&allowed)
+            .temp()
+            .into_result_strict()
+            .unwrap();
+        assert_eq!(entries.fields_count(), 2);
+    }
+
+    #[test]
+    fn test_empty_fields() {
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+             \r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"second\"\r\n\r\n\
+             \r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             \r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"third\"\r\n\r\n\
+             \r\n\
+             --boundary--";
+
+        // some clients send the boundary straight after the headers
+        const NO_CRLF: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"second\"\r\n\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"third\"\r\n\r\n\
+             --boundary--";
+
+        const NAMES: [&str; 4] = ["first", "second", "file", "third"];
+
+        struct OneByteReader(&'static [u8]);
+
+        impl Read for OneByteReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = buf.len().min(1);
+                self.0.read(&mut buf[..len])
+            }
+        }
+
+        fn read_all<R: Read>(mut multipart: Multipart<R>) {
+            let mut fields = Vec::new();
+
+            while let Some(mut field) = multipart.read_entry().unwrap() {
+                let mut buf = [0u8; 16];
+                assert_eq!(field.data.read(&mut buf).unwrap(), 0);
+                // EOF stays EOF
+                assert_eq!(field.data.read(&mut buf).unwrap(), 0);
+                fields.push(field.headers.name.to_string());
+            }
+
+            assert_eq!(fields, NAMES);
+        }
+
+        for body in [BODY, NO_CRLF] {
+            for strict in [false, true] {
+                let mut multipart = multipart(body);
+                multipart.set_strict(strict);
+                read_all(multipart);
+            }
+
+            // with the body arriving a byte at a time
+            read_all(Multipart::with_body(
+                OneByteReader(body.as_bytes()),
+                BOUNDARY,
+            ));
+            read_all(Multipart::with_buffered_body(
+                io::BufReader::with_capacity(1, body.as_bytes()),
+                BOUNDARY,
+            ));
+
+            let parsed: Vec<_> = parse::parse_multipart(body.as_bytes(), BOUNDARY.as_bytes())
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(parsed.iter().map(|p| p.name).collect::<Vec<_>>(), NAMES);
+            assert!(parsed.iter().all(|p| p.body.is_empty()));
+
+            let mut parser = push::PushParser::new(BOUNDARY);
+            let mut fields = Vec::new();
+            for event in parser.feed(body.as_bytes()) {
+                match event.unwrap() {
+                    push::PushEvent::FieldStart(headers) => fields.push(headers.name.to_string()),
+                    push::PushEvent::FieldData(data) => assert!(data.is_empty()),
+                    _ => (),
+                }
+            }
+            parser.finish().unwrap();
+            assert_eq!(fields, NAMES);
+        }
+
+        let entries = multipart(BODY).into_entries().unwrap();
+        for name in NAMES {
+            let data = &entries.fields[name][0].data;
+            assert_eq!(data.size(), 0, "{name}");
+        }
+        assert_eq!(
+            entries.fields["first"][0].data,
+            SavedData::Text(String::new())
+        );
+    }
+
+    #[test]
+    fn test_save_cancel() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        /// Sets `cancel` once `after` bytes have been read, like a watchdog would.
+        struct Watchdog<R> {
+            inner: R,
+            read: usize,
+            after: usize,
+            cancel: Arc<AtomicBool>,
+        }
+
+        impl<R: Read> Read for Watchdog<R> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = buf.len().min(1024);
+                let read = self.inner.read(&mut buf[..len])?;
+                self.read += read;
+
+                if self.read >= self.after {
+                    self.cancel.store(true, Ordering::Relaxed);
+                }
+
+                Ok(read)
+            }
+        }
+
+        let body = format!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             value\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"upload\"; filename=\"big.bin\"\r\n\r\n\
+             {}\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"after\"\r\n\r\n\
+             value\r\n\
+             --boundary--",
+            "a".repeat(256 * 1024)
+        );
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let watchdog = Watchdog {
+            inner: body.as_bytes(),
+            read: 0,
+            after: 64 * 1024,
+            cancel: cancel.clone(),
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut request = Multipart::with_body(watchdog, BOUNDARY);
+        let res = request
+            .save()
+            .memory_threshold(0)
+            .with_cancel(cancel)
+            .with_dir(dir.path());
+
+        let SaveResult::Partial(partial, PartialReason::Cancelled) = res else {
+            panic!("expected `Partial` with `Cancelled`");
+        };
+
+        // stopped partway through the upload
+        let source = partial.partial.as_ref().unwrap();
+        assert_eq!(&*source.source.headers.name, "upload");
+        assert!(source.dest.is_none());
+        assert!(partial.entries.fields.contains_key("text"));
+        assert!(!partial.entries.fields.contains_key("after"));
+
+        // the text field is kept in memory, so no files are left
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        // a flag that's never set changes nothing
+        let entries = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             value\r\n\
+             --boundary--",
+        )
+        .save()
+        .with_cancel(Arc::new(AtomicBool::new(false)))
+        .temp()
+        .into_result_strict()
+        .unwrap();
+        assert_eq!(entries.fields_count(), 1);
+    }
+
+    #[test]
+    fn test_saved_field_open() {
+        let large = "a".repeat(1024);
+        let body = format!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"small\"; filename=\"small.bin\"\r\n\r\n\
+             small\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"large\"; filename=\"large.bin\"\r\n\r\n\
+             {large}\r\n\
+             --boundary--"
+        );
+
+        let entries = Multipart::with_body(body.as_bytes(), BOUNDARY)
+            .save()
+            .memory_threshold(16)
+            .temp()
+            .into_result_strict()
+            .unwrap();
+
+        let small = &entries.fields["small"][0];
+        let large_field = &entries.fields["large"][0];
+        assert!(!matches!(small.data, SavedData::File(..)));
+        assert!(matches!(large_field.data, SavedData::File(..)));
+
+        for (field, expected) in [(small, "small"), (large_field, &*large)] {
+            let mut data = String::new();
+            field.open().unwrap().read_to_string(&mut data).unwrap();
+            assert_eq!(data, expected);
+        }
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        use super::Entries;
+
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             value\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\r\n\
+             contents\r\n\
+             --boundary--";
+
+        let mut multipart = Multipart::from_bytes(BODY.as_bytes().to_vec(), BOUNDARY);
+        assert_eq!(multipart.drain_remaining().unwrap(), 2);
+        multipart.rewind().unwrap();
+
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        assert_eq!(&*field.headers.name, "text");
+        let mut data = String::new();
+        field.data.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "value");
+
+        let entries = Entries::from_bytes(BODY, BOUNDARY).unwrap();
+        assert_eq!(entries.fields_count(), 2);
+        assert_eq!(
+            entries.fields["text"][0].data,
+            SavedData::Text("value".into())
+        );
+        assert_eq!(entries.fields["file"][0].data.size(), 8);
+
+        assert!(Entries::from_bytes(&b"--boundary\r\nbad"[..], BOUNDARY).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_export_metadata_jsonl() {
+        use super::field::FieldHeaders;
+        use super::MultipartObserver;
+
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        struct CountFields(Arc<AtomicUsize>);
+
+        impl MultipartObserver for CountFields {
+            fn on_field_start(&mut self, _headers: &FieldHeaders) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             value\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"avatar\"; filename=\"avatar.png\"\r\n\
+             Content-Type: image/png\r\n\r\n\
+             not really a png\r\n\
+             --boundary--",
+        );
+        let fields = Arc::new(AtomicUsize::new(0));
+        request.set_observer(Box::new(CountFields(fields.clone())));
+
+        // the fields are still saved as usual
+        let mut out = Vec::new();
+        let entries = request
+            .export_metadata_jsonl(&mut out, |request| request.save().temp().into_result())
+            .unwrap()
+            .unwrap();
+        assert_eq!(entries.fields_count(), 2);
+        // and the observer is still notified
+        assert_eq!(fields.load(Ordering::Relaxed), 2);
+
+        let out = String::from_utf8(out).unwrap();
+        let lines = out
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            [
+                serde_json::json!({
+                    "name": "text",
+                    "filename": null,
+                    "content_type": null,
+                    "size": 5,
+                    "hash": null,
+                }),
+                serde_json::json!({
+                    "name": "avatar",
+                    "filename": "avatar.png",
+                    "content_type": "image/png",
+                    "size": 16,
+                    "hash": null,
+                }),
+            ]
+        );
+    }
+
+    #[cfg(all(feature = "serde", feature = "hashing"))]
+    #[test]
+    fn test_export_metadata_jsonl_hash() {
+        use sha2::{Digest, Sha256};
+
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             value\r\n\
+             --boundary--",
+        );
+        let mut out = Vec::new();
+        request
+            .export_metadata_jsonl(&mut out, |request| {
+                request.save().hash_with(Sha256::new()).temp().into_result()
+            })
+            .unwrap()
+            .unwrap();
+
+        let line: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            line["hash"],
+            format!("{:x}", Sha256::digest(b"value")).as_str()
+        );
+    }
+
+    #[test]
+    fn test_drain_remaining() {
+        let body = format!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"wanted\"\r\n\r\n\
+             data\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file1\"; filename=\"a.bin\"\r\n\r\n\
+             {}\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file2\"; filename=\"b.bin\"\r\n\r\n\
+             data\r\n\
+             --boundary--",
+            "a".repeat(64 * 1024)
+        );
+
+        let mut request = Multipart::with_body(body.as_bytes(), BOUNDARY);
+
+        let mut field = request.read_entry().unwrap().unwrap();
+        assert_eq!(&*field.headers.name, "wanted");
+        // the partially read field isn't counted
+        field.data.read_exact(&mut [0; 2]).unwrap();
+
+        assert_eq!(request.drain_remaining().unwrap(), 2);
+
+        let state = request.debug_state();
+        assert!(state.at_end);
+        assert_eq!(state.consumed, body.len() as u64);
+        assert_eq!(state.fields_read, 3);
+
+        assert!(request.read_entry().unwrap().is_none());
+        assert_eq!(request.drain_remaining().unwrap(), 0);
+
+        // nothing to drain
+        assert_eq!(multipart("").drain_remaining().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_empty_body() {
+        let mut empty = multipart("");
+        assert!(empty.read_entry().unwrap().is_none());
+        // still at the end
+        assert!(empty.read_entry().unwrap().is_none());
+
+        let entries = multipart("").save().temp().into_result_strict().unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_opening_boundary_only() {
+        let err = multipart("--boundary").read_entry().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        multipart("--boundary\r\n").read_entry().err().unwrap();
+
+        let err = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+             truncated",
+        )
+        .foreach_entry(|_| ())
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_max_fields() {
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+             first\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"second\"\r\n\r\n\
+             second\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"third\"\r\n\r\n\
+             third\r\n\
+             --boundary--";
+
+        let mut limited = multipart(BODY);
+        limited.set_max_fields(2);
+
+        assert_eq!(
+            &*limited.read_entry().unwrap().unwrap().headers.name,
+            "first"
+        );
+        assert_eq!(
+            &*limited.read_entry().unwrap().unwrap().headers.name,
+            "second"
+        );
+
+        let err = limited.read_entry().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // exactly at the limit is fine
+        let mut limited = multipart(BODY);
+        limited.set_max_fields(3);
+        let mut count = 0;
+        limited.foreach_entry(|_| count += 1).unwrap();
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_limits() {
+        use super::limits::{LimitError, Limits};
+        use super::MultipartError;
+
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             0123456789\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\r\n\
+             0123456789abcdefghij\r\n\
+             --boundary--";
+
+        fn limit_error(limits: Limits) -> Option<LimitError> {
+            let mut request = Multipart::with_limits(BODY.as_bytes(), "boundary", limits);
+
+            let err = loop {
+                let mut field = match request.read_entry() {
+                    Ok(Some(field)) => field,
+                    Ok(None) => return None,
+                    Err(e) => break e,
+                };
+
+                if let Err(e) = io::copy(&mut field.data, &mut io::sink()) {
+                    break e;
+                }
+            };
+
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            let limit = err.get_ref().unwrap().downcast_ref::<LimitError>().cloned();
+
+            // the error sticks
+            let again = request.read_entry().err().unwrap();
+            assert_eq!(again.to_string(), err.to_string());
+
+            limit
+        }
+
+        // exactly at the limits is fine
+        assert_eq!(
+            limit_error(Limits {
+                max_fields: Some(2),
+                max_field_size: Some(10),
+                max_file_size: Some(20),
+                max_total_size: Some(30),
+            }),
+            None
+        );
+
+        assert_eq!(
+            limit_error(Limits {
+                max_fields: Some(1),
+                ..Limits::default()
+            }),
+            Some(LimitError::TooManyFields { max: 1 })
+        );
+
+        // files and other fields are limited separately
+        assert_eq!(
+            limit_error(Limits {
+                max_field_size: Some(9),
+                max_file_size: Some(20),
+                ..Limits::default()
+            }),
+            Some(LimitError::FieldTooLarge {
+                field: "text".into(),
+                max: 9
+            })
+        );
+        assert_eq!(
+            limit_error(Limits {
+                max_field_size: Some(10),
+                max_file_size: Some(19),
+                ..Limits::default()
+            }),
+            Some(LimitError::FileTooLarge {
+                field: "file".into(),
+                max: 19
+            })
+        );
+
+        assert_eq!(
+            limit_error(Limits {
+                max_total_size: Some(29),
+                ..Limits::default()
+            }),
+            Some(LimitError::TotalTooLarge { max: 29 })
+        );
+
+        // saving reads through the limits too, both in memory and into a file
+        for memory_threshold in [0, 1024] {
+            let mut request = Multipart::with_limits(
+                BODY.as_bytes(),
+                "boundary",
+                Limits {
+                    max_file_size: Some(4),
+                    ..Limits::default()
+                },
+            );
+            match request.save().memory_threshold(memory_threshold).temp() {
+                SaveResult::Partial(_, PartialReason::IoError(e)) => {
+                    assert!(
+                        e.to_string().contains("larger than the maximum of 4 bytes"),
+                        "{e}"
+                    );
+                    // not wrapped in a `SaveError`, which is only for the filesystem
+                    assert!(matches!(
+                        MultipartError::from(e),
+                        MultipartError::Limit(LimitError::FileTooLarge { max: 4, .. })
+                    ));
+                }
+                _ => panic!("saving should have stopped at the limit"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_multipart_error() {
+        use super::limits::{LimitError, Limits};
+        use super::MultipartError;
+
+        fn read_all<R: Read>(mut request: Multipart<R>) -> MultipartError {
+            loop {
+                let mut field = match request.read_entry() {
+                    Ok(Some(field)) => field,
+                    Ok(None) => panic!("the request should fail"),
+                    Err(e) => break e.into(),
+                };
+
+                if let Err(e) = io::copy(&mut field.data, &mut io::sink()) {
+                    break e.into();
+                }
+            }
+        }
+
+        let err = read_all(multipart("--boundary\r\nbad\r\n\r\ndata\r\n--boundary--"));
+        assert!(matches!(err, MultipartError::InvalidHeaders(_)), "{err}");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = read_all(multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             text",
+        ));
+        assert!(matches!(err, MultipartError::Truncated(_)), "{err}");
+
+        let err = read_all(multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             text\r\n\
+             --boundaryXX",
+        ));
+        assert!(matches!(err, MultipartError::MalformedBoundary(_)), "{err}");
+
+        let limits = Limits {
+            max_fields: Some(0),
+            ..Limits::default()
+        };
+        let body = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             text\r\n\
+             --boundary--";
+        let err = read_all(Multipart::with_limits(body.as_bytes(), "boundary", limits));
+        assert!(matches!(
+            err,
+            MultipartError::Limit(LimitError::TooManyFields { max: 0 })
+        ));
+
+        let err = read_all(Multipart::with_body(ErrorReader, "boundary"));
+        match err {
+            MultipartError::Io(ref e) => assert_eq!(e.kind(), io::ErrorKind::ConnectionReset),
+            _ => panic!("expected an I/O error: {err}"),
+        }
+
+        // converting back keeps the kind, and the `LimitError` for `downcast_ref()`
+        let err = io::Error::from(MultipartError::Truncated("truncated".into()));
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(matches!(
+            MultipartError::from(err),
+            MultipartError::Truncated(msg) if msg == "truncated"
+        ));
+
+        let err = io::Error::from(MultipartError::Limit(LimitError::TotalTooLarge { max: 1 }));
+        assert!(err.get_ref().unwrap().is::<LimitError>());
+    }
+
+    #[test]
+    fn test_nested_multipart() {
+        use super::field::LongFilename;
+        use super::limits::LimitError;
+        use super::MultipartError;
+
+        fn read_nested<R: Read>(mut request: Multipart<R>) -> Vec<(String, String, String)> {
+            let mut files = Vec::new();
+
+            while let Some(mut field) = request.read_entry().unwrap() {
+                let Some(mut nested) = field.nested() else {
+                    continue;
+                };
+
+                nested
+                    .foreach_entry(|mut file| {
+                        let mut data = String::new();
+                        file.data.read_to_string(&mut data).unwrap();
+                        files.push((
+                            file.headers.name.to_string(),
+                            file.headers.filename.unwrap_or_default(),
+                            data,
+                        ));
+                    })
+                    .unwrap();
+            }
+
+            files
+        }
+
+        let request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             not nested\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"files\"\r\n\
+             Content-Type: multipart/mixed; boundary=\"inner\"\r\n\r\n\
+             --inner\r\n\
+             Content-Disposition: file; filename=\"a.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             first\r\n\
+             --inner\r\n\
+             Content-Disposition: attachment; name=\"ignored\"; filename=\"b.txt\"\r\n\r\n\
+             second\r\n\
+             --inner--\r\n\
+             --boundary--",
+        );
+
+        assert_eq!(
+            read_nested(request),
+            [
+                ("files".into(), "a.txt".into(), "first".into()),
+                ("files".into(), "b.txt".into(), "second".into()),
+            ]
+        );
+
+        // fields with another content-type aren't nested
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"a.bin\"\r\n\
+             Content-Type: multipart/form-data; boundary=inner\r\n\r\n\
+             data\r\n\
+             --boundary--",
+        );
+        assert!(request.read_entry().unwrap().unwrap().nested().is_none());
+
+        #[cfg(feature = "client")]
+        {
+            let mut client = crate::client::lazy::Multipart::new();
+            client.add_file_group(
+                "group",
+                vec![
+                    ("a.txt", &b"first"[..], None),
+                    ("b.txt", &b"second"[..], Some(mime::TEXT_PLAIN)),
+                ],
+            );
+
+            let mut prepared = client.prepare().unwrap();
+            let boundary = prepared.boundary().to_owned();
+            let mut body = Vec::new();
+            prepared.read_to_end(&mut body).unwrap();
+
+            assert_eq!(
+                read_nested(Multipart::with_body(&body[..], boundary)),
+                [
+                    ("group".into(), "a.txt".into(), "first".into()),
+                    ("group".into(), "b.txt".into(), "second".into()),
+                ]
+            );
+        }
+
+        // the nested files count towards the outer limits
+        let body = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"files\"\r\n\
+             Content-Type: multipart/mixed; boundary=inner\r\n\r\n\
+             --inner\r\n\
+             Content-Disposition: file; filename=\"a.txt\"\r\n\r\n\
+             first\r\n\
+             --inner\r\n\
+             Content-Disposition: file; filename=\"b.txt\"\r\n\r\n\
+             second\r\n\
+             --inner--\r\n\
+             --boundary--";
+
+        let mut request = Multipart::with_body(body.as_bytes(), BOUNDARY);
+        request.set_max_fields(2);
+        let mut field = request.read_entry().unwrap().unwrap();
+        let mut nested = field.nested().unwrap();
+        assert!(nested.read_entry().unwrap().is_some());
+        let err = nested.read_entry().err().unwrap();
+        assert!(matches!(
+            MultipartError::from(err),
+            MultipartError::Limit(LimitError::TooManyFields { max: 2 })
+        ));
+
+        // and the fields after them
+        let body = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"files\"\r\n\
+             Content-Type: multipart/mixed; boundary=inner\r\n\r\n\
+             --inner\r\n\
+             Content-Disposition: file; filename=\"a.txt\"\r\n\r\n\
+             first\r\n\
+             --inner--\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             text\r\n\
+             --boundary--";
+
+        let mut request = Multipart::with_body(body.as_bytes(), BOUNDARY);
+        request.set_max_fields(2);
+        let mut field = request.read_entry().unwrap().unwrap();
+        field.nested().unwrap().foreach_entry(|_| ()).unwrap();
+        drop(field);
+        let err = request.read_entry().err().unwrap();
+        assert!(matches!(
+            MultipartError::from(err),
+            MultipartError::Limit(LimitError::TooManyFields { max: 2 })
+        ));
+
+        let mut request = Multipart::with_body(body.as_bytes(), BOUNDARY);
+        request.set_max_filename_len(4, LongFilename::Reject);
+        let mut field = request.read_entry().unwrap().unwrap();
+        let mut nested = field.nested().unwrap();
+        assert!(nested.read_entry().is_err());
+
+        let mut request = Multipart::with_body(body.as_bytes(), BOUNDARY).strict_rfc7578(true);
+        request.set_strict(true);
+        let mut field = request.read_entry().unwrap().unwrap();
+        let nested = field.nested().unwrap();
+        assert!(nested.reader.is_strict());
+        assert!(nested.reader.is_strict_rfc7578());
+    }
+
+    #[test]
+    fn test_transfer_encoding() {
+        use crate::server::field::TransferEncoding;
+
+        let body = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"base64\"\r\n\
+             Content-Transfer-Encoding: BASE64\r\n\r\n\
+             aGVsbG8s\r\n\
+             IHdvcmxk\r\nIQ==\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"qp\"\r\n\
+             Content-Transfer-Encoding: quoted-printable\r\n\r\n\
+             caf=C3=A9 =3D soft=\r\n\
+             break \t\r\n\
+             hard=\n\
+             =ZZ done=\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"invalid\"\r\n\
+             Content-Transfer-Encoding: base64\r\n\r\n\
+             not*base64\r\n\
+             --boundary--";
+
+        let expected: [(&str, &[u8]); 2] = [
+            ("base64", b"hello, world!"),
+            ("qp", "café = softbreak\r\nhard=ZZ done=".as_bytes()),
+        ];
+
+        let mut request = multipart(body).with_warnings();
+
+        for (name, data) in expected {
+            let mut field = request.read_entry().unwrap().unwrap().with_raw_capture(64);
+            assert_eq!(&*field.headers.name, name);
+
+            let mut read = Vec::new();
+            field.data.read_to_end(&mut read).unwrap();
+            assert_eq!(read, data);
+            // the raw bytes are as sent
+            assert!(field.raw_bytes().unwrap().len() > data.len());
+        }
+
+        let mut field = request.read_entry().unwrap().unwrap();
+        assert_eq!(
+            field.headers.transfer_encoding,
+            Some(TransferEncoding::Base64)
+        );
+        let err = field.data.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        drop(field);
+
+        assert!(request.read_entry().unwrap().is_none());
+        assert!(request.warnings().is_empty());
+
+        let mut request = multipart(body).with_warnings();
+        request.set_decode_transfer_encoding(false);
+
+        let mut field = request.read_entry().unwrap().unwrap();
+        assert_eq!(
+            field.headers.transfer_encoding,
+            Some(TransferEncoding::Base64)
+        );
+        let mut data = String::new();
+        field.data.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "aGVsbG8s\r\nIHdvcmxk\r\nIQ==");
+        drop(field);
+
+        request.foreach_entry(|_| ()).unwrap();
+        assert_eq!(
+            request.warnings()[0].reason,
+            WarningReason::UnsupportedTransferEncoding("base64".into())
+        );
+        assert_eq!(request.warnings().len(), 3);
+    }
+
+    #[test]
+    fn test_filename_ext() {
+        let body = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"?.txt\"; \
+             filename*=UTF-8''%E2%82%AC.txt\r\n\r\n\
+             \r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"split\"; \
+             filename*0*=iso-8859-1''caf%E9; filename*1=.txt\r\n\r\n\
+             \r\n\
+             --boundary--";
+
+        let mut filenames = Vec::new();
+        multipart(body)
+            .foreach_entry(|field| filenames.push(field.headers.filename.unwrap()))
+            .unwrap();
+        assert_eq!(filenames, ["€.txt", "café.txt"]);
+
+        #[cfg(feature = "client")]
+        {
+            let filenames = ["€ \"quoted\".txt", "plain.txt"];
+
+            let mut client = crate::client::lazy::Multipart::new();
+            for filename in filenames {
+                client.add_stream("file", &b""[..], Some(filename), None);
+            }
+
+            let mut prepared = client.prepare().unwrap();
+            let boundary = prepared.boundary().to_owned();
+            let mut body = Vec::new();
+            prepared.read_to_end(&mut body).unwrap();
+
+            let mut read = Vec::new();
+            Multipart::with_body(&body[..], boundary)
+                .foreach_entry(|field| read.push(field.headers.filename.unwrap()))
+                .unwrap();
+            read.sort();
+            assert_eq!(read, ["plain.txt", "€ \"quoted\".txt"]);
+        }
+    }
+
+    #[test]
+    fn test_save_result_combinators() {
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+             first\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"second\"\r\n\r\n\
+             second\r\n\
+             --boundary--";
+
+        // the `Full` case
+        let entries = multipart(BODY).save().temp().into_entries_opt().unwrap();
+        assert_eq!(entries.fields_count(), 2);
+        let entries = multipart(BODY).save().temp().okay_warn().unwrap();
+        assert_eq!(entries.fields_count(), 2);
+        let entries = multipart(BODY).save().temp().into_full_result().unwrap();
+        assert_eq!(entries.fields_count(), 2);
+
+        // the `Partial` case
+        assert!(multipart(BODY)
+            .save()
+            .count_limit(1)
+            .temp()
+            .into_entries_opt()
+            .is_none());
+
+        let entries = multipart(BODY)
+            .save()
+            .count_limit(1)
+            .temp()
+            .okay_warn()
+            .unwrap();
+        assert_eq!(entries.fields_count(), 1);
+
+        let err = multipart(BODY)
+            .save()
+            .count_limit(1)
+            .temp()
+            .into_full_result()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "field count limit of 1 reached");
+
+        // the `Error` case, since a directory can't be created under a file
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        let bad_dir = file.join("mpart");
+
+        let mut request = multipart(BODY);
+        assert!(request
+            .save()
+            .with_dir(&bad_dir)
+            .into_entries_opt()
+            .is_none());
+        assert!(request.save().with_dir(&bad_dir).okay_warn().is_err());
+        assert!(request
+            .save()
+            .with_dir(&bad_dir)
+            .into_full_result()
+            .is_err());
+    }
+
+    #[test]
+    fn test_owned_headers() {
+        use super::field::FieldHeaders;
+
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             contents\r\n\
+             --boundary--",
+        );
+
+        let headers = {
+            let mut field = request.read_entry().unwrap().unwrap();
+            let headers = field.headers();
+            field.data.read_to_end(&mut Vec::new()).unwrap();
+            headers
+        };
+
+        assert!(request.read_entry().unwrap().is_none());
+        assert_eq!(
+            headers,
+            FieldHeaders {
+                name: "file".into(),
+                filename: Some("file.txt".into()),
+                filename_truncated: false,
+                content_type: Some(mime::TEXT_PLAIN),
+                content_length: None,
+                checksum: None,
+                content_disposition_raw: Some(
+                    "form-data; name=\"file\"; filename=\"file.txt\"".into()
+                ),
+                declared_size: None,
+                transfer_encoding: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_content_disposition_raw() {
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition:  form-data; NAME=\"a;b\" ; filename=\"\\\"quoted\\\".txt\"\r\n\r\n\
+             contents\r\n\
+             --boundary--",
+        );
+
+        let field = request.read_entry().unwrap().unwrap();
+        assert_eq!(&*field.headers.name, "a;b");
+        assert_eq!(
+            field.headers.content_disposition_raw.as_deref(),
+            Some("form-data; NAME=\"a;b\" ; filename=\"\\\"quoted\\\".txt\"")
+        );
+    }
+
+    #[test]
+    fn test_declared_size() {
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"; size=100\r\n\r\n\
+             shorter than declared\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"quoted\"; size=\"4\"\r\n\r\n\
+             data\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"invalid\"; size=-1\r\n\r\n\
+             data\r\n\
+             --boundary--",
+        );
+
+        let mut field = request.read_entry().unwrap().unwrap();
+        assert_eq!(field.headers.declared_size, Some(100));
+        // the boundary ends the field, not the declared size
+        let mut data = String::new();
+        field.data.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "shorter than declared");
+
+        let field = request.read_entry().unwrap().unwrap();
+        assert_eq!(field.headers.declared_size, Some(4));
+
+        let field = request.read_entry().unwrap().unwrap();
+        assert_eq!(field.headers.declared_size, None);
+
+        assert!(request.read_entry().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_anonymous() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut entries = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             some data\r\n\
+             --boundary--",
+        )
+        .save()
+        .memory_threshold(0)
+        .anonymous(true)
+        .with_dir(dir.path())
+        .into_result_strict()
+        .unwrap();
+
+        // nothing is visible in the filesystem
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let mut field = entries.fields.get_mut("file").unwrap().pop().unwrap();
+        assert!(matches!(field.data, SavedData::Anonymous(_, 9)));
+
+        // readers of the same file don't share a position
+        let mut first = field.data.readable().unwrap();
+        let mut second = field.open().unwrap();
+        let mut buf = [0; 4];
+        first.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"some");
+
+        let mut data = String::new();
+        second.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "some data");
+
+        data.clear();
+        first.read_to_string(&mut data).unwrap();
+        assert_eq!(data, " data");
+        drop((first, second));
+
+        let path = dir.path().join("file.bin");
+        field.materialize(&path).unwrap();
+        assert!(matches!(field.data, SavedData::File(ref saved, 9) if *saved == path));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "some data");
+
+        // can't materialize twice
+        field.materialize(dir.path().join("other.bin")).unwrap_err();
+    }
+
+    #[test]
+    fn test_text_policy() {
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             some text\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             some file\r\n\
+             --boundary--";
+
+        let entries = multipart(BODY)
+            .save()
+            .memory_threshold(4)
+            .temp()
+            .into_result_strict()
+            .unwrap();
+
+        assert!(
+            matches!(entries.fields["text"][0].data, SavedData::Text(ref text) if text == "some text")
+        );
+        assert!(matches!(
+            entries.fields["file"][0].data,
+            SavedData::File(_, 9)
+        ));
+
+        let entries = multipart(BODY)
+            .save()
+            .memory_threshold(4)
+            .text_policy(TextPolicy::ToDisk)
+            .temp()
+            .into_result_strict()
+            .unwrap();
+
+        assert!(matches!(
+            entries.fields["text"][0].data,
+            SavedData::File(_, 9)
+        ));
+        assert!(matches!(
+            entries.fields["file"][0].data,
+            SavedData::File(_, 9)
+        ));
+    }
+
+    #[test]
+    fn test_normalize_text_newlines() {
+        use super::save::NewlineMode;
+
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             one\r\ntwo\nthree\rfour\r\n\r\nfive\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             one\r\ntwo\nthree\r\n\
+             --boundary--";
+
+        for (mode, expected) in [
+            (NewlineMode::Preserve, "one\r\ntwo\nthree\rfour\r\n\r\nfive"),
+            (NewlineMode::ToLf, "one\ntwo\nthree\nfour\n\nfive"),
+            (
+                NewlineMode::ToCrlf,
+                "one\r\ntwo\r\nthree\r\nfour\r\n\r\nfive",
+            ),
+        ] {
+            // a tiny buffer splits the `\r\n` pairs across reads
+            let body = io::BufReader::with_capacity(3, BODY.as_bytes());
+
+            let entries = Multipart::with_buffered_body(body, "boundary")
+                .save()
+                .normalize_text_newlines(mode)
+                .temp()
+                .into_result_strict()
+                .unwrap();
+
+            assert!(
+                matches!(entries.fields["text"][0].data, SavedData::Text(ref text) if text == expected),
+                "{mode:?}"
+            );
+            assert!(
+                matches!(entries.fields["file"][0].data, SavedData::Text(ref text) if text == "one\r\ntwo\nthree"),
+                "{mode:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_raw_capture() {
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"read\"\r\n\r\n\
+             read\r\nvalue\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"saved\"\r\n\r\n\
+             saved value\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"too long\"\r\n\r\n\
+             too long value\r\n\
+             --boundary--";
+
+        let mut request = multipart(BODY);
+
+        // captured through `Read`
+        let field = request.read_entry().unwrap().unwrap();
+        assert_eq!(field.raw_bytes(), None);
+        let mut field = field.with_raw_capture(64);
+        let mut value = String::new();
+        field.data.read_to_string(&mut value).unwrap();
+        assert_eq!(field.raw_bytes(), Some(&b"read\r\nvalue"[..]));
+
+        // captured through `BufRead`
+        let mut field = request.read_entry().unwrap().unwrap().with_raw_capture(64);
+        let saved = field.data.save().temp().into_result_strict().unwrap();
+        assert!(matches!(saved, SavedData::Text(ref text) if text == "saved value"));
+        assert_eq!(field.raw_bytes(), Some(&b"saved value"[..]));
+
+        let mut field = request.read_entry().unwrap().unwrap().with_raw_capture(8);
+        io::copy(&mut field.data, &mut io::sink()).unwrap();
+        assert_eq!(field.raw_bytes(), None);
+    }
+
+    #[test]
+    fn test_dyn_http_request() {
+        use super::{DynHttpRequest, HttpRequest};
+
+        struct Request {
+            boundary: Option<&'static str>,
+            quoted: bool,
+            body: &'static [u8],
+        }
+
+        impl HttpRequest for Request {
+            type Body = &'static [u8];
+
+            fn multipart_boundary(&self) -> Option<&str> {
+                self.boundary
+            }
+
+            fn multipart_boundary_quoted(&self) -> bool {
+                self.quoted
+            }
+
+            fn body(self) -> &'static [u8] {
+                self.body
+            }
+        }
+
+        let requests: Vec<Box<dyn DynHttpRequest>> = vec![
+            Box::new(Request {
+                boundary: Some("boundary"),
+                quoted: true,
+                body: b"--boundary\r\n\
+                    Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+                    value\r\n\
+                    --boundary--",
+            }),
+            Box::new(Request {
+                boundary: None,
+                quoted: false,
+                body: b"field=value",
+            }),
+        ];
+
+        let mut requests = requests.into_iter();
+
+        let mut request = Multipart::from_request(requests.next().unwrap())
+            .ok()
+            .unwrap();
+        assert!(request.boundary_was_quoted());
+        let mut field = request.read_entry().unwrap().unwrap();
+        assert_eq!(&*field.headers.name, "field");
+        let mut value = String::new();
+        field.data.read_to_string(&mut value).unwrap();
+        assert_eq!(value, "value");
+
+        let not_multipart = Multipart::from_request(requests.next().unwrap())
+            .err()
+            .unwrap();
+        assert_eq!(DynHttpRequest::multipart_boundary(&*not_multipart), None);
+        let mut body = String::new();
+        not_multipart.into_body().read_to_string(&mut body).unwrap();
+        assert_eq!(body, "field=value");
+    }
+
+    #[test]
+    fn test_from_request_any_subtype() {
+        use super::HttpRequest;
+
+        /// A `multipart/related` request.
+        struct Related;
+
+        impl HttpRequest for Related {
+            type Body = &'static [u8];
+
+            fn multipart_boundary(&self) -> Option<&str> {
+                None
+            }
+
+            fn multipart_boundary_any_subtype(&self) -> Option<&str> {
+                Some(BOUNDARY)
+            }
+
+            fn body(self) -> &'static [u8] {
+                b"--boundary\r\n\
+                  Content-Disposition: form-data; name=\"meta\"\r\n\
+                  Content-Type: application/json\r\n\r\n\
+                  {}\r\n\
+                  --boundary--"
+            }
+        }
+
+        assert!(Multipart::from_request(Related).is_err());
+
+        let mut request = Multipart::from_request_any_subtype(Related).ok().unwrap();
+        let field = request.read_entry().unwrap().unwrap();
+        assert_eq!(&*field.headers.name, "meta");
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_as_bytes() {
+        let mut entries = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"binary\"; filename=\"data.bin\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             \x00\x01binary\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             text\r\n\
+             --boundary--",
+        )
+        .save()
+        .temp()
+        .into_result_strict()
+        .unwrap();
+
+        let binary = &entries.fields["binary"][0];
+        assert_eq!(binary.as_bytes().unwrap(), &b"\x00\x01binary"[..]);
+        assert_eq!(entries.fields["text"][0].as_bytes().unwrap(), &b"text"[..]);
+
+        let SavedData::Bytes(ref saved) = binary.data else {
+            panic!("expected `SavedData::Bytes`")
+        };
+        let ptr = saved.as_ptr();
+
+        // not copied
+        let binary = entries.fields.remove("binary").unwrap().pop().unwrap();
+        let bytes = binary.data.into_bytes().unwrap();
+        assert_eq!(bytes, &b"\x00\x01binary"[..]);
+        assert_eq!(bytes.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_observer() {
+        use super::field::FieldHeaders;
+        use super::MultipartObserver;
+
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Default)]
+        struct Counts {
+            fields: Vec<String>,
+            bytes: u64,
+            complete: Vec<(usize, u64)>,
+            errors: usize,
+        }
+
+        struct Counter(Arc<Mutex<Counts>>);
+
+        impl MultipartObserver for Counter {
+            fn on_field_start(&mut self, headers: &FieldHeaders) {
+                self.0.lock().unwrap().fields.push(headers.name.to_string());
+            }
+
+            fn on_bytes(&mut self, n: u64) {
+                self.0.lock().unwrap().bytes += n;
+            }
+
+            fn on_complete(&mut self, field_count: usize, total_bytes: u64) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .complete
+                    .push((field_count, total_bytes));
+            }
+
+            fn on_error(&mut self, _error: &io::Error) {
+                self.0.lock().unwrap().errors += 1;
+            }
+        }
+
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             some text\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\r\n\
+             file data\r\n\
+             --boundary--";
+
+        let counts = Arc::new(Mutex::new(Counts::default()));
+
+        let mut request = multipart(BODY);
+        request.set_observer(Box::new(Counter(counts.clone())));
+        request.save().temp().into_result_strict().unwrap();
+        // reading past the end doesn't complete the request again
+        assert!(request.read_entry().unwrap().is_none());
+
+        {
+            let counts = counts.lock().unwrap();
+            assert_eq!(counts.fields, ["text", "file"]);
+            assert_eq!(counts.bytes, 18);
+            assert_eq!(counts.complete, [(2, BODY.len() as u64)]);
+            assert_eq!(counts.errors, 0);
+        }
+
+        let mut request = multipart(BODY);
+        request.set_max_fields(1);
+        request.set_observer(Box::new(Counter(counts.clone())));
+        request.foreach_entry(|_| ()).unwrap_err();
+        assert_eq!(counts.lock().unwrap().errors, 1);
+    }
+
+    #[test]
+    fn test_closing_boundary_padding() {
+        for closing in [
+            "--boundary--",
+            "--boundary--\r\n",
+            "--boundary--  \r\n",
+            "--boundary-- \t",
+            "--boundary---",
+            "--boundary---\r\n",
+            "--boundary-- - \r\n",
+        ] {
+            let body = format!(
+                "--boundary\r\n\
+                 Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+                 value\r\n\
+                 {closing}"
+            );
+
+            let mut request = Multipart::with_body(body.as_bytes(), "boundary");
+
+            let mut field = request.read_entry().unwrap().unwrap();
+            let mut value = String::new();
+            field.data.read_to_string(&mut value).unwrap();
+            assert_eq!(value, "value", "{closing:?}");
+
+            assert!(request.read_entry().unwrap().is_none(), "{closing:?}");
+
+            // the padding is consumed with the boundary
+            assert_eq!(
+                request.debug_state().consumed,
+                body.len() as u64,
+                "{closing:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parallel_writes() {
+        // larger than a chunk so each file is queued in several
+        let data: Vec<String> = (0..8)
+            .map(|i| format!("{i}").repeat(100 * 1024 + i))
+            .collect();
+
+        let mut body = String::new();
+
+        for (i, data) in data.iter().enumerate() {
+            body.push_str(&format!(
+                "--boundary\r\n\
+                 Content-Disposition: form-data; name=\"file{i}\"; filename=\"{i}.txt\"\r\n\r\n\
+                 {data}\r\n"
+            ));
+        }
+
+        body.push_str(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             some text\r\n\
+             --boundary--",
+        );
+
+        let entries = Multipart::with_body(body.as_bytes(), "boundary")
+            .save()
+            .size_limit(None)
+            .memory_threshold(0)
+            .parallel_writes(4)
+            .temp()
+            .into_result_strict()
+            .unwrap();
+
+        assert_eq!(entries.fields_count(), 9);
+        assert!(
+            matches!(entries.fields["text"][0].data, SavedData::Text(ref text) if text == "some text")
+        );
+
+        for (i, data) in data.iter().enumerate() {
+            let field = &entries.fields[&*format!("file{i}")][0];
+            let SavedData::File(ref path, size) = field.data else {
+                panic!("expected `SavedData::File`");
+            };
+            assert_eq!(size, data.len() as u64);
+            assert_eq!(fs::read_to_string(path).unwrap(), *data);
+        }
+    }
+
+    #[test]
+    fn test_parallel_writes_error() {
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             some text\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\r\n\
+             file data\r\n\
+             --boundary--",
+        );
+
+        let entries = request
+            .save()
+            .memory_threshold(0)
+            // fails to open any file, since none exist
+            .mod_open_opts(|opts| {
+                opts.create_new(false).create(false);
+            })
+            .parallel_writes(2)
+            .temp();
+
+        let SaveResult::Partial(partial, PartialReason::IoError(_)) = entries else {
+            panic!("expected `Partial` with `IoError`");
+        };
+
+        // the field whose file failed is removed
+        assert!(partial.entries.fields.contains_key("text"));
+        assert!(!partial.entries.fields.contains_key("file"));
+        assert_eq!(partial.entries.fields_count(), 1);
+    }
+
+    #[test]
+    fn test_save_error() {
+        use super::save::SaveError;
+        use std::error::Error as _;
+
+        for parallel_writes in [0, 2] {
+            let mut request = multipart(
+                "--boundary\r\n\
+                 Content-Disposition: form-data; name=\"avatar\"; filename=\"a.png\"\r\n\r\n\
+                 image data\r\n\
+                 --boundary--",
+            );
+
+            let err = request
+                .save()
+                .memory_threshold(0)
+                // fails to open the file, since it doesn't exist
+                .mod_open_opts(|opts| {
+                    opts.create_new(false).create(false);
+                })
+                .parallel_writes(parallel_writes)
+                .temp()
+                .into_result_strict()
+                .unwrap_err();
+
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+            let save_err = err.get_ref().unwrap().downcast_ref::<SaveError>().unwrap();
+            assert_eq!(save_err.field(), "avatar");
+            assert_eq!(save_err.io_error().kind(), io::ErrorKind::NotFound);
+
+            let path = save_err.path().unwrap();
+            assert!(
+                err.to_string().starts_with(&format!(
+                    "failed saving field `avatar` to {}: ",
+                    path.display()
+                )),
+                "{err}"
+            );
+
+            let source = err.source().unwrap().downcast_ref::<io::Error>().unwrap();
+            assert_eq!(source.kind(), io::ErrorKind::NotFound);
+        }
+    }
+
+    #[test]
+    fn test_into_file_and_persist() {
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             some text\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\r\n\
+             file data\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"kept\"; filename=\"kept.txt\"\r\n\r\n\
+             kept data\r\n\
+             --boundary--",
+        );
+
+        let mut entries = request
+            .save()
+            .memory_threshold(0)
+            .temp()
+            .into_entries()
+            .unwrap();
+
+        let mut take = |name| entries.fields.remove(name).unwrap().pop().unwrap();
+
+        let file = take("file");
+        let SavedData::File(ref path, _) = file.data else {
+            panic!("expected `SavedData::File`");
+        };
+        let path = path.clone();
+
+        let mut data = String::new();
+        file.into_file().unwrap().read_to_string(&mut data).unwrap();
+        assert_eq!(data, "file data");
+        assert!(!path.exists());
+
+        let mut data = String::new();
+        take("text")
+            .into_file()
+            .unwrap()
+            .read_to_string(&mut data)
+            .unwrap();
+        assert_eq!(data, "some text");
+
+        let dest = tempfile::tempdir().unwrap();
+        let kept = take("kept");
+        let kept_path = dest.path().join("kept.txt");
+
+        kept.persist(&kept_path).unwrap();
+        drop(entries);
+
+        assert_eq!(fs::read_to_string(&kept_path).unwrap(), "kept data");
+
+        let text = SavedData::Text("persisted text".into());
+        let text_path = dest.path().join("text.txt");
+        text.persist(&text_path).unwrap();
+        assert_eq!(fs::read_to_string(&text_path).unwrap(), "persisted text");
+
+        // only the persisted files are left behind
+        assert_eq!(fs::read_dir(dest.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let body = format!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"{}.txt\"\r\n\
+             Content-Length: 4\r\n\r\n\
+             data\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"; name=\"other\"\r\n\
+             Content-Length: four\r\n\
+             Content-MD5: not base64\r\n\r\n\
+             text\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"encoded\"\r\n\
+             Content-Transfer-Encoding: x-uuencode\r\n\
+             Content-Length: 4\r\n\r\n\
+             ZGF0YQ==\r\n\
+             --boundary--",
+            "a".repeat(300)
+        );
+
+        let mut multipart = Multipart::with_body(body.as_bytes(), "boundary").with_warnings();
+        multipart.foreach_entry(|_| ()).unwrap();
+
+        let warning = |field: &str, reason| ParseWarning {
+            reason,
+            field: Some(field.into()),
+        };
+
+        assert_eq!(
+            multipart.warnings(),
+            [
+                warning("file", WarningReason::FilenameTruncated { len: 304 }),
+                warning("text", WarningReason::DuplicateParam("name")),
+                warning("text", WarningReason::InvalidContentLength("four".into())),
+                warning(
+                    "text",
+                    WarningReason::InvalidChecksum {
+                        header: "Content-MD5",
+                        value: "not base64".into(),
+                    }
+                ),
+                warning(
+                    "encoded",
+                    WarningReason::UnsupportedTransferEncoding("x-uuencode".into())
+                ),
+                warning(
+                    "encoded",
+                    WarningReason::ContentLengthMismatch {
+                        declared: 4,
+                        actual: 8,
+                    }
+                ),
+            ]
+        );
+
+        // not collected unless enabled
+        let mut multipart = Multipart::with_body(body.as_bytes(), "boundary");
+        multipart.foreach_entry(|_| ()).unwrap();
+        assert!(multipart.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_foreach_entry_ctl() {
+        use std::ops::ControlFlow;
+
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+             first\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"second\"\r\n\r\n\
+             second\r\n\
+             --boundary--",
+        );
+
+        let mut names = Vec::new();
+        request
+            .foreach_entry_ctl(|field| {
+                names.push(field.headers.name.to_string());
+                ControlFlow::Break(())
+            })
+            .unwrap();
+
+        assert_eq!(names, ["first"]);
+        // the rest is still there
+        assert_eq!(
+            &*request.read_entry().unwrap().unwrap().headers.name,
+            "second"
+        );
+
+        let mut names = Vec::new();
+        multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+             first\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"second\"\r\n\r\n\
+             second\r\n\
+             --boundary--",
+        )
+        .foreach_entry_ctl(|field| {
+            names.push(field.headers.name.to_string());
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+
+        assert_eq!(names, ["first", "second"]);
+    }
+
+    #[test]
+    fn test_find_field() {
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             some text\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"FILE\"\r\n\r\n\
+             not this one\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\r\n\
+             file data\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"after\"\r\n\r\n\
+             more text\r\n\
+             --boundary--",
+        );
+
+        let mut field = request.find_field("file").unwrap().unwrap();
+        assert_eq!(field.headers.filename.as_deref(), Some("file.txt"));
+        let mut text = String::new();
+        field.data.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "file data");
+
+        // fields after the match are still there, but the skipped ones are gone
+        assert!(request.find_field("text").unwrap().is_none());
+        assert!(request.read_entry().unwrap().is_none());
+
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             some text\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"after\"\r\n\r\n\
+             more text\r\n\
+             --boundary--",
+        );
+
+        let mut field = request.find_field("after").unwrap().unwrap();
+        let mut text = String::new();
+        field.data.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "more text");
+
+        let mut truncated = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             some text",
+        );
+        assert!(truncated.find_field("file").is_err());
+    }
+
+    /// A seekable body which counts the bytes read from it.
+    struct CountingReader {
+        inner: Cursor<Vec<u8>>,
+        read: u64,
+    }
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let read = self.inner.read(buf)?;
+            self.read += read as u64;
+            Ok(read)
+        }
+    }
+
+    impl Seek for CountingReader {
+        fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_seek_skip() {
+        const LEN: usize = 1024 * 1024;
+
+        let body = |declared_len: usize| {
+            let mut body = format!(
+                "--boundary\r\n\
+                 Content-Disposition: form-data; name=\"skipped\"; filename=\"large.bin\"\r\n\
+                 Content-Length: {declared_len}\r\n\r\n"
+            )
+            .into_bytes();
+            body.resize(body.len() + LEN, b'x');
+            body.extend_from_slice(
+                b"\r\n--boundary\r\n\
+                  Content-Disposition: form-data; name=\"wanted\"\r\n\r\n\
+                  data\r\n\
+                  --boundary--",
+            );
+
+            CountingReader {
+                inner: Cursor::new(body),
+                read: 0,
+            }
+        };
+
+        let find_wanted = |multipart: &mut Multipart<CountingReader>| {
+            let mut field = multipart.find_field("wanted").unwrap().unwrap();
+            let mut data = String::new();
+            field.data.read_to_string(&mut data).unwrap();
+            assert_eq!(data, "data");
+        };
+
+        let mut multipart = Multipart::with_body(body(LEN), "boundary");
+        multipart.set_trusted_seek_skip(true);
+        find_wanted(&mut multipart);
+        assert!(multipart.into_inner().read < 64 * 1024);
+
+        // a wrong declared length means the field is read after all
+        for declared_len in [LEN - 10, LEN + 10, LEN * 2] {
+            let mut multipart = Multipart::with_body(body(declared_len), "boundary");
+            multipart.set_trusted_seek_skip(true);
+            find_wanted(&mut multipart);
+            assert!(multipart.into_inner().read > LEN as u64);
+        }
+
+        // not enabled
+        let mut multipart = Multipart::with_body(body(LEN), "boundary");
+        find_wanted(&mut multipart);
+        assert!(multipart.into_inner().read > LEN as u64);
+
+        // a length spanning the next part skips it, which is why the body must be trusted
+        let data = "x".repeat(LEN);
+        let hidden = format!(
+            "{data}\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"hidden\"\r\n\r\n\
+             {data}"
+        );
+        let body = format!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\
+             Content-Length: {}\r\n\r\n\
+             {hidden}\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"c\"\r\n\r\n\
+             c\r\n\
+             --boundary--",
+            hidden.len()
+        );
+
+        for (seek_skip, expected) in [(false, &["a", "hidden", "c"][..]), (true, &["a", "c"])] {
+            let mut multipart = Multipart::with_body(Cursor::new(body.as_bytes()), "boundary");
+            multipart.set_trusted_seek_skip(seek_skip);
+
+            let mut names = Vec::new();
+            multipart
+                .foreach_entry(|field| names.push(field.headers.name.to_string()))
+                .unwrap();
+            assert_eq!(names, expected);
+        }
+    }
+
+    #[test]
+    fn test_try_with_body() {
+        use super::boundary::{BoundaryValidation, InvalidBoundary};
+
+        let body = "--boundary\r\n\
+                    Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+                    data\r\n\
+                    --boundary--";
+
+        for boundary in ["boundary", " boundary\t"] {
+            let mut multipart =
+                Multipart::try_with_body(body.as_bytes(), boundary, BoundaryValidation::Lenient)
+                    .ok()
+                    .unwrap();
+            let field = multipart.read_entry().unwrap().unwrap();
+            assert_eq!(&*field.headers.name, "field");
+        }
+
+        let validate = |boundary, validation| {
+            Multipart::try_with_body(body.as_bytes(), boundary, validation)
+                .err()
+                .unwrap()
+        };
+
+        assert_eq!(
+            validate("", BoundaryValidation::Strict),
+            InvalidBoundary::Empty
+        );
+        assert_eq!(
+            validate("\t", BoundaryValidation::Lenient),
+            InvalidBoundary::Empty
+        );
+        assert_eq!(
+            validate("bound\0ary", BoundaryValidation::Lenient),
+            InvalidBoundary::InvalidChar { ch: '\0', index: 5 }
+        );
+        assert_eq!(
+            validate("boundary\t", BoundaryValidation::Strict),
+            InvalidBoundary::InvalidChar { ch: '\t', index: 8 }
+        );
+    }
+
+    #[test]
+    fn test_into_entries() {
+        let entries = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             some text\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\r\n\
+             file data\r\n\
+             --boundary--",
+        )
+        .into_entries()
+        .unwrap();
+
+        assert_eq!(entries.fields_count(), 2);
+        assert!(entries.fields.contains_key("text"));
+        assert!(entries.fields.contains_key("file"));
+
+        // a partial save is an error wrapping the reason, e.g. the default count limit
+        let mut body = String::new();
+        for i in 0..=256 {
+            body += &format!(
+                "--boundary\r\nContent-Disposition: form-data; name=\"{i}\"\r\n\r\n{i}\r\n"
+            );
+        }
+        body += "--boundary--";
+
+        let mut request = Multipart::with_body(body.as_bytes(), "boundary");
+        let err = request.read_entries().unwrap_err();
+        assert!(matches!(
+            err.get_ref().unwrap().downcast_ref::<PartialReason>(),
+            Some(PartialReason::CountLimit { limit: 256 })
+        ));
+
+        // I/O errors are returned as-is
+        let err = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             truncated",
+        )
+        .into_entries()
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_partial_reasons() {
+        type Builder<'a> = crate::server::SaveBuilder<&'a mut Multipart<&'static [u8]>>;
+
+        fn reason(limits: impl FnOnce(Builder<'_>) -> Builder<'_>) -> PartialReason {
+            let mut request = multipart(
+                "--boundary\r\n\
+                 Content-Disposition: form-data; name=\"a\"\r\n\r\n\
+                 0123456789\r\n\
+                 --boundary\r\n\
+                 Content-Disposition: form-data; name=\"b\"\r\n\r\n\
+                 0123456789\r\n\
+                 --boundary--",
+            );
+
+            match limits(request.save()).temp() {
+                SaveResult::Partial(_, reason) => reason,
+                _ => panic!("expected a partial save"),
+            }
+        }
+
+        assert!(matches!(
+            reason(|save| save.count_limit(1)),
+            PartialReason::CountLimit { limit: 1 }
+        ));
+
+        match reason(|save| save.size_limit(5)) {
+            PartialReason::FieldSizeLimit { field, limit } => {
+                assert_eq!(field.as_deref(), Some("a"));
+                assert_eq!(limit, 5);
+            }
+            other => panic!("unexpected reason: {other}"),
+        }
+
+        // the first field fits, the second only partially
+        assert!(matches!(
+            reason(|save| save.total_size_limit(15)),
+            PartialReason::TotalSizeLimit { limit: 15 }
+        ));
+
+        // with room for both fields, nothing is reported
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\r\n\
+             0123456789\r\n\
+             --boundary--",
+        );
+        assert!(matches!(
+            request.save().size_limit(10).total_size_limit(10).temp(),
+            SaveResult::Full(_)
+        ));
+    }
+
+    #[test]
+    fn test_save_with_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = SaveConfig::new()
+            .size_limit(5)
+            .memory_threshold(0)
+            .dir(dir.path());
+
+        for name in ["first", "second"] {
+            let body = format!(
+                "--boundary\r\n\
+                 Content-Disposition: form-data; name=\"{name}\"; filename=\"{name}.txt\"\r\n\r\n\
+                 0123456789\r\n\
+                 --boundary--"
+            );
+            let mut request = Multipart::with_body(body.as_bytes(), BOUNDARY);
+
+            match request.save_with(&config) {
+                SaveResult::Partial(partial, PartialReason::FieldSizeLimit { field, limit }) => {
+                    assert_eq!(field.as_deref(), Some(name));
+                    assert_eq!(limit, 5);
+                    assert_eq!(partial.entries.save_dir.as_path(), dir.path());
+                }
+                _ => panic!("expected the size limit from the config"),
+            }
+        }
+
+        // the same options as `save()` by default
+        let mut request = multipart(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+             text\r\n\
+             --boundary--",
+        );
+        let entries = request
+            .save_with(&SaveConfig::default())
+            .into_result()
+            .unwrap();
+        assert!(entries.save_dir.is_temporary());
+        assert_eq!(
+            entries.fields["text"][0].data,
+            SavedData::Text("text".into())
+        );
+    }
+
+    #[test]
+    fn test_save_from_config() {
+        let config = SaveConfig::new()
+            .size_limit(5)
+            .memory_threshold(0)
+            .sanitize_filenames(|filename| filename.replace(['/', '\\'], "_"));
+
+        let body = "--boundary\r\n\
+                    Content-Disposition: form-data; name=\"file\"; filename=\"../a.txt\"\r\n\r\n\
+                    0123456789\r\n\
+                    --boundary--";
+
+        // setters on the builder override the config for this request only
+        let entries = Multipart::with_body(body.as_bytes(), BOUNDARY)
+            .save_from(&config)
+            .size_limit(None)
+            .temp()
+            .into_result()
+            .unwrap();
+
+        let field = &entries.fields["file"][0];
+        assert_eq!(field.headers.filename.as_deref(), Some(".._a.txt"));
+        assert_eq!(field.data.size(), 10);
+
+        match Multipart::with_body(body.as_bytes(), BOUNDARY).save_with(&config) {
+            SaveResult::Partial(_, PartialReason::FieldSizeLimit { limit, .. }) => {
+                assert_eq!(limit, 5)
+            }
+            _ => panic!("expected the size limit from the config"),
+        }
+    }
+
+    #[test]
+    fn test_text_fields_sorted() {
+        let body: &[u8] = b"--boundary\r\n\
+             Content-Disposition: form-data; name=\"b\"\r\n\r\n\
+             first b\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             \xff\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\r\n\
+             a\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"b\"\r\n\r\n\
+             second b\r\n\
+             --boundary--";
+
+        let entries = Multipart::with_body(body, BOUNDARY).into_entries().unwrap();
+        assert_eq!(entries.fields_count(), 4);
+
+        let sorted = entries.text_fields_sorted();
+        assert_eq!(
+            sorted.into_iter().collect::<Vec<_>>(),
+            [
+                ("a".to_owned(), vec!["a".to_owned()]),
+                (
+                    "b".to_owned(),
+                    vec!["first b".to_owned(), "second b".to_owned()]
+                ),
+            ]
+        );
+    }
+
+    /// A body which blocks after it's read to the end, like an open connection.
+    struct NeverEnds;
+
+    impl Read for NeverEnds {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::WouldBlock.into())
+        }
+    }
+
+    #[test]
+    fn test_content_length_limit() {
+        const BODY: &str = "--boundary\r\n\
+                            Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+                            first\r\n\
+                            --boundary\r\n\
+                            Content-Disposition: form-data; name=\"second\"\r\n\r\n\
+                            second\r\n\
+                            --boundary--";
+
+        let len = BODY.len() as u64;
+        let read_all = |limit: u64| {
+            let mut request = multipart(BODY).with_content_length_limit(limit);
+            let mut names = Vec::new();
+
+            loop {
+                match request.read_entry() {
+                    Ok(Some(mut field)) => {
+                        let mut data = String::new();
+                        field.data.read_to_string(&mut data)?;
+                        names.push(field.headers.name.to_string());
+                    }
+                    Ok(None) => return Ok(names),
+                    Err(e) => return Err(e),
+                }
+            }
+        };
+
+        assert_eq!(read_all(len).unwrap(), ["first", "second"]);
+        // the body may be shorter than the limit, as long as it's complete
+        assert_eq!(read_all(len + 100).unwrap(), ["first", "second"]);
+
+        // cut off in the middle of the closing boundary
+        let err = read_all(len - 3).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // cut off in the body of the second field
+        let err = read_all(len - 17).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // a field after the limit is never returned
+        let mut request = multipart(BODY)
+            .with_content_length_limit(BODY.find("\r\n--boundary\r\n").unwrap() as u64 + 2);
+        let mut field = request.read_entry().unwrap().unwrap();
+        assert_eq!(&*field.headers.name, "first");
+        let mut data = String::new();
+        field.data.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "first");
+        assert_eq!(
+            request.read_entry().err().unwrap().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+
+        // a connection which stays open after the body isn't read from again once the body
+        // is buffered, which would block
+        let open = BODY.as_bytes().chain(NeverEnds);
+        let mut request = Multipart::with_body(open, BOUNDARY).with_content_length_limit(len);
+        assert!(request.read_entry().unwrap().is_some());
+        assert!(request.read_entry().unwrap().is_some());
+        assert!(request.read_entry().unwrap().is_none());
+
+        // the same with a buffered body
+        let mut request =
+            Multipart::with_buffered_body(io::BufReader::new(BODY.as_bytes()), BOUNDARY)
+                .with_content_length_limit(len - 3);
+        assert!(request.read_entry().unwrap().is_some());
+        assert!(request.read_entry().unwrap().is_some());
+        assert!(request.read_entry().is_err());
+    }
+
+    /// A body which fails every other read with `WouldBlock` or `Interrupted`, and otherwise
+    /// returns a few bytes at a time.
+    struct FlakyReader {
+        inner: &'static [u8],
+        reads: usize,
+    }
+
+    impl Read for FlakyReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+
+            match self.reads % 4 {
+                1 => return Err(io::ErrorKind::WouldBlock.into()),
+                3 => return Err(io::ErrorKind::Interrupted.into()),
+                _ => (),
+            }
+
+            let len = buf.len().min(self.reads % 7 + 1);
+            self.inner.read(&mut buf[..len])
+        }
+    }
+
+    fn is_retryable(e: &io::Error) -> bool {
+        matches!(
+            e.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+        )
+    }
+
+    fn read_fields_retrying<R: Read>(multipart: &mut Multipart<R>) -> Vec<(String, Vec<u8>)> {
+        let mut fields = Vec::new();
+
+        loop {
+            let mut field = match multipart.read_entry() {
+                Ok(Some(field)) => field,
+                Ok(None) => return fields,
+                Err(e) if is_retryable(&e) => continue,
+                Err(e) => panic!("unexpected error: {e}"),
+            };
+
+            let mut data = Vec::new();
+            let mut buf = [0; 16];
+
+            loop {
+                match field.data.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(read) => data.extend_from_slice(&buf[..read]),
+                    Err(e) if is_retryable(&e) => (),
+                    Err(e) => panic!("unexpected error: {e}"),
+                }
+            }
+
+            fields.push((field.headers.name.to_string(), data));
+        }
+    }
+
+    #[test]
+    fn test_retry_after_would_block() {
+        const BODY: &str = "preamble\r\n\
+                            --boundary\r\n\
+                            Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+                            some text\r\n\
+                            --boundary\r\n\
+                            Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\
+                            Content-Type: text/plain\r\n\
+                            Content-Length: 37\r\n\r\n\
+                            file data which is a bit longer\r\nline\r\n\
+                            --boundary\r\n\
+                            Content-Disposition: form-data; name=\"empty\"\r\n\r\n\
+                            \r\n\
+                            --boundary--\r\n";
+
+        let expected = read_fields_retrying(&mut multipart(BODY));
+        assert_eq!(expected.len(), 3);
+
+        let body = FlakyReader {
+            inner: BODY.as_bytes(),
+            reads: 0,
+        };
+        let mut request = Multipart::with_body(body, BOUNDARY).with_warnings();
+        assert_eq!(read_fields_retrying(&mut request), expected);
+        assert_eq!(request.warnings(), &[]);
+
+        let body = FlakyReader {
+            inner: BODY.as_bytes(),
+            reads: 0,
+        };
+        let mut request = Multipart::with_buffered_body(io::BufReader::new(body), BOUNDARY);
+        assert_eq!(read_fields_retrying(&mut request), expected);
+    }
+
+    #[test]
+    fn test_debug_state() {
+        use super::MultipartDebug;
+
+        const BODY: &str = "--boundary\r\n\
+             Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+             value\r\n\
+             --boundary--";
+
+        let mut request = multipart(BODY);
+        let state = request.debug_state();
+        assert_eq!(
+            state,
+            MultipartDebug {
+                boundary: "boundary".into(),
+                consumed: 0,
+                opening_boundary_found: false,
+                at_end: false,
+                fields_read: 0,
+            }
+        );
+
+        request.foreach_entry(|_| ()).unwrap();
+        let state = request.debug_state();
+        assert!(state.opening_boundary_found);
+        assert!(state.at_end);
+        assert_eq!(state.consumed, BODY.len() as u64);
+        assert_eq!(state.fields_read, 1);
+
+        // the wrong boundary
+        let mut request = Multipart::with_body(BODY.as_bytes(), "wrong");
+        request.read_entry().err().unwrap();
+        let state = request.debug_state();
+        assert!(!state.opening_boundary_found);
+        assert!(!state.at_end);
+
+        // an empty body
+        let mut request = multipart("");
+        assert!(request.read_entry().unwrap().is_none());
+        let state = request.debug_state();
+        assert!(state.at_end);
+        assert_eq!(state.consumed, 0);
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod bench {
+    use super::Multipart;
+
+    use std::io::{self, BufRead, BufReader, Read};
+
+    use test::Bencher;
+
+    const SMALL: &[u8] = b"--boundary\r\n\
+        Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+        value\r\n\
+        --boundary--";
+
+    /// A request with a single file of 16 MB.
+    fn large_file() -> Vec<u8> {
+        let mut body = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\r\n"
+            .to_vec();
+        body.resize(body.len() + 16 * 1024 * 1024, b'x');
+        body.extend_from_slice(b"\r\n--boundary--");
+        body
+    }
+
+    fn parse<R: Read>(mut multipart: Multipart<R>) {
+        multipart
+            .foreach_entry(|mut field| {
+                io::copy(&mut field.data, &mut io::sink()).unwrap();
+            })
+            .unwrap();
+    }
+
+    fn body_reader(body: &[u8]) -> impl BufRead + '_ {
+        BufReader::with_capacity(64 * 1024, body)
+    }
+
+    // a small request is dominated by allocating the read buffer, which
+    // `with_buffered_body()` doesn't need
+    #[bench]
+    fn bench_small_with_body(b: &mut Bencher) {
+        b.iter(|| parse(Multipart::with_body(SMALL, "boundary")));
+    }
+
+    #[bench]
+    fn bench_small_with_buffered_body(b: &mut Bencher) {
+        b.iter(|| parse(Multipart::with_buffered_body(SMALL, "boundary")));
+    }
+
+    // a large file from a `BufReader` is copied into the buffer of `with_body()` as well
+    #[bench]
+    fn bench_large_with_body(b: &mut Bencher) {
+        let body = large_file();
+        b.bytes = body.len() as u64;
+        b.iter(|| parse(Multipart::with_body(body_reader(&body), "boundary")));
+    }
+
+    #[bench]
+    fn bench_large_with_buffered_body(b: &mut Bencher) {
+        let body = large_file();
+        b.bytes = body.len() as u64;
+        b.iter(|| {
+            parse(Multipart::with_buffered_body(
+                body_reader(&body),
+                "boundary",
+            ))
+        });
+    }
 }
@@ -15,7 +15,10 @@ impl<'r, 'mw: 'r, 'server: 'mw, D: 'mw> HttpRequest for Maybe<'r, 'mw, 'server,
     fn multipart_boundary(&self) -> Option<&str> {
         // we can't use the impl from the `hyper` module because it might be the wrong version
         let cont_type = try_opt!(self.0.origin.headers.get::<ContentType>());
-        cont_type.get_param("boundary").map(|v| v.as_str())
+        cont_type
+            .get_param("boundary")
+            .map(|v| v.as_str())
+            .filter(|v| !v.is_empty())
     }
 
     fn body(self) -> Self::Body {
@@ -0,0 +1,103 @@
+//! Hooks for instrumenting the parsing of requests.
+use std::io;
+
+use super::field::FieldHeaders;
+
+/// Callbacks for collecting metrics about a request as it is parsed, e.g. counters of fields
+/// and bytes. Register one with `Multipart::set_observer()`.
+///
+/// All methods do nothing by default. They are called synchronously from within `read_entry()`
+/// and the field data readers, so they must be cheap and must not block; e.g. increment
+/// an atomic counter or send to a channel without waiting.
+///
+/// When no observer is set, instrumentation costs a single `Option` check per call site.
+#[allow(clippy::module_name_repetitions)]
+pub trait MultipartObserver: Send {
+    /// A new field was found; its data will be read next.
+    fn on_field_start(&mut self, _headers: &FieldHeaders) {}
+
+    /// `n` bytes of field data were read.
+    fn on_bytes(&mut self, _n: u64) {}
+
+    /// The closing boundary was reached after `field_count` fields and `total_bytes` bytes
+    /// of the body (including boundaries and headers).
+    fn on_complete(&mut self, _field_count: usize, _total_bytes: u64) {}
+
+    /// Reading the next field failed, e.g. because the request was malformed or exceeded
+    /// a limit.
+    fn on_error(&mut self, _error: &io::Error) {}
+
+    /// The lowercase hex digest of the current field's data was computed while saving it with
+    /// a hash function set with `SaveBuilder::hash_with()`. Called after the field's data has
+    /// been read, before the next field starts.
+    fn on_field_digest(&mut self, _digest: &str) {}
+}
+
+/// The observer of a `Multipart` and whether it has been told the request is complete,
+/// since `read_entry()` may be called again after the end.
+pub(crate) struct Observer {
+    pub(crate) inner: Box<dyn MultipartObserver>,
+    pub(crate) finished: bool,
+}
+
+/// Collects the metadata of each field as it is read, for `Multipart::export_metadata_jsonl()`.
+///
+/// This is kept by the `Multipart` next to its observer rather than being one, so an observer
+/// set with `Multipart::set_observer()` is still notified as usual.
+#[cfg(feature = "serde")]
+#[derive(Default)]
+pub(crate) struct MetadataRecorder {
+    fields: Vec<FieldMetadata>,
+}
+
+#[cfg(feature = "serde")]
+struct FieldMetadata {
+    name: std::sync::Arc<str>,
+    filename: Option<String>,
+    content_type: Option<String>,
+    size: u64,
+    hash: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl MetadataRecorder {
+    pub(crate) fn field_start(&mut self, headers: &FieldHeaders) {
+        self.fields.push(FieldMetadata {
+            name: headers.name.clone(),
+            filename: headers.filename.clone(),
+            content_type: headers.content_type.as_ref().map(ToString::to_string),
+            size: 0,
+            hash: None,
+        });
+    }
+
+    pub(crate) fn bytes(&mut self, n: u64) {
+        if let Some(field) = self.fields.last_mut() {
+            field.size += n;
+        }
+    }
+
+    pub(crate) fn digest(&mut self, digest: &str) {
+        if let Some(field) = self.fields.last_mut() {
+            field.hash = Some(digest.to_owned());
+        }
+    }
+
+    /// Write the metadata of each field as a line of JSON.
+    pub(crate) fn write_jsonl<W: io::Write>(&self, out: &mut W) -> io::Result<()> {
+        for field in &self.fields {
+            let line = serde_json::json!({
+                "name": &*field.name,
+                "filename": field.filename,
+                "content_type": field.content_type,
+                "size": field.size,
+                "hash": field.hash,
+            });
+
+            serde_json::to_writer(&mut *out, &line)?;
+            out.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+}
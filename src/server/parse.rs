@@ -0,0 +1,943 @@
+//! Parsing of `multipart/form-data` bodies which are already in memory.
+//!
+//! `parse_multipart()` works on byte slices only and doesn't need `std::io`, which makes it
+//! usable where there is no `Read` to wrap, e.g. in a WASM sandbox. The parts it yields borrow
+//! everything from the body, so nothing is copied.
+//!
+//! The boundary search and the header and `Content-Disposition` parsing here are the same
+//! routines `BoundaryReader` and `Multipart` use, so both accept the same bodies.
+//!
+//! ```rust
+//! use mpart::server::parse::parse_multipart;
+//!
+//! let body = b"preamble\r\n--boundary\r\n\
+//!     Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+//!     some text\r\n\
+//!     --boundary\r\n\
+//!     Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\
+//!     Content-Type: text/plain\r\n\r\n\
+//!     file contents\r\n\
+//!     --boundary--\r\n";
+//!
+//! let parts = parse_multipart(body, b"boundary")
+//!     .collect::<Result<Vec<_>, _>>()
+//!     .unwrap();
+//!
+//! assert_eq!(parts[0].name, "text");
+//! assert_eq!(parts[0].body, b"some text");
+//! assert_eq!(parts[1].filename, Some("file.txt"));
+//! assert_eq!(parts[1].content_type(), Some("text/plain"));
+//! assert_eq!(parts[1].body, b"file contents");
+//! ```
+use httparse::{Status, EMPTY_HEADER};
+use thiserror::Error;
+
+use std::borrow::Cow;
+use std::str;
+
+use super::field::{mixes_line_endings, LineEndingPolicy};
+
+/// The maximum number of headers a field may have.
+pub(crate) const MAX_HEADERS: usize = 8;
+
+/// Split `body` on `boundary` (without the leading `--`) and parse the headers of each part.
+///
+/// The preamble before the opening boundary and the epilogue after the closing boundary are
+/// skipped, and an empty body has no parts. Like `Multipart`, a boundary is recognized anywhere
+/// unless `Parts::strict()` is set.
+///
+/// The iterator yields an error and then stops if the body is malformed.
+pub fn parse_multipart<'a>(body: &'a [u8], boundary: &[u8]) -> Parts<'a> {
+    let mut dashed = Vec::with_capacity(boundary.len() + 4);
+    dashed.extend_from_slice(b"\r\n--");
+    dashed.extend_from_slice(boundary);
+
+    Parts {
+        rest: body,
+        delimiter: dashed,
+        strict: false,
+        line_ending_policy: LineEndingPolicy::default(),
+        started: false,
+        done: false,
+    }
+}
+
+/// The iterator over the parts of a body returned by `parse_multipart()`.
+#[derive(Clone, Debug)]
+pub struct Parts<'a> {
+    /// The body after the last boundary consumed.
+    rest: &'a [u8],
+    /// The boundary with its leading `--` and preceding CRLF.
+    delimiter: Vec<u8>,
+    strict: bool,
+    line_ending_policy: LineEndingPolicy,
+    started: bool,
+    done: bool,
+}
+
+impl<'a> Parts<'a> {
+    /// Only recognize the boundary if it is preceded by CRLF (or is at the very start of the
+    /// body) and followed by CRLF or `--`. See `Multipart::set_strict()`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set what to do with part headers mixing CRLF and LF line endings. With the default
+    /// `LineEndingPolicy::Lenient` they are accepted; with `LineEndingPolicy::Strict` they are
+    /// a `ParseError::MixedLineEndings`. See `Multipart::set_line_ending_policy()`.
+    pub fn line_ending_policy(mut self, policy: LineEndingPolicy) -> Self {
+        self.line_ending_policy = policy;
+        self
+    }
+
+    /// The part of the body not parsed yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.rest
+    }
+
+    fn boundary(&self) -> &[u8] {
+        &self.delimiter[2..]
+    }
+
+    /// Find the next boundary, returning the data before it and the index after it.
+    fn next_boundary(&self) -> Result<(&'a [u8], usize), ParseError> {
+        let rest = self.rest;
+
+        let found = if self.strict {
+            let at_start = !self.started;
+            find_delimiter(rest, self.boundary(), &self.delimiter, at_start)
+        } else {
+            find_boundary(rest, self.boundary())
+        };
+
+        let Ok(idx) = found else {
+            return Err(ParseError::UnexpectedEnd);
+        };
+
+        let mut end = idx + self.boundary().len();
+
+        // the CRLF preceding the boundary belongs to it
+        let data = if rest[idx..].starts_with(b"\r\n") {
+            end += 2;
+            &rest[..idx]
+        } else {
+            rest[..idx].strip_suffix(b"\r\n").unwrap_or(&rest[..idx])
+        };
+
+        Ok((data, end))
+    }
+
+    /// Consume the boundary ending at `end` and what follows it.
+    fn consume_boundary(&mut self, end: usize) -> Result<(), ParseError> {
+        let after = &self.rest[end..];
+
+        match after_boundary(after) {
+            AfterBoundary::Part(len) => self.rest = &after[len..],
+            AfterBoundary::Closing(len) => {
+                // the rest is the epilogue
+                self.rest = &after[len..];
+                self.done = true;
+            }
+            AfterBoundary::Incomplete => return Err(ParseError::UnexpectedEnd),
+            AfterBoundary::Invalid(first, second) => {
+                return Err(ParseError::InvalidBoundary(first, second))
+            }
+        }
+
+        self.started = true;
+        Ok(())
+    }
+
+    fn next_part(&mut self) -> Result<Option<ParsedPart<'a>>, ParseError> {
+        if !self.started {
+            if self.rest.is_empty() {
+                self.done = true;
+                return Ok(None);
+            }
+
+            // skip the preamble
+            let (_, end) = self.next_boundary()?;
+            self.consume_boundary(end)?;
+
+            if self.done {
+                return Ok(None);
+            }
+        }
+
+        let rest = self.rest;
+        let (data, end) = self.next_boundary()?;
+        self.consume_boundary(end)?;
+
+        // `data` with the CRLF preceding the boundary, if any
+        let delimited = &rest[..rest.len().min(data.len() + 2)];
+        let delimited = Some(delimited).filter(|delimited| delimited.ends_with(b"\r\n"));
+
+        ParsedPart::parse(data, delimited, self.line_ending_policy).map(Some)
+    }
+}
+
+impl<'a> Iterator for Parts<'a> {
+    type Item = Result<ParsedPart<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let res = self.next_part();
+
+        if res.is_err() {
+            self.done = true;
+        }
+
+        res.transpose()
+    }
+}
+
+impl std::iter::FusedIterator for Parts<'_> {}
+
+/// A part of a body parsed by `parse_multipart()`, borrowing from the body.
+///
+/// ### Warning: Values are Client-Provided
+/// As with `FieldHeaders`, everything here comes from the client and should be considered
+/// **untrustworthy**.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParsedPart<'a> {
+    /// The field's name from the form.
+    pub name: &'a str,
+    /// The filename of this entry, if supplied. Unlike `FieldHeaders::filename`, this is not
+    /// truncated.
+    pub filename: Option<&'a str>,
+    /// All the headers of this part.
+    pub headers: PartHeaders<'a>,
+    /// The data of this part.
+    pub body: &'a [u8],
+}
+
+impl<'a> ParsedPart<'a> {
+    /// Parse the part in `data`. `delimited` is `data` followed by the CRLF preceding the
+    /// boundary, if there is one.
+    fn parse(
+        data: &'a [u8],
+        delimited: Option<&'a [u8]>,
+        mixed: LineEndingPolicy,
+    ) -> Result<Self, ParseError> {
+        let mut raw_headers = [EMPTY_HEADER; MAX_HEADERS];
+
+        let (data, headers_len) = match httparse::parse_headers(data, &mut raw_headers)? {
+            Status::Complete((len, _)) => (data, len),
+            // If the client didn't send a CRLF for an empty body, the one ending the headers
+            // was taken as the one preceding the boundary.
+            Status::Partial => {
+                match delimited.map(|d| httparse::parse_headers(d, &mut raw_headers)) {
+                    Some(Ok(Status::Complete((len, _)))) => (delimited.unwrap_or(data), len),
+                    _ => return Err(ParseError::IncompleteHeaders),
+                }
+            }
+        };
+
+        if mixed == LineEndingPolicy::Strict && mixes_line_endings(&data[..headers_len]) {
+            return Err(ParseError::MixedLineEndings);
+        }
+
+        let headers = str::from_utf8(&data[..headers_len])
+            .map(PartHeaders)
+            .map_err(|_| ParseError::InvalidUtf8)?;
+
+        let cont_disp = headers
+            .get("Content-Disposition")
+            .ok_or(ParseError::MissingContentDisposition)?;
+
+        let disp = FormDataDisposition::parse(cont_disp)
+            .map_err(|(reason, _)| ParseError::InvalidContentDisposition(reason))?;
+
+        Ok(ParsedPart {
+            name: disp.name,
+            filename: disp.filename,
+            headers,
+            body: &data[headers_len..],
+        })
+    }
+
+    /// The filename of this entry decoded from the extended `filename*` parameter if the client
+    /// sent one ([RFC 5987][rfc5987]), as `FieldHeaders::filename` is, or `filename` otherwise.
+    ///
+    /// `filename` is the plain parameter only, which clients may have stripped of any non-ASCII
+    /// characters or encoded in some other way.
+    ///
+    /// [rfc5987]: https://tools.ietf.org/html/rfc5987
+    pub fn decoded_filename(&self) -> Option<Cow<'a, str>> {
+        let ext = self
+            .headers
+            .get("Content-Disposition")
+            .and_then(|val| val.split_once(';'))
+            .and_then(|(_, params)| get_ext_param(params, "filename"));
+
+        ext.map(Cow::Owned)
+            .or_else(|| self.filename.map(Cow::Borrowed))
+    }
+
+    /// The value of the `Content-Type` header, if supplied.
+    pub fn content_type(&self) -> Option<&'a str> {
+        self.headers.get("Content-Type")
+    }
+}
+
+/// The headers of a `ParsedPart`, as they appear in the body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartHeaders<'a>(&'a str);
+
+impl<'a> PartHeaders<'a> {
+    /// The value of the first header named `name`, matched case-insensitively.
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        self.iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, val)| val)
+    }
+
+    /// The headers as name-value pairs in the order they appear.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        // `httparse` has already checked that every line is a valid header
+        self.0
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, val)| (name, val.trim()))
+    }
+
+    /// The raw headers, including the empty line ending them.
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+}
+
+/// An error parsing a body with `parse_multipart()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ParseError {
+    /// The body ended before the closing boundary, or there was no boundary at all.
+    #[error("unexpected end of request body")]
+    UnexpectedEnd,
+    /// A boundary was followed by something other than CRLF or `--`.
+    #[error("unexpected bytes following multipart boundary: {:X} {:X}", .0, .1)]
+    InvalidBoundary(u8, u8),
+    /// The headers of a part could not be parsed.
+    #[error("an error occurred while parsing field headers: {}", .0)]
+    Headers(#[from] httparse::Error),
+    /// The headers of a part are missing the trailing empty line.
+    #[error("field headers section missing trailing CRLF-CRLF")]
+    IncompleteHeaders,
+    /// The headers of a part mix CRLF and LF line endings, with `LineEndingPolicy::Strict`.
+    #[error("field headers mix CRLF and LF line endings")]
+    MixedLineEndings,
+    /// The headers of a part are not valid UTF-8.
+    #[error("field headers are not valid UTF-8")]
+    InvalidUtf8,
+    /// A part has no `Content-Disposition` header.
+    #[error("\"Content-Disposition\" header not found in field headers")]
+    MissingContentDisposition,
+    /// A part's `Content-Disposition` header is not `form-data` with a name.
+    #[error("invalid \"Content-Disposition\" header: {}", .0)]
+    InvalidContentDisposition(&'static str),
+}
+
+/// The name and filename from a `Content-Disposition: form-data` header.
+pub(crate) struct FormDataDisposition<'a> {
+    pub name: &'a str,
+    pub filename: Option<&'a str>,
+    /// The filename decoded from the extended `filename*` parameter, which takes precedence
+    /// over `filename`.
+    pub filename_ext: Option<String>,
+    /// The non-standard `size` parameter, if present and a valid number.
+    pub size: Option<u64>,
+    /// A parameter which appeared more than once; only the first value is used.
+    pub duplicate_param: Option<&'static str>,
+}
+
+impl<'a> FormDataDisposition<'a> {
+    /// Parse the value of the header. On error, returns the reason and the offending part of it.
+    pub fn parse(val: &'a str) -> Result<Self, (&'static str, &'a str)> {
+        // Content-Disposition: ?
+        let Some((disp_type, after_disp_type)) = val.split_once(';') else {
+            return Err((
+                "expected additional data after Content-Disposition type",
+                val,
+            ));
+        };
+
+        // assert Content-Disposition: form-data
+        // but needs to be parsed out to trim the spaces (allowed by spec IIRC)
+        if disp_type.trim() != "form-data" {
+            return Err(("unexpected Content-Disposition value", disp_type));
+        }
+
+        // Content-Disposition: form-data; name={field_name}; filename={filename}
+        // The values should be quoted but some clients send bare tokens.
+        let Some(name) = get_param(after_disp_type, "name") else {
+            return Err((
+                "expected field name and maybe filename, got",
+                after_disp_type,
+            ));
+        };
+
+        Ok(Self::with_params(name, after_disp_type))
+    }
+
+    /// Parse the value of the header of a part of a nested `multipart/mixed` field, which has a
+    /// disposition type like `file` or `attachment` and usually no name. `name` is empty then.
+    pub fn parse_nested(val: &'a str) -> Self {
+        let params = val.split_once(';').map_or("", |(_, params)| params);
+        Self::with_params(get_param(params, "name").unwrap_or_default(), params)
+    }
+
+    fn with_params(name: &'a str, params: &'a str) -> Self {
+        let duplicate_param = ["name", "filename", "filename*"]
+            .into_iter()
+            .find(|name| count_params(params, name) > 1);
+
+        FormDataDisposition {
+            name,
+            filename: get_param(params, "filename"),
+            filename_ext: get_ext_param(params, "filename"),
+            size: get_param(params, "size").and_then(|size| size.parse().ok()),
+            duplicate_param,
+        }
+    }
+}
+
+/// Split header parameters on `;`, except inside quoted values.
+fn split_params(params: &str) -> impl Iterator<Item = &str> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    params.split(move |c| {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ';' => return !in_quotes,
+            _ => (),
+        }
+
+        false
+    })
+}
+
+/// Get the value of the parameter `name` in `params`, quoted or not, with surrounding
+/// whitespace and quotes trimmed. The name is matched case-insensitively.
+fn get_param<'a>(params: &'a str, name: &str) -> Option<&'a str> {
+    split_params(params)
+        .filter_map(|param| param.split_once('='))
+        .find(|(param_name, _)| param_name.trim().eq_ignore_ascii_case(name))
+        .map(|(_, val)| val.trim().trim_matches('"'))
+}
+
+/// Get the value of the extended parameter `name*` in `params`, as defined by
+/// [RFC 5987](https://tools.ietf.org/html/rfc5987) (`name*=UTF-8''%C3%9F.txt`), or joined from
+/// the continuations `name*0`, `name*1*`, ... of
+/// [RFC 2231](https://tools.ietf.org/html/rfc2231#section-3).
+///
+/// Returns `None` if there is none, or if it is in a charset other than UTF-8 or ISO-8859-1 or
+/// doesn't decode, so the plain parameter should be used instead.
+fn get_ext_param(params: &str, name: &str) -> Option<String> {
+    // (section number, whether it's percent-encoded, value)
+    let mut sections: Vec<(u32, bool, &str)> = Vec::new();
+
+    for (param_name, val) in split_params(params).filter_map(|param| param.split_once('=')) {
+        let Some(suffix) = strip_prefix_ignore_case(param_name.trim(), name)
+            .and_then(|suffix| suffix.strip_prefix('*'))
+        else {
+            continue;
+        };
+
+        let val = val.trim();
+
+        if suffix.is_empty() {
+            // `name*` on its own wins over any continuations
+            return decode_ext_value(val);
+        }
+
+        let (num, encoded) = match suffix.strip_suffix('*') {
+            Some(num) => (num, true),
+            None => (suffix, false),
+        };
+
+        let Ok(num) = num.parse() else { continue };
+
+        if !sections.iter().any(|&(n, ..)| n == num) {
+            sections.push((num, encoded, val));
+        }
+    }
+
+    if sections.is_empty() {
+        return None;
+    }
+
+    sections.sort_by_key(|&(num, ..)| num);
+
+    // the sections must be numbered from 0 without gaps
+    if sections.iter().zip(0..).any(|(&(num, ..), i)| num != i) {
+        log::warn!("Ignoring {}* parameter with missing sections", name);
+        return None;
+    }
+
+    let mut charset = "us-ascii";
+    let mut bytes = Vec::new();
+
+    for (i, &(_, encoded, val)) in sections.iter().enumerate() {
+        if !encoded {
+            bytes.extend_from_slice(val.trim_matches('"').as_bytes());
+            continue;
+        }
+
+        let val = if i == 0 {
+            let (section_charset, _, val) = split_ext_value(val)?;
+            charset = section_charset;
+            val
+        } else {
+            val
+        };
+
+        bytes.extend(percent_decode(val)?);
+    }
+
+    decode_charset(charset, bytes)
+}
+
+/// Decode an RFC 5987 `ext-value`: `charset'language'percent-encoded`.
+fn decode_ext_value(val: &str) -> Option<String> {
+    let (charset, _, encoded) = split_ext_value(val)?;
+    decode_charset(charset, percent_decode(encoded)?)
+}
+
+/// Split an `ext-value` into its charset, language and value.
+fn split_ext_value(val: &str) -> Option<(&str, &str, &str)> {
+    // some clients quote it even though it's a token
+    let mut parts = val.trim_matches('"').splitn(3, '\'');
+    Some((parts.next()?, parts.next()?, parts.next()?))
+}
+
+fn percent_decode(val: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(val.len());
+    let mut bytes = val.bytes();
+
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            decoded.push(u8::from_str_radix(str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            decoded.push(byte);
+        }
+    }
+
+    Some(decoded)
+}
+
+/// Decode `bytes` in `charset`, which must be one of those RFC 5987 requires support for.
+fn decode_charset(charset: &str, bytes: Vec<u8>) -> Option<String> {
+    if charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("us-ascii") {
+        String::from_utf8(bytes)
+            .map_err(|_| log::warn!("Ignoring extended parameter which is not valid UTF-8"))
+            .ok()
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") {
+        Some(bytes.into_iter().map(char::from).collect())
+    } else {
+        log::warn!(
+            "Ignoring extended parameter in unsupported charset: {:?}",
+            charset
+        );
+        None
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(val: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = val.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &val[prefix.len()..])
+}
+
+/// Count the parameters named `name` in `params`.
+fn count_params(params: &str, name: &str) -> usize {
+    split_params(params)
+        .filter_map(|param| param.split_once('='))
+        .filter(|(param_name, _)| param_name.trim().eq_ignore_ascii_case(name))
+        .count()
+}
+
+/// What follows a boundary.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum AfterBoundary {
+    /// A part follows after this many bytes of transport padding and CRLF.
+    Part(usize),
+    /// It is the closing boundary, followed by this many bytes of dashes, padding and CRLF.
+    Closing(usize),
+    /// More data is needed to tell.
+    Incomplete,
+    /// The boundary is followed by these bytes instead of CRLF or `--`.
+    Invalid(u8, u8),
+}
+
+/// Check the bytes following a boundary.
+pub(crate) fn after_boundary(after: &[u8]) -> AfterBoundary {
+    if after.len() < 2 {
+        return AfterBoundary::Incomplete;
+    }
+
+    // Skip transport padding (RFC 2046, section 5.1.1) before the line end. Clients have
+    // also been seen sending extra dashes after the closing boundary.
+    let is_padding = |b: &u8| matches!(b, b' ' | b'\t');
+
+    if after.starts_with(b"--") {
+        let padding = after[2..]
+            .iter()
+            .take_while(|b| **b == b'-' || is_padding(b))
+            .count();
+
+        let mut len = 2 + padding;
+
+        // the rest is the epilogue, which is ignored anyway
+        if after[len..].starts_with(b"\r\n") {
+            len += 2;
+        }
+
+        return AfterBoundary::Closing(len);
+    }
+
+    let padding = after.iter().take_while(|b| is_padding(b)).count();
+    let line_end = &after[padding..];
+
+    if line_end.starts_with(b"\r\n") {
+        AfterBoundary::Part(padding + 2)
+    } else if line_end.len() < 2 {
+        AfterBoundary::Incomplete
+    } else {
+        AfterBoundary::Invalid(line_end[0], line_end[1])
+    }
+}
+
+/// Find the boundary occurrence or the highest length to safely yield
+///
+/// Only the positions of the first byte of `boundary` (`-`, or CR for the delimiter in strict
+/// mode) are compared against it, which `memchr` finds far faster than a bytewise scan. The
+/// first candidate which is cut off by the end of `buf` but matches so far may be the start of
+/// a boundary split across two fills, so nothing from it onwards is yielded yet.
+pub(crate) fn find_boundary(buf: &[u8], boundary: &[u8]) -> Result<usize, usize> {
+    let mut pos = 0;
+
+    while let Some(idx) = memchr::memchr(boundary[0], &buf[pos..]) {
+        let candidate = &buf[pos + idx..];
+
+        if candidate.starts_with(boundary) {
+            return Ok(pos + idx);
+        }
+
+        if boundary.starts_with(candidate) {
+            return Err(pos + idx);
+        }
+
+        pos += idx + 1;
+    }
+
+    Err(buf.len())
+}
+
+/// Find a boundary occurrence which is delimited exactly per RFC 2046, or the highest length to
+/// safely yield.
+///
+/// `delimiter` is `boundary` with the preceding CRLF. If `at_start` is set the boundary may
+/// also start at index 0 without a CRLF.
+pub(crate) fn find_delimiter(
+    buf: &[u8],
+    boundary: &[u8],
+    delimiter: &[u8],
+    at_start: bool,
+) -> Result<usize, usize> {
+    // the index of the candidate, and the index after it where the CRLF or `--` should be
+    let mut candidate = if at_start && buf.starts_with(boundary) {
+        Ok((0, boundary.len()))
+    } else if at_start && !buf.is_empty() && boundary.starts_with(buf) {
+        // the opening boundary may be cut off by the end of the buffer; don't yield any of it
+        // as preamble, or it won't be recognized without a CRLF afterwards
+        return Err(0);
+    } else {
+        find_boundary(buf, delimiter).map(|idx| (idx, idx + delimiter.len()))
+    };
+
+    loop {
+        let (idx, end) = candidate?;
+
+        match buf.get(end..end + 2) {
+            Some(b"\r\n" | b"--") => return Ok(idx),
+            // not enough data to tell yet
+            None => return Err(idx),
+            // something that looks like the boundary but isn't delimited properly; skip it
+            Some(_) => (),
+        }
+
+        let next = idx + 1;
+        candidate = find_boundary(&buf[next..], delimiter)
+            .map(|idx| (next + idx, next + idx + delimiter.len()))
+            .map_err(|yield_len| next + yield_len);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ParsedPart;
+    use super::{find_boundary, find_delimiter, get_ext_param, parse_multipart, ParseError};
+    use crate::server::field::LineEndingPolicy;
+    use crate::server::Multipart;
+
+    use std::io::Read;
+
+    fn parse(body: &[u8]) -> Result<Vec<ParsedPart<'_>>, ParseError> {
+        parse_multipart(body, b"boundary").collect()
+    }
+
+    #[test]
+    fn test_line_ending_policy() {
+        let body = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"a\"\n\
+            Content-Type: text/plain\r\n\r\n\
+            value\r\n\
+            --boundary--";
+
+        let parts = parse(body).unwrap();
+        assert_eq!(parts[0].name, "a");
+        assert_eq!(parts[0].content_type(), Some("text/plain"));
+
+        let strict = parse_multipart(body, b"boundary")
+            .line_ending_policy(LineEndingPolicy::Strict)
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(strict, Err(ParseError::MixedLineEndings));
+    }
+
+    #[test]
+    fn test_find_boundary() {
+        let find = |buf: &[u8]| find_boundary(buf, b"--boundary");
+
+        assert_eq!(find(b"data--boundary"), Ok(4));
+        // candidates which don't match are skipped
+        assert_eq!(find(b"-- --bound--boundary--"), Ok(10));
+        assert_eq!(find(b"------boundary"), Ok(4));
+        // a boundary which may continue in the next fill is held back
+        assert_eq!(find(b"data--bound"), Err(4));
+        assert_eq!(find(b"data-"), Err(4));
+        assert_eq!(find(b"data--bounds--b"), Err(12));
+        assert_eq!(find(b"no dashes"), Err(9));
+        assert_eq!(find(b""), Err(0));
+    }
+
+    #[test]
+    fn test_find_delimiter() {
+        let find =
+            |buf: &[u8], at_start| find_delimiter(buf, b"--boundary", b"\r\n--boundary", at_start);
+
+        // only the opening boundary may lack the preceding CRLF
+        assert_eq!(find(b"--boundary\r\n", true), Ok(0));
+        assert_eq!(find(b"--boundary\r\n", false), Err(10));
+        assert_eq!(find(b"data--boundary\r\n", true), Err(14));
+        assert_eq!(find(b"data\r\n--boundary\r\n", false), Ok(4));
+        assert_eq!(find(b"data\r\n--boundary--", true), Ok(4));
+        // not enough data to tell yet
+        assert_eq!(find(b"--boundary", true), Err(0));
+        assert_eq!(find(b"--bound", true), Err(0));
+        assert_eq!(find(b"data\r\n--bound", false), Err(4));
+    }
+
+    #[test]
+    fn test_parse_multipart() {
+        let body = b"preamble\r\n--boundary\r\n\
+            Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+            data\r\n\
+            --boundary\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\
+            content-type: text/plain\r\n\
+            X-Extra:   value  \r\n\r\n\
+            file\r\ndata\r\n\r\n\
+            --boundary--\r\nepilogue";
+
+        let mut parts = parse_multipart(body, b"boundary");
+
+        let field = parts.next().unwrap().unwrap();
+        assert_eq!(field.name, "field");
+        assert_eq!(field.filename, None);
+        assert_eq!(field.content_type(), None);
+        assert_eq!(field.body, b"data");
+
+        let file = parts.next().unwrap().unwrap();
+        assert_eq!(file.name, "file");
+        assert_eq!(file.filename, Some("file.txt"));
+        assert_eq!(file.content_type(), Some("text/plain"));
+        assert_eq!(file.headers.get("x-extra"), Some("value"));
+        assert_eq!(file.headers.iter().count(), 3);
+        assert_eq!(file.body, b"file\r\ndata\r\n");
+
+        assert!(parts.next().is_none());
+        assert_eq!(parts.remaining(), b"epilogue");
+    }
+
+    #[test]
+    fn test_ext_param() {
+        let ext = |params| get_ext_param(params, "filename");
+
+        assert_eq!(
+            ext("; filename=\"a.txt\"; filename*=UTF-8''%C3%9F%20%22.txt").as_deref(),
+            Some("ß \".txt")
+        );
+        assert_eq!(
+            ext("; FILENAME*=\"iso-8859-1'en'caf%E9.txt\"").as_deref(),
+            Some("café.txt")
+        );
+        // RFC 2231 continuations, in any order
+        assert_eq!(
+            ext("; filename*1=\"part \"; filename*0*=utf-8''%C3%9F; filename*2*=%C3%9F").as_deref(),
+            Some("ßpart ß")
+        );
+
+        // fall back to the plain parameter
+        assert_eq!(ext("; filename=\"a.txt\""), None);
+        assert_eq!(ext("; filename*=UTF-8''%FF"), None);
+        assert_eq!(ext("; filename*=UTF-8''%F"), None);
+        assert_eq!(ext("; filename*=koi8-r''%C1"), None);
+        assert_eq!(ext("; filename*=no-quotes"), None);
+        assert_eq!(ext("; filename*0=a; filename*2=c"), None);
+
+        let body = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"; \
+            filename*=UTF-8''%C3%9F.txt\r\n\r\n\
+            \r\n\
+            --boundary\r\n\
+            Content-Disposition: form-data; name=\"plain\"; filename=\"b.txt\"\r\n\r\n\
+            \r\n\
+            --boundary--";
+        let parts = parse(body).unwrap();
+
+        assert_eq!(parts[0].filename, Some("a.txt"));
+        assert_eq!(parts[0].decoded_filename().as_deref(), Some("ß.txt"));
+        assert_eq!(parts[1].decoded_filename().as_deref(), Some("b.txt"));
+    }
+
+    #[test]
+    fn test_parse_lf() {
+        // headers may end in bare LF, and LFs before the boundary's CRLF are data
+        let parts = parse(
+            b"--boundary\r\n\
+              Content-Disposition: form-data; name=\"field\"\n\n\
+              data\n\n\r\n\
+              --boundary--",
+        )
+        .unwrap();
+
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].name, "field");
+        assert_eq!(parts[0].body, b"data\n\n");
+    }
+
+    #[test]
+    fn test_parse_no_parts() {
+        assert_eq!(parse(b""), Ok(vec![]));
+        assert_eq!(parse(b"--boundary--"), Ok(vec![]));
+        assert_eq!(parse(b"preamble\r\n--boundary--\r\n"), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let field = |rest: &str| {
+            format!("--boundary\r\nContent-Disposition: form-data; name=\"field\"\r\n\r\n{rest}")
+        };
+
+        // no boundary at all
+        assert_eq!(parse(b"data"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parse(b"--boundary"), Err(ParseError::UnexpectedEnd));
+        // truncated
+        assert_eq!(
+            parse(field("data").as_bytes()),
+            Err(ParseError::UnexpectedEnd)
+        );
+        assert_eq!(
+            parse(field("data\r\n--boundary\x00\x00").as_bytes()),
+            Err(ParseError::InvalidBoundary(0, 0))
+        );
+        assert_eq!(
+            parse(b"--boundary\r\nContent-Type: text/plain\r\n\r\n\r\n--boundary--"),
+            Err(ParseError::MissingContentDisposition)
+        );
+        assert_eq!(
+            parse(b"--boundary\r\nContent-Disposition: attachment\r\n\r\n\r\n--boundary--"),
+            Err(ParseError::InvalidContentDisposition(
+                "expected additional data after Content-Disposition type"
+            ))
+        );
+        assert_eq!(
+            parse(b"--boundary\r\nContent-Disposition: form-data; name=a\r\n--boundary--"),
+            Err(ParseError::IncompleteHeaders)
+        );
+
+        // the parts before the error are still yielded, but nothing after it
+        let body = field("data\r\n--boundary\r\nbad\r\n--boundary--");
+        let mut parts = parse_multipart(body.as_bytes(), b"boundary");
+        assert_eq!(parts.next().unwrap().unwrap().body, b"data");
+        assert!(parts.next().unwrap().is_err());
+        assert!(parts.next().is_none());
+    }
+
+    #[test]
+    fn test_parse_strict() {
+        let body = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+            data--boundary data\r\n--boundary-ish\r\n\
+            --boundary--";
+
+        let parts: Vec<_> = parse_multipart(body, b"boundary")
+            .strict(true)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].body, b"data--boundary data\r\n--boundary-ish");
+
+        assert!(parse(body).is_err());
+    }
+
+    #[test]
+    fn test_parse_same_as_multipart() {
+        let bodies: &[&[u8]] = &[
+            b"--boundary\r\n\
+              Content-Disposition: form-data; name=\"a\"\r\n\r\n\
+              1\r\n\
+              --boundary \t\r\n\
+              Content-Disposition: form-data; name=b; filename=\"b.txt\"\r\n\r\n\
+              \r\n2\r\n\r\n\
+              --boundary--",
+            b"\r\n\r\n--boundary\r\n\
+              Content-Disposition: form-data; name=\"a\"\n\n\
+              1\n\n\r\n\
+              --boundary----  \r\n",
+            b"--boundary\r\n\
+              Content-Disposition: form-data; name=\"a\"\r\n\r\n\
+              inline--boundary\r\n\
+              Content-Disposition: form-data; name=\"b\"\r\n\r\n\
+              \r\n--boundary--",
+        ];
+
+        for body in bodies {
+            let parsed: Vec<_> = parse(body)
+                .unwrap()
+                .into_iter()
+                .map(|part| (part.name.to_owned(), part.body.to_vec()))
+                .collect();
+
+            let mut multipart = Multipart::with_body(*body, "boundary");
+            let mut read = Vec::new();
+
+            while let Some(mut field) = multipart.read_entry().unwrap() {
+                let mut data = Vec::new();
+                field.data.read_to_end(&mut data).unwrap();
+                read.push((field.headers.name.to_string(), data));
+            }
+
+            assert_eq!(parsed, read, "{:?}", String::from_utf8_lossy(body));
+        }
+    }
+}
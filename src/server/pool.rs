@@ -0,0 +1,169 @@
+//! Reuse of read buffers across requests.
+use buf_redux::Buffer;
+
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// A pool of read buffers to be reused by `Multipart` instances created with
+/// `Multipart::with_body_pooled()`, to save allocating a new buffer for every request.
+///
+/// A buffer is taken from the pool when the `Multipart` is created and returned to it,
+/// cleared, when the `Multipart` is released with `Multipart::release()` or unwrapped with
+/// `into_inner()`. A `Multipart` which is simply dropped deallocates its buffer instead.
+/// If the pool is empty, a new buffer is allocated.
+///
+/// Cloning the pool is cheap and the clones share the same buffers, so one can be kept
+/// in shared server state and used from any thread.
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct MultipartPool {
+    free: Arc<Mutex<Vec<Buffer>>>,
+    max_buffers: usize,
+}
+
+impl MultipartPool {
+    /// Create an empty pool which keeps at most `max_buffers` unused buffers around;
+    /// any others are deallocated when returned.
+    #[must_use]
+    pub fn new(max_buffers: usize) -> Self {
+        MultipartPool {
+            free: Arc::new(Mutex::new(Vec::new())),
+            max_buffers,
+        }
+    }
+
+    /// The number of unused buffers currently in the pool.
+    #[must_use]
+    pub fn available(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub(crate) fn take(&self) -> Buffer {
+        self.lock().pop().unwrap_or_else(Buffer::new)
+    }
+
+    pub(crate) fn put(&self, mut buffer: Buffer) {
+        // a new reader would otherwise see the leftover data of the previous request
+        buffer.clear();
+
+        let mut free = self.lock();
+
+        if free.len() < self.max_buffers {
+            free.push(buffer);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Buffer>> {
+        // the buffers are cleared on the way in so there's no state to be corrupted
+        self.free.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for MultipartPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultipartPool")
+            .field("available", &self.available())
+            .field("max_buffers", &self.max_buffers)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MultipartPool;
+
+    use crate::server::Multipart;
+
+    use std::io::Read;
+
+    #[test]
+    fn test_buffer_reuse() {
+        let pool = MultipartPool::new(1);
+
+        let mut first = Multipart::with_body_pooled(
+            &b"--boundary\r\n\
+               Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+               secret\r\n\
+               --boundary--"[..],
+            "boundary",
+            &pool,
+        );
+
+        // leave the field data unread in the buffer
+        assert_eq!(&*first.read_entry().unwrap().unwrap().headers.name, "first");
+        assert_eq!(pool.available(), 0);
+
+        first.release();
+        assert_eq!(pool.available(), 1);
+
+        let mut second = Multipart::with_body_pooled(
+            &b"--boundary\r\n\
+               Content-Disposition: form-data; name=\"second\"\r\n\r\n\
+               public\r\n\
+               --boundary--"[..],
+            "boundary",
+            &pool,
+        );
+        assert_eq!(pool.available(), 0);
+
+        let mut field = second.read_entry().unwrap().unwrap();
+        assert_eq!(&*field.headers.name, "second");
+
+        let mut data = String::new();
+        field.data.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "public");
+        assert!(second.read_entry().unwrap().is_none());
+
+        // the body is returned from `into_inner()` but the buffer goes back to the pool
+        let rest = second.into_inner();
+        assert!(rest.is_empty());
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[test]
+    fn test_max_buffers() {
+        let pool = MultipartPool::new(1);
+
+        let first = Multipart::with_body_pooled(&b""[..], "boundary", &pool);
+        let second = Multipart::with_body_pooled(&b""[..], "boundary", &pool);
+
+        first.release();
+        second.release();
+        assert_eq!(pool.available(), 1);
+
+        // dropping without `release()` doesn't return the buffer
+        let pool = MultipartPool::new(1);
+        drop(Multipart::with_body_pooled(&b""[..], "boundary", &pool));
+        assert_eq!(pool.available(), 0);
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod bench {
+    use super::MultipartPool;
+
+    use crate::server::Multipart;
+
+    use test::Bencher;
+
+    const BODY: &[u8] = b"--boundary\r\n\
+        Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+        value\r\n\
+        --boundary--";
+
+    fn parse(mut multipart: Multipart<&[u8]>) {
+        multipart.foreach_entry(|_| ()).unwrap();
+        multipart.release();
+    }
+
+    #[bench]
+    fn bench_unpooled(b: &mut Bencher) {
+        b.iter(|| parse(Multipart::with_body(BODY, "boundary")));
+    }
+
+    #[bench]
+    fn bench_pooled(b: &mut Bencher) {
+        let pool = MultipartPool::new(1);
+        b.iter(|| parse(Multipart::with_body_pooled(BODY, "boundary", &pool)));
+    }
+}
@@ -0,0 +1,438 @@
+//! Parsing of a body fed in chunks, for servers which can't provide a blocking `Read`.
+//!
+//! ```rust
+//! use mpart::server::push::{PushEvent, PushParser};
+//!
+//! let mut parser = PushParser::new("boundary");
+//! let mut data = Vec::new();
+//!
+//! for chunk in [
+//!     &b"--boundary\r\nContent-Disposition: form-data; name=\"te"[..],
+//!     b"xt\"\r\n\r\nsome ",
+//!     b"text\r\n--boundary--",
+//! ] {
+//!     for event in parser.feed(chunk) {
+//!         match event.unwrap() {
+//!             PushEvent::FieldStart(headers) => assert_eq!(&*headers.name, "text"),
+//!             PushEvent::FieldData(bytes) => data.extend_from_slice(bytes),
+//!             PushEvent::FieldEnd | PushEvent::Done => (),
+//!         }
+//!     }
+//! }
+//!
+//! parser.finish().unwrap();
+//! assert_eq!(data, b"some text");
+//! ```
+use std::io;
+
+use super::error::{malformed_boundary, truncated};
+use super::field::{
+    headers_too_large, FieldHeaders, FilenameLimit, LineEndingPolicy, MAX_HEADERS_LEN,
+};
+use super::parse::{after_boundary, find_boundary, AfterBoundary};
+
+/// A `multipart/form-data` parser which is fed the body in chunks of any size and reports
+/// what it found in them as `PushEvent`s.
+///
+/// This follows the same rules as `Multipart`: the preamble and epilogue are skipped and the
+/// boundary is recognized anywhere. Only the part of the input which may still turn out to be
+/// a boundary or the headers of a field is buffered; field data is passed on as soon as
+/// possible, borrowed from the buffer.
+#[derive(Debug)]
+pub struct PushParser {
+    buf: Vec<u8>,
+    state: State,
+}
+
+#[derive(Debug)]
+struct State {
+    /// The boundary with its leading `--`.
+    boundary: Vec<u8>,
+    /// The start of the input not parsed yet in `buf`.
+    pos: usize,
+    line_ending_policy: LineEndingPolicy,
+    /// The total number of bytes fed so far.
+    fed: u64,
+    stage: Stage,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    Preamble,
+    Headers,
+    Data,
+    /// The closing boundary was found but `PushEvent::Done` not yet returned.
+    Closed,
+    Done,
+    Failed,
+}
+
+impl PushParser {
+    /// Create a parser for a body with the given boundary, without the leading `--`.
+    pub fn new<B: Into<Vec<u8>>>(boundary: B) -> Self {
+        let mut boundary = boundary.into();
+        safemem::prepend(b"--", &mut boundary);
+
+        PushParser {
+            buf: Vec::new(),
+            state: State {
+                boundary,
+                pos: 0,
+                line_ending_policy: LineEndingPolicy::default(),
+                fed: 0,
+                stage: Stage::Preamble,
+            },
+        }
+    }
+
+    /// Set what to do with field headers mixing CRLF and LF line endings, as with
+    /// `Multipart::set_line_ending_policy()`. The default is `LineEndingPolicy::Lenient`.
+    pub fn set_line_ending_policy(&mut self, policy: LineEndingPolicy) {
+        self.state.line_ending_policy = policy;
+    }
+
+    /// Add the next chunk of the body and iterate over the events found so far.
+    ///
+    /// Events which are not taken from the iterator are returned by the next call instead,
+    /// so dropping it early loses nothing. After an error, no more events are returned.
+    pub fn feed(&mut self, chunk: &[u8]) -> PushEvents<'_> {
+        self.buf.drain(..self.state.pos);
+        self.state.pos = 0;
+
+        self.buf.extend_from_slice(chunk);
+        self.state.fed += chunk.len() as u64;
+
+        PushEvents {
+            buf: &self.buf,
+            state: &mut self.state,
+        }
+    }
+
+    /// Signal the end of the body.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` of kind `io::ErrorKind::UnexpectedEof` unless the closing boundary
+    /// was found or the body was completely empty, as for `Multipart::read_entry()`.
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self.state.stage {
+            Stage::Closed | Stage::Done => Ok(()),
+            Stage::Preamble if self.state.fed == 0 => Ok(()),
+            _ => Err(truncated("unexpected end of request body")),
+        }
+    }
+
+    /// Whether the closing boundary has been found.
+    pub fn is_done(&self) -> bool {
+        matches!(self.state.stage, Stage::Closed | Stage::Done)
+    }
+}
+
+/// Something found in the body by `PushParser`.
+#[derive(Debug)]
+pub enum PushEvent<'a> {
+    /// A field starts with these headers, boxed as they are much larger than the other events.
+    FieldStart(Box<FieldHeaders>),
+    /// The next piece of the data of the current field.
+    FieldData(&'a [u8]),
+    /// The current field has ended.
+    FieldEnd,
+    /// The closing boundary was found; nothing else follows.
+    Done,
+}
+
+/// The iterator over the events found in the input so far, returned by `PushParser::feed()`.
+#[derive(Debug)]
+pub struct PushEvents<'a> {
+    buf: &'a [u8],
+    state: &'a mut State,
+}
+
+impl<'a> PushEvents<'a> {
+    /// The next event, or `None` if more input is needed.
+    fn next_event(&mut self) -> io::Result<Option<PushEvent<'a>>> {
+        let buf: &'a [u8] = self.buf;
+        let state = &mut *self.state;
+
+        loop {
+            let rest = &buf[state.pos..];
+
+            match state.stage {
+                Stage::Preamble => match find_boundary(rest, &state.boundary) {
+                    Ok(idx) => {
+                        state.pos += idx;
+
+                        if !state.consume_boundary(buf)? {
+                            return Ok(None);
+                        }
+                    }
+                    Err(skip) => {
+                        log::debug!("Discarding {} bytes of preamble", skip);
+                        state.pos += skip;
+                        return Ok(None);
+                    }
+                },
+                Stage::Headers => {
+                    let mut warnings = Vec::new();
+
+                    match FieldHeaders::parse_slice(
+                        rest,
+                        FilenameLimit::default(),
+                        state.line_ending_policy,
+                        &mut warnings,
+                    )? {
+                        Some((headers, len)) => {
+                            state.pos += len;
+                            state.stage = Stage::Data;
+                            return Ok(Some(PushEvent::FieldStart(Box::new(headers))));
+                        }
+                        None if rest.len() > MAX_HEADERS_LEN => return Err(headers_too_large()),
+                        None => return Ok(None),
+                    }
+                }
+                Stage::Data => {
+                    let (data_len, found) = match find_boundary(rest, &state.boundary) {
+                        Ok(idx) => (idx, true),
+                        Err(len) => (len, false),
+                    };
+
+                    // the CRLF preceding the boundary belongs to it, so hold back a CRLF
+                    // (or CR) at the end in case the boundary follows in the next chunk
+                    let data = &rest[..data_len];
+                    let data = data
+                        .strip_suffix(b"\r\n")
+                        .or_else(|| data.strip_suffix(b"\r").filter(|_| !found))
+                        .unwrap_or(data);
+
+                    if !data.is_empty() {
+                        state.pos += data.len();
+                        return Ok(Some(PushEvent::FieldData(data)));
+                    }
+
+                    if !found {
+                        return Ok(None);
+                    }
+
+                    state.pos += data_len;
+
+                    if !state.consume_boundary(buf)? {
+                        // back up to the CRLF so it's still held back next time
+                        state.pos -= rest[..data_len].len() - data.len();
+                        return Ok(None);
+                    }
+
+                    return Ok(Some(PushEvent::FieldEnd));
+                }
+                Stage::Closed => {
+                    state.stage = Stage::Done;
+                    return Ok(Some(PushEvent::Done));
+                }
+                Stage::Done | Stage::Failed => {
+                    // nothing is parsed anymore, so don't keep it around
+                    state.pos = buf.len();
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+impl State {
+    /// Consume the boundary at `pos` in `buf` and what follows it, moving on to the next stage.
+    ///
+    /// Returns `false` if more input is needed to tell what follows it.
+    fn consume_boundary(&mut self, buf: &[u8]) -> io::Result<bool> {
+        let after = self.pos + self.boundary.len();
+
+        match after_boundary(&buf[after..]) {
+            AfterBoundary::Part(len) => {
+                self.pos = after + len;
+                self.stage = Stage::Headers;
+            }
+            AfterBoundary::Closing(len) => {
+                // the rest is the epilogue
+                self.pos = after + len;
+                self.stage = Stage::Closed;
+            }
+            AfterBoundary::Incomplete => return Ok(false),
+            AfterBoundary::Invalid(first, second) => {
+                return Err(malformed_boundary(format!(
+                    "unexpected bytes following multipart boundary: {:X} {:X}",
+                    first, second
+                )));
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl<'a> Iterator for PushEvents<'a> {
+    type Item = io::Result<PushEvent<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.next_event();
+
+        if res.is_err() {
+            self.state.stage = Stage::Failed;
+        }
+
+        res.transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PushEvent, PushParser};
+    use crate::server::field::LineEndingPolicy;
+
+    use std::io;
+
+    #[derive(Debug, PartialEq)]
+    enum Owned {
+        Start(String, Option<String>),
+        Data(Vec<u8>),
+        End,
+        Done,
+    }
+
+    /// Feed `body` in chunks of `chunk_size`, merging adjacent data events.
+    fn parse_chunked(body: &[u8], chunk_size: usize) -> io::Result<Vec<Owned>> {
+        let mut parser = PushParser::new("boundary");
+        let mut events = Vec::new();
+
+        for chunk in body.chunks(chunk_size) {
+            for event in parser.feed(chunk) {
+                match event? {
+                    PushEvent::FieldStart(headers) => {
+                        events.push(Owned::Start(headers.name.to_string(), headers.filename))
+                    }
+                    PushEvent::FieldData(data) => {
+                        assert!(!data.is_empty());
+
+                        if let Some(Owned::Data(prev)) = events.last_mut() {
+                            prev.extend_from_slice(data);
+                        } else {
+                            events.push(Owned::Data(data.to_vec()));
+                        }
+                    }
+                    PushEvent::FieldEnd => events.push(Owned::End),
+                    PushEvent::Done => events.push(Owned::Done),
+                }
+            }
+        }
+
+        parser.finish()?;
+        Ok(events)
+    }
+
+    #[test]
+    fn test_line_ending_policy() {
+        let body = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"a\"\n\
+            Content-Type: text/plain\r\n\r\n\
+            value\r\n\
+            --boundary--";
+
+        let parse = |policy| {
+            let mut parser = PushParser::new("boundary");
+            parser.set_line_ending_policy(policy);
+            parser
+                .feed(body)
+                .collect::<io::Result<Vec<_>>>()
+                .map(|events| events.len())
+        };
+
+        assert_eq!(parse(LineEndingPolicy::Lenient).unwrap(), 4);
+
+        let err = parse(LineEndingPolicy::Strict).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("mix CRLF and LF"), "{err}");
+    }
+
+    #[test]
+    fn test_small_chunks() {
+        let body = b"preamble --bound\r\n\
+            --boundary\r\n\
+            Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+            text with \r\n--bound and \r\r\n and a trailing CR\r\r\n\
+            --boundary\r\n\
+            Content-Disposition: form-data; name=\"empty\"\r\n\r\n\
+            \r\n\
+            --boundary\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\
+            Content-Type: application/octet-stream\r\n\r\n\
+            \x00\xff\r\n\r\n\
+            --boundary--\r\n\
+            epilogue --boundary";
+
+        let expected = [
+            Owned::Start("text".into(), None),
+            Owned::Data(b"text with \r\n--bound and \r\r\n and a trailing CR\r".to_vec()),
+            Owned::End,
+            Owned::Start("empty".into(), None),
+            Owned::End,
+            Owned::Start("file".into(), Some("file.bin".into())),
+            Owned::Data(b"\x00\xff\r\n".to_vec()),
+            Owned::End,
+            Owned::Done,
+        ];
+
+        for chunk_size in 1..=body.len() {
+            assert_eq!(
+                parse_chunked(body, chunk_size).unwrap(),
+                expected,
+                "chunk size {chunk_size}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_events_kept_for_next_feed() {
+        let mut parser = PushParser::new("boundary");
+
+        let first = parser
+            .feed(b"--boundary\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\na\r\n--boundary--")
+            .next();
+        assert!(matches!(first, Some(Ok(PushEvent::FieldStart(_)))));
+
+        // the rest of the events weren't taken
+        let rest: Vec<_> = parser.feed(b"").map(Result::unwrap).collect();
+        assert!(matches!(
+            rest[..],
+            [
+                PushEvent::FieldData(b"a"),
+                PushEvent::FieldEnd,
+                PushEvent::Done
+            ]
+        ));
+        assert!(parser.is_done());
+    }
+
+    #[test]
+    fn test_errors() {
+        // an empty body has no fields
+        assert_eq!(parse_chunked(b"", 1).unwrap(), []);
+
+        let truncated = b"--boundary\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\ndata";
+        for chunk_size in [1, truncated.len()] {
+            let err = parse_chunked(truncated, chunk_size).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        }
+
+        let no_name = b"--boundary\r\nContent-Disposition: form-data\r\n\r\n\r\n--boundary--";
+        let err = parse_chunked(no_name, 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let invalid = b"--boundary\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n\
+            \r\n--boundaryXY";
+        let err = parse_chunked(invalid, 3).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // nothing more after an error
+        let mut parser = PushParser::new("boundary");
+        assert!(parser.feed(invalid).any(|event| event.is_err()));
+        assert!(parser.feed(b"--boundary--").next().is_none());
+        assert!(parser.finish().is_err());
+    }
+}
@@ -0,0 +1,116 @@
+//! A wrapper for request bodies whose reads may return `Ok(0)` before the end of the body.
+use std::io::{self, Read};
+
+/// A `Read` adapter which retries a read returning `Ok(0)` up to a given number of times before
+/// passing it on as the end of the stream.
+///
+/// `Multipart` follows the contract of `Read` and takes `Ok(0)` as the end of the body, so a
+/// reader which returns it spuriously, e.g. when no data has arrived yet, cuts the request short
+/// with an `io::ErrorKind::UnexpectedEof` error. Wrap such a reader in this before passing it to
+/// `Multipart::with_body()`.
+///
+/// Retries are counted per read, and happen immediately; a reader which returns `Ok(0)` until
+/// data arrives should block or return `io::ErrorKind::WouldBlock` instead.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct RetryReader<R> {
+    inner: R,
+    max_retries: u32,
+}
+
+impl<R: Read> RetryReader<R> {
+    /// Wrap `inner`, retrying a read which returns `Ok(0)` up to `max_retries` times.
+    pub fn new(inner: R, max_retries: u32) -> Self {
+        RetryReader { inner, max_retries }
+    }
+
+    /// Unwrap the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for RetryReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // `Ok(0)` is the only possible result for an empty buffer
+        if buf.is_empty() {
+            return self.inner.read(buf);
+        }
+
+        for _ in 0..self.max_retries {
+            match self.inner.read(buf) {
+                Ok(0) => continue,
+                res => return res,
+            }
+        }
+
+        self.inner.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RetryReader;
+
+    use crate::server::Multipart;
+
+    use std::io::{self, Read};
+
+    const BODY: &[u8] = b"--boundary\r\n\
+        Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+        data\r\n\
+        --boundary--";
+
+    /// Returns `Ok(0)` once before each read of real data.
+    struct HiccupReader {
+        data: &'static [u8],
+        hiccup: bool,
+    }
+
+    impl Read for HiccupReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.hiccup = !self.hiccup;
+
+            if self.hiccup {
+                return Ok(0);
+            }
+
+            let len = buf.len().min(8);
+            self.data.read(&mut buf[..len])
+        }
+    }
+
+    fn hiccups() -> HiccupReader {
+        HiccupReader {
+            data: BODY,
+            hiccup: false,
+        }
+    }
+
+    #[test]
+    fn test_spurious_eof() {
+        let mut multipart = Multipart::with_body(hiccups(), "boundary");
+        assert_eq!(
+            multipart.read_entry().err().unwrap().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_retry() {
+        let mut multipart = Multipart::with_body(RetryReader::new(hiccups(), 1), "boundary");
+
+        let mut field = multipart.read_entry().unwrap().unwrap();
+        assert_eq!(&*field.headers.name, "field");
+
+        let mut data = String::new();
+        field.data.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "data");
+
+        assert!(multipart.read_entry().unwrap().is_none());
+
+        // a genuine EOF is still passed on
+        let mut reader = RetryReader::new(&b""[..], 3);
+        assert_eq!(reader.read(&mut [0; 4]).unwrap(), 0);
+    }
+}
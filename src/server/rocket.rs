@@ -0,0 +1,208 @@
+//! Server-side integration with [Rocket](https://rocket.rs) 0.5.
+//! Enabled with the `rocket` feature.
+//!
+//! `MultipartForm` is a data guard which saves all fields of a `multipart/form-data` request
+//! with the same machinery as `Multipart::save()`. Requests with any other `Content-Type` are
+//! forwarded with `415 Unsupported Media Type`.
+//!
+//! The body is read into memory up to the `data-form` limit of the request (2 MiB by default)
+//! before parsing begins; bodies exceeding it fail with `413 Payload Too Large`. The fields are
+//! saved with the `SaveConfig` in Rocket's managed state, if any, or the default one:
+//!
+//! ```rust,no_run
+//! use mpart::server::rocket::MultipartForm;
+//! use mpart::server::save::SaveConfig;
+//!
+//! #[rocket::post("/upload", data = "<form>")]
+//! fn upload(form: MultipartForm) -> String {
+//!     format!("received {} fields", form.fields_count())
+//! }
+//!
+//! # fn main() {
+//! let rocket = rocket::build()
+//!     .manage(SaveConfig::new().size_limit(16 * 1024 * 1024).count_limit(16))
+//!     .mount("/", rocket::routes![upload]);
+//! # let _ = rocket;
+//! # }
+//! ```
+use std::io::{self, Cursor};
+use std::ops::{Deref, DerefMut};
+
+use rocket::data::{self, Data, FromData, Limits};
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::Request;
+
+use super::save::PartialReason;
+use super::save::SaveConfig;
+use super::save::SaveResult::{Error, Full, Partial};
+use super::{Entries, Multipart};
+
+/// A data guard which saves the fields of a `multipart/form-data` request, dereferencing to the
+/// saved `Entries`.
+///
+/// Fails with the `io::Error` which stopped saving, with `400 Bad Request` for a malformed
+/// request, `413 Payload Too Large` for exceeding a limit and `500 Internal Server Error`
+/// otherwise.
+#[derive(Debug)]
+pub struct MultipartForm {
+    entries: Entries,
+}
+
+impl MultipartForm {
+    /// Take the saved `Entries`.
+    #[must_use]
+    pub fn into_entries(self) -> Entries {
+        self.entries
+    }
+}
+
+impl Deref for MultipartForm {
+    type Target = Entries;
+
+    fn deref(&self) -> &Entries {
+        &self.entries
+    }
+}
+
+impl DerefMut for MultipartForm {
+    fn deref_mut(&mut self) -> &mut Entries {
+        &mut self.entries
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromData<'r> for MultipartForm {
+    type Error = io::Error;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let content_type = req.headers().get_one("Content-Type");
+
+        let Some(boundary) = content_type.and_then(super::form_data_boundary) else {
+            return Outcome::Forward((data, Status::UnsupportedMediaType));
+        };
+
+        let boundary = boundary.to_owned();
+        let boundary_quoted = content_type.is_some_and(super::boundary_quoted);
+
+        let limit = req.limits().get("data-form").unwrap_or(Limits::DATA_FORM);
+
+        let body = match data.open(limit).into_bytes().await {
+            Ok(body) if body.is_complete() => body.into_inner(),
+            Ok(_) => {
+                return Outcome::Error((
+                    Status::PayloadTooLarge,
+                    io::Error::other(format!("request body exceeds the limit of {limit}")),
+                ))
+            }
+            Err(e) => return Outcome::Error((Status::BadRequest, e)),
+        };
+
+        let config = req
+            .rocket()
+            .state::<SaveConfig>()
+            .cloned()
+            .unwrap_or_default();
+
+        // saving writes files, so keep it off the async workers
+        let res = rocket::tokio::task::spawn_blocking(move || {
+            let mut multipart = Multipart {
+                boundary_quoted,
+                ..Multipart::with_body(Cursor::new(body), boundary)
+            };
+
+            match multipart.save_with(&config) {
+                Full(entries) => Ok(entries),
+                Partial(_, PartialReason::IoError(e)) | Error(e) => Err((status_of(&e), e)),
+                Partial(
+                    _,
+                    reason @ (PartialReason::CountLimit { .. }
+                    | PartialReason::FieldSizeLimit { .. }
+                    | PartialReason::TotalSizeLimit { .. }),
+                ) => Err((Status::PayloadTooLarge, io::Error::other(reason))),
+                Partial(_, reason) => Err((
+                    Status::BadRequest,
+                    io::Error::new(io::ErrorKind::InvalidData, reason),
+                )),
+            }
+        })
+        .await;
+
+        match res {
+            Ok(Ok(entries)) => Outcome::Success(MultipartForm { entries }),
+            Ok(Err(e)) => Outcome::Error(e),
+            Err(e) => Outcome::Error((Status::InternalServerError, io::Error::other(e))),
+        }
+    }
+}
+
+/// The status to respond with for an error from saving a request.
+fn status_of(e: &io::Error) -> Status {
+    match e.kind() {
+        io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => Status::BadRequest,
+        _ => Status::InternalServerError,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MultipartForm;
+
+    use crate::server::save::SaveConfig;
+
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use rocket::{Build, Rocket};
+
+    const BODY: &str = "--boundary\r\n\
+        Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+        some text\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"file.bin\"\r\n\
+        Content-Type: application/octet-stream\r\n\r\n\
+        file data\r\n\
+        --boundary--";
+
+    #[rocket::post("/upload", data = "<form>")]
+    fn upload(form: MultipartForm) -> String {
+        let text = &form.text_fields_sorted()["text"][0];
+        let file = &form.fields["file"][0];
+
+        format!("{} {} {}", form.fields_count(), text, file.data.size())
+    }
+
+    fn rocket() -> Rocket<Build> {
+        rocket::custom(rocket::Config::debug_default()).mount("/", rocket::routes![upload])
+    }
+
+    fn post(client: &Client, content_type: ContentType) -> (Status, String) {
+        let res = client
+            .post("/upload")
+            .header(content_type)
+            .body(BODY)
+            .dispatch();
+
+        (res.status(), res.into_string().unwrap_or_default())
+    }
+
+    fn form_data() -> ContentType {
+        ContentType::new("multipart", "form-data").with_params(("boundary", "boundary"))
+    }
+
+    #[test]
+    fn test_rocket_data_guard() {
+        let client = Client::tracked(rocket()).unwrap();
+
+        let (status, body) = post(&client, form_data());
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body, "2 some text 9");
+
+        let (status, _) = post(&client, ContentType::Plain);
+        assert_eq!(status, Status::UnsupportedMediaType);
+
+        // the managed config applies
+        let client = Client::tracked(rocket().manage(SaveConfig::new().size_limit(4))).unwrap();
+        let (status, _) = post(&client, form_data());
+        assert_eq!(status, Status::PayloadTooLarge);
+    }
+}
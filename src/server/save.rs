@@ -3,21 +3,37 @@
 pub use buf_redux::BufReader;
 pub use tempfile::TempDir;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{self, File, OpenOptions};
 use std::io::prelude::*;
+use std::ops::ControlFlow;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::{cmp, env, io, mem, str, u32, u64};
+use std::{cmp, env, fmt, io, mem, str};
 use tempfile;
 
+use mime::Mime;
+
+#[cfg(feature = "hashing")]
+use digest::DynDigest;
+
 use crate::server::field::{
-    FieldHeaders, MultipartData, MultipartField, ReadEntry, ReadEntryResult,
+    ChecksumAlgorithm, FieldHeaders, MultipartData, MultipartField, ReadEntry, ReadEntryResult,
 };
 
-use self::PartialReason::{IoError, SizeLimit, Utf8Error};
+#[cfg(feature = "checksums")]
+use crate::server::field::Checksum;
+
+use self::PartialReason::{
+    Cancelled, CountLimit, FieldSizeLimit, IoError, TotalSizeLimit, Utf8Error,
+};
 use self::SaveResult::{Error, Full, Partial};
-use self::TextPolicy::{Force, Ignore};
+use self::Utf8Policy::{Force, Ignore};
+
+mod parallel;
+
+use self::parallel::WritePool;
 
 const RANDOM_FILENAME_LEN: usize = 12;
 
@@ -25,8 +41,61 @@ fn rand_filename() -> String {
     crate::random_alphanumeric(RANDOM_FILENAME_LEN)
 }
 
+/// Where `SaveBuilder` keeps the data of text fields, i.e. ones without a filename, when saving
+/// a whole request. Set with `SaveBuilder::text_policy()`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TextPolicy {
+    /// Keep text fields in memory, up to `size_limit`, regardless of `memory_threshold`.
+    /// Only fields with a filename are written to disk once they exceed `memory_threshold`.
+    #[default]
+    InMemory,
+    /// Treat text fields like any other field, writing them to disk once they exceed
+    /// `memory_threshold`.
+    ToDisk,
+}
+
+/// How `SaveBuilder` normalizes line endings in text fields kept in memory.
+/// Set with `SaveBuilder::normalize_text_newlines()`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NewlineMode {
+    /// Keep line endings as they were sent.
+    #[default]
+    Preserve,
+    /// Convert `\r\n` and lone `\r` to `\n`.
+    ToLf,
+    /// Convert lone `\n` and lone `\r` to `\r\n`.
+    ToCrlf,
+}
+
+impl NewlineMode {
+    fn normalize(self, text: String) -> String {
+        let newline = match self {
+            NewlineMode::Preserve => return text,
+            NewlineMode::ToLf if !text.contains('\r') => return text,
+            NewlineMode::ToLf => "\n",
+            NewlineMode::ToCrlf => "\r\n",
+        };
+
+        let mut normalized = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' => {
+                    chars.next_if_eq(&'\n');
+                    normalized.push_str(newline);
+                }
+                '\n' => normalized.push_str(newline),
+                c => normalized.push(c),
+            }
+        }
+
+        normalized
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-enum TextPolicy {
+enum Utf8Policy {
     /// Attempt to read a text field as text, falling back to binary on error
     Try,
     /// Attempt to read a text field as text, returning any errors
@@ -65,7 +134,14 @@ enum TextPolicy {
 /// By default, small fields (a few kilobytes or smaller) will be read directly to memory
 /// without creating a file. This behavior is controlled by the `memory_threshold()` setter. You can
 /// *roughly* tune the maximum memory a single request uses by tuning
-/// `count_limit * memory_threshold`
+/// `count_limit * memory_threshold`, though this only covers file fields by default:
+///
+/// When saving a whole request, text fields (ones without a filename) are by default
+/// (`TextPolicy::InMemory`) kept in memory up to `size_limit` regardless of `memory_threshold`,
+/// as they are form values rather than uploads, so they may take up to
+/// `count_limit * size_limit`. Set `text_policy(TextPolicy::ToDisk)` to apply
+/// `memory_threshold` to them as well, which you should do if you raise `size_limit` far enough
+/// that holding `count_limit` text fields of that size in memory is a problem.
 ///
 /// If a field appears to contain text data (its content-type is `text/*` or it doesn't declare
 /// one), `SaveBuilder` can read it to a string instead of saving the raw bytes as long as it falls
@@ -85,6 +161,51 @@ enum TextPolicy {
 /// unnecessary work if most fields end up being written to the filesystem, however, but this
 /// can be turned off with `ignore_text()` if it fits the use-case.)
 ///
+/// Browsers send line breaks in text fields as `\r\n`. To convert them, set
+/// `normalize_text_newlines()`; this only applies to fields which end up as
+/// `SavedData::Text`, never to file fields or data saved as bytes or to disk.
+///
+/// ### Cleanup on Error
+/// If an I/O error interrupts a field after its file has been created, the partially written
+/// file is deleted and the field's result is `SaveResult::Error`, so no `SavedData` ever points
+/// to a file which was abandoned halfway. This can be turned off with `cleanup_on_error(false)`,
+/// in which case the `Partial` result carries the path of the partial file and it is up to you
+/// to remove it.
+///
+/// Files which were saved completely are never deleted by the save operation itself;
+/// see `Entries` for how long they live.
+///
+/// ### Anonymous Files
+/// With `anonymous(true)`, fields are saved to files which have no name in the filesystem,
+/// so partially uploaded (or never used) data is never visible to other processes and
+/// disappears by itself when dropped. They are created in the directory the file would otherwise
+/// have been saved in, with `O_TMPFILE` on Linux where supported or else as a file which is
+/// deleted immediately after it is created.
+///
+/// Call `SavedField::materialize()` to give the data a name. On Linux, files created with
+/// `O_TMPFILE` are linked into place without copying; otherwise the data is copied.
+///
+/// ### Parallel Writes
+/// Fields are parsed one after another, so by default the next field isn't read until the
+/// previous one has been written to disk. With `parallel_writes(n)`, fields which exceed
+/// `memory_threshold` are handed off to a pool of `n` threads to be written while the request
+/// continues to be read, which can help with many large files on fast storage.
+///
+/// A field's data is queued in chunks, and reading blocks while there are too many chunks waiting
+/// or no thread is free for the next field, so the memory used stays bounded if the disk can't
+/// keep up. If writing a file fails, the field is removed from `Entries` and the result is
+/// `Partial` with `PartialReason::IoError` once all files are written. This has no effect with
+/// `anonymous(true)` or when saving individual fields.
+///
+/// ### Hashing
+/// With the `hashing` feature, `hash_with()` sets a hash function to compute a digest of each
+/// field as its data is read, in the same pass that saves it. The lowercase hex digest is
+/// recorded in `SavedField::digest` for fields saved as part of `Entries`, or can be retrieved
+/// with `take_digest()` after saving an individual field.
+///
+/// With the `checksums` feature, `verify_checksums(true)` also checks the data of each field
+/// against the checksum the client sent in its headers, if any, in the same pass.
+///
 /// ### Warning: Do **not** trust user input!
 /// It is a serious security risk to create files or directories with paths based on user input.
 /// A malicious user could craft a path which can be used to overwrite important files, such as
@@ -100,11 +221,9 @@ enum TextPolicy {
 #[allow(clippy::module_name_repetitions)]
 pub struct SaveBuilder<S> {
     savable: S,
-    open_opts: OpenOptions,
-    size_limit: u64,
-    count_limit: u32,
-    memory_threshold: u64,
-    text_policy: TextPolicy,
+    config: SaveConfig,
+    hasher: FieldHasher,
+    cancel: Option<Arc<AtomicBool>>,
 }
 
 /// Common methods for whole requests as well as individual fields.
@@ -112,39 +231,38 @@ impl<S> SaveBuilder<S> {
     /// Implementation detail but not problematic to have accessible.
     #[doc(hidden)]
     pub fn new(savable: S) -> SaveBuilder<S> {
-        let mut open_opts = OpenOptions::new();
-        open_opts.write(true).create_new(true);
+        Self::from_config(savable, SaveConfig::default())
+    }
 
+    pub(crate) fn from_config(savable: S, config: SaveConfig) -> SaveBuilder<S> {
         SaveBuilder {
             savable,
-            open_opts,
-            // 8 MiB, on the conservative end compared to most frameworks
-            size_limit: 8 * 1024 * 1024,
-            // Arbitrary, I have no empirical data for this
-            count_limit: 256,
-            // 10KiB, used by Apache Commons
-            // https://commons.apache.org/proper/commons-fileupload/apidocs/org/apache/commons/fileupload/disk/DiskFileItemFactory.html
-            memory_threshold: 10 * 1024,
-            text_policy: TextPolicy::Try,
+            config,
+            hasher: FieldHasher::default(),
+            cancel: None,
+        }
+    }
+
+    fn map_config<F: FnOnce(SaveConfig) -> SaveConfig>(self, map: F) -> Self {
+        SaveBuilder {
+            config: map(self.config),
+            ..self
         }
     }
 
     /// Set the maximum number of bytes to write out *per file*.
     ///
     /// Can be `u64` or `Option<u64>`. If `None` or `u64::MAX`, clears the limit.
-    pub fn size_limit<L: Into<Option<u64>>>(mut self, limit: L) -> Self {
-        self.size_limit = limit.into().unwrap_or(u64::MAX);
-        self
+    pub fn size_limit<L: Into<Option<u64>>>(self, limit: L) -> Self {
+        self.map_config(|config| config.size_limit(limit))
     }
 
     /// Modify the `OpenOptions` used to open any files for writing.
     ///
     /// The `write` flag will be reset to `true` after the closure returns. (It'd be pretty
     /// pointless otherwise, right?)
-    pub fn mod_open_opts<F: FnOnce(&mut OpenOptions)>(mut self, opts_fn: F) -> Self {
-        opts_fn(&mut self.open_opts);
-        self.open_opts.write(true);
-        self
+    pub fn mod_open_opts<F: FnOnce(&mut OpenOptions)>(self, opts_fn: F) -> Self {
+        self.map_config(|config| config.mod_open_opts(opts_fn))
     }
 
     /// Set the threshold at which to switch from copying a field into memory to copying
@@ -153,10 +271,7 @@ impl<S> SaveBuilder<S> {
     /// If `0`, forces fields to save directly to the filesystem.
     /// If `u64::MAX`, effectively forces fields to always save to memory.
     pub fn memory_threshold(self, memory_threshold: u64) -> Self {
-        Self {
-            memory_threshold,
-            ..self
-        }
+        self.map_config(|config| config.memory_threshold(memory_threshold))
     }
 
     /// When encountering a field that is apparently text, try to read it to a string or fall
@@ -167,10 +282,7 @@ impl<S> SaveBuilder<S> {
     ///
     /// Has no effect once `memory_threshold` has been reached.
     pub fn try_text(self) -> Self {
-        Self {
-            text_policy: TextPolicy::Try,
-            ..self
-        }
+        self.map_config(SaveConfig::try_text)
     }
 
     /// When encountering a field that is apparently text, read it to a string or return an error.
@@ -180,19 +292,74 @@ impl<S> SaveBuilder<S> {
     ///
     /// (RFC: should this continue to validate UTF-8 when writing to the filesystem?)
     pub fn force_text(self) -> Self {
-        Self {
-            text_policy: TextPolicy::Force,
-            ..self
-        }
+        self.map_config(SaveConfig::force_text)
     }
 
     /// Don't try to read or validate any field data as UTF-8.
     pub fn ignore_text(self) -> Self {
+        self.map_config(SaveConfig::ignore_text)
+    }
+
+    /// Set how to normalize line endings in text fields kept in memory.
+    /// Defaults to `NewlineMode::Preserve`.
+    ///
+    /// Only applies to data saved as `SavedData::Text`; when saving a whole request, file fields
+    /// are left untouched even if they are read as text. The whole value is normalized at once
+    /// after it has been read, so a `\r\n` pair is never split across reads.
+    pub fn normalize_text_newlines(self, newline_mode: NewlineMode) -> Self {
+        self.map_config(|config| config.normalize_text_newlines(newline_mode))
+    }
+
+    /// Set whether to delete a partially written file when an I/O error interrupts saving
+    /// a field. Defaults to `true`.
+    ///
+    /// See the "Cleanup on Error" section in the type-level docs for more info.
+    pub fn cleanup_on_error(self, cleanup_on_error: bool) -> Self {
+        self.map_config(|config| config.cleanup_on_error(cleanup_on_error))
+    }
+
+    /// Set whether to create any missing parent directories of a field's file. Defaults to `true`.
+    ///
+    /// If `false`, saving a field to a path whose parent directory doesn't exist fails with
+    /// `io::ErrorKind::NotFound` instead.
+    pub fn create_dirs(self, create_dirs: bool) -> Self {
+        self.map_config(|config| config.create_dirs(create_dirs))
+    }
+
+    /// Set whether to save fields to anonymous files which have no name in the filesystem
+    /// until `SavedField::materialize()` is called. Defaults to `false`.
+    ///
+    /// See the "Anonymous Files" section in the type-level docs for more info.
+    pub fn anonymous(self, anonymous: bool) -> Self {
+        self.map_config(|config| config.anonymous(anonymous))
+    }
+
+    /// Stop saving with `PartialReason::Cancelled` once `cancel` is set, e.g. by a watchdog
+    /// thread when the client has gone away or a deadline has passed.
+    ///
+    /// The flag is checked before each buffer of data is saved and before each field, so
+    /// saving stops promptly without waiting for a large field to finish. When saving a whole
+    /// request, the file of the field that was being saved is deleted; fields saved before it
+    /// are kept in `PartialEntries::entries`.
+    pub fn with_cancel(self, cancel: Arc<AtomicBool>) -> Self {
         Self {
-            text_policy: TextPolicy::Ignore,
+            cancel: Some(cancel),
             ..self
         }
     }
+
+    /// Compute a digest of each field's data with the given hash function as it is saved,
+    /// e.g. `sha2::Sha256::new()`.
+    ///
+    /// The data is hashed exactly as it is read from the request, in the same pass.
+    /// See the "Hashing" section in the type-level docs for where the digest is recorded.
+    ///
+    /// Requires the `hashing` feature.
+    #[cfg(feature = "hashing")]
+    pub fn hash_with<D: DynDigest + 'static>(mut self, digest: D) -> Self {
+        self.hasher.digest = Some(Box::new(digest));
+        self
+    }
 }
 
 /// Save API for whole multipart requests.
@@ -200,14 +367,91 @@ impl<M> SaveBuilder<M>
 where
     M: ReadEntry,
 {
+    /// Set the maximum number of bytes to save from all fields together, in memory and on disk.
+    /// The default is no limit, so up to `count_limit` times `size_limit` bytes may be saved.
+    ///
+    /// A field which doesn't fit in what is left stops the save operation with
+    /// `PartialReason::TotalSizeLimit`.
+    ///
+    /// Can be `u64` or `Option<u64>`. If `None` or `u64::MAX`, clears the limit.
+    pub fn total_size_limit<L: Into<Option<u64>>>(self, limit: L) -> Self {
+        self.map_config(|config| config.total_size_limit(limit))
+    }
+
     /// Set the maximum number of fields to process.
     ///
     /// Can be `u32` or `Option<u32>`. If `None` or `u32::MAX`, clears the limit.
-    pub fn count_limit<L: Into<Option<u32>>>(mut self, count_limit: L) -> Self {
-        self.count_limit = count_limit.into().unwrap_or(u32::MAX);
+    pub fn count_limit<L: Into<Option<u32>>>(self, count_limit: L) -> Self {
+        self.map_config(|config| config.count_limit(count_limit))
+    }
+
+    /// Set whether to verify the data of each field against the checksum the client sent
+    /// in its headers, if any. Defaults to `false`.
+    ///
+    /// See `field::Checksum` for the supported headers and hash functions. The digest is
+    /// computed as the field is saved, and if it doesn't match, saving stops with
+    /// `PartialReason::ChecksumMismatch` and the saved data in `PartialEntries::partial`.
+    /// Fields without a checksum or cut short by a limit are not checked.
+    ///
+    /// Requires the `checksums` feature.
+    #[cfg(feature = "checksums")]
+    pub fn verify_checksums(mut self, verify: bool) -> Self {
+        self.hasher.verify_checksums = verify;
         self
     }
 
+    /// Write fields to disk on a pool of `threads` threads instead of one after another.
+    /// Defaults to `0`, meaning fields are written on the calling thread.
+    ///
+    /// See the "Parallel Writes" section in the type-level docs for more info.
+    pub fn parallel_writes(self, threads: usize) -> Self {
+        self.map_config(|config| config.parallel_writes(threads))
+    }
+
+    /// Set where to keep the data of text fields, i.e. fields without a filename.
+    /// Defaults to `TextPolicy::InMemory`.
+    ///
+    /// Forms usually contain a few short text values besides any file uploads, so by default
+    /// these are kept in memory (up to `size_limit`) instead of creating files for them.
+    pub fn text_policy(self, text_policy: TextPolicy) -> Self {
+        self.map_config(|config| config.text_policy(text_policy))
+    }
+
+    /// Set whether to drop file fields with an empty filename and no data instead of adding them
+    /// to `Entries`. Defaults to `false`.
+    ///
+    /// Browsers send such a field for an `<input type="file">` with no file selected.
+    /// Skipped fields don't count towards `count_limit`.
+    pub fn skip_empty_files(self, skip_empty_files: bool) -> Self {
+        self.map_config(|config| config.skip_empty_files(skip_empty_files))
+    }
+
+    /// Only accept fields whose content-type matches one of the given types, e.g. `image/*`
+    /// for an image upload endpoint. An empty list (the default) accepts every field.
+    ///
+    /// A type matches if its top-level type and subtype are the same, ignoring parameters;
+    /// a subtype of `*` matches any subtype and `*/*` matches anything. Fields without a
+    /// `Content-Type` header are treated as `text/plain` as per the spec.
+    ///
+    /// The save operation stops at the first field which doesn't match with
+    /// `PartialReason::ContentType`, before any of its data is read or written out.
+    /// The field is returned in `PartialEntries::partial`.
+    pub fn allowed_content_types(self, allowed: &[Mime]) -> Self {
+        self.map_config(|config| config.allowed_content_types(allowed))
+    }
+
+    /// Replace the filename of each saved field with the result of `sanitizer`, e.g. to strip
+    /// path separators and control characters before the filename is used or displayed.
+    ///
+    /// The sanitized filename is what ends up in `SavedField::headers`; it doesn't affect
+    /// where the data is saved, as files are always given random names.
+    pub fn sanitize_filenames<F>(self, sanitizer: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.map_config(|config| config.sanitize_filenames(sanitizer))
+    }
+
     /// Save all fields in the request using a new temporary directory prefixed with
     /// `multipart-rs` in the OS temporary directory.
     ///
@@ -262,6 +506,20 @@ where
         self.with_entries(Entries::new(SaveDir::Perm(dir)))
     }
 
+    /// Save all fields in the request to the directory set with `SaveConfig::dir()`, or else
+    /// a new temporary directory with the prefix set with `SaveConfig::temp_prefix()`.
+    ///
+    /// See `with_entries()` for more info.
+    pub fn with_config_dir(self) -> EntriesSaveResult<M> {
+        match self.config.dir.clone() {
+            Some(dir) => self.with_dir(dir),
+            None => {
+                let prefix = self.config.temp_prefix.clone();
+                self.temp_with_prefix(&prefix)
+            }
+        }
+    }
+
     /// Commence the save operation using the existing `Entries` instance.
     ///
     /// May be used to resume a saving operation after handling an error.
@@ -271,36 +529,147 @@ where
     ///
     /// Note that `PartialReason::CountLimit` will still be returned if the number of fields
     /// reaches `u32::MAX`, but this would be an extremely degenerate case.
+    ///
+    /// I/O errors from the filesystem while saving a field are wrapped in a `SaveError`
+    /// naming the field and the path it was saved to. Errors from reading the request, e.g.
+    /// a `LimitError`, are returned as they are.
     pub fn with_entries(self, mut entries: Entries) -> EntriesSaveResult<M> {
         let SaveBuilder {
             savable,
+            config,
+            hasher,
+            cancel,
+        } = self;
+
+        let SaveConfig {
             open_opts,
             count_limit,
             size_limit,
+            total_size_limit,
             memory_threshold,
+            utf8_policy,
             text_policy,
-        } = self;
+            newline_mode,
+            cleanup_on_error,
+            create_dirs,
+            skip_empty_files,
+            allowed_content_types,
+            anonymous,
+            parallel_writes,
+            sanitizer,
+            dir: _,
+            temp_prefix: _,
+        } = config;
+
+        let pool = if parallel_writes > 1 && !anonymous {
+            Some(WritePool::new(parallel_writes))
+        } else {
+            None
+        };
 
         let mut res = ReadEntry::read_entry(savable);
 
         let _ = entries.recount_fields();
 
-        let save_field = |field: &mut MultipartField<M>, entries: &Entries| {
-            let text_policy = if field.is_text() { text_policy } else { Ignore };
+        // the total saved so far, against `total_size_limit`
+        let mut total_size = 0u64;
+
+        let save_field = |field: &mut MultipartField<M>, entries: &Entries, total_size: u64| {
+            let utf8_policy = if field.is_text() { utf8_policy } else { Ignore };
+
+            let newline_mode = if field.is_file() {
+                NewlineMode::Preserve
+            } else {
+                newline_mode
+            };
+
+            let memory_threshold = if text_policy == TextPolicy::InMemory && !field.is_file() {
+                u64::MAX
+            } else {
+                memory_threshold
+            };
+
+            let total_left = total_size_limit.saturating_sub(total_size);
 
             let mut saver = SaveBuilder {
                 savable: &mut field.data,
-                open_opts: open_opts.clone(),
-                count_limit,
-                size_limit,
-                memory_threshold,
-                text_policy,
+                config: SaveConfig {
+                    open_opts: open_opts.clone(),
+                    count_limit,
+                    size_limit: cmp::min(size_limit, total_left),
+                    total_size_limit,
+                    memory_threshold,
+                    utf8_policy,
+                    text_policy,
+                    newline_mode,
+                    cleanup_on_error,
+                    create_dirs,
+                    skip_empty_files,
+                    allowed_content_types: Vec::new(),
+                    anonymous,
+                    parallel_writes: 0,
+                    sanitizer: None,
+                    dir: None,
+                    temp_prefix: String::new(),
+                },
+                hasher: hasher.fork(&field.headers),
+                cancel: cancel.clone(),
+            };
+
+            let path = entries.save_dir.as_path().join(rand_filename());
+            let err_path = if anonymous { None } else { Some(path.clone()) };
+
+            // only errors from the filesystem, not e.g. a `LimitError` from reading the field
+            let wrap = |e| SaveError::wrap(field.headers.name.clone(), err_path.clone(), e);
+
+            let res = match pool {
+                Some(ref pool) => saver.with_path_pooled(path, pool, wrap),
+                None => saver.with_path_map_err(path, wrap),
+            };
+
+            let res = match (res, saver.hasher.verify_checksum(&field.headers.name)) {
+                (Full(saved), Err(reason)) => Partial(saved, reason),
+                // the field didn't fit in what's left of the total
+                (Partial(saved, FieldSizeLimit { .. }), _) if total_left < size_limit => Partial(
+                    saved,
+                    TotalSizeLimit {
+                        limit: total_size_limit,
+                    },
+                ),
+                (Partial(saved, FieldSizeLimit { limit, .. }), _) => Partial(
+                    saved,
+                    FieldSizeLimit {
+                        field: Some(field.headers.name.clone()),
+                        limit,
+                    },
+                ),
+                (res, _) => res,
             };
 
-            saver.with_dir(entries.save_dir.as_path())
+            (res, saver.hasher.finish())
         };
 
-        while entries.fields_count < count_limit {
+        let res = (|| loop {
+            if is_cancelled(cancel.as_deref()) {
+                break Partial(
+                    PartialEntries {
+                        entries,
+                        partial: None,
+                    },
+                    Cancelled,
+                );
+            }
+
+            if entries.fields_count >= count_limit {
+                break Partial(
+                    PartialEntries {
+                        entries,
+                        partial: None,
+                    },
+                    CountLimit { limit: count_limit },
+                );
+            }
+
             let mut field: MultipartField<M> = match res {
                 ReadEntryResult::Entry(field) => field,
                 ReadEntryResult::End(_) => return Full(entries), // normal exit point
@@ -315,12 +684,72 @@ where
                 }
             };
 
-            let (dest, reason) = match save_field(&mut field, &entries) {
+            if !allowed_content_types.is_empty() {
+                let content_type = field
+                    .headers
+                    .content_type
+                    .as_ref()
+                    .unwrap_or(&mime::TEXT_PLAIN);
+
+                if !allowed_content_types
+                    .iter()
+                    .any(|allowed| content_type_matches(allowed, content_type))
+                {
+                    let reason = PartialReason::ContentType {
+                        field: field.headers.name.clone(),
+                        content_type: content_type.clone(),
+                    };
+
+                    return Partial(
+                        PartialEntries {
+                            entries,
+                            partial: Some(PartialSavedField {
+                                source: field,
+                                dest: None,
+                            }),
+                        },
+                        reason,
+                    );
+                }
+            }
+
+            let (saved, digest) = save_field(&mut field, &entries, total_size);
+
+            if let Some(ref digest) = digest {
+                field.data.observe_digest(digest);
+            }
+
+            let (dest, reason) = match saved {
                 Full(saved) => {
-                    entries.push_field(field.headers, saved);
+                    total_size = total_size.saturating_add(saved.size());
+
+                    if skip_empty_files
+                        && field.headers.filename.as_deref() == Some("")
+                        && saved.size() == 0
+                    {
+                        log::debug!("Skipping empty file field {:?}", field.headers.name);
+                    } else {
+                        let mut headers = field.headers;
+
+                        if let (Some(sanitizer), Some(filename)) =
+                            (&sanitizer, headers.filename.as_mut())
+                        {
+                            *filename = (sanitizer.0)(filename);
+                        }
+
+                        entries.push_field(SavedField::new(headers, saved).with_digest(digest));
+                    }
                     res = ReadEntry::read_entry(field.data.into_inner());
                     continue;
                 }
+                // don't leave the partial file of a cancelled save behind
+                Partial(saved, Cancelled) => {
+                    if let SavedData::File(ref path, _) = saved {
+                        remove_file_logged(path);
+                    }
+
+                    (None, Cancelled)
+                }
                 Partial(saved, reason) => (Some(saved), reason),
                 Error(error) => (None, PartialReason::IoError(error)),
             };
@@ -335,15 +764,260 @@ where
                 },
                 reason,
             );
+        })();
+
+        match pool {
+            Some(pool) => finish_pooled(res, &pool.finish()),
+            None => res,
         }
+    }
+}
 
-        Partial(
-            PartialEntries {
-                entries,
-                partial: None,
-            },
-            PartialReason::CountLimit,
-        )
+/// The options of `SaveBuilder` for whole requests as a standalone value, so they can be set
+/// up once and used for every request, e.g. with `Multipart::save_with()`.
+///
+/// The defaults are the same as for `Multipart::save()`, and the setters behave the same as
+/// the `SaveBuilder` methods of the same name; a `SaveBuilder` holds a `SaveConfig` and its
+/// setters change it. Additionally, a config can have the directory to save files in; by
+/// default, a new temporary directory is created for every request.
+///
+/// Hash functions, checksum verification and cancellation are set per request, by starting
+/// a `SaveBuilder` from the config with `Multipart::save_from()`.
+#[derive(Clone, Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct SaveConfig {
+    open_opts: OpenOptions,
+    size_limit: u64,
+    total_size_limit: u64,
+    count_limit: u32,
+    memory_threshold: u64,
+    utf8_policy: Utf8Policy,
+    text_policy: TextPolicy,
+    newline_mode: NewlineMode,
+    cleanup_on_error: bool,
+    create_dirs: bool,
+    skip_empty_files: bool,
+    allowed_content_types: Vec<Mime>,
+    anonymous: bool,
+    parallel_writes: usize,
+    sanitizer: Option<FilenameSanitizer>,
+    dir: Option<PathBuf>,
+    temp_prefix: String,
+}
+
+/// The function set with `SaveConfig::sanitize_filenames()`.
+#[derive(Clone)]
+struct FilenameSanitizer(Arc<dyn Fn(&str) -> String + Send + Sync>);
+
+impl fmt::Debug for FilenameSanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Fn(&str) -> String")
+    }
+}
+
+impl SaveConfig {
+    /// The default options, the same as `Multipart::save()`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See `SaveBuilder::size_limit()`.
+    #[must_use]
+    pub fn size_limit<L: Into<Option<u64>>>(mut self, limit: L) -> Self {
+        self.size_limit = limit.into().unwrap_or(u64::MAX);
+        self
+    }
+
+    /// See `SaveBuilder::total_size_limit()`.
+    #[must_use]
+    pub fn total_size_limit<L: Into<Option<u64>>>(mut self, limit: L) -> Self {
+        self.total_size_limit = limit.into().unwrap_or(u64::MAX);
+        self
+    }
+
+    /// See `SaveBuilder::count_limit()`.
+    #[must_use]
+    pub fn count_limit<L: Into<Option<u32>>>(mut self, count_limit: L) -> Self {
+        self.count_limit = count_limit.into().unwrap_or(u32::MAX);
+        self
+    }
+
+    /// See `SaveBuilder::mod_open_opts()`.
+    #[must_use]
+    pub fn mod_open_opts<F: FnOnce(&mut OpenOptions)>(mut self, opts_fn: F) -> Self {
+        opts_fn(&mut self.open_opts);
+        self.open_opts.write(true);
+        self
+    }
+
+    /// See `SaveBuilder::memory_threshold()`.
+    #[must_use]
+    pub fn memory_threshold(self, memory_threshold: u64) -> Self {
+        Self {
+            memory_threshold,
+            ..self
+        }
+    }
+
+    /// See `SaveBuilder::try_text()`.
+    #[must_use]
+    pub fn try_text(self) -> Self {
+        Self {
+            utf8_policy: Utf8Policy::Try,
+            ..self
+        }
+    }
+
+    /// See `SaveBuilder::force_text()`.
+    #[must_use]
+    pub fn force_text(self) -> Self {
+        Self {
+            utf8_policy: Utf8Policy::Force,
+            ..self
+        }
+    }
+
+    /// See `SaveBuilder::ignore_text()`.
+    #[must_use]
+    pub fn ignore_text(self) -> Self {
+        Self {
+            utf8_policy: Utf8Policy::Ignore,
+            ..self
+        }
+    }
+
+    /// See `SaveBuilder::text_policy()`.
+    #[must_use]
+    pub fn text_policy(self, text_policy: TextPolicy) -> Self {
+        Self {
+            text_policy,
+            ..self
+        }
+    }
+
+    /// See `SaveBuilder::normalize_text_newlines()`.
+    #[must_use]
+    pub fn normalize_text_newlines(self, newline_mode: NewlineMode) -> Self {
+        Self {
+            newline_mode,
+            ..self
+        }
+    }
+
+    /// See `SaveBuilder::cleanup_on_error()`.
+    #[must_use]
+    pub fn cleanup_on_error(self, cleanup_on_error: bool) -> Self {
+        Self {
+            cleanup_on_error,
+            ..self
+        }
+    }
+
+    /// See `SaveBuilder::create_dirs()`.
+    #[must_use]
+    pub fn create_dirs(self, create_dirs: bool) -> Self {
+        Self {
+            create_dirs,
+            ..self
+        }
+    }
+
+    /// See `SaveBuilder::skip_empty_files()`.
+    #[must_use]
+    pub fn skip_empty_files(self, skip_empty_files: bool) -> Self {
+        Self {
+            skip_empty_files,
+            ..self
+        }
+    }
+
+    /// See `SaveBuilder::allowed_content_types()`.
+    #[must_use]
+    pub fn allowed_content_types(self, allowed: &[Mime]) -> Self {
+        Self {
+            allowed_content_types: allowed.to_vec(),
+            ..self
+        }
+    }
+
+    /// See `SaveBuilder::anonymous()`.
+    #[must_use]
+    pub fn anonymous(self, anonymous: bool) -> Self {
+        Self { anonymous, ..self }
+    }
+
+    /// See `SaveBuilder::parallel_writes()`.
+    #[must_use]
+    pub fn parallel_writes(self, threads: usize) -> Self {
+        Self {
+            parallel_writes: threads,
+            ..self
+        }
+    }
+
+    /// See `SaveBuilder::sanitize_filenames()`.
+    #[must_use]
+    pub fn sanitize_filenames<F>(self, sanitizer: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        Self {
+            sanitizer: Some(FilenameSanitizer(Arc::new(sanitizer))),
+            ..self
+        }
+    }
+
+    /// Save the files of every request to this directory, as with `SaveBuilder::with_dir()`,
+    /// instead of a new temporary directory each.
+    ///
+    /// As with any `SaveDir::Perm`, the files are left in place when `Entries` is dropped.
+    #[must_use]
+    pub fn dir<P: Into<PathBuf>>(self, dir: P) -> Self {
+        Self {
+            dir: Some(dir.into()),
+            ..self
+        }
+    }
+
+    /// Set the prefix of the temporary directory created for each request if no `dir()` is set.
+    /// Defaults to `multipart-rs`, as for `SaveBuilder::temp()`.
+    #[must_use]
+    pub fn temp_prefix(self, prefix: &str) -> Self {
+        Self {
+            temp_prefix: prefix.to_owned(),
+            ..self
+        }
+    }
+}
+
+impl Default for SaveConfig {
+    fn default() -> Self {
+        let mut open_opts = OpenOptions::new();
+        open_opts.write(true).create_new(true);
+
+        SaveConfig {
+            open_opts,
+            // 8 MiB, on the conservative end compared to most frameworks
+            size_limit: 8 * 1024 * 1024,
+            total_size_limit: u64::MAX,
+            // Arbitrary, I have no empirical data for this
+            count_limit: 256,
+            // 10KiB, used by Apache Commons
+            // https://commons.apache.org/proper/commons-fileupload/apidocs/org/apache/commons/fileupload/disk/DiskFileItemFactory.html
+            memory_threshold: 10 * 1024,
+            utf8_policy: Utf8Policy::Try,
+            text_policy: TextPolicy::default(),
+            newline_mode: NewlineMode::default(),
+            cleanup_on_error: true,
+            create_dirs: true,
+            skip_empty_files: false,
+            allowed_content_types: Vec::new(),
+            anonymous: false,
+            parallel_writes: 0,
+            sanitizer: None,
+            dir: None,
+            temp_prefix: "multipart-rs".to_owned(),
+        }
     }
 }
 
@@ -383,56 +1057,164 @@ where
 
     /// Save the field data, potentially using a file with the given path.
     ///
-    /// Creates any missing directories in the path (RFC: skip this step?).
+    /// Creates any missing directories in the path unless `create_dirs(false)` was set.
     /// Uses the contained `OpenOptions` to create the file.
     /// Truncates the file to the given `size_limit`, if set.
     ///
     /// The no directories or files will be created until the set `memory_threshold` is reached.
     /// If `size_limit` is set and less than or equal to `memory_threshold`,
     /// then the disk will never be touched.
+    ///
+    /// If an I/O error occurs after the file is created, the file is deleted and `Error` is
+    /// returned, unless `cleanup_on_error(false)` was set.
+    ///
+    /// If `anonymous(true)` was set, an anonymous file is created in the parent directory
+    /// of `path` instead and `path` itself is not used.
     pub fn with_path<P: Into<PathBuf>>(&mut self, path: P) -> FieldSaveResult {
-        let bytes = if self.text_policy == Ignore {
+        self.with_path_map_err(path.into(), |e| e)
+    }
+
+    /// Like `with_path()`, but errors from the filesystem rather than from reading the field
+    /// are passed through `map_err`.
+    fn with_path_map_err<F>(&mut self, path: PathBuf, map_err: F) -> FieldSaveResult
+    where
+        F: Fn(io::Error) -> io::Error,
+    {
+        let bytes = match self.save_until_threshold() {
+            ControlFlow::Continue(bytes) => bytes,
+            ControlFlow::Break(res) => return res,
+        };
+
+        let opened = self.prepare_parent(&path).and_then(|()| {
+            if self.config.anonymous {
+                let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+                tempfile::tempfile_in(dir.unwrap_or_else(|| Path::new(".")))
+            } else {
+                self.config.open_opts.open(&path)
+            }
+        });
+
+        let file = match opened {
+            Ok(file) => file,
+            Err(e) => return Error(map_err(e)),
+        };
+
+        let mut file = MapWriteErr(file, map_err);
+
+        let res = match try_write_all(&bytes, &mut file) {
+            Full(size) => self
+                .write_to(&mut file)
+                .map(|written| (size as u64).saturating_add(written)),
+            Partial(size, reason) => Partial(size as u64, reason),
+            Error(e) => Error(e),
+        };
+
+        if self.config.anonymous {
+            // there's nothing to clean up as the file disappears when dropped
+            return res.map(move |size| SavedData::Anonymous(file.0, size));
+        }
+
+        match res {
+            Partial(_, IoError(e)) | Error(e) if self.config.cleanup_on_error => {
+                remove_file_logged(&path);
+                Error(e)
+            }
+            res => res.map(move |size| SavedData::File(path, size)),
+        }
+    }
+
+    /// Like `with_path_map_err()`, but the file is written by a thread of `pool`.
+    fn with_path_pooled<F>(
+        &mut self,
+        path: PathBuf,
+        pool: &WritePool,
+        map_err: F,
+    ) -> FieldSaveResult
+    where
+        F: Fn(io::Error) -> io::Error,
+    {
+        let bytes = match self.save_until_threshold() {
+            ControlFlow::Continue(bytes) => bytes,
+            ControlFlow::Break(res) => return res,
+        };
+
+        if let Err(e) = self.prepare_parent(&path) {
+            return Error(map_err(e));
+        }
+
+        let writer = match pool.dispatch(
+            path.clone(),
+            self.config.open_opts.clone(),
+            self.config.cleanup_on_error,
+        ) {
+            Ok(writer) => writer,
+            Err(e) => return Error(map_err(e)),
+        };
+
+        let mut writer = MapWriteErr(writer, map_err);
+
+        let res = match try_write_all(&bytes, &mut writer) {
+            Full(size) => self
+                .write_to(&mut writer)
+                .map(|written| (size as u64).saturating_add(written)),
+            Partial(size, reason) => Partial(size as u64, reason),
+            Error(e) => Error(e),
+        };
+
+        // errors while writing the file are collected by `finish_pooled()`
+        match res {
+            Partial(_, IoError(e)) | Error(e) if self.config.cleanup_on_error => {
+                writer.0.abort();
+                Error(e)
+            }
+            res => match (res, writer.0.finish()) {
+                (Full(size), Ok(())) => Full(SavedData::File(path, size)),
+                (Full(_), Err(e)) => Error((writer.1)(e)),
+                (Error(e), _) => Error(e),
+                (Partial(size, reason), _) => Partial(SavedData::File(path, size), reason),
+            },
+        }
+    }
+
+    /// Read the field into memory until `memory_threshold`, returning the data read if it
+    /// should be continued in a file, or else the result.
+    fn save_until_threshold(&mut self) -> ControlFlow<FieldSaveResult, Vec<u8>> {
+        let bytes = if self.config.utf8_policy == Ignore {
             Vec::new()
         } else {
             let (text, reason) = match self.save_text() {
-                Full(full) => return Full(full.into()),
+                Full(full) => {
+                    return ControlFlow::Break(Full(
+                        self.config.newline_mode.normalize(full).into(),
+                    ))
+                }
                 Partial(partial, reason) => (partial, reason),
-                Error(e) => return Error(e),
+                Error(e) => return ControlFlow::Break(Error(e)),
             };
             match reason {
-                SizeLimit if !self.cmp_size_limit(text.len()) => text.into_bytes(),
-                Utf8Error(_) if self.text_policy != Force => text.into_bytes(),
-                other => return Partial(text.into(), other),
+                FieldSizeLimit { .. } if !self.cmp_size_limit(text.len()) => text.into_bytes(),
+                Utf8Error(_) if self.config.utf8_policy != Force => text.into_bytes(),
+                other => {
+                    return ControlFlow::Break(Partial(
+                        self.config.newline_mode.normalize(text).into(),
+                        other,
+                    ))
+                }
             }
         };
 
         let (bytes, reason) = match self.save_mem(bytes) {
-            Full(full) => return Full(full.into()),
+            Full(full) => return ControlFlow::Break(Full(full.into())),
             Partial(partial, reason) => (partial, reason),
-            Error(e) => return Error(e),
+            Error(e) => return ControlFlow::Break(Error(e)),
         };
 
         match reason {
-            SizeLimit if !self.cmp_size_limit(bytes.len()) => (),
-            other => return Partial(bytes.into(), other),
+            FieldSizeLimit { .. } if !self.cmp_size_limit(bytes.len()) => {
+                ControlFlow::Continue(bytes)
+            }
+            other => ControlFlow::Break(Partial(bytes.into(), other)),
         }
-
-        let path = path.into();
-
-        let mut file = match create_dir_all(&path).and_then(|()| self.open_opts.open(&path)) {
-            Ok(file) => file,
-            Err(e) => return Error(e),
-        };
-
-        let data = match try_write_all(&bytes, &mut file)
-            .map(move |size| SavedData::File(path, size as u64))
-        {
-            Full(full) => full,
-            other => return other,
-        };
-
-        self.write_to(file)
-            .map(move |written| data.add_size(written))
     }
 
     /// Write out the field data to `dest`, truncating if a limit was set.
@@ -442,17 +1224,29 @@ where
     ///
     /// Retries on interrupts.
     pub fn write_to<W: Write>(&mut self, mut dest: W) -> SaveResult<u64, u64> {
-        if self.size_limit < u64::MAX {
-            try_copy_limited(
-                &mut self.savable,
-                |buf| try_write_all(buf, &mut dest),
-                self.size_limit,
-            )
+        let with_buf = cancellable(
+            self.cancel.clone(),
+            self.hasher
+                .hash_consumed(|buf| try_write_all(buf, &mut dest)),
+        );
+
+        if self.config.size_limit < u64::MAX {
+            try_copy_limited(&mut self.savable, with_buf, self.config.size_limit)
         } else {
-            try_read_buf(&mut self.savable, |buf| try_write_all(buf, &mut dest))
+            try_read_buf(&mut self.savable, with_buf)
         }
     }
 
+    /// Take the lowercase hex digest of the data saved so far, resetting the hasher.
+    ///
+    /// Returns `None` if no hash function was set with `hash_with()`.
+    ///
+    /// Requires the `hashing` feature.
+    #[cfg(feature = "hashing")]
+    pub fn take_digest(&mut self) -> Option<String> {
+        self.hasher.finish()
+    }
+
     fn save_mem(&mut self, mut bytes: Vec<u8>) -> SaveResult<Vec<u8>, Vec<u8>> {
         let pre_read = bytes.len() as u64;
         match self.read_mem(
@@ -511,22 +1305,129 @@ where
         with_buf: Wb,
         pre_read: u64,
     ) -> SaveResult<u64, u64> {
-        let limit = cmp::min(self.size_limit, self.memory_threshold).saturating_sub(pre_read);
-        try_copy_limited(&mut self.savable, with_buf, limit)
+        let limit =
+            cmp::min(self.config.size_limit, self.config.memory_threshold).saturating_sub(pre_read);
+        try_copy_limited(
+            &mut self.savable,
+            cancellable(self.cancel.clone(), self.hasher.hash_consumed(with_buf)),
+            limit,
+        )
+    }
+
+    fn prepare_parent(&self, path: &Path) -> io::Result<()> {
+        if self.config.create_dirs {
+            return create_dir_all(path);
+        }
+
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() && !parent.is_dir() => {
+                Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("parent directory of {} does not exist", path.display()),
+                ))
+            }
+            _ => Ok(()),
+        }
     }
 
     fn cmp_size_limit(&self, size: usize) -> bool {
-        size as u64 >= self.size_limit
+        size as u64 >= self.config.size_limit
     }
 }
 
 /// A field that has been saved (to memory or disk) from a multipart request.
-#[derive(Debug)]
+///
+/// Fields are equal if their headers and data are; see `SavedData` for how data is compared.
+#[derive(Debug, PartialEq)]
 pub struct SavedField {
     /// The headers of the field that was saved.
     pub headers: FieldHeaders,
     /// The data of the field which may reside in memory or on disk.
     pub data: SavedData,
+    /// The lowercase hex digest of the field's data, if a hash function was set with
+    /// `SaveBuilder::hash_with()`.
+    ///
+    /// Requires the `hashing` feature.
+    #[cfg(feature = "hashing")]
+    pub digest: Option<String>,
+}
+
+impl SavedField {
+    pub(crate) fn new(headers: FieldHeaders, data: SavedData) -> Self {
+        SavedField {
+            headers,
+            data,
+            #[cfg(feature = "hashing")]
+            digest: None,
+        }
+    }
+
+    /// Get a reader over the data of this field, whether it's in memory or in a file, so
+    /// fields can be processed the same way wherever they were saved.
+    ///
+    /// See `SavedData::readable()` for more info.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the data is in a file which could not be opened.
+    pub fn open(&self) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(self.data.readable()?))
+    }
+
+    /// Give the data of this field a name in the filesystem at `path`, which must not exist yet.
+    ///
+    /// See `SavedData::materialize()` for more info.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the data is already in a named file or it could not be written
+    /// to `path`.
+    pub fn materialize<P: Into<PathBuf>>(&mut self, path: P) -> io::Result<()> {
+        self.data.materialize(path)
+    }
+
+    /// Take the data of this field as an open file, seeked to the start and owned by the caller.
+    ///
+    /// See `SavedData::into_file()` for more info.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the file could not be opened or unlinked, or a temporary file
+    /// for in-memory data could not be written.
+    pub fn into_file(self) -> io::Result<File> {
+        self.data.into_file()
+    }
+
+    /// Move the data of this field to `to`, replacing any file there.
+    ///
+    /// See `SavedData::persist()` for more info.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the data could not be moved or written to `to`.
+    pub fn persist<P: AsRef<Path>>(self, to: P) -> io::Result<()> {
+        self.data.persist(to)
+    }
+
+    /// Get a copy of the data of this field as `bytes::Bytes` if it was saved in memory.
+    ///
+    /// See `SavedData::as_bytes()` for more info.
+    ///
+    /// Requires the `bytes` feature.
+    #[cfg(feature = "bytes")]
+    #[must_use]
+    pub fn as_bytes(&self) -> Option<bytes::Bytes> {
+        self.data.as_bytes()
+    }
+
+    #[cfg_attr(not(feature = "hashing"), allow(unused_mut, unused_variables))]
+    fn with_digest(mut self, digest: Option<String>) -> Self {
+        #[cfg(feature = "hashing")]
+        {
+            self.digest = digest;
+        }
+        self
+    }
 }
 
 /// A saved field's data container (in memory or on disk)
@@ -538,58 +1439,193 @@ pub enum SavedData {
     Bytes(Vec<u8>),
     /// A path to a file on the filesystem and its size as written by `multipart`.
     File(PathBuf, u64),
+    /// An open file with no name in the filesystem and its size as written by `multipart`,
+    /// as saved with `SaveBuilder::anonymous(true)`.
+    Anonymous(File, u64),
 }
 
-impl SavedData {
-    /// Get an adapter for this data which implements `Read`.
+impl SavedData {
+    /// Get an adapter for this data which implements `Read`.
+    ///
+    /// If the data is in a file, the file is opened in read-only mode. An `Anonymous` file
+    /// is read at its own position, so several readers don't interfere with each other.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if there is error in opening the `file`
+    pub fn readable(&self) -> io::Result<DataReader<'_>> {
+        use self::SavedData::{Anonymous, Bytes, File, Text};
+
+        match *self {
+            Text(ref text) => Ok(DataReader::Bytes(text.as_ref())),
+            Bytes(ref bytes) => Ok(DataReader::Bytes(bytes)),
+            File(ref path, _) => Ok(DataReader::File(BufReader::new(fs::File::open(path)?))),
+            Anonymous(ref file, _) => Ok(DataReader::Anonymous(BufReader::new(
+                PositionalReader::new(file),
+            ))),
+        }
+    }
+
+    /// Get the size of the data, in memory or on disk.
+    ///
+    /// #### Note
+    /// The size on disk may not match the size of the file if it is externally modified.
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        use self::SavedData::{Anonymous, Bytes, File, Text};
+
+        match *self {
+            Text(ref text) => text.len() as u64,
+            Bytes(ref bytes) => bytes.len() as u64,
+            File(_, size) | Anonymous(_, size) => size,
+        }
+    }
+
+    /// Returns `true` if the data is known to be in memory (`Text | Bytes`)
+    #[must_use]
+    pub fn is_memory(&self) -> bool {
+        use self::SavedData::{Anonymous, Bytes, File, Text};
+
+        match *self {
+            Text(_) | Bytes(_) => true,
+            File(_, _) | Anonymous(_, _) => false,
+        }
+    }
+
+    /// Get a copy of the data as `bytes::Bytes` if it is in memory.
+    ///
+    /// Use `into_bytes()` to convert the data without copying.
+    ///
+    /// Requires the `bytes` feature.
+    #[cfg(feature = "bytes")]
+    #[must_use]
+    pub fn as_bytes(&self) -> Option<bytes::Bytes> {
+        match *self {
+            SavedData::Text(ref text) => Some(bytes::Bytes::copy_from_slice(text.as_bytes())),
+            SavedData::Bytes(ref bytes) => Some(bytes::Bytes::copy_from_slice(bytes)),
+            SavedData::File(..) | SavedData::Anonymous(..) => None,
+        }
+    }
+
+    /// Convert the data to `bytes::Bytes` if it is in memory, taking ownership of the buffer
+    /// without copying. Data on disk is returned as-is in `Err`.
+    ///
+    /// Requires the `bytes` feature.
+    ///
+    /// # Errors
+    ///
+    /// Will return `self` if the data is `File` or `Anonymous`.
+    #[cfg(feature = "bytes")]
+    pub fn into_bytes(self) -> Result<bytes::Bytes, Self> {
+        match self {
+            SavedData::Text(text) => Ok(text.into_bytes().into()),
+            SavedData::Bytes(bytes) => Ok(bytes.into()),
+            data @ (SavedData::File(..) | SavedData::Anonymous(..)) => Err(data),
+        }
+    }
+
+    /// Give this data a name in the filesystem at `path`, which must not exist yet,
+    /// and change `self` to `SavedData::File`.
+    ///
+    /// `Anonymous` files are linked into place without copying if possible (on Linux,
+    /// if the file was created with `O_TMPFILE` on the same filesystem as `path`), or else
+    /// copied. `Text` and `Bytes` are written out to the file.
     ///
-    /// If the data is in a file, the file is opened in read-only mode.
+    /// Missing parent directories of `path` are not created.
     ///
     /// # Errors
     ///
-    /// Will return `Error` if there is error in opening the `file`
-    pub fn readable(&self) -> io::Result<DataReader<'_>> {
-        use self::SavedData::{Bytes, File, Text};
+    /// Will return `Error` if `self` is already `SavedData::File`, or if `path` exists or
+    /// could not be written to.
+    pub fn materialize<P: Into<PathBuf>>(&mut self, path: P) -> io::Result<()> {
+        use self::SavedData::{Anonymous, Bytes, File, Text};
 
-        match *self {
-            Text(ref text) => Ok(DataReader::Bytes(text.as_ref())),
-            Bytes(ref bytes) => Ok(DataReader::Bytes(bytes)),
-            File(ref path, _) => Ok(DataReader::File(BufReader::new(fs::File::open(path)?))),
-        }
+        let path = path.into();
+
+        let res = match *self {
+            Text(ref text) => write_new_file(&path, text.as_bytes()),
+            Bytes(ref bytes) => write_new_file(&path, bytes),
+            Anonymous(ref file, _) => materialize_anonymous(file, &path),
+            File(ref existing, _) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("data is already saved to {}", existing.display()),
+            )),
+        };
+
+        res.map(|()| *self = File(path, self.size()))
     }
 
-    /// Get the size of the data, in memory or on disk.
+    /// Take the data as an open file, seeked to the start and owned by the caller.
     ///
-    /// #### Note
-    /// The size on disk may not match the size of the file if it is externally modified.
-    #[must_use]
-    pub fn size(&self) -> u64 {
-        use self::SavedData::{Bytes, File, Text};
+    /// A `File` is opened for reading and writing and its name is removed from the filesystem,
+    /// so it is unaffected by the cleanup of the directory it was saved in and is deleted once
+    /// the returned handle is closed; use `persist()` to keep it under a name instead.
+    /// `Text` and `Bytes` are written out to a new anonymous temporary file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the file could not be opened or unlinked, or a temporary file
+    /// for in-memory data could not be written.
+    pub fn into_file(self) -> io::Result<File> {
+        use self::SavedData::{Anonymous, Bytes, File, Text};
 
-        match *self {
-            Text(ref text) => text.len() as u64,
-            Bytes(ref bytes) => bytes.len() as u64,
-            File(_, size) => size,
+        match self {
+            Text(text) => anonymous_with(text.as_bytes()),
+            Bytes(bytes) => anonymous_with(&bytes),
+            File(path, _) => {
+                let file = OpenOptions::new().read(true).write(true).open(&path)?;
+                fs::remove_file(&path)?;
+                Ok(file)
+            }
+            Anonymous(mut file, _) => {
+                file.seek(io::SeekFrom::Start(0))?;
+                Ok(file)
+            }
         }
     }
 
-    /// Returns `true` if the data is known to be in memory (`Text | Bytes`)
-    #[must_use]
-    pub fn is_memory(&self) -> bool {
-        use self::SavedData::{Bytes, File, Text};
+    /// Move the data to `to`, replacing any file there, so it is kept when the directory
+    /// it was saved in is cleaned up.
+    ///
+    /// A `File` is renamed to `to`. If `to` is on another filesystem, it is instead copied
+    /// to a temporary name next to `to` and renamed from there, then the original is deleted.
+    /// Other data is materialized next to `to` the same way (see `materialize()`). Either way,
+    /// the file only appears at `to` once it is complete.
+    ///
+    /// Missing parent directories of `to` are not created.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the data could not be moved or written to `to`.
+    pub fn persist<P: AsRef<Path>>(self, to: P) -> io::Result<()> {
+        let to = to.as_ref();
 
-        match *self {
-            Text(_) | Bytes(_) => true,
-            File(_, _) => false,
+        match self {
+            SavedData::File(path, _) => match fs::rename(&path, to) {
+                Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                    log::debug!("Copying {:?} to {:?} across filesystems", path, to);
+                    persist_via_temp(to, |temp| fs::copy(&path, temp).map(drop))?;
+                    remove_file_logged(&path);
+                    Ok(())
+                }
+                res => res,
+            },
+            mut data => persist_via_temp(to, |temp| data.materialize(temp)),
         }
     }
+}
 
-    fn add_size(self, add: u64) -> Self {
-        use self::SavedData::File;
+/// Data is compared without reading files: `File` is equal if the path and size are.
+/// `Anonymous` files can't be identified, so they are never equal.
+impl PartialEq for SavedData {
+    fn eq(&self, other: &Self) -> bool {
+        use self::SavedData::{Bytes, File, Text};
 
-        match self {
-            File(path, size) => File(path, size.saturating_add(add)),
-            other => other,
+        match (self, other) {
+            (Text(a), Text(b)) => a == b,
+            (Bytes(a), Bytes(b)) => a == b,
+            (File(a, a_size), File(b, b_size)) => a == b && a_size == b_size,
+            _ => false,
         }
     }
 }
@@ -606,46 +1642,102 @@ impl From<Vec<u8>> for SavedData {
     }
 }
 
+#[cfg(feature = "bytes")]
+impl From<bytes::Bytes> for SavedData {
+    fn from(b: bytes::Bytes) -> Self {
+        SavedData::Bytes(b.into())
+    }
+}
+
 /// A `Read` (and `BufRead`) adapter for `SavedData`
 pub enum DataReader<'a> {
     /// In-memory data source (`SavedData::Bytes | Text`)
     Bytes(&'a [u8]),
     /// On-disk data source (`SavedData::File`)
     File(BufReader<File>),
+    /// Anonymous on-disk data source (`SavedData::Anonymous`)
+    Anonymous(BufReader<PositionalReader<'a>>),
 }
 
 impl<'a> Read for DataReader<'a> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        use self::DataReader::{Bytes, File};
+        use self::DataReader::{Anonymous, Bytes, File};
 
         match *self {
             Bytes(ref mut bytes) => bytes.read(buf),
             File(ref mut file) => file.read(buf),
+            Anonymous(ref mut file) => file.read(buf),
         }
     }
 }
 
 impl<'a> BufRead for DataReader<'a> {
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
-        use self::DataReader::{Bytes, File};
+        use self::DataReader::{Anonymous, Bytes, File};
 
         match *self {
             Bytes(ref mut bytes) => bytes.fill_buf(),
             File(ref mut file) => file.fill_buf(),
+            Anonymous(ref mut file) => file.fill_buf(),
         }
     }
 
     fn consume(&mut self, amt: usize) {
-        use self::DataReader::{Bytes, File};
+        use self::DataReader::{Anonymous, Bytes, File};
 
         match *self {
             Bytes(ref mut bytes) => bytes.consume(amt),
             File(ref mut file) => file.consume(amt),
+            Anonymous(ref mut file) => file.consume(amt),
         }
     }
 }
 
+/// Reads a borrowed file from the start with positional reads, leaving the offset of the
+/// file itself alone, so it can be shared by several readers.
+pub struct PositionalReader<'a> {
+    file: &'a File,
+    pos: u64,
+}
+
+impl<'a> PositionalReader<'a> {
+    fn new(file: &'a File) -> Self {
+        PositionalReader { file, pos: 0 }
+    }
+}
+
+impl<'a> Read for PositionalReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        #[cfg(unix)]
+        let read = std::os::unix::fs::FileExt::read_at(self.file, buf, self.pos)?;
+        // this does move the offset, but every reader passes its own position anyway
+        #[cfg(windows)]
+        let read = std::os::windows::fs::FileExt::seek_read(self.file, buf, self.pos)?;
+        #[cfg(not(any(unix, windows)))]
+        let read = {
+            let mut file = self.file;
+            file.seek(io::SeekFrom::Start(self.pos))?;
+            file.read(buf)?
+        };
+
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
 /// A result of `Multipart::save()`.
+///
+/// ### Ownership of Saved Files
+/// The files referenced by `fields` live as long as the directory they were saved in:
+///
+/// * If `save_dir` is `SaveDir::Temp`, the directory and everything in it is deleted when this
+///   value is dropped. Move any files you want to keep elsewhere first, or call
+///   `save_dir.keep()`.
+/// * If `save_dir` is `SaveDir::Perm`, the files are left where they are when this value is
+///   dropped; call `cleanup()` to delete them, e.g. if the request is rejected after saving.
+///
+/// Fields moved out of `fields` are not tracked anymore; their files are only deleted if they
+/// are still in the temporary directory when it is dropped.
 #[derive(Debug)]
 pub struct Entries {
     /// The fields of the multipart request, mapped by field name -> value.
@@ -673,6 +1765,40 @@ impl Entries {
         }
     }
 
+    /// Parse a request body which is already in memory and save all its fields with the default
+    /// settings of `Multipart::save()`, to a new temporary directory.
+    ///
+    /// Equivalent to `Multipart::from_bytes(body, boundary).into_entries()`.
+    ///
+    /// # Errors
+    ///
+    /// See `Multipart::read_entries()`.
+    pub fn from_bytes<B: Into<Vec<u8>>, Bnd: Into<String>>(
+        body: B,
+        boundary: Bnd,
+    ) -> io::Result<Self> {
+        crate::server::Multipart::from_bytes(body, boundary).into_entries()
+    }
+
+    /// Delete the files of all fields in `fields` which were saved to disk and remove those
+    /// fields, regardless of whether `save_dir` is temporary.
+    ///
+    /// Useful to avoid leaking files in a permanent directory if the request is rejected after
+    /// saving. Errors deleting files are logged and otherwise ignored.
+    pub fn cleanup(&mut self) {
+        for field in self.fields.values().flatten() {
+            if let SavedData::File(ref path, _) = field.data {
+                remove_file_logged(path);
+            }
+        }
+
+        self.fields.retain(|_, fields| {
+            fields.retain(|field| !matches!(field.data, SavedData::File(..)));
+            !fields.is_empty()
+        });
+        self.recount_fields();
+    }
+
     /// Returns `true` if `fields` is empty, `false` otherwise.
     #[must_use]
     pub fn is_empty(&self) -> bool {
@@ -700,17 +1826,40 @@ impl Entries {
         self.fields_count
     }
 
-    fn push_field(&mut self, mut headers: FieldHeaders, data: SavedData) {
+    /// The values of the text fields, sorted by field name, with the values for each name in
+    /// the order they were submitted. Fields saved as bytes or files are left out.
+    ///
+    /// Unlike iterating `fields`, the order is deterministic, e.g. for signing the form or
+    /// for snapshot tests.
+    #[must_use]
+    pub fn text_fields_sorted(&self) -> BTreeMap<String, Vec<String>> {
+        self.fields
+            .iter()
+            .filter_map(|(name, fields)| {
+                let values: Vec<String> = fields
+                    .iter()
+                    .filter_map(|field| match field.data {
+                        SavedData::Text(ref text) => Some(text.clone()),
+                        _ => None,
+                    })
+                    .collect();
+
+                (!values.is_empty()).then(|| (name.to_string(), values))
+            })
+            .collect()
+    }
+
+    pub(crate) fn push_field(&mut self, mut field: SavedField) {
         use std::collections::hash_map::Entry::{Occupied, Vacant};
 
-        match self.fields.entry(headers.name.clone()) {
+        match self.fields.entry(field.headers.name.clone()) {
             Vacant(vacant) => {
-                vacant.insert(vec![SavedField { headers, data }]);
+                vacant.insert(vec![field]);
             }
             Occupied(occupied) => {
                 // dedup the field name by reusing the key's `Arc`
-                headers.name = occupied.key().clone();
-                occupied.into_mut().push(SavedField { headers, data });
+                field.headers.name = occupied.key().clone();
+                occupied.into_mut().push(field);
             }
         }
 
@@ -753,6 +1902,13 @@ impl Entries {
     }
 }
 
+/// Entries are equal if their `fields` are; `save_dir` is not compared.
+impl PartialEq for Entries {
+    fn eq(&self, other: &Self) -> bool {
+        self.fields == other.fields
+    }
+}
+
 /// The save directory for `Entries`. May be temporary (delete-on-drop) or permanent.
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug)]
@@ -795,7 +1951,7 @@ impl SaveDir {
         use self::SaveDir::{Perm, Temp};
 
         match self {
-            Temp(tempdir) => tempdir.into_path(),
+            Temp(tempdir) => tempdir.keep(),
             Perm(pathbuf) => pathbuf,
         }
     }
@@ -813,7 +1969,7 @@ impl SaveDir {
     pub fn keep(&mut self) {
         use self::SaveDir::{Perm, Temp};
         *self = match mem::replace(self, Perm(PathBuf::new())) {
-            Temp(tempdir) => Perm(tempdir.into_path()),
+            Temp(tempdir) => Perm(tempdir.keep()),
             old_self @ Perm(_) => old_self,
         };
     }
@@ -848,14 +2004,54 @@ impl AsRef<Path> for SaveDir {
 /// The reason the save operation quit partway through.
 #[derive(Debug)]
 pub enum PartialReason {
-    /// The count limit for files in the request was hit.
+    /// The count limit for fields in the request was hit.
+    ///
+    /// The next field has not been read.
+    CountLimit {
+        /// The limit set with `SaveBuilder::count_limit()`.
+        limit: u32,
+    },
+    /// The size limit for an individual field was hit.
+    ///
+    /// The field was partially saved, up to the limit.
+    FieldSizeLimit {
+        /// The name of the offending field, or `None` when saving an individual field.
+        field: Option<Arc<str>>,
+        /// The limit set with `SaveBuilder::size_limit()`.
+        limit: u64,
+    },
+    /// The limit on the size of all fields together was hit.
     ///
-    /// The associated file has not been saved to the filesystem.
-    CountLimit,
-    /// The size limit for an individual file was hit.
+    /// The field being saved when it was hit was partially saved, up to the limit.
+    TotalSizeLimit {
+        /// The limit set with `SaveBuilder::total_size_limit()`.
+        limit: u64,
+    },
+    /// A field's content-type was not allowed by `SaveBuilder::allowed_content_types()`.
     ///
-    /// The file was partially written to the filesystem.
-    SizeLimit,
+    /// The associated field has not been read or saved to the filesystem.
+    ContentType {
+        /// The name of the offending field.
+        field: Arc<str>,
+        /// The field's content-type (`text/plain` if it didn't have one).
+        content_type: Mime,
+    },
+    /// A field's data didn't match the checksum in its headers, with
+    /// `SaveBuilder::verify_checksums(true)`.
+    ///
+    /// The field was saved completely.
+    ChecksumMismatch {
+        /// The name of the offending field.
+        field: Arc<str>,
+        /// The hash function of the checksum.
+        algorithm: ChecksumAlgorithm,
+        /// The lowercase hex digest from the field's headers.
+        expected: String,
+        /// The lowercase hex digest of the field's data.
+        actual: String,
+    },
+    /// Saving was cancelled with the flag passed to `SaveBuilder::with_cancel()`.
+    Cancelled,
     /// An error occurred during the operation.
     IoError(io::Error),
     /// An error returned from validating a field as UTF-8 due to `SaveBuilder::force_text()`
@@ -874,7 +2070,73 @@ impl From<str::Utf8Error> for PartialReason {
     }
 }
 
+impl fmt::Display for PartialReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CountLimit { limit } => write!(f, "field count limit of {limit} reached"),
+            FieldSizeLimit {
+                field: Some(field),
+                limit,
+            } => write!(f, "field {field:?} exceeds the size limit of {limit} bytes"),
+            FieldSizeLimit { field: None, limit } => {
+                write!(f, "field exceeds the size limit of {limit} bytes")
+            }
+            TotalSizeLimit { limit } => {
+                write!(f, "fields exceed the total size limit of {limit} bytes")
+            }
+            PartialReason::ContentType {
+                field,
+                content_type,
+            } => write!(
+                f,
+                "field {field:?} has disallowed content-type {content_type}"
+            ),
+            PartialReason::ChecksumMismatch {
+                field,
+                algorithm,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "field {field:?} does not match its {algorithm} checksum: \
+                 expected {expected}, got {actual}"
+            ),
+            Cancelled => f.write_str("saving was cancelled"),
+            IoError(e) => write!(f, "I/O error: {e}"),
+            Utf8Error(e) => write!(f, "field is not valid UTF-8: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PartialReason {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IoError(e) => Some(e),
+            Utf8Error(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl PartialReason {
+    /// Convert `self` to an `io::Error`, wrapping it unless it already is one.
+    ///
+    /// The wrapped `PartialReason` can be recovered with `io::Error::get_ref()` and
+    /// `downcast_ref()`.
+    fn into_io_error(self) -> io::Error {
+        let kind = match self {
+            IoError(e) => return e,
+            CountLimit { .. } | FieldSizeLimit { .. } | TotalSizeLimit { .. } | Cancelled => {
+                io::ErrorKind::Other
+            }
+            PartialReason::ContentType { .. }
+            | PartialReason::ChecksumMismatch { .. }
+            | Utf8Error(_) => io::ErrorKind::InvalidData,
+        };
+
+        io::Error::new(kind, self)
+    }
+
     /// Return `io::Error` in the `IoError` case or panic otherwise.
     #[must_use]
     pub fn unwrap_err(self) -> io::Error {
@@ -896,6 +2158,72 @@ impl PartialReason {
     }
 }
 
+/// An I/O error that occurred while saving a field of a whole request, with the name of the
+/// field and the path it was being saved to.
+///
+/// Errors from the filesystem while saving a whole request are wrapped in this, keeping their
+/// `io::ErrorKind`; it can be recovered with `io::Error::get_ref()` and `downcast_ref()`. The
+/// original error is returned by `source()` of either. Errors from reading the request, e.g.
+/// a `LimitError`, are not wrapped.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct SaveError {
+    field: Arc<str>,
+    path: Option<PathBuf>,
+    error: io::Error,
+}
+
+impl SaveError {
+    /// The name of the field that was being saved.
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// The path the field was being saved to, or `None` if it was being saved to an anonymous
+    /// file.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The underlying I/O error.
+    pub fn io_error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Take the underlying I/O error.
+    pub fn into_io_error(self) -> io::Error {
+        self.error
+    }
+
+    /// Wrap `error` with context, unless it already has some.
+    fn wrap(field: Arc<str>, path: Option<PathBuf>, error: io::Error) -> io::Error {
+        if error.get_ref().is_some_and(|e| e.is::<SaveError>()) {
+            return error;
+        }
+
+        let kind = error.kind();
+        io::Error::new(kind, SaveError { field, path, error })
+    }
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed saving field `{}`", self.field)?;
+
+        if let Some(ref path) = self.path {
+            write!(f, " to {}", path.display())?;
+        }
+
+        write!(f, ": {}", self.error)
+    }
+}
+
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
 /// The field that was being read when the save operation quit.
 ///
 /// May be partially saved to the filesystem if `dest` is `Some`.
@@ -938,7 +2266,8 @@ impl<M: ReadEntry> PartialEntries<M> {
     pub fn keep_partial(mut self) -> Entries {
         if let Some(partial) = self.partial {
             if let Some(saved) = partial.dest {
-                self.entries.push_field(partial.source.headers, saved);
+                self.entries
+                    .push_field(SavedField::new(partial.source.headers, saved));
             }
         }
 
@@ -978,6 +2307,37 @@ impl<M: ReadEntry> EntriesSaveResult<M> {
             Error(_) => None,
         }
     }
+
+    /// Take the `Entries` from `self` only if all fields were saved, discarding them otherwise.
+    ///
+    /// Unlike `into_entries()`, this returns `None` in the `Partial` case.
+    pub fn into_entries_opt(self) -> Option<Entries> {
+        match self {
+            Full(entries) => Some(entries),
+            Partial(..) | Error(_) => None,
+        }
+    }
+
+    /// Like `into_result()`, but logs a warning with the reason in the `Partial` case
+    /// before returning the entries that were saved.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the save operation failed before anything was saved.
+    pub fn okay_warn(self) -> io::Result<Entries> {
+        match self {
+            Full(entries) => Ok(entries),
+            Partial(partial, reason) => {
+                log::warn!(
+                    "Saved {} fields before stopping early: {}",
+                    partial.entries.fields_count(),
+                    reason
+                );
+                Ok(partial.entries)
+            }
+            Error(e) => Err(e),
+        }
+    }
 }
 
 impl<S, P> SaveResult<S, P>
@@ -1044,6 +2404,102 @@ where
             Partial(partial, _) => Ok(partial.into()),
         }
     }
+
+    /// Map `self` to an `io::Result` which is only `Ok` in the `Full` case.
+    ///
+    /// In the `Partial` case the partial value is dropped and the reason is returned as an
+    /// `io::Error`; see `into_result_strict()` for the implications.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the save operation did not complete.
+    pub fn into_full_result(self) -> io::Result<S> {
+        match self {
+            Full(full) => Ok(full),
+            Partial(_, reason) => Err(reason.into_io_error()),
+            Error(e) => Err(e),
+        }
+    }
+}
+
+/// Remove fields whose files failed to be written by a `WritePool` from the result,
+/// reporting the first error if there wasn't one already.
+fn finish_pooled<M: ReadEntry>(
+    res: EntriesSaveResult<M>,
+    failed: &[(PathBuf, io::Error)],
+) -> EntriesSaveResult<M> {
+    let Some((failed_path, first_err)) = failed.first() else {
+        return res;
+    };
+
+    let field_of = |entries: &Entries| {
+        entries
+            .fields
+            .iter()
+            .find(|(_, fields)| {
+                fields.iter().any(
+                    |field| matches!(field.data, SavedData::File(ref path, _) if path == failed_path),
+                )
+            })
+            .map(|(name, _)| name.clone())
+    };
+
+    let remove_failed = |entries: &mut Entries| {
+        for fields in entries.fields.values_mut() {
+            fields.retain(|field| {
+                !matches!(field.data, SavedData::File(ref path, _)
+                    if failed.iter().any(|(failed, _)| failed == path))
+            });
+        }
+
+        entries.fields.retain(|_, fields| !fields.is_empty());
+        entries.recount_fields();
+    };
+
+    // `io::Error` isn't `Clone`
+    let err = |field: Option<Arc<str>>| {
+        let err = io::Error::new(first_err.kind(), first_err.to_string());
+
+        match field {
+            Some(field) => SaveError::wrap(field, Some(failed_path.clone()), err),
+            None => err,
+        }
+    };
+
+    match res {
+        Full(mut entries) => {
+            let field = field_of(&entries);
+            remove_failed(&mut entries);
+            Partial(
+                PartialEntries {
+                    entries,
+                    partial: None,
+                },
+                IoError(err(field)),
+            )
+        }
+        Partial(mut partial, reason) => {
+            let field = field_of(&partial.entries);
+            remove_failed(&mut partial.entries);
+
+            // the writer thread's error explains why writing the partial field failed
+            let reason = match reason {
+                IoError(e) => {
+                    // if the failed file isn't among the saved fields, it's the partial one's
+                    let field = field.or_else(|| {
+                        e.get_ref()
+                            .and_then(|e| e.downcast_ref::<SaveError>())
+                            .map(|e| e.field.clone())
+                    });
+                    IoError(err(field))
+                }
+                reason => reason,
+            };
+
+            Partial(partial, reason)
+        }
+        Error(e) => Error(e),
+    }
 }
 
 fn create_dir_all(path: &Path) -> io::Result<()> {
@@ -1059,6 +2515,227 @@ fn create_dir_all(path: &Path) -> io::Result<()> {
     }
 }
 
+/// Feeds field data into the hash function set with `SaveBuilder::hash_with()`, if any,
+/// and the one for the field's checksum if it is being verified.
+#[derive(Default)]
+struct FieldHasher {
+    #[cfg(feature = "hashing")]
+    digest: Option<Box<dyn DynDigest>>,
+    #[cfg(feature = "checksums")]
+    verify_checksums: bool,
+    #[cfg(feature = "checksums")]
+    checksum: Option<(Box<dyn DynDigest>, Checksum)>,
+}
+
+impl FieldHasher {
+    /// A fresh hasher of the same kind, for the field with `headers`.
+    #[cfg_attr(not(feature = "checksums"), allow(unused_variables))]
+    fn fork(&self, headers: &FieldHeaders) -> Self {
+        FieldHasher {
+            #[cfg(feature = "hashing")]
+            digest: self.digest.as_ref().map(|digest| {
+                let mut digest = digest.box_clone();
+                digest.reset();
+                digest
+            }),
+            #[cfg(feature = "checksums")]
+            verify_checksums: self.verify_checksums,
+            #[cfg(feature = "checksums")]
+            checksum: headers
+                .checksum
+                .clone()
+                .filter(|_| self.verify_checksums)
+                .map(|checksum| (checksum_digest(checksum.algorithm), checksum)),
+        }
+    }
+
+    /// Wrap `with_buf` so the bytes it reports as consumed are hashed.
+    fn hash_consumed<'a, Wb>(
+        &'a mut self,
+        mut with_buf: Wb,
+    ) -> impl FnMut(&[u8]) -> SaveResult<usize, usize> + 'a
+    where
+        Wb: FnMut(&[u8]) -> SaveResult<usize, usize> + 'a,
+    {
+        move |buf| {
+            let res = with_buf(buf);
+
+            #[cfg(feature = "hashing")]
+            if let Full(consumed) | Partial(consumed, _) = res {
+                if let Some(ref mut digest) = self.digest {
+                    digest.update(&buf[..consumed]);
+                }
+
+                #[cfg(feature = "checksums")]
+                if let Some((ref mut digest, _)) = self.checksum {
+                    digest.update(&buf[..consumed]);
+                }
+            }
+
+            res
+        }
+    }
+
+    fn finish(&mut self) -> Option<String> {
+        #[cfg(feature = "hashing")]
+        if let Some(ref mut digest) = self.digest {
+            return Some(to_hex(&digest.finalize_reset()));
+        }
+
+        None
+    }
+
+    /// Compare the digest of the data hashed so far against the checksum of `field`,
+    /// if it is being verified.
+    #[cfg_attr(not(feature = "checksums"), allow(unused_variables))]
+    fn verify_checksum(&mut self, field: &Arc<str>) -> Result<(), PartialReason> {
+        #[cfg(feature = "checksums")]
+        if let Some((ref mut digest, ref checksum)) = self.checksum {
+            let actual = digest.finalize_reset();
+
+            if *actual != *checksum.digest {
+                return Err(PartialReason::ChecksumMismatch {
+                    field: field.clone(),
+                    algorithm: checksum.algorithm,
+                    expected: to_hex(&checksum.digest),
+                    actual: to_hex(&actual),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "checksums")]
+fn checksum_digest(algorithm: ChecksumAlgorithm) -> Box<dyn DynDigest> {
+    match algorithm {
+        ChecksumAlgorithm::Md5 => Box::new(md5::Md5::default()),
+        ChecksumAlgorithm::Sha256 => Box::new(sha2::Sha256::default()),
+    }
+}
+
+#[cfg(feature = "hashing")]
+fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write as _;
+
+    let mut hex = String::with_capacity(bytes.len() * 2);
+
+    for byte in bytes {
+        let _ = write!(hex, "{byte:02x}");
+    }
+
+    hex
+}
+
+fn content_type_matches(allowed: &Mime, content_type: &Mime) -> bool {
+    if allowed.type_() == mime::STAR {
+        return true;
+    }
+
+    allowed.type_() == content_type.type_()
+        && (allowed.subtype() == mime::STAR
+            || (allowed.subtype() == content_type.subtype()
+                && allowed.suffix() == content_type.suffix()))
+}
+
+fn write_new_file(path: &Path, mut data: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+
+    io::copy(&mut data, &mut file).map(drop).inspect_err(|_| {
+        remove_file_logged(path);
+    })
+}
+
+fn anonymous_with(data: &[u8]) -> io::Result<File> {
+    let mut file = tempfile::tempfile()?;
+    file.write_all(data)?;
+    file.seek(io::SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+/// Create a file at a temporary name next to `to` with `write`, then rename it to `to`
+/// so a partially written file is never seen there.
+fn persist_via_temp<F: FnOnce(&Path) -> io::Result<()>>(to: &Path, write: F) -> io::Result<()> {
+    let temp = to.with_file_name(format!(".{}.tmp", rand_filename()));
+
+    let res = write(&temp).and_then(|()| fs::rename(&temp, to));
+
+    if res.is_err() {
+        // `write` may have failed before creating the file
+        let _ = fs::remove_file(&temp);
+    }
+
+    res
+}
+
+fn materialize_anonymous(file: &File, path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    match link_anonymous(file, path) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(e),
+        // not created with `O_TMPFILE` or on another filesystem
+        Err(e) => log::debug!("Could not link anonymous file to {:?}: {}", path, e),
+    }
+
+    let mut dest = OpenOptions::new().write(true).create_new(true).open(path)?;
+
+    io::copy(&mut PositionalReader::new(file), &mut dest)
+        .map(drop)
+        .inspect_err(|_| {
+            remove_file_logged(path);
+        })
+}
+
+/// Link a file created with `O_TMPFILE` into the filesystem at `path`.
+#[cfg(target_os = "linux")]
+fn link_anonymous(file: &File, path: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::AsRawFd;
+
+    let src = CString::new(format!("/proc/self/fd/{}", file.as_raw_fd()))
+        .expect("formatted path contains no NUL bytes");
+    let dest = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    // SAFETY: both paths are valid NUL-terminated strings which outlive the call
+    let res = unsafe {
+        libc::linkat(
+            libc::AT_FDCWD,
+            src.as_ptr(),
+            libc::AT_FDCWD,
+            dest.as_ptr(),
+            libc::AT_SYMLINK_FOLLOW,
+        )
+    };
+
+    if res == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+fn remove_file_logged(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        log::warn!("Failed to remove saved file {:?}: {}", path, e);
+    }
+}
+
+/// Passes the errors of writing to the inner writer through the function.
+struct MapWriteErr<W, F>(W, F);
+
+impl<W: Write, F: Fn(io::Error) -> io::Error> Write for MapWriteErr<W, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf).map_err(&self.1)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush().map_err(&self.1)
+    }
+}
+
 fn try_copy_limited<R: BufRead, Wb: FnMut(&[u8]) -> SaveResult<usize, usize>>(
     src: R,
     mut with_buf: Wb,
@@ -1068,7 +2745,7 @@ fn try_copy_limited<R: BufRead, Wb: FnMut(&[u8]) -> SaveResult<usize, usize>>(
     try_read_buf(src, |buf| {
         let new_copied = copied.saturating_add(buf.len() as u64);
         if new_copied > limit {
-            return Partial(0, PartialReason::SizeLimit);
+            return Partial(0, FieldSizeLimit { field: None, limit });
         }
         copied = new_copied;
 
@@ -1121,6 +2798,24 @@ fn try_read_buf<R: BufRead, Wb: FnMut(&[u8]) -> SaveResult<usize, usize>>(
     Full(total_copied)
 }
 
+fn is_cancelled(cancel: Option<&AtomicBool>) -> bool {
+    cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+}
+
+/// Wrap `with_buf` to stop with `PartialReason::Cancelled` once `cancel` is set.
+fn cancellable<Wb: FnMut(&[u8]) -> SaveResult<usize, usize>>(
+    cancel: Option<Arc<AtomicBool>>,
+    mut with_buf: Wb,
+) -> impl FnMut(&[u8]) -> SaveResult<usize, usize> {
+    move |buf| {
+        if is_cancelled(cancel.as_deref()) {
+            Partial(0, Cancelled)
+        } else {
+            with_buf(buf)
+        }
+    }
+}
+
 fn try_write_all<W: Write>(mut buf: &[u8], mut dest: W) -> SaveResult<usize, usize> {
     let mut total_copied = 0;
 
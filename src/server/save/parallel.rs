@@ -0,0 +1,252 @@
+//! Writing fields to disk on a pool of threads. See `SaveBuilder::parallel_writes()`.
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+
+/// The size of the chunks field data is handed to the writer threads in.
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// The number of chunks which may be queued for a field before reading it blocks.
+const QUEUE_LEN: usize = 4;
+
+type Errors = Arc<Mutex<Vec<(PathBuf, io::Error)>>>;
+
+struct Job {
+    path: PathBuf,
+    open_opts: OpenOptions,
+    cleanup_on_error: bool,
+    /// `None` if reading the field failed.
+    chunks: Receiver<Option<Vec<u8>>>,
+}
+
+impl Job {
+    fn run(&self) -> io::Result<()> {
+        let mut file = self.open_opts.open(&self.path)?;
+
+        let mut res = Ok(());
+        let mut aborted = false;
+
+        for chunk in &self.chunks {
+            let Some(chunk) = chunk else {
+                // the error is reported by the thread reading the request
+                aborted = true;
+                break;
+            };
+
+            res = file.write_all(&chunk);
+
+            if res.is_err() {
+                break;
+            }
+        }
+
+        let res = res.and_then(|()| file.flush());
+
+        if (res.is_err() || aborted) && self.cleanup_on_error {
+            super::remove_file_logged(&self.path);
+        }
+
+        res
+    }
+}
+
+/// A pool of threads writing fields to files.
+///
+/// A field is only handed off once a thread is free to take it, and each field can only have
+/// `QUEUE_LEN` chunks waiting to be written, so at most about `threads * QUEUE_LEN * CHUNK_LEN`
+/// bytes are held in memory while the disk lags behind.
+pub(super) struct WritePool {
+    jobs: Option<SyncSender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    errors: Errors,
+}
+
+impl WritePool {
+    pub(super) fn new(threads: usize) -> Self {
+        // a rendezvous channel, so `dispatch()` blocks until a thread is free
+        let (jobs, receiver) = mpsc::sync_channel::<Job>(0);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let errors = Errors::default();
+
+        let workers = (0..threads)
+            .map(|_| {
+                let receiver = receiver.clone();
+                let errors = errors.clone();
+
+                thread::spawn(move || loop {
+                    let job = match lock(&receiver).recv() {
+                        Ok(job) => job,
+                        // the pool is finished
+                        Err(_) => return,
+                    };
+
+                    if let Err(e) = job.run() {
+                        log::debug!("Error writing {}: {}", job.path.display(), e);
+                        lock(&errors).push((job.path, e));
+                    }
+                })
+            })
+            .collect();
+
+        WritePool {
+            jobs: Some(jobs),
+            workers,
+            errors,
+        }
+    }
+
+    /// Wait for a free thread and have it create the file at `path` and write everything
+    /// written to the returned `ChunkWriter` to it.
+    pub(super) fn dispatch(
+        &self,
+        path: PathBuf,
+        open_opts: OpenOptions,
+        cleanup_on_error: bool,
+    ) -> io::Result<ChunkWriter> {
+        let (sender, chunks) = mpsc::sync_channel(QUEUE_LEN);
+
+        let job = Job {
+            path,
+            open_opts,
+            cleanup_on_error,
+            chunks,
+        };
+
+        self.jobs
+            .as_ref()
+            .expect("`WritePool` used after `finish()`")
+            .send(job)
+            .map_err(|_| stopped())?;
+
+        Ok(ChunkWriter {
+            chunk: Vec::with_capacity(CHUNK_LEN),
+            sender,
+        })
+    }
+
+    /// Wait for all files to be written, returning the paths of the ones which failed.
+    pub(super) fn finish(mut self) -> Vec<(PathBuf, io::Error)> {
+        self.jobs = None;
+
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                log::error!("A thread writing fields to disk panicked");
+            }
+        }
+
+        mem::take(&mut *lock(&self.errors))
+    }
+}
+
+/// Buffers field data into chunks for a `WritePool` thread. Call `finish()` when done.
+pub(super) struct ChunkWriter {
+    chunk: Vec<u8>,
+    sender: SyncSender<Option<Vec<u8>>>,
+}
+
+impl ChunkWriter {
+    /// Send the last chunk, if any, to be written.
+    pub(super) fn finish(mut self) -> io::Result<()> {
+        self.send()
+    }
+
+    /// Signal that reading the field failed, so the file is deleted if `cleanup_on_error` is set.
+    pub(super) fn abort(self) {
+        // if the thread already stopped there's nothing to signal
+        let _ = self.sender.send(None);
+    }
+
+    fn send(&mut self) -> io::Result<()> {
+        if self.chunk.is_empty() {
+            return Ok(());
+        }
+
+        let chunk = mem::replace(&mut self.chunk, Vec::with_capacity(CHUNK_LEN));
+        // fails if the thread stopped after an error, which is reported by `WritePool::finish()`
+        self.sender.send(Some(chunk)).map_err(|_| stopped())
+    }
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(CHUNK_LEN - self.chunk.len());
+        self.chunk.extend_from_slice(&buf[..len]);
+
+        if self.chunk.len() == CHUNK_LEN {
+            self.send()?;
+        }
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()
+    }
+}
+
+fn stopped() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "thread writing field to disk stopped",
+    )
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod bench {
+    use crate::server::Multipart;
+
+    use test::Bencher;
+
+    const FILES: usize = 16;
+    const FILE_LEN: usize = 1024 * 1024;
+
+    fn body() -> Vec<u8> {
+        let mut body = Vec::new();
+
+        for i in 0..FILES {
+            body.extend_from_slice(
+                format!(
+                    "--boundary\r\n\
+                     Content-Disposition: form-data; name=\"file{i}\"; filename=\"{i}.bin\"\r\n\r\n"
+                )
+                .as_bytes(),
+            );
+            body.resize(body.len() + FILE_LEN, b'a' + (i % 26) as u8);
+            body.extend_from_slice(b"\r\n");
+        }
+
+        body.extend_from_slice(b"--boundary--");
+        body
+    }
+
+    fn save(body: &[u8], parallel_writes: usize) {
+        Multipart::with_body(body, "boundary")
+            .save()
+            .size_limit(None)
+            .memory_threshold(0)
+            .parallel_writes(parallel_writes)
+            .temp()
+            .into_result_strict()
+            .unwrap();
+    }
+
+    #[bench]
+    fn bench_sequential_writes(b: &mut Bencher) {
+        let body = body();
+        b.iter(|| save(&body, 0));
+    }
+
+    #[bench]
+    fn bench_parallel_writes(b: &mut Bencher) {
+        let body = body();
+        b.iter(|| save(&body, 4));
+    }
+}
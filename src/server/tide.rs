@@ -0,0 +1,183 @@
+//! Server-side integration with [Tide](https://github.com/http-rs/tide).
+//! Enabled with the `tide` feature.
+//!
+//! Converts a `tide::Request` into the async [`Multipart`](../async_/struct.Multipart.html) of
+//! the `server-async` feature, which parses the body as it arrives. Only the `futures-io`
+//! traits implemented by `tide::Body` are used, so this works on `async-std` without Tokio.
+//!
+//! ```rust,no_run
+//! use mpart::server::tide::from_request;
+//!
+//! async fn upload(mut req: tide::Request<()>) -> tide::Result<String> {
+//!     let mut multipart = from_request(&mut req)?;
+//!     let mut fields = 0;
+//!
+//!     while let Some(_field) = multipart.next_field().await? {
+//!         fields += 1;
+//!     }
+//!
+//!     Ok(format!("received {} fields", fields))
+//! }
+//!
+//! # fn main() {
+//! let mut app = tide::new();
+//! app.at("/upload").post(upload);
+//! # }
+//! ```
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_io::AsyncBufRead;
+use tide::http::headers::CONTENT_TYPE;
+use tide::{Body, Request, StatusCode};
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use super::async_::Multipart;
+
+/// The async `Multipart` type returned by [`from_request()`](fn.from_request.html).
+pub type TideMultipart = Multipart<BodyStream>;
+
+/// The error returned by [`from_request()`](fn.from_request.html) for requests which are not
+/// `multipart/form-data` or don't specify a boundary.
+///
+/// Converts into a `tide::Error` with status `400 Bad Request`, so it can be returned from an
+/// endpoint with `?`.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct NotMultipart;
+
+impl fmt::Display for NotMultipart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("request is not multipart/form-data")
+    }
+}
+
+impl From<NotMultipart> for tide::Error {
+    fn from(err: NotMultipart) -> Self {
+        tide::Error::from_str(StatusCode::BadRequest, err.to_string())
+    }
+}
+
+/// If `req` is a `multipart/form-data` request, take its body and wrap it in an async
+/// `Multipart`.
+///
+/// # Errors
+///
+/// Will return `NotMultipart` if the request's `Content-Type` isn't `multipart/form-data` with
+/// a boundary. The body is left in the request then.
+pub fn from_request<State>(req: &mut Request<State>) -> Result<TideMultipart, NotMultipart> {
+    let content_type = req.header(CONTENT_TYPE).map(|val| val.last().as_str());
+    let boundary = content_type
+        .and_then(super::form_data_boundary)
+        .map(str::to_owned)
+        .ok_or(NotMultipart)?;
+    let boundary_quoted = content_type.is_some_and(super::boundary_quoted);
+
+    Ok(Multipart::with_body(
+        BodyStream {
+            body: req.take_body(),
+        },
+        boundary,
+    )
+    .with_boundary_quoted(boundary_quoted))
+}
+
+/// The body of a Tide request as a stream of `Bytes`, as parsed by `TideMultipart`.
+pub struct BodyStream {
+    body: Body,
+}
+
+impl Stream for BodyStream {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let chunk = match ready!(Pin::new(&mut self.body).poll_fill_buf(cx)) {
+            Ok([]) => return Poll::Ready(None),
+            Ok(buf) => Bytes::copy_from_slice(buf),
+            Err(e) => return Poll::Ready(Some(Err(e))),
+        };
+
+        Pin::new(&mut self.body).consume(chunk.len());
+        Poll::Ready(Some(Ok(chunk)))
+    }
+}
+
+impl fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BodyStream").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::from_request;
+
+    use tide::http::{Method, Request, Response, Url};
+    use tide::StatusCode;
+
+    const BODY: &str = "--boundary\r\n\
+        Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+        some text\r\n\
+        --boundary--";
+
+    async fn upload(mut req: tide::Request<()>) -> tide::Result<String> {
+        let mut multipart = from_request(&mut req)?;
+        let mut fields = Vec::new();
+
+        while let Some(field) = multipart.next_field().await? {
+            let name = field.headers.name.clone();
+            let data = field.bytes().await?;
+            fields.push(format!("{name}={}", String::from_utf8_lossy(&data)));
+        }
+
+        Ok(fields.join("&"))
+    }
+
+    async fn quoted(mut req: tide::Request<()>) -> tide::Result<String> {
+        Ok(from_request(&mut req)?.boundary_was_quoted().to_string())
+    }
+
+    fn respond(path: &str, content_type: &str) -> (StatusCode, String) {
+        let mut app = tide::new();
+        app.at("/upload").post(upload);
+        app.at("/quoted").post(quoted);
+
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        let mut req = Request::new(Method::Post, url);
+        req.insert_header("Content-Type", content_type);
+        req.set_body(BODY);
+
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(async {
+                let mut res: Response = app.respond(req).await.unwrap();
+                (res.status(), res.body_string().await.unwrap())
+            })
+    }
+
+    #[test]
+    fn test_tide_request() {
+        assert_eq!(
+            respond("/upload", "multipart/form-data; boundary=boundary"),
+            (StatusCode::Ok, "text=some text".to_owned())
+        );
+
+        assert_eq!(respond("/upload", "text/plain").0, StatusCode::BadRequest);
+    }
+
+    #[test]
+    fn test_boundary_quoted() {
+        assert_eq!(
+            respond("/quoted", "multipart/form-data; boundary=\"boundary\"").1,
+            "true"
+        );
+        assert_eq!(
+            respond("/quoted", "multipart/form-data; boundary=boundary").1,
+            "false"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! A wrapper for request bodies which enforces a timeout on individual reads, and the error
+//! returned once a deadline set with `Multipart::set_deadline()` has passed.
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+/// A `Read` adapter which returns an error of kind `io::ErrorKind::TimedOut` if any single read
+/// from the inner reader takes longer than the given timeout.
+///
+/// Wrap the request body in this before passing it to `Multipart::with_body()` to stop a client
+/// which trickles bytes (a "slow loris") from tying up the handler indefinitely.
+///
+/// ### Note: The Inner Reader Must Be Interruptible
+/// A blocking read cannot be cancelled from the outside, so for a generic reader the timeout is
+/// only checked once the read returns; if the read never returns, neither will this.
+/// For `TcpStream`, use `TimeoutReader::tcp()` which also sets the socket's read timeout so
+/// the OS interrupts the read.
+///
+/// Any bytes returned by a read which overran the timeout are discarded along with it, so the
+/// request should be abandoned after a timeout.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct TimeoutReader<R> {
+    inner: R,
+    timeout: Duration,
+}
+
+impl<R: Read> TimeoutReader<R> {
+    /// Wrap `inner`, failing any read which takes longer than `timeout`.
+    pub fn new(inner: R, timeout: Duration) -> Self {
+        TimeoutReader { inner, timeout }
+    }
+
+    /// Unwrap the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl TimeoutReader<TcpStream> {
+    /// Wrap `stream`, also setting its read timeout so blocking reads are interrupted.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Error` if the socket's read timeout could not be set
+    /// (e.g. if `timeout` is zero).
+    pub fn tcp(stream: TcpStream, timeout: Duration) -> io::Result<Self> {
+        stream.set_read_timeout(Some(timeout))?;
+        Ok(TimeoutReader::new(stream, timeout))
+    }
+}
+
+impl<R: Read> Read for TimeoutReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = Instant::now();
+
+        match self.inner.read(buf) {
+            // socket timeouts are reported as `WouldBlock` on Unix platforms
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Err(timed_out()),
+            _ if start.elapsed() > self.timeout => Err(timed_out()),
+            res => res,
+        }
+    }
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "timed out reading request body")
+}
+
+/// The deadline set with `Multipart::set_deadline()` passed before the request was read.
+///
+/// Returned wrapped in an `io::Error` of kind `io::ErrorKind::TimedOut`; use
+/// `io::Error::get_ref()` to tell it apart from other timeouts:
+///
+/// ```rust
+/// use mpart::server::timeout::DeadlineExceeded;
+/// use std::io;
+///
+/// fn is_deadline_exceeded(e: &io::Error) -> bool {
+///     e.get_ref().is_some_and(|e| e.is::<DeadlineExceeded>())
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeadlineExceeded;
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline for reading request body exceeded")
+    }
+}
+
+impl Error for DeadlineExceeded {}
+
+impl From<DeadlineExceeded> for io::Error {
+    fn from(e: DeadlineExceeded) -> io::Error {
+        io::Error::new(io::ErrorKind::TimedOut, e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DeadlineExceeded, TimeoutReader};
+
+    use crate::server::Multipart;
+
+    use std::io::{self, Read};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    struct SlowReader<'a>(&'a [u8]);
+
+    impl Read for SlowReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_millis(50));
+            // trickle one byte at a time
+            let len = std::cmp::min(buf.len(), 1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn test_timeout_fires() {
+        let body = SlowReader(b"--boundary\r\n");
+        let reader = TimeoutReader::new(body, Duration::from_millis(10));
+        let mut multipart = Multipart::with_body(reader, "boundary");
+
+        let err = multipart.read_entry().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_deadline_exceeded() {
+        let body = format!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+             {}\r\n\
+             --boundary--",
+            "a".repeat(64)
+        );
+        let body = SlowReader(body.as_bytes());
+
+        let mut multipart = Multipart::with_body(body, "boundary");
+        let start = Instant::now();
+        multipart.set_deadline(start + Duration::from_millis(200));
+
+        let res = (|| {
+            while let Some(mut field) = multipart.read_entry()? {
+                io::copy(&mut field.data, &mut io::sink())?;
+            }
+            Ok(())
+        })();
+
+        let err: io::Error = res.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(err.get_ref().unwrap().is::<DeadlineExceeded>());
+        // reading the whole body would take several seconds
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // further reads fail as well
+        let err = multipart.read_entry().err().unwrap();
+        assert!(err.get_ref().unwrap().is::<DeadlineExceeded>());
+    }
+}
@@ -9,23 +9,25 @@ impl<'r> HttpRequest for &'r mut TinyHttpRequest {
     type Body = &'r mut dyn Read;
 
     fn multipart_boundary(&self) -> Option<&str> {
-        const BOUNDARY: &str = "boundary=";
-
-        let content_type = self
-            .headers()
-            .iter()
-            .find(|header| header.field.equiv("Content-Type"))?
-            .value
-            .as_str();
-        let start = content_type.find(BOUNDARY)? + BOUNDARY.len();
-        let end = content_type[start..]
-            .find(';')
-            .map_or(content_type.len(), |end| start + end);
-
-        Some(&content_type[start..end])
+        super::boundary_param(content_type(self)?)
+    }
+
+    fn multipart_boundary_any_subtype(&self) -> Option<&str> {
+        super::any_multipart_boundary(content_type(self)?)
+    }
+
+    fn multipart_boundary_quoted(&self) -> bool {
+        content_type(self).is_some_and(super::boundary_quoted)
     }
 
     fn body(self) -> Self::Body {
         self.as_reader()
     }
 }
+
+fn content_type(req: &TinyHttpRequest) -> Option<&str> {
+    req.headers()
+        .iter()
+        .find(|header| header.field.equiv("Content-Type"))
+        .map(|header| header.value.as_str())
+}
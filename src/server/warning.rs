@@ -0,0 +1,95 @@
+//! Non-fatal anomalies found while parsing a request. See `Multipart::with_warnings()`.
+use std::fmt;
+use std::sync::Arc;
+
+/// Something unusual about a request which didn't stop it from being parsed, e.g. a header
+/// which was ignored because it's invalid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct ParseWarning {
+    /// What was found.
+    pub reason: WarningReason,
+    /// The name of the field it was found in, if it concerns a field.
+    pub field: Option<Arc<str>>,
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.field {
+            Some(ref field) => write!(f, "field {:?}: {}", field, self.reason),
+            None => self.reason.fmt(f),
+        }
+    }
+}
+
+/// The reason for a `ParseWarning`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+#[allow(clippy::module_name_repetitions)]
+pub enum WarningReason {
+    /// The filename was longer than the limit set with `Multipart::set_max_filename_len()`
+    /// and was truncated.
+    FilenameTruncated {
+        /// The original length of the filename in bytes.
+        len: usize,
+    },
+    /// A parameter of the `Content-Disposition` header appeared more than once; the first
+    /// value was used.
+    DuplicateParam(&'static str),
+    /// The `Content-Length` header of a field could not be parsed and was ignored.
+    InvalidContentLength(String),
+    /// The length of a field's body didn't match its `Content-Length` header.
+    ContentLengthMismatch {
+        /// The length in the header.
+        declared: u64,
+        /// The actual length of the body.
+        actual: u64,
+    },
+    /// A checksum header of a field could not be parsed and was ignored.
+    InvalidChecksum {
+        /// The name of the header.
+        header: &'static str,
+        /// The value of the header.
+        value: String,
+    },
+    /// A field has a `Content-Transfer-Encoding` which isn't decoded: anything but `7bit`,
+    /// `8bit`, `binary`, or `base64` and `quoted-printable` unless decoding was turned off with
+    /// `Multipart::set_decode_transfer_encoding()`. Its data is read as-is, without decoding.
+    UnsupportedTransferEncoding(String),
+    /// The lines of a field's headers ended with both CRLF and LF. Only with the default
+    /// `field::LineEndingPolicy::Lenient`.
+    MixedLineEndings,
+}
+
+impl fmt::Display for WarningReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WarningReason::FilenameTruncated { len } => {
+                write!(f, "filename of {len} bytes was truncated")
+            }
+            WarningReason::DuplicateParam(param) => write!(
+                f,
+                "\"Content-Disposition\" parameter {param:?} appears more than once"
+            ),
+            WarningReason::InvalidContentLength(val) => {
+                write!(f, "ignored invalid Content-Length: {val:?}")
+            }
+            WarningReason::ContentLengthMismatch { declared, actual } => write!(
+                f,
+                "declared Content-Length: {declared} but the body was {actual} bytes long"
+            ),
+            WarningReason::InvalidChecksum { header, value } => {
+                write!(f, "ignored invalid {header}: {value:?}")
+            }
+            WarningReason::UnsupportedTransferEncoding(encoding) => {
+                write!(
+                    f,
+                    "data was not decoded from Content-Transfer-Encoding: {encoding:?}"
+                )
+            }
+            WarningReason::MixedLineEndings => {
+                f.write_str("field headers mix CRLF and LF line endings")
+            }
+        }
+    }
+}
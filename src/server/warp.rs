@@ -0,0 +1,177 @@
+//! Server-side integration with [Warp](https://github.com/seanmonstar/warp).
+//! Enabled with the `warp` feature.
+//!
+//! Provides `Filter`s which extract `multipart/form-data` requests into a `Multipart`,
+//! rejecting any other requests.
+//!
+//! With [`multipart()`](fn.multipart.html), the request body is buffered in memory before
+//! parsing begins, so you should apply
+//! [`warp::body::content_length_limit()`](https://docs.rs/warp/0.3/warp/filters/body/fn.content_length_limit.html)
+//! in front of this filter. [`multipart_stream()`](fn.multipart_stream.html) instead parses
+//! the body as it arrives, with the async `Multipart` of the `server-async` feature.
+//!
+//! ```rust,no_run
+//! use mpart::server::warp::multipart;
+//! use warp::Filter;
+//!
+//! async fn serve() {
+//!     let upload = warp::post()
+//!         .and(warp::path("upload"))
+//!         .and(warp::body::content_length_limit(16 * 1024 * 1024))
+//!         .and(multipart())
+//!         .map(|mut multipart: mpart::server::warp::WarpMultipart| {
+//!             let mut files = 0;
+//!
+//!             let res = multipart.foreach_entry(|field| {
+//!                 if field.headers.filename.is_some() {
+//!                     files += 1;
+//!                 }
+//!             });
+//!
+//!             match res {
+//!                 Ok(()) => format!("received {} files", files),
+//!                 Err(e) => format!("error reading request: {}", e),
+//!             }
+//!         });
+//!
+//!     warp::serve(upload).run(([127, 0, 0, 1], 3030)).await;
+//! }
+//! # fn main() {}
+//! ```
+use std::io::Cursor;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use warp::hyper::body::Bytes;
+use warp::reject::{self, Reject, Rejection};
+use warp::{Buf, Filter};
+
+use super::{async_, Multipart};
+
+/// The `Multipart` type extracted by [`multipart()`](fn.multipart.html).
+pub type WarpMultipart = Multipart<Cursor<Bytes>>;
+
+/// The request body as a stream of `Bytes`, as parsed by `WarpStreamMultipart`.
+pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes, warp::Error>> + Send>>;
+
+/// The async `Multipart` type extracted by [`multipart_stream()`](fn.multipart_stream.html).
+pub type WarpStreamMultipart = async_::Multipart<BodyStream>;
+
+/// The rejection returned by [`multipart()`](fn.multipart.html) for requests which are not
+/// `multipart/form-data` or don't specify a boundary.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct NotMultipart;
+
+impl Reject for NotMultipart {}
+
+/// A `Filter` which extracts a `multipart/form-data` request into a `Multipart`.
+///
+/// Rejects with [`NotMultipart`](struct.NotMultipart.html) if the request's `Content-Type`
+/// isn't `multipart/form-data` with a boundary.
+#[must_use]
+pub fn multipart() -> impl Filter<Extract = (WarpMultipart,), Error = Rejection> + Clone {
+    boundary().and(warp::body::bytes()).map(
+        |(boundary, boundary_quoted): (String, bool), body: Bytes| Multipart {
+            boundary_quoted,
+            ..Multipart::with_body(Cursor::new(body), boundary)
+        },
+    )
+}
+
+/// A `Filter` which extracts a `multipart/form-data` request into an async `Multipart` over
+/// the body as it arrives, so it isn't buffered first.
+///
+/// Rejects with [`NotMultipart`](struct.NotMultipart.html) if the request's `Content-Type`
+/// isn't `multipart/form-data` with a boundary.
+#[must_use]
+pub fn multipart_stream() -> impl Filter<Extract = (WarpStreamMultipart,), Error = Rejection> + Clone
+{
+    boundary()
+        .and(warp::body::stream())
+        .map(|(boundary, boundary_quoted): (String, bool), body| {
+            let body: BodyStream = Box::pin(ToBytes(Box::pin(body)));
+            async_::Multipart::with_body(body, boundary).with_boundary_quoted(boundary_quoted)
+        })
+}
+
+/// Extract the boundary of a `multipart/form-data` request and whether it was quoted.
+fn boundary() -> impl Filter<Extract = ((String, bool),), Error = Rejection> + Clone {
+    warp::header::optional::<String>("content-type").and_then(
+        |content_type: Option<String>| async move {
+            let content_type = content_type.as_deref();
+
+            content_type
+                .and_then(super::form_data_boundary)
+                .map(|boundary| {
+                    let quoted = content_type.is_some_and(super::boundary_quoted);
+                    (boundary.to_owned(), quoted)
+                })
+                .ok_or_else(|| reject::custom(NotMultipart))
+        },
+    )
+}
+
+/// Turns the chunks of the body stream from any `Buf` into `Bytes`.
+struct ToBytes<S>(Pin<Box<S>>);
+
+impl<S, B> Stream for ToBytes<S>
+where
+    S: Stream<Item = Result<B, warp::Error>>,
+    B: Buf,
+{
+    type Item = Result<Bytes, warp::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0
+            .as_mut()
+            .poll_next(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(|mut buf| buf.copy_to_bytes(buf.remaining()))))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{multipart, multipart_stream};
+
+    use std::io::Read;
+
+    const BODY: &str = "--boundary\r\n\
+        Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+        some text\r\n\
+        --boundary--";
+
+    fn request() -> warp::test::RequestBuilder {
+        warp::test::request()
+            .method("POST")
+            .header("content-type", "multipart/form-data; boundary=boundary")
+            .body(BODY)
+    }
+
+    #[test]
+    fn test_warp_filters() {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(async {
+                let mut multipart = request().filter(&multipart()).await.unwrap();
+                let mut field = multipart.read_entry().unwrap().unwrap();
+                let mut text = String::new();
+                field.data.read_to_string(&mut text).unwrap();
+                assert_eq!(text, "some text");
+
+                let mut multipart = request().filter(&multipart_stream()).await.unwrap();
+                let field = multipart.next_field().await.unwrap().unwrap();
+                assert_eq!(&*field.headers.name, "text");
+                assert_eq!(field.bytes().await.unwrap(), "some text");
+                assert!(multipart.next_field().await.unwrap().is_none());
+
+                let res = warp::test::request()
+                    .header("content-type", "text/plain")
+                    .filter(&multipart_stream())
+                    .await;
+                assert!(res.is_err());
+            });
+    }
+}